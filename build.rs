@@ -0,0 +1,57 @@
+//! 编译时写入构建信息：git提交哈希和ethers版本，供 `--version` 和启动日志使用
+
+use std::process::Command;
+
+fn main() {
+    // 没有.git目录的构建环境（如Docker中只复制了源码）可以通过GIT_COMMIT环境变量传入
+    let commit = std::env::var("GIT_COMMIT")
+        .ok()
+        .filter(|commit| !commit.is_empty())
+        .or_else(git_commit)
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=GIT_COMMIT={}", commit);
+    println!("cargo:rustc-env=ETHERS_VERSION={}", ethers_version());
+
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+    println!("cargo:rerun-if-changed=Cargo.lock");
+}
+
+fn git_commit() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())?;
+    let commit = String::from_utf8(output.stdout).ok()?.trim().to_string();
+
+    let dirty = Command::new("git")
+        .args(["status", "--porcelain", "--untracked-files=no"])
+        .output()
+        .ok()
+        .is_some_and(|output| !output.stdout.is_empty());
+    Some(if dirty {
+        format!("{}-dirty", commit)
+    } else {
+        commit
+    })
+}
+
+/// 从Cargo.lock中读取实际使用的ethers版本
+fn ethers_version() -> String {
+    let lock = std::fs::read_to_string("Cargo.lock").unwrap_or_default();
+    let mut lines = lock.lines();
+    while let Some(line) = lines.next() {
+        if line == "name = \"ethers\"" {
+            if let Some(version) = lines
+                .next()
+                .and_then(|line| line.strip_prefix("version = \""))
+                .and_then(|version| version.strip_suffix('"'))
+            {
+                return version.to_string();
+            }
+        }
+    }
+    "unknown".to_string()
+}
@@ -0,0 +1,986 @@
+//! 管理HTTP接口：查询运行中服务的状态和执行记录，暂停和恢复定时任务，安排一次性执行
+//!
+//! 配置了ADMIN_LISTEN_ADDR时由服务启动，`pause`/`resume`/`status`/`history`/`schedule-once` 命令通过它操作运行中的服务。
+//! `POST /distribute` 按单次覆盖的参数立即分发，与 `distribute-now` 命令相同；
+//! `POST /trigger` 与SIGUSR1相同，立即触发一次每日任务。
+
+use crate::build_info;
+use crate::contract::{DistributeOptions, DistributionResult, RewardsContract};
+use crate::debug::ContractDebugger;
+use crate::error::{DistributorError, Result};
+use crate::history::{ExecutionOutcome, ExecutionRecord};
+use crate::hooks::IntoRunResults;
+use crate::scheduler::{
+    read_one_shot_file, write_one_shot_file, DailyScheduler, JobHandle, JobInfo,
+};
+use crate::state::Trigger;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use hyper::header::{AUTHORIZATION, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::convert::Infallible;
+use std::future::Future;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// `GET /status` 中附带的最近执行记录条数
+pub const STATUS_RECENT_RUNS: usize = 5;
+
+/// `GET /status` 和 `GET /health` 中附带的之后分发时间个数
+pub const STATUS_UPCOMING_RUNS: usize = 5;
+
+/// 在指定时间注册一次性任务的闭包，由 `with_one_shot_task` 设置
+type OneShotRegistrar = Arc<
+    dyn Fn(DateTime<Utc>) -> Pin<Box<dyn Future<Output = Result<JobHandle>> + Send>> + Send + Sync,
+>;
+
+/// 返回 `POST /distribute` 使用的奖励合约，每次请求时调用，重新加载配置后返回新的实例
+type ContractSource = Arc<dyn Fn() -> RewardsContract + Send + Sync>;
+
+/// `GET /status` 的响应
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdminStatus {
+    pub version: String,
+    /// 定时任务是否暂停
+    pub paused: bool,
+    /// 最近的下次触发时间（UTC），不考虑暂停和跳过日期
+    pub next_run: Option<DateTime<Utc>>,
+    /// 每日任务之后 `STATUS_UPCOMING_RUNS` 次实际执行分发的时间（UTC），已跳过日历中排除的日期
+    #[serde(default)]
+    pub upcoming_runs: Vec<DateTime<Utc>>,
+    pub jobs: Vec<JobInfo>,
+    /// 最近的执行记录（最多 `STATUS_RECENT_RUNS` 条），按开始时间先后排列
+    pub recent_runs: Vec<ExecutionRecord>,
+}
+
+/// `POST /schedule-once` 的请求体
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleOnceRequest {
+    /// 执行时间，必须晚于当前时间
+    pub at: DateTime<Utc>,
+}
+
+/// `POST /schedule-once` 的响应，确认已注册的一次性任务
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledOnce {
+    /// 调度器中的任务ID
+    pub id: Uuid,
+    /// 请求的执行时间
+    pub at: DateTime<Utc>,
+    /// 调度器登记的触发时间（UTC）
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+/// 管理接口服务，持有运行中的调度器
+pub struct AdminServer {
+    scheduler: Arc<DailyScheduler>,
+    token: Option<String>,
+    pause_file: Option<PathBuf>,
+    one_shot: Option<OneShotRegistrar>,
+    schedule_once_file: Option<PathBuf>,
+    contract: Option<ContractSource>,
+}
+
+impl AdminServer {
+    pub fn new(scheduler: Arc<DailyScheduler>) -> Self {
+        Self {
+            scheduler,
+            token: None,
+            pause_file: None,
+            one_shot: None,
+            schedule_once_file: None,
+            contract: None,
+        }
+    }
+
+    /// 设置后除 `GET /health` 外的请求都需要 `Authorization: Bearer <token>`
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token;
+        self
+    }
+
+    /// 暂停时同时创建暂停标记文件、恢复时删除，服务重启后保持暂停状态
+    pub fn with_pause_file(mut self, path: Option<PathBuf>) -> Self {
+        self.pause_file = path;
+        self
+    }
+
+    /// `POST /schedule-once` 注册的一次性任务执行 `task`，未设置时该接口返回503
+    pub fn with_one_shot_task<F, Fut, R>(mut self, task: F) -> Self
+    where
+        F: Fn() -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = Result<R>> + Send + 'static,
+        R: IntoRunResults + 'static,
+    {
+        let scheduler = self.scheduler.clone();
+        self.one_shot = Some(Arc::new(move |at| {
+            let scheduler = scheduler.clone();
+            let task = task.clone();
+            Box::pin(async move { scheduler.add_one_shot(at, task).await })
+        }));
+        self
+    }
+
+    /// 注册的一次性任务同时写入一次性任务文件，服务停机期间过期时重启后记录为错过
+    pub fn with_schedule_once_file(mut self, path: Option<PathBuf>) -> Self {
+        self.schedule_once_file = path;
+        self
+    }
+
+    /// `POST /distribute` 使用 `contract` 返回的奖励合约，未设置时该接口返回503
+    pub fn with_contract<F>(mut self, contract: F) -> Self
+    where
+        F: Fn() -> RewardsContract + Send + Sync + 'static,
+    {
+        self.contract = Some(Arc::new(contract));
+        self
+    }
+
+    /// 在 `addr` 上监听并在后台处理请求，返回实际监听的地址（端口为0时由系统分配）
+    pub fn spawn(self, addr: SocketAddr) -> Result<SocketAddr> {
+        let server = Arc::new(self);
+        let make_service = make_service_fn(move |_| {
+            let server = server.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let server = server.clone();
+                    async move { Ok::<_, Infallible>(server.handle(request).await) }
+                }))
+            }
+        });
+        let server = Server::try_bind(&addr)
+            .map_err(|e| anyhow!("无法监听管理接口地址 {}: {}", addr, e))?
+            .serve(make_service);
+        let local_addr = server.local_addr();
+        tokio::spawn(async move {
+            if let Err(e) = server.await {
+                error!("管理接口已停止: {}", e);
+            }
+        });
+        info!("管理接口已启动: http://{}", local_addr);
+        Ok(local_addr)
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let (method, path) = (request.method().clone(), request.uri().path().to_string());
+        let public = method == Method::GET && path == "/health";
+        if !public && !self.authorized(&request) {
+            return error_response(StatusCode::UNAUTHORIZED, "缺少或错误的管理接口令牌");
+        }
+
+        match (method, path.as_str()) {
+            (Method::GET, "/health") => json_response(
+                StatusCode::OK,
+                &json!({
+                    "status": "ok",
+                    "paused": self.scheduler.is_paused(),
+                    "upcoming_runs": self.scheduler.upcoming_runs(STATUS_UPCOMING_RUNS),
+                }),
+            ),
+            (Method::GET, "/status") => json_response(StatusCode::OK, &self.status().await),
+            (Method::GET, "/history") => match history_limit(request.uri().query()) {
+                Ok(limit) => json_response(StatusCode::OK, &self.history(limit)),
+                Err(message) => error_response(StatusCode::BAD_REQUEST, &message),
+            },
+            (Method::POST, "/pause") => {
+                self.set_paused(true);
+                json_response(StatusCode::OK, &self.status().await)
+            }
+            (Method::POST, "/resume") => {
+                self.set_paused(false);
+                json_response(StatusCode::OK, &self.status().await)
+            }
+            (Method::POST, "/schedule-once") => self.schedule_once(request).await,
+            (Method::POST, "/distribute") => self.distribute(request).await,
+            (Method::POST, "/trigger") => self.trigger().await,
+            (
+                _,
+                "/health" | "/status" | "/history" | "/pause" | "/resume" | "/schedule-once"
+                | "/distribute" | "/trigger",
+            ) => error_response(StatusCode::METHOD_NOT_ALLOWED, "不支持的请求方法"),
+            _ => error_response(StatusCode::NOT_FOUND, "未知的管理接口路径"),
+        }
+    }
+
+    /// 注册一次性任务，时间已过去或请求体无效时返回400
+    async fn schedule_once(&self, request: Request<Body>) -> Response<Body> {
+        let Some(register) = &self.one_shot else {
+            return error_response(StatusCode::SERVICE_UNAVAILABLE, "服务没有配置一次性任务");
+        };
+        let body = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => body,
+            Err(e) => {
+                return error_response(StatusCode::BAD_REQUEST, &format!("读取请求体失败: {}", e))
+            }
+        };
+        let ScheduleOnceRequest { at } = match serde_json::from_slice(&body) {
+            Ok(request) => request,
+            Err(e) => {
+                return error_response(
+                    StatusCode::BAD_REQUEST,
+                    &format!("无效的请求体，应为 {{\"at\": \"RFC 3339时间\"}}: {}", e),
+                )
+            }
+        };
+
+        let handle = match register(at).await {
+            Ok(handle) => handle,
+            Err(e @ DistributorError::Config(_)) => {
+                return error_response(StatusCode::BAD_REQUEST, &e.to_string())
+            }
+            Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string()),
+        };
+        // 先注册再写文件，文件检查看到这个时间时任务已在调度器中，不会重复注册
+        if let Some(path) = &self.schedule_once_file {
+            let result = read_one_shot_file(path).and_then(|mut times| {
+                times.insert(at);
+                write_one_shot_file(path, &times)
+            });
+            if let Err(e) = result {
+                warn!("写入一次性任务文件 {} 失败: {}", path.display(), e);
+            }
+        }
+        let scheduled = ScheduledOnce {
+            id: handle.id(),
+            at,
+            next_run: self.scheduler.next_run_for(&handle).await,
+        };
+        info!(
+            "收到管理接口请求，已安排一次性任务 {} 在 {} 执行",
+            scheduled.id,
+            self.scheduler.timezone().format(at)
+        );
+        json_response(StatusCode::OK, &scheduled)
+    }
+
+    /// 按请求体中的覆盖参数立即分发，返回分发结果
+    ///
+    /// 与 `distribute-now` 命令相同：先做发送前检查，再由 `distribute_with_options` 校验参数并发送；
+    /// 与定时任务共用执行锁和执行记录，上一次执行尚未结束时返回409。
+    async fn distribute(&self, request: Request<Body>) -> Response<Body> {
+        let Some(contract) = self.contract.as_ref().map(|contract| contract()) else {
+            return error_response(
+                StatusCode::SERVICE_UNAVAILABLE,
+                "服务没有配置手动分发的合约",
+            );
+        };
+        let body = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => body,
+            Err(e) => {
+                return error_response(StatusCode::BAD_REQUEST, &format!("读取请求体失败: {}", e))
+            }
+        };
+        let mut options = if body.iter().all(u8::is_ascii_whitespace) {
+            DistributeOptions::default()
+        } else {
+            match serde_json::from_slice::<DistributeOptions>(&body) {
+                Ok(options) => options,
+                Err(e) => {
+                    return error_response(StatusCode::BAD_REQUEST, &format!("无效的请求体: {}", e))
+                }
+            }
+        };
+        options.trigger = Trigger::ManualApi;
+        info!("收到管理接口请求，立即执行分发: {:?}", options);
+
+        let result = Mutex::new(None);
+        let run = async {
+            let report = contract.preflight().await?;
+            report.log();
+            report.ensure_callable()?;
+            let distributed = ContractDebugger::new(contract.clone())
+                .manual_distribute(options)
+                .await?;
+            *result.lock().unwrap() = Some(distributed.clone());
+            Ok(distributed)
+        };
+        match self
+            .scheduler
+            .run_exclusive("管理接口手动执行", Trigger::ManualApi, run)
+            .await
+        {
+            Ok(()) => match result.into_inner().unwrap() {
+                Some(result) => json_response(StatusCode::OK, &result),
+                None => error_response(
+                    StatusCode::CONFLICT,
+                    "上一次执行尚未结束或服务正在关闭，本次已跳过",
+                ),
+            },
+            Err(e) => error_response(error_status(&e), &e.to_string()),
+        }
+    }
+
+    /// 通过 `TriggerHandle` 立即触发一次每日任务，等待执行结束后返回本次的执行记录
+    ///
+    /// 与SIGUSR1和cron触发走同一条路径：共用执行锁、超时和执行记录；上一次执行尚未结束而跳过时返回409。
+    async fn trigger(&self) -> Response<Body> {
+        let requested_at = Utc::now();
+        info!("收到管理接口请求，立即触发每日任务");
+        let result = match self.scheduler.trigger_handle().trigger_now().await {
+            Ok(result) => result,
+            Err(_) => return error_response(StatusCode::SERVICE_UNAVAILABLE, "调度器已停止"),
+        };
+        if let Err(e) = result {
+            return error_response(error_status(&e), &e.to_string());
+        }
+        let record =
+            self.scheduler.history().into_iter().rev().find(|record| {
+                record.trigger == Trigger::Manual && record.started_at >= requested_at
+            });
+        match record {
+            Some(record) if record.outcome == ExecutionOutcome::Skipped => {
+                json_response(StatusCode::CONFLICT, &record)
+            }
+            Some(record) => json_response(StatusCode::OK, &record),
+            None => error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "执行记录中找不到本次执行",
+            ),
+        }
+    }
+
+    fn authorized(&self, request: &Request<Body>) -> bool {
+        let Some(token) = &self.token else {
+            return true;
+        };
+        request
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|value| value == token)
+    }
+
+    async fn status(&self) -> AdminStatus {
+        AdminStatus {
+            version: build_info::LONG_VERSION.to_string(),
+            paused: self.scheduler.is_paused(),
+            next_run: self.scheduler.next_run().await,
+            upcoming_runs: self.scheduler.upcoming_runs(STATUS_UPCOMING_RUNS),
+            jobs: self.scheduler.list_jobs().await,
+            recent_runs: self.history(Some(STATUS_RECENT_RUNS)),
+        }
+    }
+
+    /// 调度器内存中最近 `limit` 条执行记录，未指定时返回全部
+    fn history(&self, limit: Option<usize>) -> Vec<ExecutionRecord> {
+        let mut records = self.scheduler.history();
+        let skip = limit.map_or(0, |limit| records.len().saturating_sub(limit));
+        records.drain(..skip);
+        records
+    }
+
+    /// 立即暂停或恢复调度器，并同步暂停标记文件，避免文件检查把状态改回去
+    fn set_paused(&self, paused: bool) {
+        if let Some(path) = &self.pause_file {
+            let result = if paused {
+                std::fs::write(path, Utc::now().to_rfc3339())
+            } else {
+                match std::fs::remove_file(path) {
+                    Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+                    result => result,
+                }
+            };
+            if let Err(e) = result {
+                warn!("更新暂停标记文件 {} 失败: {}", path.display(), e);
+            }
+        }
+        if paused {
+            info!("收到管理接口请求，暂停调度器");
+            self.scheduler.pause();
+        } else {
+            info!("收到管理接口请求，恢复调度器");
+            self.scheduler.resume();
+        }
+    }
+}
+
+/// 解析 `GET /history` 的 `limit` 查询参数
+fn history_limit(query: Option<&str>) -> std::result::Result<Option<usize>, String> {
+    let Some(value) = query
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .find_map(|pair| pair.strip_prefix("limit="))
+    else {
+        return Ok(None);
+    };
+    value
+        .parse()
+        .map(Some)
+        .map_err(|_| format!("无效的limit: {}，应为非负整数", value))
+}
+
+/// 执行失败时的响应状态：参数无效400，按配置跳过409，超时504，其他500
+fn error_status(e: &DistributorError) -> StatusCode {
+    match e {
+        DistributorError::InvalidOptions(_) => StatusCode::BAD_REQUEST,
+        DistributorError::JobTimeout { .. } | DistributorError::Deadline(_) => {
+            StatusCode::GATEWAY_TIMEOUT
+        }
+        e if e.is_skipped() => StatusCode::CONFLICT,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    let body = serde_json::to_vec(body).unwrap_or_default();
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &json!({ "error": message }))
+}
+
+/// 运行中服务的管理接口客户端，供命令行使用
+pub struct AdminClient {
+    base_url: String,
+    token: Option<String>,
+    http: reqwest::Client,
+}
+
+impl AdminClient {
+    /// 连接到监听在 `addr` 上的管理接口，监听所有地址时连接本机
+    pub fn new(addr: SocketAddr, token: Option<String>) -> Self {
+        let mut addr = addr;
+        if addr.ip().is_unspecified() {
+            addr.set_ip(match addr {
+                SocketAddr::V4(_) => Ipv4Addr::LOCALHOST.into(),
+                SocketAddr::V6(_) => Ipv6Addr::LOCALHOST.into(),
+            });
+        }
+        Self {
+            base_url: format!("http://{}", addr),
+            token,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub async fn status(&self) -> Result<AdminStatus> {
+        self.request(reqwest::Method::GET, "/status", None).await
+    }
+
+    /// 运行中服务内存中最近 `limit` 条执行记录，按开始时间先后排列
+    pub async fn history(&self, limit: Option<usize>) -> Result<Vec<ExecutionRecord>> {
+        let path = match limit {
+            Some(limit) => format!("/history?limit={}", limit),
+            None => "/history".to_string(),
+        };
+        self.request(reqwest::Method::GET, &path, None).await
+    }
+
+    /// 暂停定时任务，返回暂停后的状态
+    pub async fn pause(&self) -> Result<AdminStatus> {
+        self.request(reqwest::Method::POST, "/pause", None).await
+    }
+
+    /// 恢复定时任务，返回恢复后的状态
+    pub async fn resume(&self) -> Result<AdminStatus> {
+        self.request(reqwest::Method::POST, "/resume", None).await
+    }
+
+    /// 安排运行中的服务在 `at` 执行一次分发，返回调度器确认的任务
+    pub async fn schedule_once(&self, at: DateTime<Utc>) -> Result<ScheduledOnce> {
+        let body = serde_json::to_value(ScheduleOnceRequest { at })
+            .map_err(|e| anyhow!("序列化请求失败: {}", e))?;
+        self.request(reqwest::Method::POST, "/schedule-once", Some(body))
+            .await
+    }
+
+    /// 让运行中的服务按 `options` 立即分发一次，返回分发结果
+    pub async fn distribute(&self, options: &DistributeOptions) -> Result<DistributionResult> {
+        let body = serde_json::to_value(options).map_err(|e| anyhow!("序列化请求失败: {}", e))?;
+        self.request(reqwest::Method::POST, "/distribute", Some(body))
+            .await
+    }
+
+    /// 让运行中的服务立即执行一次每日任务，返回本次的执行记录
+    pub async fn trigger(&self) -> Result<ExecutionRecord> {
+        self.request(reqwest::Method::POST, "/trigger", None).await
+    }
+
+    async fn request<T: DeserializeOwned>(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        body: Option<serde_json::Value>,
+    ) -> Result<T> {
+        let mut request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+        if let Some(body) = body {
+            request = request.json(&body);
+        }
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| anyhow!("无法连接管理接口 {}: {}", self.base_url, e))?;
+        let status = response.status();
+        let body = response
+            .bytes()
+            .await
+            .map_err(|e| anyhow!("读取管理接口响应失败: {}", e))?;
+        if !status.is_success() {
+            let message = serde_json::from_slice::<serde_json::Value>(&body)
+                .ok()
+                .and_then(|body| body["error"].as_str().map(str::to_string))
+                .unwrap_or_else(|| String::from_utf8_lossy(&body).into_owned());
+            return Err(anyhow!("管理接口返回 {}: {}", status, message).into());
+        }
+        serde_json::from_slice(&body).map_err(|e| anyhow!("解析管理接口响应失败: {}", e).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::ExecutionOutcome;
+    use crate::test_utils::{distribution_reply, init_test_tracing, test_contract, MockNode};
+    use ethers::types::transaction::eip2718::TypedTransaction;
+    use ethers::types::{H256, U256};
+    use ethers::utils::keccak256;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    async fn start(
+        token: Option<&str>,
+        pause_file: Option<PathBuf>,
+    ) -> (Arc<DailyScheduler>, SocketAddr) {
+        init_test_tracing();
+        let scheduler = Arc::new(DailyScheduler::new().await.unwrap());
+        let addr = AdminServer::new(scheduler.clone())
+            .with_token(token.map(str::to_string))
+            .with_pause_file(pause_file)
+            .spawn("127.0.0.1:0".parse().unwrap())
+            .unwrap();
+        (scheduler, addr)
+    }
+
+    #[tokio::test]
+    async fn pause_and_resume_take_effect_immediately_and_show_in_status() {
+        let pause_file =
+            std::env::temp_dir().join(format!("admin-pause-{}.paused", std::process::id()));
+        let _ = std::fs::remove_file(&pause_file);
+        let (scheduler, addr) = start(None, Some(pause_file.clone())).await;
+        let client = AdminClient::new(addr, None);
+
+        assert!(!client.status().await.unwrap().paused);
+
+        let status = client.pause().await.unwrap();
+        assert!(status.paused);
+        assert!(scheduler.is_paused());
+        assert!(pause_file.exists());
+        assert!(client.status().await.unwrap().paused);
+
+        let health: serde_json::Value = reqwest::get(format!("http://{}/health", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(
+            health,
+            json!({ "status": "ok", "paused": true, "upcoming_runs": [] })
+        );
+
+        let status = client.resume().await.unwrap();
+        assert!(!status.paused);
+        assert!(!scheduler.is_paused());
+        assert!(!pause_file.exists());
+    }
+
+    #[tokio::test]
+    async fn token_is_required_except_for_health() {
+        let (scheduler, addr) = start(Some("secret"), None).await;
+
+        let error = AdminClient::new(addr, None).pause().await.unwrap_err();
+        assert!(error.to_string().contains("401"), "{}", error);
+        let error = AdminClient::new(addr, Some("wrong".to_string()))
+            .pause()
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("401"), "{}", error);
+        assert!(!scheduler.is_paused());
+
+        let health = reqwest::get(format!("http://{}/health", addr))
+            .await
+            .unwrap();
+        assert_eq!(health.status(), reqwest::StatusCode::OK);
+
+        let client = AdminClient::new(addr, Some("secret".to_string()));
+        assert!(client.pause().await.unwrap().paused);
+        assert!(scheduler.is_paused());
+    }
+
+    #[tokio::test]
+    async fn unknown_paths_and_methods_are_rejected() {
+        let (_scheduler, addr) = start(None, None).await;
+        let http = reqwest::Client::new();
+
+        let response = http
+            .get(format!("http://{}/pause", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::METHOD_NOT_ALLOWED);
+        let response = http
+            .get(format!("http://{}/missing", addr))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn client_connects_to_loopback_when_listening_on_all_addresses() {
+        let client = AdminClient::new("0.0.0.0:8081".parse().unwrap(), None);
+        assert_eq!(client.base_url, "http://127.0.0.1:8081");
+        let client = AdminClient::new("[::]:8081".parse().unwrap(), None);
+        assert_eq!(client.base_url, "http://[::1]:8081");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn schedule_once_acknowledges_time_and_fires_once() {
+        let once_file =
+            std::env::temp_dir().join(format!("admin-schedule-once-{}.once", std::process::id()));
+        let _ = std::fs::remove_file(&once_file);
+        let (fired_tx, mut fired) = tokio::sync::mpsc::unbounded_channel();
+        let scheduler = Arc::new(DailyScheduler::new().await.unwrap());
+        scheduler.start().await.unwrap();
+        let addr = AdminServer::new(scheduler.clone())
+            .with_one_shot_task(move || {
+                let fired_tx = fired_tx.clone();
+                async move {
+                    fired_tx.send(()).unwrap();
+                    Ok(())
+                }
+            })
+            .with_schedule_once_file(Some(once_file.clone()))
+            .spawn("127.0.0.1:0".parse().unwrap())
+            .unwrap();
+        let client = AdminClient::new(addr, None);
+
+        let at = Utc::now() + chrono::Duration::seconds(2);
+        let scheduled = client.schedule_once(at).await.unwrap();
+        assert_eq!(scheduled.at, at);
+        // 调度器按整秒登记触发时间，向上取整，不会早于请求的时间
+        let next_run = scheduled.next_run.unwrap();
+        assert!(next_run >= at, "{} < {}", next_run, at);
+        assert!(next_run - at < chrono::Duration::seconds(2), "{}", next_run);
+        assert_eq!(next_run.timestamp_subsec_nanos(), 0);
+        let jobs = client.status().await.unwrap().jobs;
+        assert!(jobs.iter().any(|job| job.id == scheduled.id));
+        assert_eq!(
+            read_one_shot_file(&once_file).unwrap(),
+            std::collections::BTreeSet::from([at])
+        );
+
+        tokio::time::timeout(Duration::from_secs(5), fired.recv())
+            .await
+            .expect("一次性任务没有执行")
+            .unwrap();
+        assert!(Utc::now() >= at, "一次性任务提前执行");
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(fired.try_recv().is_err(), "一次性任务执行了不止一次");
+        let jobs = client.status().await.unwrap().jobs;
+        assert!(jobs.iter().all(|job| job.id != scheduled.id));
+        let _ = std::fs::remove_file(&once_file);
+    }
+
+    #[tokio::test]
+    async fn schedule_once_rejects_past_times_and_invalid_bodies() {
+        let scheduler = Arc::new(DailyScheduler::new().await.unwrap());
+        let addr = AdminServer::new(scheduler.clone())
+            .with_one_shot_task(|| async { Ok(()) })
+            .spawn("127.0.0.1:0".parse().unwrap())
+            .unwrap();
+
+        let error = AdminClient::new(addr, None)
+            .schedule_once(Utc::now() - chrono::Duration::minutes(1))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("400"), "{}", error);
+        assert!(error.to_string().contains("已经过去"), "{}", error);
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/schedule-once", addr))
+            .body("{}")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        assert!(scheduler.list_jobs().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn schedule_once_is_unavailable_without_a_task() {
+        let (_scheduler, addr) = start(None, None).await;
+
+        let error = AdminClient::new(addr, None)
+            .schedule_once(Utc::now() + chrono::Duration::hours(1))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("503"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn history_and_status_return_recent_runs_in_order() {
+        let (scheduler, addr) = start(None, None).await;
+        let triggers = [
+            Trigger::Startup,
+            Trigger::Catchup,
+            Trigger::Manual,
+            Trigger::OneShot,
+            Trigger::Manual,
+            Trigger::Startup,
+            Trigger::Manual,
+        ];
+        for (i, trigger) in triggers.into_iter().enumerate() {
+            let run = async move {
+                if i == triggers.len() - 1 {
+                    Err(DistributorError::Config("失败".to_string()))
+                } else {
+                    Ok(())
+                }
+            };
+            let _ = scheduler.run_exclusive("测试", trigger, run).await;
+        }
+        let client = AdminClient::new(addr, None);
+
+        let history = client.history(None).await.unwrap();
+        assert_eq!(
+            history
+                .iter()
+                .map(|record| record.trigger)
+                .collect::<Vec<_>>(),
+            triggers
+        );
+        assert!(history
+            .windows(2)
+            .all(|pair| pair[0].started_at <= pair[1].started_at));
+
+        let last_two = client.history(Some(2)).await.unwrap();
+        assert_eq!(
+            last_two
+                .iter()
+                .map(|record| record.outcome)
+                .collect::<Vec<_>>(),
+            vec![ExecutionOutcome::Success, ExecutionOutcome::Failed]
+        );
+        assert_eq!(last_two[1].error.as_deref(), Some("配置错误: 失败"));
+
+        let recent = client.status().await.unwrap().recent_runs;
+        assert_eq!(recent.len(), STATUS_RECENT_RUNS);
+        assert_eq!(
+            recent
+                .iter()
+                .map(|record| record.trigger)
+                .collect::<Vec<_>>(),
+            triggers[triggers.len() - STATUS_RECENT_RUNS..]
+        );
+
+        let response = reqwest::get(format!("http://{}/history?limit=abc", addr))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn history_limit_is_read_from_query() {
+        assert_eq!(history_limit(None), Ok(None));
+        assert_eq!(history_limit(Some("")), Ok(None));
+        assert_eq!(history_limit(Some("limit=3")), Ok(Some(3)));
+        assert_eq!(history_limit(Some("x=1&limit=0")), Ok(Some(0)));
+        assert!(history_limit(Some("limit=-1")).is_err());
+    }
+
+    /// 能完整执行一次分发的模拟节点和连接它的管理接口
+    async fn start_with_contract() -> (MockNode, Arc<DailyScheduler>, SocketAddr) {
+        init_test_tracing();
+        let mined = AtomicBool::new(true);
+        let node =
+            MockNode::start(move |method, params, _| distribution_reply(method, params, &mined))
+                .await;
+        let contract = test_contract(node.url());
+        let scheduler = Arc::new(DailyScheduler::new().await.unwrap());
+        let addr = AdminServer::new(scheduler.clone())
+            .with_contract(move || contract.clone())
+            .spawn("127.0.0.1:0".parse().unwrap())
+            .unwrap();
+        (node, scheduler, addr)
+    }
+
+    #[tokio::test]
+    async fn distribute_applies_options_and_returns_result() {
+        let (node, scheduler, addr) = start_with_contract().await;
+        let options: DistributeOptions =
+            serde_json::from_value(json!({ "gas_limit": 200000, "gas_price": "2gwei" })).unwrap();
+
+        let result = AdminClient::new(addr, None)
+            .distribute(&options)
+            .await
+            .unwrap();
+
+        assert_eq!(node.count("eth_sendRawTransaction"), 1);
+        let raw: ethers::types::Bytes = serde_json::from_value(
+            node.calls()
+                .into_iter()
+                .find(|call| call.method == "eth_sendRawTransaction")
+                .unwrap()
+                .params[0]
+                .clone(),
+        )
+        .unwrap();
+        let (tx, _) = TypedTransaction::decode_signed(&ethers::utils::rlp::Rlp::new(&raw)).unwrap();
+        assert_eq!(result.tx_hash, H256(keccak256(&raw)));
+        assert_eq!(tx.gas(), Some(&U256::from(200_000)));
+        assert_eq!(tx.gas_price(), Some(U256::from(2_000_000_000u64)));
+        assert_eq!(result.trigger, Trigger::ManualApi);
+        // 不再估算Gas
+        assert_eq!(node.count("eth_estimateGas"), 0);
+
+        // 与定时任务一样写入执行记录
+        let history = scheduler.history();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].trigger, Trigger::ManualApi);
+        assert_eq!(history[0].outcome, ExecutionOutcome::Success);
+    }
+
+    #[tokio::test]
+    async fn distribute_rejects_invalid_options_without_sending() {
+        let (node, _scheduler, addr) = start_with_contract().await;
+        let url = format!("http://{}/distribute", addr);
+        let http = reqwest::Client::new();
+
+        // 格式与命令行相同，无法解析时直接拒绝
+        let response = http
+            .post(&url)
+            .body(r#"{"gas_price": "30 shannon"}"#)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), 400);
+
+        // 能解析但不合理的参数由distribute_with_options校验
+        let options: DistributeOptions =
+            serde_json::from_value(json!({ "gas_limit": "100" })).unwrap();
+        let error = AdminClient::new(addr, None)
+            .distribute(&options)
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("400"), "{}", error);
+        assert!(error.to_string().contains("21000"), "{}", error);
+
+        assert_eq!(node.count("eth_sendRawTransaction"), 0);
+    }
+
+    #[tokio::test]
+    async fn distribute_is_unavailable_without_a_contract() {
+        let (_scheduler, addr) = start(None, None).await;
+
+        let error = AdminClient::new(addr, None)
+            .distribute(&DistributeOptions::default())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("503"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn trigger_runs_daily_task_and_returns_its_record() {
+        let (scheduler, addr) = start(None, None).await;
+        let runs = Arc::new(AtomicUsize::new(0));
+        let counter = runs.clone();
+        scheduler
+            .add_daily_job(move || {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(())
+                }
+            })
+            .await
+            .unwrap();
+
+        let record = AdminClient::new(addr, None).trigger().await.unwrap();
+
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+        assert_eq!(record.trigger, Trigger::Manual);
+        assert_eq!(record.outcome, ExecutionOutcome::Success);
+        assert_eq!(scheduler.history().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn trigger_reports_failures_and_overlapping_runs() {
+        let (scheduler, addr) = start(None, None).await;
+        scheduler
+            .add_daily_job(|| async {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                Err::<(), _>(DistributorError::Config("分发失败".to_string()))
+            })
+            .await
+            .unwrap();
+        let client = AdminClient::new(addr, None);
+
+        // 第二次请求在第一次执行期间到达，与cron触发一样按重叠执行跳过
+        let (first, second) = tokio::join!(client.trigger(), async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            client.trigger().await
+        });
+
+        let failed = first.unwrap_err().to_string();
+        assert!(
+            failed.contains("500") && failed.contains("分发失败"),
+            "{}",
+            failed
+        );
+        let skipped = second.unwrap_err().to_string();
+        assert!(skipped.contains("409"), "{}", skipped);
+        assert_eq!(
+            scheduler
+                .history()
+                .iter()
+                .map(|record| record.outcome)
+                .collect::<Vec<_>>(),
+            vec![ExecutionOutcome::Skipped, ExecutionOutcome::Failed]
+        );
+    }
+
+    #[tokio::test]
+    async fn status_and_health_list_upcoming_runs() {
+        let (scheduler, addr) = start(None, None).await;
+        scheduler.add_daily_job(|| async { Ok(()) }).await.unwrap();
+
+        let status = AdminClient::new(addr, None).status().await.unwrap();
+        assert_eq!(status.upcoming_runs.len(), STATUS_UPCOMING_RUNS);
+        assert_eq!(
+            status.upcoming_runs,
+            scheduler.upcoming_runs(STATUS_UPCOMING_RUNS)
+        );
+        assert!(status
+            .upcoming_runs
+            .windows(2)
+            .all(|pair| pair[0] < pair[1]));
+
+        let health: serde_json::Value = reqwest::get(format!("http://{}/health", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(health["upcoming_runs"], json!(status.upcoming_runs));
+    }
+}
@@ -0,0 +1,372 @@
+use crate::contract::{
+    receipt_fee, DistributeOptions, DistributionResult, EmittedEvent, RewardsContract,
+};
+use crate::error::{DistributorError, Result};
+use crate::recipients::RecipientList;
+use crate::retry::retry;
+use crate::state::{BatchProgress, DistributionRecord, RecordStatus, Trigger};
+use chrono::Utc;
+use ethers::prelude::*;
+use ethers::utils::{format_ether, keccak256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Instant;
+use tracing::{info, warn};
+
+/// 分批分发中一批交易的结果
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchResult {
+    /// 批次序号，从0开始
+    pub index: usize,
+    /// 本批的接收地址数量
+    pub recipients: usize,
+    pub tx_hash: H256,
+    pub block_number: Option<U64>,
+    pub gas_used: Option<U256>,
+    pub total_fee: Option<U256>,
+    /// 之前的运行中已确认，本次没有重新发送
+    pub resumed: bool,
+}
+
+/// 接收地址列表的分批方式
+pub(crate) struct BatchPlan {
+    list: RecipientList,
+    list_hash: H256,
+    batch_size: usize,
+}
+
+impl RewardsContract {
+    /// 判断接收地址列表是否需要分批发送，不需要时返回 `None`
+    ///
+    /// 完整列表的估算Gas（按Gas策略加缓冲后）超过预算，或估算因Gas不足失败时分批；
+    /// 未配置 BATCH_SIZE 时把批次大小不断减半，直到第一批能放进预算。
+    /// 今天已有同一列表的分批进度时沿用原来的批次大小，保证能从失败的批次继续。
+    pub(crate) async fn plan_batches(&self) -> Result<Option<BatchPlan>> {
+        // Merkle模式只发送一笔设置根的交易，与列表大小无关
+        let Some(source) = self.recipients_source().filter(|_| !self.is_merkle_mode()) else {
+            return Ok(None);
+        };
+        let list = source.load()?;
+        let list_hash = H256(keccak256(list.encode_call()));
+
+        if let Some(progress) = self.todays_batch_progress(list_hash) {
+            info!(
+                "继续今天的分批分发: 已确认 {}/{} 批，每批 {} 个接收地址",
+                progress.confirmed(),
+                progress.total_batches,
+                progress.batch_size
+            );
+            return Ok(Some(BatchPlan {
+                list,
+                list_hash,
+                batch_size: progress.batch_size,
+            }));
+        }
+
+        if list.recipients.len() <= 1 {
+            return Ok(None);
+        }
+
+        let budget = self.gas_budget().await?;
+        if self.fits_gas_budget(&list, budget).await? {
+            return Ok(None);
+        }
+
+        let batch_size = match self.batch_size() {
+            Some(batch_size) => batch_size,
+            None => self.bisect_batch_size(&list, budget).await?,
+        };
+        info!(
+            "{} 个接收地址超出单笔交易的Gas预算 {}，分 {} 批发送，每批最多 {} 个",
+            list.recipients.len(),
+            budget,
+            list.recipients.len().div_ceil(batch_size),
+            batch_size
+        );
+
+        Ok(Some(BatchPlan {
+            list,
+            list_hash,
+            batch_size,
+        }))
+    }
+
+    /// 依次发送并确认每一批，已确认的批次直接跳过；某一批失败时立即返回，进度已写入状态文件
+    pub(crate) async fn distribute_batches(
+        &self,
+        plan: BatchPlan,
+        options: &DistributeOptions,
+        start_time: Instant,
+    ) -> Result<DistributionResult> {
+        let chunks = plan.list.chunks(plan.batch_size);
+        let total = chunks.len();
+        let mut progress = self
+            .todays_batch_progress(plan.list_hash)
+            .unwrap_or_else(|| BatchProgress {
+                date: self.today(),
+                list_hash: plan.list_hash,
+                batch_size: plan.batch_size,
+                total_batches: total,
+                batches: BTreeMap::new(),
+            });
+        self.save_batch_progress(&progress);
+
+        let mut results = Vec::with_capacity(total);
+        let mut events = Vec::new();
+        let mut attempts = 0;
+        for (index, chunk) in chunks.iter().enumerate() {
+            let previous = progress.batches.get(&index).cloned();
+            if let Some(record) = previous
+                .as_ref()
+                .filter(|record| record.status == RecordStatus::Confirmed)
+            {
+                info!(
+                    "第{}/{}批已在之前的运行中确认 ({:?})，跳过",
+                    index + 1,
+                    total,
+                    record.tx_hash
+                );
+                results.push(BatchResult {
+                    index,
+                    recipients: chunk.recipients.len(),
+                    tx_hash: record.tx_hash,
+                    block_number: None,
+                    gas_used: None,
+                    total_fee: None,
+                    resumed: true,
+                });
+                continue;
+            }
+
+            match self
+                .run_batch(index, chunk, previous, options, &mut progress)
+                .await
+            {
+                Ok((result, receipt, batch_attempts)) => {
+                    events.extend(receipt.logs.iter().map(EmittedEvent::from));
+                    attempts += batch_attempts;
+                    results.push(result);
+                }
+                Err(e) => {
+                    let completed = progress.confirmed();
+                    warn!(
+                        "第{}/{}批失败，已确认 {} 批，重试时从未完成的批次继续: {}",
+                        index + 1,
+                        total,
+                        completed,
+                        e
+                    );
+                    return Err(DistributorError::BatchFailed {
+                        batch: index + 1,
+                        total,
+                        completed,
+                        source: Box::new(e),
+                    });
+                }
+            }
+        }
+
+        let sum = |values: Vec<Option<U256>>| {
+            values
+                .into_iter()
+                .flatten()
+                .fold(None, |total: Option<U256>, value| {
+                    Some(total.unwrap_or_default() + value)
+                })
+        };
+        let gas_used = sum(results.iter().map(|result| result.gas_used).collect());
+        let total_fee = sum(results.iter().map(|result| result.total_fee).collect());
+        let last = results.last().expect("分批分发至少有一批");
+
+        info!(
+            "分批分发完成: {} 批，{} 个接收地址，本次Gas使用量 {:?}，总费用 {:?} wei ({} ETH)",
+            total,
+            plan.list.recipients.len(),
+            gas_used,
+            total_fee,
+            total_fee.map(format_ether).unwrap_or_default()
+        );
+
+        Ok(DistributionResult {
+            tx_hash: last.tx_hash,
+            block_number: last.block_number,
+            gas_used,
+            effective_gas_price: None,
+            total_fee,
+            total_fee_eth: total_fee.map(format_ether),
+            events,
+            attempts,
+            elapsed_ms: start_time.elapsed().as_millis() as u64,
+            verified: None,
+            trigger: options.trigger,
+            batches: results,
+        })
+    }
+
+    /// 发送（或继续等待）一批交易并等待确认
+    async fn run_batch(
+        &self,
+        index: usize,
+        chunk: &RecipientList,
+        previous: Option<DistributionRecord>,
+        options: &DistributeOptions,
+        progress: &mut BatchProgress,
+    ) -> Result<(BatchResult, TransactionReceipt, u32)> {
+        let total = progress.total_batches;
+
+        // 上次运行中已发送但未确认的批次，交易仍在节点中时继续等待而不重新发送
+        let pending = match previous.filter(|record| record.status == RecordStatus::Pending) {
+            Some(record) => self
+                .client
+                .get_transaction(record.tx_hash)
+                .await
+                .map_err(|e| DistributorError::rpc("查询交易", e))?
+                .map(|_| record.tx_hash),
+            None => None,
+        };
+
+        let (tx_hash, attempts) = match pending {
+            Some(tx_hash) => {
+                info!(
+                    "第{}/{}批的交易 {:?} 仍在等待确认",
+                    index + 1,
+                    total,
+                    tx_hash
+                );
+                (tx_hash, 0)
+            }
+            None => {
+                info!(
+                    "发送第{}/{}批，{} 个接收地址",
+                    index + 1,
+                    total,
+                    chunk.recipients.len()
+                );
+                let (to, call_data) = self.target_call(chunk.encode_call())?;
+                // 只在第一批发送前检查奖励代币余额，已有进度时之前的批次已经消耗了部分代币
+                let check_tokens = progress.batches.is_empty();
+                let (tx_hash, attempts) =
+                    self.send_call(to, call_data, options, check_tokens).await?;
+                self.record_batch(progress, index, tx_hash, RecordStatus::Pending);
+                (tx_hash, attempts)
+            }
+        };
+
+        let receipt = self.wait_for_confirmation(tx_hash).await?;
+        if receipt.status != Some(U64::from(1)) {
+            self.record_batch(progress, index, tx_hash, RecordStatus::Failed);
+            return Err(DistributorError::TransactionFailed {
+                tx_hash,
+                block_number: receipt.block_number,
+                trace: None,
+            });
+        }
+        self.record_batch(progress, index, tx_hash, RecordStatus::Confirmed);
+
+        let total_fee = receipt_fee(&receipt);
+        let result = BatchResult {
+            index,
+            recipients: chunk.recipients.len(),
+            tx_hash,
+            block_number: receipt.block_number,
+            gas_used: receipt.gas_used,
+            total_fee,
+            resumed: false,
+        };
+        info!(
+            "第{}/{}批已确认，区块号: {:?}，Gas使用量: {:?}",
+            index + 1,
+            total,
+            result.block_number,
+            result.gas_used
+        );
+        Ok((result, receipt, attempts))
+    }
+
+    /// 单笔交易的Gas预算，未配置时使用最新区块的Gas上限
+    async fn gas_budget(&self) -> Result<U256> {
+        if let Some(budget) = self.batch_gas_budget() {
+            return Ok(budget);
+        }
+
+        let block = self
+            .client
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|e| DistributorError::rpc("获取最新区块", e))?;
+        block
+            .map(|block| block.gas_limit)
+            .ok_or_else(|| anyhow::anyhow!("无法获取最新区块的Gas上限").into())
+    }
+
+    /// 估算接收地址列表的分发调用是否能放进Gas预算
+    async fn fits_gas_budget(&self, list: &RecipientList, budget: U256) -> Result<bool> {
+        let (to, call_data) = self.target_call(list.encode_call())?;
+        let estimate = retry(self.retry_policy(), "Gas估算", || async {
+            self.estimate_gas(to, call_data.clone())
+                .await
+                .map_err(DistributorError::from)
+        })
+        .await;
+
+        match estimate {
+            Ok((gas, _)) => Ok(self.buffered_gas(gas) <= budget),
+            Err(DistributorError::GasEstimation(e)) if e.is_out_of_gas() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 把批次大小不断减半，直到第一批能放进Gas预算
+    async fn bisect_batch_size(&self, list: &RecipientList, budget: U256) -> Result<usize> {
+        let mut batch_size = list.recipients.len();
+        while batch_size > 1 {
+            batch_size = batch_size.div_ceil(2);
+            let first = list.chunks(batch_size).swap_remove(0);
+            if self.fits_gas_budget(&first, budget).await? {
+                return Ok(batch_size);
+            }
+        }
+
+        Err(anyhow::anyhow!("只含一个接收地址的分发调用也超过Gas预算 {}", budget).into())
+    }
+
+    /// 今天同一接收地址列表的分批进度
+    fn todays_batch_progress(&self, list_hash: H256) -> Option<BatchProgress> {
+        self.state_store()?
+            .contract(self.contract_address())
+            .batch_progress
+            .filter(|progress| progress.date == self.today() && progress.list_hash == list_hash)
+    }
+
+    fn record_batch(
+        &self,
+        progress: &mut BatchProgress,
+        index: usize,
+        tx_hash: H256,
+        status: RecordStatus,
+    ) {
+        progress.batches.insert(
+            index,
+            DistributionRecord {
+                tx_hash,
+                nonce: None,
+                timestamp: Utc::now(),
+                status,
+                trigger: Trigger::Cron,
+                block_hash: None,
+                verified: None,
+                gas_used: None,
+                fee: None,
+            },
+        );
+        self.save_batch_progress(progress);
+    }
+
+    fn save_batch_progress(&self, progress: &BatchProgress) {
+        if let Some(store) = self.state_store() {
+            if let Err(e) = store.record_batch_progress(self.contract_address(), progress.clone()) {
+                warn!("写入状态文件失败: {}", e);
+            }
+        }
+    }
+}
@@ -0,0 +1,20 @@
+//! 编译时由 build.rs 写入的构建信息，用于确认线上运行的是哪个版本
+
+/// crate版本
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// 构建时的git提交哈希，工作区有未提交的修改时带 `-dirty` 后缀，无法获取时为 `unknown`
+pub const GIT_COMMIT: &str = env!("GIT_COMMIT");
+
+/// 依赖的ethers版本
+pub const ETHERS_VERSION: &str = env!("ETHERS_VERSION");
+
+/// `--version` 输出和启动日志使用的完整版本信息
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"),
+    " (commit ",
+    env!("GIT_COMMIT"),
+    ", ethers ",
+    env!("ETHERS_VERSION"),
+    ")"
+);
@@ -1,53 +1,1486 @@
-use anyhow::{anyhow, Result};
-use ethers::types::{Address, U256};
+use crate::error::{DistributorError, Result};
+use crate::gas::{
+    EscalationCurve, FeeEscalation, FeeHistoryConfig, GasPriceFallback, TxType, MIN_BUMP_PERCENT,
+};
+use crate::history::DEFAULT_HISTORY_CAPACITY;
+use crate::monitor::RunwayThresholds;
+use crate::preflight::{PreflightPolicies, PreflightPolicy};
+use crate::recipients::RecipientsSource;
+use crate::remote_recipients::{RemoteRecipients, DEFAULT_FETCH_TIMEOUT};
+use crate::retry::ConfirmationPoll;
+use crate::scheduler::{
+    cron_with_days, parse_cron, CronSchedule, DstPolicy, ScheduleTimezone, DEFAULT_DAILY_CRON,
+};
+use ethers::contract::MULTICALL_ADDRESS;
+use ethers::types::{Address, Bytes, U256};
+use ethers::utils::{parse_ether, parse_units};
+use serde::Deserialize;
+use chrono::{NaiveDate, Weekday};
 use std::env;
+use std::net::SocketAddr;
+use std::num::NonZeroU32;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// 构造配置错误
+macro_rules! config_error {
+    ($($arg:tt)*) => {
+        DistributorError::Config(format!($($arg)*))
+    };
+}
+
+/// Gas限制的计算策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasStrategy {
+    /// 估算值加20%缓冲（默认）
+    EstimatePlusBuffer,
+    /// 直接使用配置的 GAS_LIMIT，不做估算
+    Fixed,
+    /// 直接使用估算值，不加缓冲
+    EstimateExact,
+}
+
+impl FromStr for GasStrategy {
+    type Err = DistributorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "estimate-plus-buffer" => Ok(GasStrategy::EstimatePlusBuffer),
+            "fixed" => Ok(GasStrategy::Fixed),
+            "estimate-exact" => Ok(GasStrategy::EstimateExact),
+            other => Err(config_error!(
+                "无效的GAS_STRATEGY: {}，可选值: estimate-plus-buffer, fixed, estimate-exact",
+                other
+            )),
+        }
+    }
+}
+
+/// 交易确认的判定方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfirmationStrategy {
+    /// 收据出现即视为确认（默认）
+    Inclusion,
+    /// 收据所在区块之后共有N个区块（含收据所在区块）
+    Count(usize),
+    /// 收据所在区块不晚于节点的 `finalized` 区块
+    Finalized,
+}
+
+impl FromStr for ConfirmationStrategy {
+    type Err = DistributorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "inclusion" => Ok(ConfirmationStrategy::Inclusion),
+            "finalized" => Ok(ConfirmationStrategy::Finalized),
+            other => match other.strip_prefix("count:").map(|n| n.trim().parse::<usize>()) {
+                Some(Ok(n)) if n > 0 => Ok(ConfirmationStrategy::Count(n)),
+                _ => Err(config_error!(
+                    "无效的CONFIRMATION_STRATEGY: {}，可选值: inclusion, count:N (N为正整数), finalized",
+                    other
+                )),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for ConfirmationStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfirmationStrategy::Inclusion => write!(f, "inclusion"),
+            ConfirmationStrategy::Count(n) => write!(f, "count:{}", n),
+            ConfirmationStrategy::Finalized => write!(f, "finalized"),
+        }
+    }
+}
+
+/// 分发方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DistributionMode {
+    /// 直接调用合约分发（默认）
+    Push,
+    /// 计算接收地址和数量的Merkle根，调用 `setDailyRoot` 由用户自行领取
+    Merkle,
+}
+
+impl FromStr for DistributionMode {
+    type Err = DistributorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "push" => Ok(DistributionMode::Push),
+            "merkle" => Ok(DistributionMode::Merkle),
+            other => Err(config_error!(
+                "无效的DISTRIBUTION_MODE: {}，可选值: push, merkle",
+                other
+            )),
+        }
+    }
+}
+
+/// 分发任务的触发方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScheduleMode {
+    /// 按cron表达式在固定时刻触发（默认）
+    Cron,
+    /// 从进程启动开始每隔固定时间触发
+    Interval(Duration),
+}
+
+/// 交易签名方式
+#[derive(Clone, PartialEq, Eq)]
+pub enum SignerConfig {
+    /// 本地私钥（默认）
+    Local { private_key: String },
+    /// AWS KMS中的secp256k1密钥，私钥不离开KMS（需要启用 `aws-kms` 特性编译）
+    AwsKms { key_id: String, region: String },
+}
+
+impl std::fmt::Debug for SignerConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SignerConfig::Local { .. } => write!(f, "Local {{ private_key: <已隐藏> }}"),
+            SignerConfig::AwsKms { key_id, region } => f
+                .debug_struct("AwsKms")
+                .field("key_id", key_id)
+                .field("region", region)
+                .finish(),
+        }
+    }
+}
+
+/// CONTRACT_ADDRESS 的取值：十六进制地址或ENS名称
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ContractAddress {
+    Address(Address),
+    /// ENS名称，如 `rewards.ourdao.eth`，启动时通过节点解析
+    Ens(String),
+}
+
+impl FromStr for ContractAddress {
+    type Err = DistributorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let value = s.trim();
+        if let Ok(address) = value.parse::<Address>() {
+            return Ok(ContractAddress::Address(address));
+        }
+        // 不是地址时按ENS名称处理：至少两段、每段非空且不含空白
+        let is_name = value.contains('.')
+            && value
+                .split('.')
+                .all(|label| !label.is_empty() && !label.contains(char::is_whitespace));
+        if value.starts_with("0x") || !is_name {
+            return Err(config_error!(
+                "无效的CONTRACT_ADDRESS: {}，应为十六进制地址或ENS名称",
+                value
+            ));
+        }
+        Ok(ContractAddress::Ens(value.to_lowercase()))
+    }
+}
+
+impl std::fmt::Display for ContractAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ContractAddress::Address(address) => write!(f, "{:?}", address),
+            ContractAddress::Ens(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// CHAIN_TARGETS_PATH 中的一个分发目标：另一条链上的奖励合约，使用独立的节点、链ID和状态文件
+///
+/// 签名账户与主配置相同，按目标的链ID签名，nonce在每条链上各自分配。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChainTarget {
+    /// 日志中显示的名称，如 `arbitrum`，未设置时为 `chain-<链ID>`
+    pub name: String,
+    pub rpc_url: String,
+    pub chain_id: u64,
+    pub contract_address: Address,
+    /// 未设置时使用 GAS_LIMIT
+    pub gas_limit: Option<U256>,
+    /// 未设置时从该链的节点获取
+    pub gas_price: Option<U256>,
+    /// 未设置时使用 MAX_GAS_PRICE
+    pub max_gas_price: Option<U256>,
+    /// 未设置时使用 TX_TYPE
+    pub tx_type: Option<TxType>,
+    /// 状态文件，默认在 STATE_PATH 的文件名后加上链ID，如 `distributor_state.42161.json`
+    pub state_path: PathBuf,
+}
+
+/// CHAIN_TARGETS_PATH 文件中的一项，Gas限制和价格的写法与对应的环境变量相同
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ChainTargetEntry {
+    name: Option<String>,
+    rpc_url: String,
+    chain_id: u64,
+    contract_address: Address,
+    gas_limit: Option<serde_json::Value>,
+    gas_price: Option<String>,
+    max_gas_price: Option<String>,
+    tx_type: Option<String>,
+    state_path: Option<PathBuf>,
+}
+
+/// Multicall3聚合分发配置
+#[derive(Debug, Clone)]
+pub struct MulticallConfig {
+    /// Multicall3合约地址
+    pub address: Address,
+    /// 需要在同一笔交易中分发的奖励合约
+    pub targets: Vec<Address>,
+}
+
+/// 分发前检查奖励代币余额的配置
+#[derive(Debug, Clone)]
+pub struct TokenBalanceCheck {
+    /// 奖励代币（ERC20）地址
+    pub token: Address,
+    pub required: RequiredTokenAmount,
+}
+
+/// 分发前检查签名账户给奖励合约的ERC20授权额度的配置
+#[derive(Debug, Clone)]
+pub struct AllowanceCheck {
+    /// 需要授权的ERC20代币地址
+    pub token: Address,
+    /// 授权额度低于它时视为不足
+    pub min_allowance: U256,
+    /// 自动授权时 `approve` 的数量
+    pub approval_amount: U256,
+    /// 额度不足时是否先发送 `approve` 交易，关闭时只记录警告
+    pub auto_approve: bool,
+}
+
+/// 奖励合约至少需要持有的奖励代币数量
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequiredTokenAmount {
+    /// 固定数量（代币最小单位）
+    Fixed(U256),
+    /// 读取奖励合约的 `requiredDailyAmount()`
+    ContractView,
+}
 
 #[derive(Debug, Clone)]
 pub struct Config {
     pub rpc_url: String,
-    pub private_key: String,
-    pub contract_address: Address,
+    /// 附加到RPC请求的 `Authorization` 头
+    pub rpc_auth_header: Option<String>,
+    /// 附加到RPC请求的其他请求头
+    pub rpc_headers: Vec<(String, String)>,
+    /// RPC请求遇到限流或网络错误时的最大重试次数
+    pub rpc_max_retries: u32,
+    /// RPC重试的初始退避时间（节点未给出Retry-After时使用）
+    pub rpc_initial_backoff: Duration,
+    /// 是否按计算单元配额（如Alchemy的CUPS）额外退避
+    pub rpc_compute_unit_aware: bool,
+    /// 每秒计算单元配额
+    pub rpc_compute_units_per_second: u64,
+    /// 本地限制的每秒RPC请求数（令牌桶），未配置时不限制
+    pub rpc_requests_per_second: Option<NonZeroU32>,
+    /// 交易签名方式，由SIGNER_TYPE决定
+    pub signer: SignerConfig,
+    /// 奖励合约地址或ENS名称，ENS名称在启动时解析一次，之后只在开启 `ens_refresh_on_reload` 时重新解析
+    pub contract_address: ContractAddress,
+    /// 重新加载配置（SIGHUP）时是否重新解析ENS名称，解析出新地址时替换主奖励合约
+    pub ens_refresh_on_reload: bool,
     pub chain_id: u64,
     pub gas_limit: U256,
     pub gas_price: Option<U256>,
+    /// Gas价格上限（wei），节点建议价格超过时不发送交易
+    pub max_gas_price: Option<U256>,
+    /// 无法从节点获取Gas价格时的处理方式
+    pub gas_price_fallback: GasPriceFallback,
+    pub gas_strategy: GasStrategy,
+    /// 交易类型，`auto` 时根据链是否支持EIP-1559自动选择
+    pub tx_type: TxType,
+    /// 替换交易（节点提示加价不足）的加价策略
+    pub fee_escalation: FeeEscalation,
+    /// 设置了FEE_HISTORY_BLOCKS时按 `eth_feeHistory` 计算EIP-1559费用
+    pub fee_history: Option<FeeHistoryConfig>,
+    /// Gas估算网络错误时是否回退到配置的Gas限制（默认关闭；估算回滚时始终中止）
+    pub gas_estimation_fallback: bool,
+    /// Gas估算遇到网络错误时的最大尝试次数（含首次）
+    pub gas_estimation_retries: u32,
+    /// 可信的Gas估算下限，低于它（或不超过21000）的估算视为节点返回了错误的值
+    pub gas_estimate_min: Option<U256>,
+    /// 可信的Gas估算上限，超过时按上限发送
+    pub gas_estimate_max: Option<U256>,
+    /// 估算值加缓冲后Gas限制的上限
+    pub gas_limit_ceiling: Option<U256>,
+    /// 发送前是否先模拟执行（默认开启），模拟回滚时不发送交易
+    pub simulate_before_send: bool,
+    /// 模拟执行和Gas估算时使用的 `from` 地址，未设置时使用签名账户；实际发送仍由签名账户签名
+    pub simulate_as: Option<Address>,
+    /// 发送前模拟执行回滚、余额不足时各自的处理方式
+    pub preflight_policies: PreflightPolicies,
+    /// 确认前是否核对收据所在区块仍在主链上（会增加一次RPC调用）
+    pub reorg_protection: bool,
+    /// 交易确认的判定方式
+    pub confirmation_strategy: ConfirmationStrategy,
+    /// 等待确认时轮询收据的间隔（指数增长）
+    pub confirmation_poll: ConfirmationPoll,
+    /// 确认后是否读取合约的 `lastDistributionTime()` 校验分发确实发生
+    pub verify_distribution: bool,
+    /// 分发事件签名，配置后每小时通过 `eth_getLogs` 独立校验当天的分发
+    pub distribution_event_signature: Option<String>,
+    /// 当天开始后超过这个时长仍没有分发事件则告警
+    pub event_verify_grace: Duration,
+    /// 单次 `eth_getLogs` 查询的最大区块范围
+    pub log_block_range: u64,
+    /// 配置了MULTICALL_TARGETS时，通过Multicall3原子地分发多个奖励池
+    pub multicall: Option<MulticallConfig>,
+    /// 除 CONTRACT_ADDRESS 外需要单独发送交易分发的奖励合约
+    pub additional_contracts: Vec<Address>,
+    /// 额外的签名账户（本地私钥），多个合约按顺序轮流分配给主签名账户和这些账户，各自使用独立的nonce
+    pub additional_signers: Vec<SignerConfig>,
+    /// 多个合约同时分发的最大数量
+    pub max_concurrent_distributions: usize,
+    /// 有合约分发失败时是否停止服务并以非零状态退出，默认只通知、继续按计划运行
+    pub exit_on_distribution_failure: bool,
+    /// 覆盖分发调用的calldata（4字节选择器，可附带编码后的参数），绕过内置ABI
+    pub call_selector: Option<Bytes>,
+    /// 接收地址文件（JSON或CSV），作为 `distributeDailyRewards(address[])` 的参数
+    pub recipients: Option<RecipientsSource>,
+    /// 接收地址数据的URL，每次分发前获取并以Merkle根调用 `distributeDailyRewards(bytes32)`
+    pub remote_recipients: Option<RemoteRecipients>,
+    /// 分发方式
+    pub distribution_mode: DistributionMode,
+    /// Merkle模式下证明文件的输出路径
+    pub merkle_output_path: PathBuf,
+    /// 分批发送时每批的接收地址数量，未设置时自动确定
+    pub batch_size: Option<usize>,
+    /// 单笔交易的Gas预算，未设置时使用区块Gas上限
+    pub batch_gas_budget: Option<U256>,
+    /// 配置了REWARD_TOKEN_ADDRESS时，发送前检查奖励合约的代币余额
+    pub token_balance_check: Option<TokenBalanceCheck>,
+    /// 配置了APPROVAL_TOKEN_ADDRESS时，发送前检查签名账户给奖励合约的授权额度
+    pub allowance_check: Option<AllowanceCheck>,
+    /// 不执行分发的日期（如节假日）
+    pub skip_dates: Vec<NaiveDate>,
+    /// 周六、周日是否跳过分发
+    pub skip_weekends: bool,
+    /// 只在这些星期分发，为 `None` 时每天都分发
+    pub schedule_days: Option<Vec<Weekday>>,
+    /// 假日文件（每行一个 YYYY-MM-DD），启动和重新加载配置时读取
+    pub holidays_path: Option<PathBuf>,
+    /// cron表达式、跳过日期和日志时间使用的时区，默认UTC
+    pub schedule_timezone: ScheduleTimezone,
+    /// cron模式下每日分发任务的触发时间，按 `schedule_timezone` 和DST_POLICY计算
+    pub daily_schedule: CronSchedule,
+    /// 分发任务按cron还是按固定间隔触发
+    pub schedule_mode: ScheduleMode,
+    /// 启动时补执行停机期间错过的分发的时间窗口，为 `None` 时不补执行
+    pub catchup_window: Option<Duration>,
+    /// 每日任务触发后随机延迟的上限，为0时不延迟
+    pub schedule_jitter: Duration,
+    /// 调度器启动后是否立即执行一次分发，用于新部署时验证整个流程
+    pub run_on_startup: bool,
+    /// 测试模式：按 `test_cron` 额外执行启动检查和模拟，用于验证部署
+    pub test_mode: bool,
+    /// 测试任务的cron表达式（6段），默认每分钟一次
+    pub test_cron: String,
+    /// 测试模式下是否实际发送分发交易，默认只检查和模拟
+    pub test_mode_send: bool,
+    /// 单次运行（发送、重试、确认）的总截止时间
+    pub run_deadline: Duration,
+    /// 调度器对每次执行的超时时间，超时后释放执行锁，不取消已广播的交易
+    pub job_timeout: Option<Duration>,
+    /// 关闭服务时等待进行中的分发结束的最长时间
+    pub shutdown_grace: Duration,
+    /// 记录最近一次发送和确认的状态文件路径
+    pub state_path: PathBuf,
+    /// 其他链上的分发目标，与 CONTRACT_ADDRESS 在同一个每日任务中分发
+    pub chain_targets: Vec<ChainTarget>,
+    /// 暂停标记文件，存在时运行中的服务暂停执行定时任务（由 `pause`/`resume` 命令创建和删除）
+    pub pause_file: PathBuf,
+    /// 一次性任务文件，`schedule-once` 命令写入执行时间，运行中的服务定期读取并注册
+    pub schedule_once_file: PathBuf,
+    /// 调度器在内存中保留的执行记录条数
+    pub history_capacity: usize,
+    /// 执行记录文件，每次执行后写入，供 `history` 命令读取
+    pub history_path: PathBuf,
+    /// 管理HTTP接口的监听地址，未配置时不启动；`pause`/`resume`/`status` 命令也通过它连接运行中的服务
+    pub admin_listen_addr: Option<SocketAddr>,
+    /// 管理接口的Bearer令牌，未配置时不校验
+    pub admin_token: Option<String>,
+    /// 通知Webhook地址，未配置时只写日志
+    pub notify_webhook_url: Option<String>,
+    /// 每天（按调度时区）所有分发交易的Gas花费上限（wei），未配置时不限制
+    pub max_spend_per_day: Option<U256>,
+    /// 两次分发之间的最小间隔，不论由哪种方式触发；为 `None` 时不限制
+    pub min_interval: Option<Duration>,
+    /// 钱包余额低于该值（wei）时发送警告通知
+    pub min_balance_warn: Option<U256>,
+    /// 钱包余额低于该值（wei）时发送严重告警
+    pub min_balance_critical: Option<U256>,
+    /// 余额可维持的天数低于阈值时，诊断记为警告或失败
+    pub runway_thresholds: RunwayThresholds,
+    /// 余额检查间隔
+    pub balance_check_interval: Duration,
+    /// 超过这个时长没有成功分发时发送严重告警，未配置时不启用
+    pub dead_man_switch: Option<Duration>,
+    /// 最近一次成功分发时间的输出文件，供外部监控读取
+    pub last_success_path: Option<PathBuf>,
 }
 
 impl Config {
     pub fn from_env() -> Result<Self> {
         let rpc_url = env::var("RPC_URL")
-            .map_err(|_| anyhow!("RPC_URL 环境变量未设置"))?;
+            .map_err(|_| config_error!("RPC_URL 环境变量未设置"))?;
+        
+        let rpc_auth_header = env::var("RPC_AUTH_HEADER")
+            .ok()
+            .filter(|value| !value.trim().is_empty());
+        
+        let rpc_headers = match env::var("RPC_HEADERS") {
+            Ok(headers) if !headers.trim().is_empty() => headers
+                .split(',')
+                .map(|pair| {
+                    pair.split_once('=')
+                        .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                        .ok_or_else(|| config_error!("无效的RPC_HEADERS格式，应为 名称=值,名称=值"))
+                })
+                .collect::<Result<Vec<_>>>()?,
+            _ => Vec::new(),
+        };
         
-        let private_key = env::var("PRIVATE_KEY")
-            .map_err(|_| anyhow!("PRIVATE_KEY 环境变量未设置"))?;
+        let rpc_max_retries = env::var("RPC_MAX_RETRIES")
+            .unwrap_or_else(|_| "5".to_string())
+            .parse::<u32>()
+            .map_err(|_| config_error!("无效的RPC_MAX_RETRIES格式"))?;
+        
+        let rpc_initial_backoff = env::var("RPC_INITIAL_BACKOFF_MS")
+            .unwrap_or_else(|_| "1000".to_string())
+            .parse::<u64>()
+            .map(Duration::from_millis)
+            .map_err(|_| config_error!("无效的RPC_INITIAL_BACKOFF_MS格式"))?;
+        
+        let rpc_compute_unit_aware = env::var("RPC_COMPUTE_UNIT_AWARE")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的RPC_COMPUTE_UNIT_AWARE格式，应为 true 或 false"))?;
+        
+        let rpc_compute_units_per_second = env::var("RPC_COMPUTE_UNITS_PER_SECOND")
+            .unwrap_or_else(|_| "330".to_string())
+            .parse::<u64>()
+            .map_err(|_| config_error!("无效的RPC_COMPUTE_UNITS_PER_SECOND格式"))?;
+        
+        let rpc_requests_per_second = match env::var("RPC_REQUESTS_PER_SECOND") {
+            Ok(rps) if !rps.trim().is_empty() => Some(
+                rps.trim()
+                    .parse::<NonZeroU32>()
+                    .map_err(|_| config_error!("无效的RPC_REQUESTS_PER_SECOND，应为正整数"))?,
+            ),
+            _ => None,
+        };
+        
+        let signer = match env::var("SIGNER_TYPE").unwrap_or_else(|_| "local".to_string()).trim() {
+            "local" => SignerConfig::Local {
+                private_key: env::var("PRIVATE_KEY")
+                    .map_err(|_| config_error!("PRIVATE_KEY 环境变量未设置"))?,
+            },
+            "aws-kms" => SignerConfig::AwsKms {
+                key_id: env::var("AWS_KMS_KEY_ID")
+                    .ok()
+                    .filter(|key_id| !key_id.trim().is_empty())
+                    .ok_or_else(|| config_error!("SIGNER_TYPE=aws-kms 需要设置AWS_KMS_KEY_ID"))?,
+                region: env::var("AWS_REGION")
+                    .or_else(|_| env::var("AWS_DEFAULT_REGION"))
+                    .ok()
+                    .filter(|region| !region.trim().is_empty())
+                    .ok_or_else(|| config_error!("SIGNER_TYPE=aws-kms 需要设置AWS_REGION"))?,
+            },
+            other => return Err(config_error!("无效的SIGNER_TYPE: {}，可选值: local, aws-kms", other)),
+        };
         
         let contract_address = env::var("CONTRACT_ADDRESS")
-            .map_err(|_| anyhow!("CONTRACT_ADDRESS 环境变量未设置"))?
-            .parse::<Address>()
-            .map_err(|_| anyhow!("无效的合约地址格式"))?;
+            .map_err(|_| config_error!("CONTRACT_ADDRESS 环境变量未设置"))?
+            .parse::<ContractAddress>()?;
+        
+        let ens_refresh_on_reload = env::var("ENS_REFRESH_ON_RELOAD")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的ENS_REFRESH_ON_RELOAD格式，应为 true 或 false"))?;
         
         let chain_id = env::var("CHAIN_ID")
             .unwrap_or_else(|_| "1".to_string())
             .parse::<u64>()
-            .map_err(|_| anyhow!("无效的链ID格式"))?;
+            .map_err(|_| config_error!("无效的链ID格式"))?;
         
-        let gas_limit = env::var("GAS_LIMIT")
-            .unwrap_or_else(|_| "500000".to_string())
-            .parse::<U256>()
-            .map_err(|_| anyhow!("无效的Gas限制格式"))?;
+        let gas_limit = env::var("GAS_LIMIT").unwrap_or_else(|_| "500000".to_string());
+        let gas_limit = parse_gas_limit(&gas_limit)
+            .map_err(|e| config_error!("无效的GAS_LIMIT: {}", e))?;
         
         let gas_price = env::var("GAS_PRICE")
             .ok()
-            .map(|price| price.parse::<U256>())
+            .filter(|price| !price.trim().is_empty())
+            .map(|price| parse_gas_price(&price))
+            .transpose()
+            .map_err(|e| config_error!("无效的GAS_PRICE: {}", e))?;
+        
+        let max_gas_price = env::var("MAX_GAS_PRICE")
+            .ok()
+            .filter(|price| !price.trim().is_empty())
+            .map(|price| parse_gas_price(&price))
             .transpose()
-            .map_err(|_| anyhow!("无效的Gas价格格式"))?;
+            .map_err(|e| config_error!("无效的MAX_GAS_PRICE: {}", e))?;
+        
+        let gas_price_fallback = env::var("ON_GAS_PRICE_UNAVAILABLE")
+            .unwrap_or_else(|_| "use-default".to_string())
+            .parse::<GasPriceFallback>()?;
+        
+        let gas_strategy = env::var("GAS_STRATEGY")
+            .unwrap_or_else(|_| "estimate-plus-buffer".to_string())
+            .parse::<GasStrategy>()?;
+        
+        let tx_type = env::var("TX_TYPE")
+            .unwrap_or_else(|_| "legacy".to_string())
+            .parse::<TxType>()?;
+        
+        let gas_estimation_fallback = env::var("GAS_ESTIMATION_FALLBACK")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的GAS_ESTIMATION_FALLBACK格式，应为 true 或 false"))?;
+        
+        let gas_estimation_retries = env::var("GAS_ESTIMATION_RETRIES")
+            .unwrap_or_else(|_| "3".to_string())
+            .parse::<u32>()
+            .map_err(|_| config_error!("无效的GAS_ESTIMATION_RETRIES格式"))?;
+        
+        let gas_estimate_min = match env::var("GAS_ESTIMATE_MIN") {
+            Ok(min) if !min.trim().is_empty() => Some(
+                parse_gas_limit(&min).map_err(|e| config_error!("无效的GAS_ESTIMATE_MIN: {}", e))?,
+            ),
+            _ => None,
+        };
+        
+        let gas_estimate_max = match env::var("GAS_ESTIMATE_MAX") {
+            Ok(max) if !max.trim().is_empty() => Some(
+                parse_gas_limit(&max).map_err(|e| config_error!("无效的GAS_ESTIMATE_MAX: {}", e))?,
+            ),
+            _ => None,
+        };
+        if let (Some(min), Some(max)) = (gas_estimate_min, gas_estimate_max) {
+            if min > max {
+                return Err(config_error!("GAS_ESTIMATE_MIN ({}) 不能大于 GAS_ESTIMATE_MAX ({})", min, max));
+            }
+        }
+        
+        let gas_limit_ceiling = match env::var("GAS_LIMIT_CEILING") {
+            Ok(ceiling) if !ceiling.trim().is_empty() => Some(
+                parse_gas_limit(&ceiling).map_err(|e| config_error!("无效的GAS_LIMIT_CEILING: {}", e))?,
+            ),
+            _ => None,
+        };
+        if let (Some(min), Some(ceiling)) = (gas_estimate_min, gas_limit_ceiling) {
+            if min > ceiling {
+                return Err(config_error!("GAS_ESTIMATE_MIN ({}) 不能大于 GAS_LIMIT_CEILING ({})", min, ceiling));
+            }
+        }
+        
+        let simulate_before_send = env::var("SIMULATE_BEFORE_SEND")
+            .unwrap_or_else(|_| "true".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的SIMULATE_BEFORE_SEND格式，应为 true 或 false"))?;
+
+        let simulate_as = match env::var("SIMULATE_AS") {
+            Ok(address) if !address.trim().is_empty() => Some(
+                address
+                    .trim()
+                    .parse::<Address>()
+                    .map_err(|_| config_error!("无效的SIMULATE_AS格式"))?,
+            ),
+            _ => None,
+        };
+        
+        let preflight_policy = |name: &str| -> Result<PreflightPolicy> {
+            env::var(name)
+                .unwrap_or_else(|_| "abort".to_string())
+                .parse::<PreflightPolicy>()
+                .map_err(|e| config_error!("无效的{}: {}", name, e))
+        };
+        let preflight_policies = PreflightPolicies {
+            on_simulation_revert: preflight_policy("ON_SIMULATION_REVERT")?,
+            on_insufficient_balance: preflight_policy("ON_INSUFFICIENT_BALANCE")?,
+        };
+        
+        let reorg_protection = env::var("REORG_PROTECTION")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的REORG_PROTECTION格式，应为 true 或 false"))?;
+        
+        // CONFIRMATIONS=N 是 CONFIRMATION_STRATEGY=count:N 的旧写法，两者不能同时设置
+        let confirmation_strategy = match (
+            env::var("CONFIRMATION_STRATEGY").ok().filter(|s| !s.trim().is_empty()),
+            env::var("CONFIRMATIONS").ok().filter(|s| !s.trim().is_empty()),
+        ) {
+            (Some(_), Some(_)) => {
+                return Err(config_error!("CONFIRMATION_STRATEGY和CONFIRMATIONS不能同时设置"))
+            }
+            (Some(strategy), None) => strategy.parse::<ConfirmationStrategy>()?,
+            (None, Some(confirmations)) => match confirmations
+                .trim()
+                .parse::<usize>()
+                .map_err(|e| config_error!("无效的CONFIRMATIONS: {}", e))?
+            {
+                0 | 1 => ConfirmationStrategy::Inclusion,
+                n => ConfirmationStrategy::Count(n),
+            },
+            (None, None) => ConfirmationStrategy::Inclusion,
+        };
+        
+        let poll_millis = |name: &str, default: &str| -> Result<Duration> {
+            env::var(name)
+                .unwrap_or_else(|_| default.to_string())
+                .parse::<u64>()
+                .map(Duration::from_millis)
+                .map_err(|_| config_error!("无效的{}格式，应为毫秒数", name))
+        };
+        let confirmation_poll = ConfirmationPoll {
+            initial: poll_millis("CONFIRMATION_POLL_INITIAL_MS", "1000")?,
+            max: poll_millis("CONFIRMATION_POLL_MAX_MS", "15000")?,
+            multiplier: env::var("CONFIRMATION_POLL_MULTIPLIER")
+                .unwrap_or_else(|_| "2".to_string())
+                .parse::<f64>()
+                .map_err(|_| config_error!("无效的CONFIRMATION_POLL_MULTIPLIER格式"))?,
+        };
+        if confirmation_poll.initial.is_zero() {
+            return Err(config_error!("CONFIRMATION_POLL_INITIAL_MS必须大于0"));
+        }
+        if confirmation_poll.max < confirmation_poll.initial {
+            return Err(config_error!("CONFIRMATION_POLL_MAX_MS不能小于CONFIRMATION_POLL_INITIAL_MS"));
+        }
+        if !(1.0..=10.0).contains(&confirmation_poll.multiplier) {
+            return Err(config_error!("CONFIRMATION_POLL_MULTIPLIER必须在1到10之间"));
+        }
+        
+        let verify_distribution = env::var("VERIFY_DISTRIBUTION")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的VERIFY_DISTRIBUTION格式，应为 true 或 false"))?;
+        
+        let distribution_event_signature = env::var("DISTRIBUTION_EVENT_SIGNATURE")
+            .ok()
+            .filter(|signature| !signature.trim().is_empty());
+        
+        let event_verify_grace = env::var("EVENT_VERIFY_GRACE_HOURS")
+            .unwrap_or_else(|_| "8".to_string())
+            .parse::<u64>()
+            .map(|hours| Duration::from_secs(hours * 3600))
+            .map_err(|_| config_error!("无效的EVENT_VERIFY_GRACE_HOURS格式"))?;
+        
+        let log_block_range = env::var("LOG_BLOCK_RANGE")
+            .unwrap_or_else(|_| "2000".to_string())
+            .parse::<u64>()
+            .map_err(|_| config_error!("无效的LOG_BLOCK_RANGE格式"))?;
+        
+        let multicall = match env::var("MULTICALL_TARGETS") {
+            Ok(targets) if !targets.trim().is_empty() => {
+                let targets = targets
+                    .split(',')
+                    .map(|target| target.trim().parse::<Address>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| config_error!("无效的MULTICALL_TARGETS格式，应为逗号分隔的合约地址"))?;
+
+                let address = match env::var("MULTICALL_ADDRESS") {
+                    Ok(address) if !address.trim().is_empty() => address
+                        .trim()
+                        .parse::<Address>()
+                        .map_err(|_| config_error!("无效的Multicall合约地址格式"))?,
+                    _ => MULTICALL_ADDRESS,
+                };
+
+                Some(MulticallConfig { address, targets })
+            }
+            _ => None,
+        };
+        
+        let additional_contracts = match env::var("ADDITIONAL_CONTRACTS") {
+            Ok(contracts) if !contracts.trim().is_empty() => contracts
+                .split(',')
+                .map(|contract| contract.trim().parse::<Address>())
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| config_error!("无效的ADDITIONAL_CONTRACTS格式，应为逗号分隔的合约地址"))?,
+            _ => Vec::new(),
+        };
+        if !additional_contracts.is_empty() && multicall.is_some() {
+            return Err(config_error!("ADDITIONAL_CONTRACTS和MULTICALL_TARGETS不能同时设置"));
+        }
+        
+        let additional_signers: Vec<SignerConfig> = match env::var("ADDITIONAL_PRIVATE_KEYS") {
+            Ok(keys) if !keys.trim().is_empty() => keys
+                .split(',')
+                .map(|key| SignerConfig::Local {
+                    private_key: key.trim().to_string(),
+                })
+                .collect(),
+            _ => Vec::new(),
+        };
+        if !additional_signers.is_empty() && additional_contracts.is_empty() {
+            return Err(config_error!("ADDITIONAL_PRIVATE_KEYS需要配合ADDITIONAL_CONTRACTS使用"));
+        }
+        
+        let max_concurrent_distributions = match env::var("MAX_CONCURRENT_DISTRIBUTIONS")
+            .unwrap_or_else(|_| "4".to_string())
+            .parse::<usize>()
+        {
+            Ok(max) if max > 0 => max,
+            _ => return Err(config_error!("无效的MAX_CONCURRENT_DISTRIBUTIONS，应为正整数")),
+        };
+        
+        let exit_on_distribution_failure = env::var("EXIT_ON_DISTRIBUTION_FAILURE")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的EXIT_ON_DISTRIBUTION_FAILURE格式，应为 true 或 false"))?;
+        
+        let call_selector = match env::var("CALL_SELECTOR") {
+            Ok(selector) if !selector.trim().is_empty() => {
+                let call_data = selector
+                    .trim()
+                    .parse::<Bytes>()
+                    .map_err(|_| config_error!("无效的CALL_SELECTOR格式，应为十六进制"))?;
+                if call_data.len() < 4 {
+                    return Err(config_error!("CALL_SELECTOR至少需要4字节的函数选择器"));
+                }
+                Some(call_data)
+            }
+            _ => None,
+        };
+        
+        let allow_empty_recipients = env::var("ALLOW_EMPTY_RECIPIENTS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的ALLOW_EMPTY_RECIPIENTS格式，应为 true 或 false"))?;
+        
+        let recipients = env::var("RECIPIENTS_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(|path| RecipientsSource {
+                path: PathBuf::from(path.trim()),
+                allow_empty: allow_empty_recipients,
+            });
+        if recipients.is_some() && call_selector.is_some() {
+            return Err(config_error!("CALL_SELECTOR和RECIPIENTS_PATH不能同时设置"));
+        }
+        
+        let recipients_fetch_timeout = env::var("RECIPIENTS_FETCH_TIMEOUT_SECS")
+            .ok()
+            .filter(|secs| !secs.trim().is_empty())
+            .map(|secs| match secs.trim().parse::<u64>() {
+                Ok(secs) if secs > 0 => Ok(Duration::from_secs(secs)),
+                _ => Err(config_error!("无效的RECIPIENTS_FETCH_TIMEOUT_SECS，应为正整数")),
+            })
+            .transpose()?
+            .unwrap_or(DEFAULT_FETCH_TIMEOUT);
+        let remote_recipients = env::var("RECIPIENTS_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty())
+            .map(|url| RemoteRecipients::new(url.trim()).with_timeout(recipients_fetch_timeout));
+        if remote_recipients.is_some() && (recipients.is_some() || call_selector.is_some()) {
+            return Err(config_error!("RECIPIENTS_URL不能与RECIPIENTS_PATH或CALL_SELECTOR同时设置"));
+        }
+        
+        let distribution_mode = env::var("DISTRIBUTION_MODE")
+            .unwrap_or_else(|_| "push".to_string())
+            .parse::<DistributionMode>()?;
+        if distribution_mode == DistributionMode::Merkle && remote_recipients.is_some() {
+            return Err(config_error!(
+                "RECIPIENTS_URL调用 distributeDailyRewards(bytes32)，不能与DISTRIBUTION_MODE=merkle同时设置"
+            ));
+        }
+        if distribution_mode == DistributionMode::Merkle && recipients.is_none() {
+            return Err(config_error!("DISTRIBUTION_MODE=merkle 需要设置RECIPIENTS_PATH"));
+        }
+        
+        let merkle_output_path = env::var("MERKLE_OUTPUT_PATH")
+            .map(PathBuf::from)
+            .unwrap_or_else(|_| PathBuf::from("merkle_proofs.json"));
+        
+        let batch_size = match env::var("BATCH_SIZE") {
+            Ok(size) if !size.trim().is_empty() => match size.trim().parse::<usize>() {
+                Ok(size) if size > 0 => Some(size),
+                _ => return Err(config_error!("无效的BATCH_SIZE，应为正整数")),
+            },
+            _ => None,
+        };
+        
+        let batch_gas_budget = match env::var("BATCH_GAS_BUDGET") {
+            Ok(budget) if !budget.trim().is_empty() => Some(
+                parse_gas_limit(&budget).map_err(|e| config_error!("无效的BATCH_GAS_BUDGET: {}", e))?,
+            ),
+            _ => None,
+        };
+        
+        let token_balance_check = match env::var("REWARD_TOKEN_ADDRESS") {
+            Ok(token) if !token.trim().is_empty() => {
+                let token = token
+                    .trim()
+                    .parse::<Address>()
+                    .map_err(|_| config_error!("无效的REWARD_TOKEN_ADDRESS格式"))?;
+                let required = match env::var("MIN_REWARD_TOKEN_BALANCE") {
+                    Ok(amount) if !amount.trim().is_empty() => RequiredTokenAmount::Fixed(
+                        U256::from_dec_str(amount.trim())
+                            .map_err(|_| config_error!("无效的MIN_REWARD_TOKEN_BALANCE，应为代币最小单位的十进制整数"))?,
+                    ),
+                    _ => RequiredTokenAmount::ContractView,
+                };
+                Some(TokenBalanceCheck { token, required })
+            }
+            _ => None,
+        };
+
+        let allowance_check = match env::var("APPROVAL_TOKEN_ADDRESS") {
+            Ok(token) if !token.trim().is_empty() => {
+                let token = token
+                    .trim()
+                    .parse::<Address>()
+                    .map_err(|_| config_error!("无效的APPROVAL_TOKEN_ADDRESS格式"))?;
+                let min_allowance = env::var("MIN_ALLOWANCE")
+                    .ok()
+                    .filter(|amount| !amount.trim().is_empty())
+                    .ok_or_else(|| config_error!("设置了APPROVAL_TOKEN_ADDRESS时必须设置MIN_ALLOWANCE"))
+                    .and_then(|amount| {
+                        U256::from_dec_str(amount.trim())
+                            .map_err(|_| config_error!("无效的MIN_ALLOWANCE，应为代币最小单位的十进制整数"))
+                    })?;
+                let approval_amount = match env::var("APPROVAL_AMOUNT") {
+                    Ok(amount) if amount.trim().eq_ignore_ascii_case("max") => U256::MAX,
+                    Ok(amount) if !amount.trim().is_empty() => U256::from_dec_str(amount.trim())
+                        .map_err(|_| config_error!("无效的APPROVAL_AMOUNT，应为代币最小单位的十进制整数或 max"))?,
+                    _ => U256::MAX,
+                };
+                if approval_amount < min_allowance {
+                    return Err(config_error!(
+                        "APPROVAL_AMOUNT ({}) 不能小于 MIN_ALLOWANCE ({})",
+                        approval_amount,
+                        min_allowance
+                    ));
+                }
+                let auto_approve = env::var("AUTO_APPROVE")
+                    .unwrap_or_else(|_| "false".to_string())
+                    .parse::<bool>()
+                    .map_err(|_| config_error!("无效的AUTO_APPROVE格式，应为 true 或 false"))?;
+                Some(AllowanceCheck {
+                    token,
+                    min_allowance,
+                    approval_amount,
+                    auto_approve,
+                })
+            }
+            _ => None,
+        };
+        
+        let skip_dates = match env::var("SKIP_DATES") {
+            Ok(dates) if !dates.trim().is_empty() => dates
+                .split(',')
+                .map(|date| NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d"))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|_| config_error!("无效的SKIP_DATES格式，应为逗号分隔的 YYYY-MM-DD 日期"))?,
+            _ => Vec::new(),
+        };
+        
+        let skip_weekends = env::var("SKIP_WEEKENDS")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的SKIP_WEEKENDS格式，应为 true 或 false"))?;
+        
+        let schedule_days = match env::var("SCHEDULE_DAYS") {
+            Ok(days) if !days.trim().is_empty() => {
+                let mut parsed = Vec::new();
+                for day in days.split(',') {
+                    let day = day.trim().parse::<Weekday>().map_err(|_| {
+                        config_error!(
+                            "无效的SCHEDULE_DAYS: {}，应为逗号分隔的 mon,tue,wed,thu,fri,sat,sun",
+                            day.trim()
+                        )
+                    })?;
+                    if !parsed.contains(&day) {
+                        parsed.push(day);
+                    }
+                }
+                parsed.sort_by_key(Weekday::num_days_from_monday);
+                // 七天都允许时与不设置相同
+                (parsed.len() < 7).then_some(parsed)
+            }
+            _ => None,
+        };
+        
+        let holidays_path = env::var("HOLIDAYS_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from);
+        
+        let schedule_timezone = env::var("SCHEDULE_TIMEZONE")
+            .unwrap_or_else(|_| "utc".to_string())
+            .parse::<ScheduleTimezone>()
+            .map_err(|e| config_error!("无效的SCHEDULE_TIMEZONE: {}", e))?;
+        
+        // 未自定义cron表达式时把SCHEDULE_DAYS写入星期字段；自定义时由每日任务触发后按星期跳过
+        let schedule_cron = match env::var("SCHEDULE_CRON") {
+            Ok(cron) if !cron.trim().is_empty() => cron,
+            _ => match &schedule_days {
+                Some(days) => cron_with_days(DEFAULT_DAILY_CRON, days),
+                None => DEFAULT_DAILY_CRON.to_string(),
+            },
+        };
+        let dst_policy = env::var("DST_POLICY")
+            .unwrap_or_else(|_| "earliest".to_string())
+            .parse::<DstPolicy>()
+            .map_err(|e| config_error!("无效的DST_POLICY: {}", e))?;
+        let daily_schedule =
+            CronSchedule::new(&schedule_cron, schedule_timezone)?.with_dst_policy(dst_policy);
+        
+        let schedule_mode = match env::var("SCHEDULE_MODE")
+            .unwrap_or_else(|_| "cron".to_string())
+            .trim()
+        {
+            "cron" => ScheduleMode::Cron,
+            "interval" => {
+                let secs = env::var("INTERVAL_SECS")
+                    .map_err(|_| config_error!("SCHEDULE_MODE=interval 时需要设置INTERVAL_SECS"))?
+                    .trim()
+                    .parse::<u64>()
+                    .map_err(|_| config_error!("无效的INTERVAL_SECS，应为正整数"))?;
+                if secs == 0 {
+                    return Err(config_error!("INTERVAL_SECS必须大于0"));
+                }
+                ScheduleMode::Interval(Duration::from_secs(secs))
+            }
+            other => {
+                return Err(config_error!(
+                    "无效的SCHEDULE_MODE: {}，可选值: cron, interval",
+                    other
+                ))
+            }
+        };
+        
+        let catchup_window = match env::var("CATCHUP_WINDOW_HOURS")
+            .unwrap_or_else(|_| "6".to_string())
+            .parse::<u64>()
+        {
+            Ok(0) => None,
+            Ok(hours) => Some(Duration::from_secs(hours * 3600)),
+            Err(_) => return Err(config_error!("无效的CATCHUP_WINDOW_HOURS，应为非负整数")),
+        };
+        
+        let schedule_jitter = env::var("SCHEDULE_JITTER_SECS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u64>()
+            .map(Duration::from_secs)
+            .map_err(|_| config_error!("无效的SCHEDULE_JITTER_SECS，应为非负整数"))?;
+        
+        let run_on_startup = env::var("RUN_ON_STARTUP")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的RUN_ON_STARTUP格式，应为 true 或 false"))?;
+        
+        let test_mode = env::var("TEST_MODE")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的TEST_MODE格式，应为 true 或 false"))?;
+        
+        let test_cron = env::var("TEST_CRON")
+            .ok()
+            .filter(|cron| !cron.trim().is_empty())
+            .unwrap_or_else(|| "0 * * * * *".to_string());
+        if test_mode {
+            parse_cron(&test_cron)?;
+        }
+        
+        let test_mode_send = env::var("TEST_MODE_SEND")
+            .unwrap_or_else(|_| "false".to_string())
+            .parse::<bool>()
+            .map_err(|_| config_error!("无效的TEST_MODE_SEND格式，应为 true 或 false"))?;
+        
+        let run_deadline = env::var("RUN_DEADLINE_SECS")
+            .unwrap_or_else(|_| "14400".to_string())
+            .parse::<u64>()
+            .map(Duration::from_secs)
+            .map_err(|_| config_error!("无效的RUN_DEADLINE_SECS格式"))?;
+        
+        let job_timeout = match env::var("JOB_TIMEOUT_SECS").ok().filter(|s| !s.trim().is_empty()) {
+            Some(secs) => match secs.trim().parse::<u64>() {
+                Ok(0) | Err(_) => return Err(config_error!("无效的JOB_TIMEOUT_SECS，应为正整数")),
+                Ok(secs) => Some(Duration::from_secs(secs)),
+            },
+            None => None,
+        };
+        
+        let shutdown_grace = env::var("SHUTDOWN_GRACE_SECS")
+            .unwrap_or_else(|_| "120".to_string())
+            .parse::<u64>()
+            .map(Duration::from_secs)
+            .map_err(|_| config_error!("无效的SHUTDOWN_GRACE_SECS，应为非负整数"))?;
+        
+        let fee_escalation = {
+            let defaults = FeeEscalation::default();
+            let percent = |name: &str, default: u64| {
+                env::var(name)
+                    .ok()
+                    .filter(|value| !value.trim().is_empty())
+                    .map(|value| value.trim().parse::<u64>())
+                    .transpose()
+                    .map(|value| value.unwrap_or(default))
+                    .map_err(|_| config_error!("无效的{}，应为非负整数", name))
+            };
+            let bump_percent = percent("FEE_BUMP_PERCENT", defaults.bump_percent)?;
+            if bump_percent < MIN_BUMP_PERCENT {
+                return Err(config_error!(
+                    "FEE_BUMP_PERCENT不能低于{}，否则节点会拒绝替换交易",
+                    MIN_BUMP_PERCENT
+                ));
+            }
+            FeeEscalation {
+                initial_premium_percent: percent("FEE_INITIAL_PREMIUM_PERCENT", 0)?,
+                bump_percent,
+                max_bumps: percent("MAX_FEE_BUMPS", defaults.max_bumps as u64)? as u32,
+                max_price: env::var("FEE_ESCALATION_MAX_PRICE")
+                    .ok()
+                    .filter(|price| !price.trim().is_empty())
+                    .map(|price| parse_gas_price(&price))
+                    .transpose()
+                    .map_err(|e| config_error!("无效的FEE_ESCALATION_MAX_PRICE: {}", e))?,
+                curve: env::var("FEE_ESCALATION_CURVE")
+                    .unwrap_or_else(|_| "flat".to_string())
+                    .parse::<EscalationCurve>()?,
+                window: run_deadline,
+            }
+        };
+
+        let fee_history = match env::var("FEE_HISTORY_BLOCKS")
+            .unwrap_or_else(|_| "0".to_string())
+            .trim()
+            .parse::<u64>()
+        {
+            Ok(0) => None,
+            Ok(block_count) if block_count > 1024 => {
+                return Err(config_error!("FEE_HISTORY_BLOCKS不能超过1024"))
+            }
+            Ok(block_count) => {
+                let number = |name: &str, default: &str| {
+                    env::var(name)
+                        .ok()
+                        .filter(|value| !value.trim().is_empty())
+                        .unwrap_or_else(|| default.to_string())
+                        .trim()
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|value| value.is_finite())
+                };
+                let reward_percentile = number("FEE_HISTORY_PERCENTILE", "50")
+                    .filter(|percentile| (0.0..=100.0).contains(percentile))
+                    .ok_or_else(|| config_error!("无效的FEE_HISTORY_PERCENTILE，应为0到100之间的数"))?;
+                let base_fee_multiplier = number("BASE_FEE_MULTIPLIER", "2")
+                    .filter(|multiplier| *multiplier >= 1.0)
+                    .ok_or_else(|| config_error!("无效的BASE_FEE_MULTIPLIER，应为不小于1的数"))?;
+                Some(FeeHistoryConfig {
+                    block_count,
+                    reward_percentile,
+                    base_fee_multiplier,
+                })
+            }
+            Err(_) => return Err(config_error!("无效的FEE_HISTORY_BLOCKS，应为非负整数")),
+        };
+        
+        let state_path: PathBuf = env::var("STATE_PATH")
+            .unwrap_or_else(|_| "distributor_state.json".to_string())
+            .into();
+        
+        let chain_targets = match env::var("CHAIN_TARGETS_PATH") {
+            Ok(path) if !path.trim().is_empty() => {
+                read_chain_targets(Path::new(path.trim()), &state_path)?
+            }
+            _ => Vec::new(),
+        };
+        
+        let pause_file = env::var("PAUSE_FILE")
+            .unwrap_or_else(|_| "distributor.paused".to_string())
+            .into();
+        
+        let schedule_once_file = env::var("SCHEDULE_ONCE_FILE")
+            .unwrap_or_else(|_| "distributor.once".to_string())
+            .into();
+        
+        let history_capacity = match env::var("HISTORY_CAPACITY").ok().filter(|s| !s.trim().is_empty()) {
+            Some(capacity) => match capacity.trim().parse::<usize>() {
+                Ok(0) | Err(_) => return Err(config_error!("无效的HISTORY_CAPACITY，应为正整数")),
+                Ok(capacity) => capacity,
+            },
+            None => DEFAULT_HISTORY_CAPACITY,
+        };
+        
+        let history_path = env::var("HISTORY_PATH")
+            .unwrap_or_else(|_| "distributor_history.json".to_string())
+            .into();
+        
+        let admin_listen_addr = match env::var("ADMIN_LISTEN_ADDR").ok().filter(|s| !s.trim().is_empty()) {
+            Some(addr) => Some(addr.trim().parse::<SocketAddr>().map_err(|_| {
+                config_error!("无效的ADMIN_LISTEN_ADDR: {}，应为 IP:端口，如 127.0.0.1:8081", addr.trim())
+            })?),
+            None => None,
+        };
+        
+        let admin_token = env::var("ADMIN_TOKEN")
+            .ok()
+            .map(|token| token.trim().to_string())
+            .filter(|token| !token.is_empty());
+        
+        let notify_webhook_url = env::var("NOTIFY_WEBHOOK_URL")
+            .ok()
+            .filter(|url| !url.trim().is_empty());
+        
+        let min_balance_warn = parse_ether_var("MIN_BALANCE_WARN")?;
+        let min_balance_critical = parse_ether_var("MIN_BALANCE_CRITICAL")?;
+
+        let runway_thresholds = {
+            let defaults = RunwayThresholds::default();
+            let days = |name: &str, default: f64| {
+                env::var(name)
+                    .ok()
+                    .filter(|value| !value.trim().is_empty())
+                    .map(|value| value.trim().parse::<u64>().map(|days| days as f64))
+                    .transpose()
+                    .map(|value| value.unwrap_or(default))
+                    .map_err(|_| config_error!("无效的{}，应为非负整数", name))
+            };
+            let thresholds = RunwayThresholds {
+                warn_days: days("RUNWAY_WARN_DAYS", defaults.warn_days)?,
+                fail_days: days("RUNWAY_FAIL_DAYS", defaults.fail_days)?,
+            };
+            if thresholds.fail_days > thresholds.warn_days {
+                return Err(config_error!(
+                    "RUNWAY_FAIL_DAYS ({}) 不能大于 RUNWAY_WARN_DAYS ({})",
+                    thresholds.fail_days,
+                    thresholds.warn_days
+                ));
+            }
+            thresholds
+        };
+        
+        let max_spend_per_day = parse_ether_var("MAX_SPEND_PER_DAY")?;
+
+        let min_interval = match env::var("MIN_INTERVAL_HOURS")
+            .unwrap_or_else(|_| "0".to_string())
+            .parse::<u64>()
+        {
+            Ok(0) => None,
+            Ok(hours) => Some(Duration::from_secs(hours.saturating_mul(3600))),
+            Err(_) => return Err(config_error!("无效的MIN_INTERVAL_HOURS，应为非负整数")),
+        };
+        
+        let balance_check_interval = env::var("BALANCE_CHECK_INTERVAL_SECS")
+            .unwrap_or_else(|_| "3600".to_string())
+            .parse::<u64>()
+            .map(Duration::from_secs)
+            .map_err(|_| config_error!("无效的BALANCE_CHECK_INTERVAL_SECS格式"))?;
+        
+        let dead_man_switch = match env::var("DEAD_MAN_SWITCH_HOURS") {
+            Ok(hours) if !hours.trim().is_empty() => Some(
+                hours
+                    .trim()
+                    .parse::<u64>()
+                    .map(|hours| Duration::from_secs(hours * 3600))
+                    .map_err(|_| config_error!("无效的DEAD_MAN_SWITCH_HOURS格式"))?,
+            ),
+            _ => None,
+        };
+        
+        let last_success_path = env::var("LAST_SUCCESS_PATH")
+            .ok()
+            .filter(|path| !path.trim().is_empty())
+            .map(PathBuf::from);
         
         Ok(Config {
             rpc_url,
-            private_key,
+            rpc_auth_header,
+            rpc_headers,
+            rpc_max_retries,
+            rpc_initial_backoff,
+            rpc_compute_unit_aware,
+            rpc_compute_units_per_second,
+            rpc_requests_per_second,
+            signer,
             contract_address,
+            ens_refresh_on_reload,
             chain_id,
             gas_limit,
             gas_price,
+            max_gas_price,
+            gas_price_fallback,
+            gas_strategy,
+            tx_type,
+            fee_escalation,
+            fee_history,
+            gas_estimation_fallback,
+            gas_estimation_retries,
+            gas_estimate_min,
+            gas_estimate_max,
+            gas_limit_ceiling,
+            simulate_before_send,
+            simulate_as,
+            preflight_policies,
+            reorg_protection,
+            confirmation_strategy,
+            confirmation_poll,
+            verify_distribution,
+            distribution_event_signature,
+            event_verify_grace,
+            log_block_range,
+            multicall,
+            additional_contracts,
+            additional_signers,
+            max_concurrent_distributions,
+            exit_on_distribution_failure,
+            call_selector,
+            recipients,
+            remote_recipients,
+            distribution_mode,
+            merkle_output_path,
+            batch_size,
+            batch_gas_budget,
+            token_balance_check,
+            allowance_check,
+            skip_dates,
+            skip_weekends,
+            schedule_days,
+            holidays_path,
+            schedule_timezone,
+            daily_schedule,
+            schedule_mode,
+            catchup_window,
+            schedule_jitter,
+            run_on_startup,
+            test_mode,
+            test_cron,
+            test_mode_send,
+            run_deadline,
+            job_timeout,
+            shutdown_grace,
+            state_path,
+            chain_targets,
+            pause_file,
+            schedule_once_file,
+            history_capacity,
+            history_path,
+            admin_listen_addr,
+            admin_token,
+            notify_webhook_url,
+            min_balance_warn,
+            min_balance_critical,
+            runway_thresholds,
+            max_spend_per_day,
+            min_interval,
+            balance_check_interval,
+            dead_man_switch,
+            last_success_path,
         })
     }
-}
\ No newline at end of file
+}
+
+impl Config {
+    /// 应用分发目标的节点、链ID、合约地址、Gas参数和状态文件后的配置
+    ///
+    /// 只对主链有意义的配置（RPC请求头、额外合约和签名账户、Multicall、代币余额和授权检查）不会带到目标链。
+    pub fn for_chain_target(&self, target: &ChainTarget) -> Config {
+        let mut config = self.clone();
+        config.rpc_url = target.rpc_url.clone();
+        config.rpc_auth_header = None;
+        config.rpc_headers = Vec::new();
+        config.chain_id = target.chain_id;
+        config.contract_address = ContractAddress::Address(target.contract_address);
+        config.gas_limit = target.gas_limit.unwrap_or(self.gas_limit);
+        config.gas_price = target.gas_price;
+        config.max_gas_price = target.max_gas_price.or(self.max_gas_price);
+        config.tx_type = target.tx_type.unwrap_or(self.tx_type);
+        config.state_path = target.state_path.clone();
+        config.multicall = None;
+        config.additional_contracts = Vec::new();
+        config.additional_signers = Vec::new();
+        config.token_balance_check = None;
+        config.allowance_check = None;
+        config.chain_targets = Vec::new();
+        config
+    }
+}
+
+/// 读取分发目标文件（JSON数组）
+///
+/// 每个目标的状态文件必须互不相同，也不能与 STATE_PATH 相同，否则同一文件会被多个实例同时写入。
+fn read_chain_targets(path: &Path, state_path: &Path) -> Result<Vec<ChainTarget>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| config_error!("读取CHAIN_TARGETS_PATH {} 失败: {}", path.display(), e))?;
+    let entries: Vec<ChainTargetEntry> = serde_json::from_str(&content)
+        .map_err(|e| config_error!("解析CHAIN_TARGETS_PATH {} 失败: {}", path.display(), e))?;
+
+    let mut targets: Vec<ChainTarget> = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let name = entry
+            .name
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(|| format!("chain-{}", entry.chain_id));
+        let gas_limit = entry
+            .gas_limit
+            .map(|value| {
+                let value = value.as_str().map(str::to_string).unwrap_or_else(|| value.to_string());
+                parse_gas_limit(&value)
+            })
+            .transpose()
+            .map_err(|e| config_error!("分发目标 {} 的gas_limit无效: {}", name, e))?;
+        let gas_price = entry
+            .gas_price
+            .map(|price| parse_gas_price(&price))
+            .transpose()
+            .map_err(|e| config_error!("分发目标 {} 的gas_price无效: {}", name, e))?;
+        let max_gas_price = entry
+            .max_gas_price
+            .map(|price| parse_gas_price(&price))
+            .transpose()
+            .map_err(|e| config_error!("分发目标 {} 的max_gas_price无效: {}", name, e))?;
+        let tx_type = entry.tx_type.map(|tx_type| tx_type.parse::<TxType>()).transpose()?;
+        let target_state_path = entry.state_path.unwrap_or_else(|| {
+            let stem = state_path.file_stem().unwrap_or_default().to_string_lossy();
+            let file_name = match state_path.extension() {
+                Some(extension) => format!("{}.{}.{}", stem, entry.chain_id, extension.to_string_lossy()),
+                None => format!("{}.{}", stem, entry.chain_id),
+            };
+            state_path.with_file_name(file_name)
+        });
+
+        if target_state_path == state_path
+            || targets.iter().any(|target| target.state_path == target_state_path)
+        {
+            return Err(config_error!(
+                "分发目标 {} 的状态文件 {} 与其他目标或STATE_PATH重复，请为它设置不同的state_path",
+                name,
+                target_state_path.display()
+            ));
+        }
+        targets.push(ChainTarget {
+            name,
+            rpc_url: entry.rpc_url,
+            chain_id: entry.chain_id,
+            contract_address: entry.contract_address,
+            gas_limit,
+            gas_price,
+            max_gas_price,
+            tx_type,
+            state_path: target_state_path,
+        });
+    }
+    Ok(targets)
+}
+
+/// 读取以ETH为单位的可选金额，返回wei
+fn parse_ether_var(name: &str) -> Result<Option<U256>> {
+    match env::var(name) {
+        Ok(value) if !value.trim().is_empty() => parse_ether(value.trim())
+            .map(Some)
+            .map_err(|_| config_error!("无效的{}格式，应为以ETH为单位的数值", name)),
+        _ => Ok(None),
+    }
+}
+
+/// 解析Gas限制，只接受十进制整数（Gas数量没有单位）
+pub fn parse_gas_limit(value: &str) -> std::result::Result<U256, String> {
+    let value = value.trim();
+    U256::from_dec_str(value).map_err(|_| format!("{}，应为十进制整数，例如 500000", value))
+}
+
+/// 解析带单位的Gas价格，返回wei
+///
+/// 支持 `30gwei`、`0.00000003eth`、`30000000000wei`，不带单位的整数按wei处理。
+pub fn parse_gas_price(value: &str) -> std::result::Result<U256, String> {
+    let value = value.trim().to_lowercase();
+    let unit_start = value
+        .find(|c: char| c.is_ascii_alphabetic())
+        .unwrap_or(value.len());
+    let (amount, unit) = value.split_at(unit_start);
+    let amount = amount.trim();
+
+    let (unit, decimals) = match unit.trim() {
+        "" | "wei" => ("wei", 0),
+        "gwei" => ("gwei", 9),
+        "eth" | "ether" => ("ether", 18),
+        other => {
+            return Err(format!(
+                "{}，未知的单位 {}，可选单位: wei, gwei, eth",
+                value, other
+            ))
+        }
+    };
+    // 不带单位的小数无法判断本意是gwei还是wei，直接拒绝
+    if unit == "wei" && amount.contains('.') {
+        return Err(format!("{}，wei不能有小数，请写明单位，例如 30gwei", value));
+    }
+    // 小数位超过单位精度时会被截断，同样拒绝
+    if amount
+        .split_once('.')
+        .is_some_and(|(_, fraction)| fraction.len() > decimals)
+    {
+        return Err(format!("{}，小数位数超过{}的精度", value, unit));
+    }
+
+    parse_units(amount, unit)
+        .map(Into::into)
+        .map_err(|_| format!("{}，应为数值加单位，例如 30gwei", value))
+}
+
+/// 管理接口请求体中的Gas覆盖参数：与命令行相同的格式，也接受JSON整数
+///
+/// 序列化为十进制字符串，Gas价格按wei输出，解析时同样由 `parse_gas_limit` / `parse_gas_price` 校验。
+pub(crate) mod gas_override {
+    use super::{parse_gas_limit, parse_gas_price};
+    use ethers::types::U256;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Raw {
+        Text(String),
+        Number(u64),
+    }
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<U256>,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(&value.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn gas_limit<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Option<U256>, D::Error> {
+        parse_with(deserializer, parse_gas_limit, "Gas限制")
+    }
+
+    pub fn gas_price<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> std::result::Result<Option<U256>, D::Error> {
+        parse_with(deserializer, parse_gas_price, "Gas价格")
+    }
+
+    fn parse_with<'de, D: Deserializer<'de>>(
+        deserializer: D,
+        parse: fn(&str) -> std::result::Result<U256, String>,
+        name: &str,
+    ) -> std::result::Result<Option<U256>, D::Error> {
+        let value = match Option::<Raw>::deserialize(deserializer)? {
+            None => return Ok(None),
+            Some(Raw::Text(value)) => value,
+            Some(Raw::Number(value)) => value.to_string(),
+        };
+        parse(&value)
+            .map(Some)
+            .map_err(|e| de::Error::custom(format!("无效的{}: {}", name, e)))
+    }
+}
@@ -1,29 +1,315 @@
-use anyhow::Result;
+use crate::batch::BatchResult;
+use crate::config::{
+    gas_override, AllowanceCheck, ConfirmationStrategy, GasStrategy, MulticallConfig,
+    RequiredTokenAmount, TokenBalanceCheck,
+};
+use crate::debug::{decode_receipt_logs, ContractDebugger};
+use crate::error::{DistributorError, ErrorKind, Result};
+use crate::gas::{
+    FeeEscalation, FeeHistoryConfig, FeeMode, FeeModeDetector, GasPriceFallback, LastKnownFees,
+    SpendBudget, TxType,
+};
+use crate::history::ChainDistributionRecord;
+use crate::merkle::MerkleTree;
+use crate::preflight::{PreflightCheck, PreflightPolicies};
+use crate::provider::DistributorClient;
+use crate::recipients::{RecipientList, RecipientsSource};
+use crate::remote_recipients::{encode_root_call, RemoteRecipients};
+use crate::retry::{retry, ConfirmationPoll, RetryPolicy};
+use crate::scheduler::ScheduleTimezone;
+use crate::state::{DistributionRecord, RecordStatus, StateStore, Trigger};
+use chrono::{DateTime, NaiveDate, Utc};
+use ethers::contract::multicall_contract::{Call3, Multicall3};
+use ethers::contract::EthError;
 use ethers::prelude::*;
 use ethers::types::transaction::eip2718::TypedTransaction;
-use std::sync::Arc;
-use tracing::{debug, error, info, warn};
+use ethers::utils::{format_ether, keccak256};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::{Entry, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::sync::mpsc;
+use tracing::field::{debug, display, Empty};
+use tracing::{info, instrument, warn, Span};
 
 abigen!(
     RewardsContractABI,
     r#"[
         function distributeDailyRewards() external
+        function lastDistributionTime() external view returns (uint256)
+        function setDailyRoot(bytes32 root, uint256 totalAmount) external
+        function requiredDailyAmount() external view returns (uint256)
     ]"#
 );
 
+abigen!(
+    ERC20,
+    r#"[
+        function balanceOf(address account) external view returns (uint256)
+        function allowance(address owner, address spender) external view returns (uint256)
+        function approve(address spender, uint256 amount) external returns (bool)
+    ]"#
+);
+
+/// Gas估算失败的原因
+///
+/// 回滚意味着交易发送后必然失败，不值得重试；传输错误则可能是暂时性的。
+#[derive(Debug, Error)]
+pub enum GasEstimationError {
+    /// 节点执行估算时合约回滚
+    #[error("Gas估算时合约执行回滚: {reason}")]
+    Reverted { reason: String },
+    /// RPC请求本身失败（网络、超时、限流等）
+    #[error("Gas估算RPC请求失败: {0}")]
+    Transport(String),
+    /// 节点返回的估算值明显不合理（例如只够一笔普通转账）
+    #[error("Gas估算值 {estimate} 不可信，低于下限 {min}")]
+    Implausible { estimate: U256, min: U256 },
+}
+
+impl GasEstimationError {
+    /// 节点因Gas不足（超过区块或调用的Gas上限）无法完成估算
+    pub fn is_out_of_gas(&self) -> bool {
+        let message = match self {
+            GasEstimationError::Reverted { reason } => reason,
+            GasEstimationError::Transport(message) => message,
+            GasEstimationError::Implausible { .. } => return false,
+        }
+        .to_lowercase();
+        message.contains("out of gas")
+            || message.contains("gas required exceeds")
+            || message.contains("exceeds block gas limit")
+    }
+}
+
+/// 发送前模拟执行（`eth_call`）失败的原因
+#[derive(Debug, Error)]
+pub enum SimulationError {
+    /// 模拟执行时合约回滚，发送后必然失败
+    #[error("模拟执行时合约回滚: {reason}")]
+    Reverted { reason: String },
+    /// RPC请求本身失败（网络、超时、限流等）
+    #[error("模拟执行RPC请求失败: {0}")]
+    Transport(String),
+}
+
+/// 一次分发（发送并确认）的完整结果，可序列化为JSON用于通知和历史记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistributionResult {
+    pub tx_hash: H256,
+    pub block_number: Option<U64>,
+    pub gas_used: Option<U256>,
+    /// 实际生效的Gas价格（EIP-1559交易取收据中的effectiveGasPrice）
+    pub effective_gas_price: Option<U256>,
+    /// 总费用 = gas_used * effective_gas_price，单位wei
+    pub total_fee: Option<U256>,
+    /// 总费用，单位ETH（十进制字符串，便于对账）
+    pub total_fee_eth: Option<String>,
+    pub events: Vec<EmittedEvent>,
+    /// 本次运行的发送尝试次数（接管已有交易时为0）
+    pub attempts: u32,
+    /// 从开始发送到确认的耗时（毫秒）
+    pub elapsed_ms: u64,
+    /// 链上 `lastDistributionTime()` 是否已更新到今天，未开启校验时为 `None`
+    pub verified: Option<bool>,
+    /// 触发这次分发的方式（定时、补执行或启动时执行），定时触发时不输出
+    #[serde(default, skip_serializing_if = "Trigger::is_cron")]
+    pub trigger: Trigger,
+    /// 分批发送时每一批的结果，此时其他字段为所有批次的汇总
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub batches: Vec<BatchResult>,
+}
+
+/// 交易收据中的一条日志
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmittedEvent {
+    pub address: Address,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+}
+
+impl From<&Log> for EmittedEvent {
+    fn from(log: &Log) -> Self {
+        Self {
+            address: log.address,
+            topics: log.topics.clone(),
+            data: log.data.clone(),
+        }
+    }
+}
+
+/// 单次运行超过截止时间
+#[derive(Debug, Error)]
+pub enum DeadlineError {
+    /// 交易已发送但在截止时间前未确认，下次运行会继续等待这笔交易
+    #[error(
+        "运行超过截止时间 {deadline:?}，交易仍在等待确认 (pending past deadline): {tx_hash:?}"
+    )]
+    PendingPastDeadline { deadline: Duration, tx_hash: H256 },
+    /// 截止时间前尚未发送任何交易
+    #[error("运行超过截止时间 {deadline:?}，交易尚未发送")]
+    BeforeSend { deadline: Duration },
+}
+
+/// Gas价格的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum GasPriceSource {
+    /// 配置中的 GAS_PRICE
+    Config,
+    /// 本次运行的 `DistributeOptions` 覆盖
+    Override,
+    /// 节点 `eth_gasPrice` 的建议价格
+    Node,
+    /// 节点不可用时的内置默认价格
+    Default,
+    /// 节点不可用时使用最近一次从节点获取的价格
+    LastKnown,
+}
+
+impl GasPriceSource {
+    /// 是否为节点建议或默认价格（而不是配置或覆盖的固定价格）
+    pub fn is_suggested(&self) -> bool {
+        matches!(
+            self,
+            GasPriceSource::Node | GasPriceSource::Default | GasPriceSource::LastKnown
+        )
+    }
+}
+
+impl std::fmt::Display for GasPriceSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GasPriceSource::Config => write!(f, "配置"),
+            GasPriceSource::Override => write!(f, "本次运行覆盖"),
+            GasPriceSource::Node => write!(f, "节点"),
+            GasPriceSource::Default => write!(f, "默认值"),
+            GasPriceSource::LastKnown => write!(f, "最近一次获取的价格"),
+        }
+    }
+}
+
+/// 单次分发的参数覆盖，未设置的字段使用配置值
+///
+/// 也是管理接口 `POST /distribute` 的请求体，Gas参数与命令行格式相同（如 `"30gwei"`），触发方式由服务端设置。
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DistributeOptions {
+    /// 覆盖Gas限制，设置后不再估算
+    #[serde(
+        serialize_with = "gas_override::serialize",
+        deserialize_with = "gas_override::gas_limit"
+    )]
+    pub gas_limit: Option<U256>,
+    /// 覆盖Gas价格（EIP-1559交易中作为 `maxFeePerGas`）
+    #[serde(
+        serialize_with = "gas_override::serialize",
+        deserialize_with = "gas_override::gas_price"
+    )]
+    pub gas_price: Option<U256>,
+    /// 覆盖EIP-1559小费 `maxPriorityFeePerGas`
+    #[serde(
+        serialize_with = "gas_override::serialize",
+        deserialize_with = "gas_override::gas_price"
+    )]
+    pub priority_fee: Option<U256>,
+    /// 跳过发送前的模拟执行
+    pub skip_simulation: bool,
+    /// 触发这次分发的方式，记录在状态文件和结果中
+    #[serde(skip)]
+    pub trigger: Trigger,
+}
+
+/// 当前生效的费用相关配置
+#[derive(Debug, Clone, Serialize)]
+pub struct ConfiguredFees {
+    pub gas_limit: U256,
+    /// 未配置时为 `None`，发送时使用节点建议价格
+    pub gas_price: Option<U256>,
+    pub chain_id: u64,
+    pub tx_type: TxType,
+}
+
 #[derive(Clone)]
 pub struct RewardsContract {
-    contract: RewardsContractABI<SignerMiddleware<Provider<Http>, LocalWallet>>,
-    pub client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
+    contract: RewardsContractABI<DistributorClient>,
+    pub client: Arc<DistributorClient>,
     gas_limit: U256,
     gas_price: Option<U256>,
+    max_gas_price: Option<U256>,
+    /// 替换交易的加价策略
+    fee_escalation: FeeEscalation,
+    /// 按 `eth_feeHistory` 计算EIP-1559费用，未设置时使用节点的默认估算
+    fee_history: Option<FeeHistoryConfig>,
+    /// 每日Gas花费上限，未设置时不限制
+    spend_budget: Option<SpendBudget>,
+    /// 两次分发之间的最小间隔，未设置时不限制
+    min_interval: Option<Duration>,
+    /// 判断“今天”使用的时区，与调度时区一致
+    schedule_timezone: ScheduleTimezone,
     chain_id: u64,
+    gas_strategy: GasStrategy,
+    gas_estimation_fallback: bool,
+    gas_estimation_retries: u32,
+    gas_estimate_min: Option<U256>,
+    gas_estimate_max: Option<U256>,
+    /// 加缓冲后Gas限制的上限
+    gas_limit_ceiling: Option<U256>,
+    simulate_before_send: bool,
+    /// 模拟执行和Gas估算的 `from` 地址，未设置时使用签名账户
+    simulate_as: Option<Address>,
+    /// 发送前检查（模拟执行、余额）未通过时的处理方式
+    preflight_policies: PreflightPolicies,
+    reorg_protection: bool,
+    confirmation_strategy: ConfirmationStrategy,
+    /// 等待确认时轮询收据的间隔
+    confirmation_poll: ConfirmationPoll,
+    verify_distribution: bool,
+    /// 分发事件的topic0，用于独立校验分发
+    distribution_event: Option<H256>,
+    /// 解析后的分发事件，用于从事件参数中取出金额和接收地址数
+    distribution_event_abi: Option<abi::Event>,
+    /// 单次 `eth_getLogs` 查询的最大区块范围
+    log_block_range: u64,
+    multicall: Option<MulticallConfig>,
+    /// 覆盖分发调用的calldata，设置后不使用内置ABI
+    call_data_override: Option<Bytes>,
+    /// 接收地址文件，设置后调用 `distributeDailyRewards(address[])`
+    recipients: Option<RecipientsSource>,
+    /// 接收地址数据的URL，设置后计算Merkle根并调用 `distributeDailyRewards(bytes32)`
+    remote_recipients: Option<RemoteRecipients>,
+    /// Merkle模式下证明文件的输出路径，设置后调用 `setDailyRoot` 而不是直接分发
+    merkle_output: Option<PathBuf>,
+    /// 每批接收地址数量，未设置时超出Gas预算后按二分自动确定
+    batch_size: Option<usize>,
+    /// 单笔交易的Gas预算，未设置时使用最新区块的Gas上限
+    batch_gas_budget: Option<U256>,
+    /// 发送前检查奖励合约持有的奖励代币是否足够
+    token_balance_check: Option<TokenBalanceCheck>,
+    /// 发送前检查签名账户给奖励合约的授权额度，不足时按配置先发送授权交易
+    allowance_check: Option<AllowanceCheck>,
+    /// 交易类型（legacy/EIP-1559），所有克隆共享自动检测的结果
+    fee_mode: Arc<FeeModeDetector>,
+    /// 无法从节点获取Gas价格时的处理方式
+    gas_price_fallback: GasPriceFallback,
+    /// 最近一次从节点成功获取的费用，所有克隆共享
+    last_known_fees: Arc<LastKnownFees>,
+    /// 已发送但尚未确认的交易，所有克隆共享，避免下次运行重复发送
+    pending_tx: Arc<Mutex<Option<H256>>>,
+    /// 同一签名账户下一个可用的nonce，所有克隆及 `for_address` 创建的实例共享
+    next_nonce: Arc<tokio::sync::Mutex<Option<U256>>>,
+    state: Option<Arc<StateStore>>,
+    retry_policy: RetryPolicy,
+    /// 节点是否支持 `debug_traceTransaction`，首次诊断失败交易时探测
+    trace_supported: Arc<Mutex<Option<bool>>>,
 }
 
 impl RewardsContract {
     pub fn new(
         address: Address,
-        client: Arc<SignerMiddleware<Provider<Http>, LocalWallet>>,
+        client: Arc<DistributorClient>,
         gas_limit: U256,
         gas_price: Option<U256>,
         chain_id: u64,
@@ -35,147 +321,3403 @@ impl RewardsContract {
             client,
             gas_limit,
             gas_price,
+            max_gas_price: None,
+            fee_escalation: FeeEscalation::default(),
+            fee_history: None,
+            spend_budget: None,
+            min_interval: None,
+            schedule_timezone: ScheduleTimezone::Utc,
             chain_id,
+            gas_strategy: GasStrategy::EstimatePlusBuffer,
+            gas_estimation_fallback: false,
+            gas_estimation_retries: 3,
+            gas_estimate_min: None,
+            gas_estimate_max: None,
+            gas_limit_ceiling: None,
+            simulate_before_send: true,
+            simulate_as: None,
+            preflight_policies: PreflightPolicies::default(),
+            reorg_protection: false,
+            confirmation_strategy: ConfirmationStrategy::Inclusion,
+            confirmation_poll: ConfirmationPoll::default(),
+            verify_distribution: false,
+            distribution_event: None,
+            distribution_event_abi: None,
+            log_block_range: DEFAULT_LOG_BLOCK_RANGE,
+            multicall: None,
+            call_data_override: None,
+            recipients: None,
+            remote_recipients: None,
+            merkle_output: None,
+            batch_size: None,
+            batch_gas_budget: None,
+            token_balance_check: None,
+            allowance_check: None,
+            fee_mode: Arc::new(FeeModeDetector::new(TxType::Legacy)),
+            gas_price_fallback: GasPriceFallback::default(),
+            last_known_fees: Arc::new(LastKnownFees::default()),
+            pending_tx: Arc::new(Mutex::new(None)),
+            next_nonce: Arc::new(tokio::sync::Mutex::new(None)),
+            state: None,
+            retry_policy: RetryPolicy::default(),
+            trace_supported: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// 用相同的配置和签名账户创建另一个奖励合约的实例
+    ///
+    /// 新实例与当前实例共享nonce分配，可以安全地并发分发；未确认交易和状态按合约地址分别记录。
+    pub fn for_address(&self, address: Address) -> Self {
+        let mut contract = self.clone();
+        contract.contract = RewardsContractABI::new(address, self.client.clone());
+        contract.pending_tx = Arc::new(Mutex::new(None));
+        let state = contract.state.take();
+        contract.with_state_store(state)
+    }
+
+    /// 用相同的配置和合约地址创建使用另一个签名账户的实例
+    ///
+    /// 新实例使用独立的nonce分配，不同签名账户的交易可以并行发送，互不阻塞。
+    pub fn for_signer(&self, client: Arc<DistributorClient>) -> Self {
+        let mut contract = self.clone();
+        contract.contract = RewardsContractABI::new(self.contract.address(), client.clone());
+        contract.client = client;
+        contract.pending_tx = Arc::new(Mutex::new(None));
+        contract.next_nonce = Arc::new(tokio::sync::Mutex::new(None));
+        let state = contract.state.take();
+        contract.with_state_store(state)
+    }
+
+    /// 通过构建器创建合约实例
+    pub fn builder() -> RewardsContractBuilder {
+        RewardsContractBuilder::default()
+    }
+
+    /// 设置RPC调用的重试策略
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// 设置Gas限制的计算策略
+    pub fn with_gas_strategy(mut self, gas_strategy: GasStrategy) -> Self {
+        self.gas_strategy = gas_strategy;
+        self
+    }
+
+    /// 设置Gas价格上限
+    pub fn with_max_gas_price(mut self, max_gas_price: Option<U256>) -> Self {
+        self.max_gas_price = max_gas_price;
+        self
+    }
+
+    /// 设置替换交易的加价策略
+    pub fn with_fee_escalation(mut self, fee_escalation: FeeEscalation) -> Self {
+        self.fee_escalation = fee_escalation;
+        self
+    }
+
+    /// 按最近若干区块的 `eth_feeHistory` 计算EIP-1559费用，为 `None` 时使用节点的默认估算
+    pub fn with_fee_history(mut self, fee_history: Option<FeeHistoryConfig>) -> Self {
+        self.fee_history = fee_history;
+        self
+    }
+
+    /// 设置每日Gas花费上限，达到上限后当天不再发送交易
+    pub fn with_spend_budget(mut self, spend_budget: Option<SpendBudget>) -> Self {
+        self.spend_budget = spend_budget;
+        self
+    }
+
+    /// 设置两次分发之间的最小间隔，需要状态文件记录上一次分发的时间
+    pub fn with_min_interval(mut self, min_interval: Option<Duration>) -> Self {
+        self.min_interval = min_interval;
+        self
+    }
+
+    /// 设置判断当天是否已分发使用的时区（默认UTC），应与SCHEDULE_TIMEZONE一致
+    pub fn with_schedule_timezone(mut self, timezone: ScheduleTimezone) -> Self {
+        self.schedule_timezone = timezone;
+        self
+    }
+
+    /// 调度时区的今天
+    pub(crate) fn today(&self) -> NaiveDate {
+        self.schedule_timezone.date_of(Utc::now())
+    }
+
+    /// 设置交易类型，`TxType::Auto` 时根据链的最新区块自动选择
+    pub fn with_tx_type(mut self, tx_type: TxType) -> Self {
+        self.fee_mode = Arc::new(FeeModeDetector::new(tx_type));
+        self
+    }
+
+    /// 设置无法从节点获取Gas价格时的处理方式（默认使用内置默认价格）
+    pub fn with_gas_price_fallback(mut self, fallback: GasPriceFallback) -> Self {
+        self.gas_price_fallback = fallback;
+        self
+    }
+
+    /// Gas估算网络错误时是否回退到配置的Gas限制（估算回滚时始终中止）
+    pub fn with_gas_estimation_fallback(mut self, enabled: bool) -> Self {
+        self.gas_estimation_fallback = enabled;
+        self
+    }
+
+    /// Gas估算遇到网络错误时的最大尝试次数（含首次）
+    pub fn with_gas_estimation_retries(mut self, attempts: u32) -> Self {
+        self.gas_estimation_retries = attempts;
+        self
+    }
+
+    /// 设置可信的Gas估算范围，超出范围的估算在 `resolve_gas_limit` 中被拒绝或截断
+    pub fn with_gas_estimate_bounds(mut self, min: Option<U256>, max: Option<U256>) -> Self {
+        self.gas_estimate_min = min;
+        self.gas_estimate_max = max;
+        self
+    }
+
+    /// 设置估算值加缓冲后Gas限制的上限，未设置时不限制
+    pub fn with_gas_limit_ceiling(mut self, ceiling: Option<U256>) -> Self {
+        self.gas_limit_ceiling = ceiling;
+        self
+    }
+
+    /// 发送前是否先用 `eth_call` 模拟执行，模拟回滚时不发送交易
+    pub fn with_simulate_before_send(mut self, enabled: bool) -> Self {
+        self.simulate_before_send = enabled;
+        self
+    }
+
+    /// 以指定地址的身份模拟执行和估算Gas，用于分发权限属于其他地址、签名账户只负责转发的合约
+    pub fn with_simulate_as(mut self, address: Option<Address>) -> Self {
+        self.simulate_as = address;
+        self
+    }
+
+    /// 设置发送前检查未通过时的处理方式，默认都中止分发
+    pub fn with_preflight_policies(mut self, policies: PreflightPolicies) -> Self {
+        self.preflight_policies = policies;
+        self
+    }
+
+    /// 确认前是否核对收据所在区块仍在主链上，防止把重组掉的区块当作已确认
+    pub fn with_reorg_protection(mut self, enabled: bool) -> Self {
+        self.reorg_protection = enabled;
+        self
+    }
+
+    /// 设置等待确认时轮询收据的间隔（默认从1秒开始翻倍，最长15秒）
+    pub fn with_confirmation_poll(mut self, poll: ConfirmationPoll) -> Self {
+        self.confirmation_poll = poll;
+        self
+    }
+
+    /// 设置确认所需的区块数，1 表示收据出现即视为确认
+    pub fn with_confirmations(mut self, confirmations: usize) -> Self {
+        self.confirmation_strategy = match confirmations {
+            0 | 1 => ConfirmationStrategy::Inclusion,
+            n => ConfirmationStrategy::Count(n),
+        };
+        self
+    }
+
+    /// 设置交易确认的判定方式
+    pub fn with_confirmation_strategy(mut self, strategy: ConfirmationStrategy) -> Self {
+        self.confirmation_strategy = strategy;
+        self
+    }
+
+    /// 确认后是否读取 `lastDistributionTime()` 校验链上状态确实已更新
+    pub fn with_verify_distribution(mut self, enabled: bool) -> Self {
+        self.verify_distribution = enabled;
+        self
+    }
+
+    /// 设置分发事件签名，如 `RewardsDistributed(uint256,uint256)`
+    ///
+    /// 也可以带上参数名，如 `RewardsDistributed(uint256 totalAmount, uint256 recipientCount)`，
+    /// 查询链上分发记录时按参数名取出金额和接收地址数。
+    pub fn with_distribution_event(mut self, signature: Option<&str>) -> Self {
+        let event = signature.and_then(parse_distribution_event);
+        self.distribution_event = match &event {
+            Some(event) => Some(event.signature()),
+            None => signature.map(|signature| H256(keccak256(signature.trim()))),
+        };
+        self.distribution_event_abi = event;
+        self
+    }
+
+    /// 设置单次 `eth_getLogs` 查询的最大区块范围
+    pub fn with_log_block_range(mut self, blocks: u64) -> Self {
+        self.log_block_range = blocks.max(1);
+        self
+    }
+
+    /// 通过Multicall3聚合分发多个奖励合约
+    pub fn with_multicall(mut self, multicall: Option<MulticallConfig>) -> Self {
+        self.multicall = multicall;
+        self
+    }
+
+    /// 用原始calldata（函数选择器及编码后的参数）替代内置ABI生成的分发调用
+    pub fn with_call_data(mut self, call_data: Option<Bytes>) -> Self {
+        self.call_data_override = call_data;
+        self
+    }
+
+    /// 从文件读取接收地址列表作为分发调用的参数，每次分发时重新读取
+    pub fn with_recipients(mut self, recipients: Option<RecipientsSource>) -> Self {
+        self.recipients = recipients;
+        self
+    }
+
+    /// 每次分发前从URL获取接收地址和数量，以计算出的Merkle根调用 `distributeDailyRewards(bytes32)`
+    ///
+    /// 同时设置了 `with_merkle_output` 时把证明写入该文件。
+    pub fn with_remote_recipients(mut self, remote: Option<RemoteRecipients>) -> Self {
+        self.remote_recipients = remote;
+        self
+    }
+
+    /// Merkle模式：根据接收地址和数量计算Merkle根，写出证明文件后调用 `setDailyRoot`
+    pub fn with_merkle_output(mut self, output_path: Option<PathBuf>) -> Self {
+        self.merkle_output = output_path;
+        self
+    }
+
+    /// 接收地址列表超出单笔交易Gas预算时的分批方式
+    pub fn with_batching(mut self, batch_size: Option<usize>, gas_budget: Option<U256>) -> Self {
+        self.batch_size = batch_size.filter(|size| *size > 0);
+        self.batch_gas_budget = gas_budget;
+        self
+    }
+
+    /// 发送前检查奖励合约持有的奖励代币是否足够本次分发
+    pub fn with_token_balance_check(mut self, check: Option<TokenBalanceCheck>) -> Self {
+        self.token_balance_check = check;
+        self
+    }
+
+    /// 发送前检查签名账户给每个奖励合约的ERC20授权额度
+    pub fn with_allowance_check(mut self, check: Option<AllowanceCheck>) -> Self {
+        self.allowance_check = check;
+        self
+    }
+
+    /// 使用持久化的状态存储，进程重启后恢复未确认的交易
+    pub fn with_state_store(mut self, state: Option<Arc<StateStore>>) -> Self {
+        if let Some(store) = &state {
+            let record = store.contract(self.contract.address()).last_attempt;
+            if let Some(record) = record.filter(|r| r.status == RecordStatus::Pending) {
+                info!("从状态文件恢复未确认的交易: {:?}", record.tx_hash);
+                self.set_pending_transaction(Some(record.tx_hash));
+            }
         }
+        self.state = state;
+        self
     }
 
     /// 简化的每日奖励分发函数
+    ///
+    /// 今天已经确认过分发时直接返回那笔交易；存在未确认的交易时返回它继续等待，
+    /// 两种情况都不会发送新交易。
     pub async fn distribute_daily_rewards(&self) -> Result<H256> {
+        self.send_or_resume(&DistributeOptions::default())
+            .await
+            .map(|(tx_hash, _)| tx_hash)
+    }
+
+    /// 发送分发交易或接管已有交易，返回交易哈希和发送尝试次数
+    async fn send_or_resume(&self, options: &DistributeOptions) -> Result<(H256, u32)> {
         info!("开始分发每日奖励...");
 
-        // 估算Gas
-        let gas_estimate = self.estimate_gas().await.unwrap_or(self.gas_limit);
-        let gas_with_buffer = gas_estimate * 120 / 100; // 20% buffer
+        self.revalidate_confirmed_today().await;
+        if let Some(tx_hash) = self.confirmed_today() {
+            info!("今天的分发已确认 ({:?})，跳过发送", tx_hash);
+            return Ok((tx_hash, 0));
+        }
+
+        if let Some(tx_hash) = self.resumable_pending_transaction().await? {
+            return Ok((tx_hash, 0));
+        }
 
-        info!("使用Gas限制: {}", gas_with_buffer);
+        if let Some(tx_hash) = self.recover_pending_distribution(options.trigger).await? {
+            return Ok((tx_hash, 0));
+        }
 
-        // 构建并发送交易
-        let tx_request = self.build_transaction(gas_with_buffer).await?;
+        let (to, call_data) = self.distribution_call().await?;
+        self.send_call(to, call_data, options, true).await
+    }
 
-        info!("发送交易到网络...");
-        let pending_tx = self.client.send_transaction(tx_request, None).await?;
-        let tx_hash = pending_tx.tx_hash();
+    /// 检查签名账户给每个奖励合约的授权额度 `allowance(签名账户, 奖励合约)`
+    ///
+    /// 低于MIN_ALLOWANCE时，开启AUTO_APPROVE则先发送 `approve` 并等待确认，否则只记录警告，
+    /// 由之后的模拟执行或发送暴露回滚。未配置授权代币时直接通过。
+    pub async fn check_allowance(&self) -> Result<()> {
+        let Some(check) = &self.allowance_check else {
+            return Ok(());
+        };
+        let token = ERC20::new(check.token, self.client.clone());
+        let owner = self.client.address();
 
-        info!("交易已发送，哈希: {:?}", tx_hash);
+        for spender in self.reward_contracts() {
+            let allowance = token
+                .allowance(owner, spender)
+                .call()
+                .await
+                .map_err(|e| contract_call_error("读取授权额度", e))?;
+            if allowance >= check.min_allowance {
+                info!("奖励合约 {:?} 的授权额度: {}", spender, allowance);
+                continue;
+            }
+            if !check.auto_approve {
+                warn!(
+                    "签名账户 {:?} 给奖励合约 {:?} 的代币 {:?} 授权额度 {} 低于 {}，分发可能回滚；开启AUTO_APPROVE可自动授权",
+                    owner, spender, check.token, allowance, check.min_allowance
+                );
+                continue;
+            }
 
-        Ok(tx_hash)
+            warn!(
+                "奖励合约 {:?} 的授权额度 {} 低于 {}，发送授权交易 approve({})",
+                spender, allowance, check.min_allowance, check.approval_amount
+            );
+            // 持有nonce锁发送，避免与分发交易使用相同的nonce；发送后让分发交易重新从节点获取nonce
+            let mut next_nonce = self.lock_nonce().await;
+            let call = token.approve(spender, check.approval_amount);
+            let pending = call
+                .send()
+                .await
+                .map_err(|e| contract_call_error("发送授权交易", e))?;
+            let tx_hash = pending.tx_hash();
+            let receipt = pending.await.map_err(|e| {
+                DistributorError::Other(anyhow::anyhow!(
+                    "等待授权交易 {:?} 确认失败: {}",
+                    tx_hash,
+                    e
+                ))
+            })?;
+            *next_nonce = None;
+            drop(next_nonce);
+            match receipt {
+                Some(receipt) if receipt.status == Some(U64::from(1)) => {
+                    info!(
+                        "授权交易已确认: {:?}，区块号: {:?}",
+                        tx_hash, receipt.block_number
+                    );
+                }
+                Some(receipt) => {
+                    return Err(DistributorError::TransactionFailed {
+                        tx_hash,
+                        block_number: receipt.block_number,
+                        trace: None,
+                    })
+                }
+                None => {
+                    return Err(DistributorError::Other(anyhow::anyhow!(
+                        "授权交易 {:?} 已从交易池中消失",
+                        tx_hash
+                    )))
+                }
+            }
+        }
+        Ok(())
     }
 
-    /// 构建交易
-    async fn build_transaction(&self, gas_limit: U256) -> Result<TransactionRequest> {
-        let call_data = self
-            .contract
-            .distribute_daily_rewards()
-            .calldata()
-            .ok_or_else(|| anyhow::anyhow!("无法生成调用数据"))?;
+    /// 检查每个奖励合约持有的奖励代币是否达到所需数量，不足时返回 `InsufficientRewardTokens`
+    ///
+    /// 所需数量为配置的固定值，或读取奖励合约的 `requiredDailyAmount()`。未配置奖励代币时直接通过。
+    pub async fn check_token_balance(&self) -> Result<()> {
+        let Some(check) = &self.token_balance_check else {
+            return Ok(());
+        };
+        let token = ERC20::new(check.token, self.client.clone());
 
-        let nonce = self
-            .client
-            .get_transaction_count(self.client.address(), None)
-            .await?;
+        for contract in self.reward_contracts() {
+            let need = match check.required {
+                RequiredTokenAmount::Fixed(amount) => amount,
+                RequiredTokenAmount::ContractView => {
+                    RewardsContractABI::new(contract, self.client.clone())
+                        .required_daily_amount()
+                        .call()
+                        .await
+                        .map_err(|e| contract_call_error("读取requiredDailyAmount", e))?
+                }
+            };
+            let have = token
+                .balance_of(contract)
+                .call()
+                .await
+                .map_err(|e| contract_call_error("读取奖励代币余额", e))?;
+
+            if have < need {
+                warn!(
+                    "奖励合约 {:?} 的代币余额不足: 持有 {}，需要 {}",
+                    contract, have, need
+                );
+                return Err(DistributorError::InsufficientRewardTokens {
+                    contract,
+                    token: check.token,
+                    have,
+                    need,
+                });
+            }
+            info!(
+                "奖励合约 {:?} 的代币余额: {}，需要 {}",
+                contract, have, need
+            );
+        }
 
-        let gas_price = self.get_gas_price().await?;
+        Ok(())
+    }
 
-        let tx_request = TransactionRequest {
-            to: Some(self.contract.address().into()),
-            value: Some(U256::zero()),
-            gas: Some(gas_limit),
-            gas_price: Some(gas_price),
-            data: Some(call_data),
-            nonce: Some(nonce),
-            chain_id: Some(self.chain_id.into()),
-            ..Default::default()
+    /// 确定Gas限制、通过发送前检查后发送调用，返回交易哈希和发送尝试次数
+    ///
+    /// `check_tokens` 为false时不检查奖励代币余额（分批发送时只在第一批之前检查）。
+    pub(crate) async fn send_call(
+        &self,
+        to: Address,
+        call_data: Bytes,
+        options: &DistributeOptions,
+        check_tokens: bool,
+    ) -> Result<(H256, u32)> {
+        // 所有触发方式最终都在这里发送新的分发，check_tokens为true时是一次新分发的第一笔交易
+        if check_tokens {
+            self.check_min_interval()?;
+        }
+
+        let gas_limit = match options.gas_limit {
+            Some(gas_limit) => {
+                info!("使用Gas限制: {} (本次运行覆盖)", gas_limit);
+                gas_limit
+            }
+            None => {
+                let gas_limit = self.resolve_gas_limit(to, call_data.clone()).await?;
+                info!("使用Gas限制: {} (策略: {:?})", gas_limit, self.gas_strategy);
+                gas_limit
+            }
         };
 
-        Ok(tx_request)
+        self.run_send_checks(to, &call_data, gas_limit, options, check_tokens)
+            .await?;
+        self.check_spend_budget(gas_limit, options).await?;
+
+        // 构建并发送交易，每次尝试都重新获取nonce
+        let started = Instant::now();
+        let attempt = AtomicU32::new(0);
+        let (tx_hash, attempts) = retry(&self.retry_policy, "发送分发交易", || async {
+            // 上一次尝试可能已被节点收到（例如发送超时），重试前先接管内存池中等价的交易
+            if attempt.fetch_add(1, Ordering::SeqCst) > 0 && self.has_pending_nonce().await? {
+                if let Some(tx) = self.find_pending_call(to, &call_data).await? {
+                    return Ok(self.adopt_pending(tx, options.trigger));
+                }
+            }
+            self.send_transaction(to, call_data.clone(), gas_limit, options, started)
+                .await
+        })
+        .await?;
+
+        info!("交易已发送，哈希: {:?}", tx_hash);
+
+        Ok((tx_hash, attempts))
     }
 
-    /// Gas估算
-    async fn estimate_gas(&self) -> Result<U256> {
-        let call_data = self
-            .contract
-            .distribute_daily_rewards()
-            .calldata()
-            .ok_or_else(|| anyhow::anyhow!("无法生成调用数据"))?;
+    /// 发送前检查流水线：先模拟执行，再检查余额，都通过（或按配置继续）后才发送
+    ///
+    /// 每一步未通过时按 `PreflightPolicies` 中各自的处理方式中止、跳过或继续。
+    async fn run_send_checks(
+        &self,
+        to: Address,
+        call_data: &Bytes,
+        gas_limit: U256,
+        options: &DistributeOptions,
+        check_tokens: bool,
+    ) -> Result<()> {
+        // 授权不足时分发必然回滚，先补足授权再模拟执行
+        if check_tokens {
+            self.check_allowance().await?;
+        }
 
-        let tx_request = TransactionRequest {
-            to: Some(self.contract.address().into()),
-            data: Some(call_data),
-            from: Some(self.client.address()),
-            ..Default::default()
-        };
+        if self.simulate_before_send && !options.skip_simulation {
+            match self.simulate_call(to, call_data.clone(), gas_limit).await {
+                Ok(()) => info!("发送前模拟执行成功"),
+                Err(SimulationError::Reverted { reason }) => {
+                    warn!("发送前模拟执行回滚: {}", reason);
+                    self.preflight_policies.on_simulation_revert.apply(
+                        PreflightCheck::Simulation,
+                        DistributorError::Reverted {
+                            reason: Some(reason),
+                            source: None,
+                        },
+                    )?;
+                }
+                // 模拟请求本身失败不代表交易会回滚，交给发送步骤的重试处理
+                Err(e) => warn!("{}，继续发送", e),
+            }
+        }
 
-        let typed_tx: TypedTransaction = tx_request.into();
-        match self.client.estimate_gas(&typed_tx, None).await {
-            Ok(gas) => Ok(gas),
-            Err(e) => {
-                warn!("Gas估算失败: {}", e);
-                Ok(self.gas_limit)
+        let mut balance_checks = vec![self.check_gas_funds(gas_limit, options).await];
+        if check_tokens {
+            balance_checks.push(self.check_token_balance().await);
+        }
+        for result in balance_checks {
+            match result {
+                Err(
+                    e @ (DistributorError::InsufficientGasFunds { .. }
+                    | DistributorError::InsufficientRewardTokens { .. }),
+                ) => self
+                    .preflight_policies
+                    .on_insufficient_balance
+                    .apply(PreflightCheck::Balance, e)?,
+                result => result?,
             }
         }
+        Ok(())
     }
 
-    /// 获取Gas价格
-    async fn get_gas_price(&self) -> Result<U256> {
-        if let Some(price) = self.gas_price {
-            return Ok(price);
+    /// 本次交易最多花费的Gas费用：Gas限制 × Gas价格（不超过上限）
+    pub(crate) async fn max_gas_cost(
+        &self,
+        gas_limit: U256,
+        options: &DistributeOptions,
+    ) -> Result<U256> {
+        let (mut gas_price, _) = self.get_gas_price(options).await?;
+        if let Some(cap) = self.max_gas_price {
+            gas_price = gas_price.min(cap);
         }
+        Ok(gas_limit.saturating_mul(gas_price))
+    }
 
-        match self.client.get_gas_price().await {
-            Ok(network_price) => Ok(network_price),
-            Err(_) => {
-                let default_price = ethers::utils::parse_units("30", "gwei")?;
-                Ok(default_price
-                    .try_into()
-                    .map_err(|_| anyhow::anyhow!("Gas价格转换失败"))?)
+    /// 距离状态文件中最近一次成功分发不足最小间隔时返回 `TooSoon`
+    fn check_min_interval(&self) -> Result<()> {
+        let (Some(interval), Some(store)) = (self.min_interval, &self.state) else {
+            return Ok(());
+        };
+        let Some(last) = store.last_distribution(self.contract.address()) else {
+            return Ok(());
+        };
+        let next = chrono::Duration::from_std(interval)
+            .ok()
+            .and_then(|interval| last.checked_add_signed(interval))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC);
+        if Utc::now() < next {
+            warn!(
+                "距离上一次分发 ({}) 不足最小间隔，{} 之前不会再次分发",
+                last, next
+            );
+            return Err(DistributorError::TooSoon { last, next });
+        }
+        Ok(())
+    }
+
+    /// 检查当天已花费的Gas加上本次最多花费是否超过每日上限
+    async fn check_spend_budget(&self, gas_limit: U256, options: &DistributeOptions) -> Result<()> {
+        let (Some(budget), Some(store)) = (&self.spend_budget, &self.state) else {
+            return Ok(());
+        };
+        let spent = store.spent_on(budget.timezone.date_of(Utc::now()));
+        let need = self.max_gas_cost(gas_limit, options).await?;
+        if spent.saturating_add(need) > budget.max_per_day {
+            return Err(DistributorError::SpendBudgetExceeded {
+                spent,
+                need,
+                budget: budget.max_per_day,
+            });
+        }
+        info!(
+            "今天已花费Gas {} wei，本次最多 {} wei，每日上限 {} wei",
+            spent, need, budget.max_per_day
+        );
+        Ok(())
+    }
+
+    /// 把已上链交易的Gas花费计入当天的累计，用于每日花费上限
+    fn record_spend(&self, receipt: &TransactionReceipt) {
+        let (Some(budget), Some(store)) = (&self.spend_budget, &self.state) else {
+            return;
+        };
+        let Some(cost) = receipt_fee(receipt) else {
+            return;
+        };
+        let date = budget.timezone.date_of(Utc::now());
+        match store.record_spend(date, receipt.transaction_hash, cost) {
+            Ok(total) => info!(
+                "今天累计Gas花费 {} wei (每日上限 {} wei)",
+                total, budget.max_per_day
+            ),
+            Err(e) => warn!("写入状态文件失败: {}", e),
+        }
+    }
+
+    /// 检查签名账户的余额是否足以支付 Gas限制 × Gas价格，查询失败时只记录警告
+    async fn check_gas_funds(&self, gas_limit: U256, options: &DistributeOptions) -> Result<()> {
+        let need = self.max_gas_cost(gas_limit, options).await?;
+        let account = self.client.address();
+        let have = match self.client.get_balance(account, None).await {
+            Ok(balance) => balance,
+            Err(e) => {
+                warn!("查询签名账户余额失败: {}，跳过Gas费用检查", e);
+                return Ok(());
             }
+        };
+        if have < need {
+            return Err(DistributorError::InsufficientGasFunds {
+                account,
+                have,
+                need,
+            });
         }
+        Ok(())
     }
 
-    /// 等待交易确认
-    pub async fn wait_for_confirmation(&self, tx_hash: H256) -> Result<TransactionReceipt> {
-        info!("等待交易确认: {:?}", tx_hash);
+    /// 构建并发送一次交易，成功后记录为未确认交易
+    async fn send_transaction(
+        &self,
+        to: Address,
+        call_data: Bytes,
+        gas_limit: U256,
+        options: &DistributeOptions,
+        started: Instant,
+    ) -> Result<H256, DistributorError> {
+        // 同一签名账户的发送串行执行：持锁期间分配nonce并发送，并发分发多个合约时不会使用相同的nonce
+        let mut next_nonce = self.next_nonce.lock().await;
+        let mut tx_request = self
+            .build_transaction(to, call_data, gas_limit, options, *next_nonce)
+            .await?;
+        tx_request.set_from(self.client.address());
+        let nonce = tx_request.nonce().copied();
 
-        let timeout = std::time::Duration::from_secs(300); // 5分钟超时
-        let start_time = std::time::Instant::now();
+        let result = self.send_signed(tx_request, started).await;
+        *next_nonce = match &result {
+            Ok(_) => nonce.map(|nonce| nonce + 1),
+            // nonce冲突时下次重新从节点获取
+            Err(e) if matches!(e.kind(), ErrorKind::NonceTooLow | ErrorKind::NonceConflict) => None,
+            // 其他失败时nonce没有被使用，保持不变
+            Err(_) => *next_nonce,
+        };
+        drop(next_nonce);
+        let tx_hash = result?;
+        self.set_pending_transaction(Some(tx_hash));
 
-        loop {
-            if start_time.elapsed() > timeout {
-                return Err(anyhow::anyhow!("交易确认超时"));
+        if let Some(store) = &self.state {
+            let record = DistributionRecord {
+                tx_hash,
+                nonce,
+                timestamp: Utc::now(),
+                status: RecordStatus::Pending,
+                trigger: options.trigger,
+                block_hash: None,
+                verified: None,
+                gas_used: None,
+                fee: None,
+            };
+            if let Err(e) = store.record_attempt(self.contract.address(), record) {
+                warn!("写入状态文件失败: {}", e);
             }
+        }
 
-            match self.client.get_transaction_receipt(tx_hash).await? {
-                Some(receipt) => {
-                    if receipt.status == Some(U64::from(1)) {
-                        info!("交易执行成功");
-                    } else {
-                        warn!("交易执行失败");
+        Ok(tx_hash)
+    }
+
+    /// 本地签名并发送交易，处理节点返回的已知交易、替换加价不足和nonce过低
+    ///
+    /// `started` 为本次发送开始的时间，时间曲线按它计算加价比例。
+    #[instrument(
+        name = "send",
+        skip_all,
+        fields(nonce = ?tx_request.nonce(), replacements = Empty, tx_hash = Empty)
+    )]
+    pub(crate) async fn send_signed(
+        &self,
+        mut tx_request: TypedTransaction,
+        started: Instant,
+    ) -> Result<H256> {
+        info!("发送交易到网络...");
+        let mut replacements = 0;
+        let tx_hash = loop {
+            // 本地签名，发送失败时也能知道交易哈希
+            let signature = self.client.signer().sign_transaction(&tx_request).await?;
+            let raw_tx = tx_request.rlp_signed(&signature);
+            let tx_hash = H256(keccak256(&raw_tx));
+
+            let err = match self.client.send_raw_transaction(raw_tx).await {
+                Ok(pending_tx) => break pending_tx.tx_hash(),
+                Err(e) => DistributorError::rpc("发送交易", e),
+            };
+            match err.kind() {
+                ErrorKind::AlreadyKnown => {
+                    info!("节点已收到这笔交易 {:?}，改为等待它确认", tx_hash);
+                    break tx_hash;
+                }
+                ErrorKind::ReplacementUnderpriced
+                    if replacements < self.fee_escalation.max_bumps =>
+                {
+                    replacements += 1;
+                    self.bump_replacement_fees(&mut tx_request, started.elapsed())?;
+                }
+                ErrorKind::NonceTooLow => {
+                    // nonce已被使用：先确认是不是我们上一笔交易已经打包，否则交给重试重新获取nonce
+                    if let Some(tx_hash) = self.mined_pending_transaction().await {
+                        info!("nonce已被使用，上一笔分发交易 {:?} 已打包", tx_hash);
+                        return Ok(tx_hash);
                     }
-                    return Ok(receipt);
+                    return Err(err);
                 }
-                None => {
-                    tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                ErrorKind::UnsupportedTxType => {
+                    // 链可能已升级或切换了节点，下次构建交易时重新检测
+                    self.fee_mode.invalidate();
+                    return Err(err);
                 }
+                _ => return Err(err),
             }
-        }
+        };
+
+        Span::current().record("replacements", replacements);
+        Span::current().record("tx_hash", debug(tx_hash));
+        Ok(tx_hash)
     }
 
-    /// 访问器方法
-    pub fn client_address(&self) -> Address {
-        self.client.address()
+    /// 按加价策略提高交易费用，超过费用上限时中止
+    fn bump_replacement_fees(&self, tx: &mut TypedTransaction, elapsed: Duration) -> Result<()> {
+        let percent = self.fee_escalation.bump_percent_at(elapsed);
+        let bump = |fee: U256| self.fee_escalation.bump(fee, percent);
+        let fee = match tx {
+            TypedTransaction::Eip1559(inner) => {
+                // 替换1559交易时maxFeePerGas和maxPriorityFeePerGas都需要加价
+                inner.max_priority_fee_per_gas = inner.max_priority_fee_per_gas.map(bump);
+                inner.max_fee_per_gas = inner.max_fee_per_gas.map(bump);
+                inner.max_fee_per_gas
+            }
+            _ => {
+                let gas_price = tx.gas_price().map(bump);
+                if let Some(gas_price) = gas_price {
+                    tx.set_gas_price(gas_price);
+                }
+                gas_price
+            }
+        }
+        .unwrap_or_default();
+
+        let cap = self.fee_escalation.max_price.or(self.max_gas_price);
+        if let Some(cap) = cap.filter(|cap| fee > *cap) {
+            return Err(DistributorError::GasPriceExceedsCap { price: fee, cap });
+        }
+        warn!(
+            "替换交易加价不足，费用提高{}%后重新发送: {} gwei",
+            percent,
+            ethers::utils::format_units(fee, "gwei").unwrap_or_default()
+        );
+        Ok(())
     }
 
-    pub fn contract_address(&self) -> Address {
-        self.contract.address()
+    /// 上一笔分发交易（如果有）已经打包时返回它的哈希
+    async fn mined_pending_transaction(&self) -> Option<H256> {
+        let tx_hash = self.pending_transaction()?;
+        match self.client.get_transaction_receipt(tx_hash).await {
+            Ok(receipt) => receipt.map(|_| tx_hash),
+            Err(e) => {
+                warn!("查询上一笔分发交易的收据失败: {}", e);
+                None
+            }
+        }
     }
 
-    pub fn inner_contract(
-        &self,
-    ) -> &RewardsContractABI<SignerMiddleware<Provider<Http>, LocalWallet>> {
-        &self.contract
+    /// 按配置的Gas策略确定交易的Gas限制
+    async fn resolve_gas_limit(&self, to: Address, call_data: Bytes) -> Result<U256> {
+        if self.gas_strategy == GasStrategy::Fixed {
+            return Ok(self.gas_limit);
+        }
+
+        // 网络错误按重试次数退避重试；回滚说明交易必然失败，直接中止
+        let policy = RetryPolicy {
+            max_attempts: self.gas_estimation_retries.max(1),
+            ..self.retry_policy.clone()
+        };
+        let estimate = retry(&policy, "Gas估算", || async {
+            self.estimate_gas(to, call_data.clone())
+                .await
+                .map_err(DistributorError::from)
+        })
+        .await
+        .and_then(|(gas, _)| self.check_gas_estimate(gas).map_err(DistributorError::from));
+
+        // 重试后仍失败或估算不可信时默认中止，只有显式开启回退才使用配置的Gas限制
+        let gas_estimate = match estimate {
+            Ok(gas) => gas,
+            Err(e @ DistributorError::GasEstimation(GasEstimationError::Reverted { .. })) => {
+                return Err(e)
+            }
+            Err(e) if self.gas_estimation_fallback => {
+                warn!(
+                    "{}，已开启GAS_ESTIMATION_FALLBACK，改用配置的Gas限制 {}，交易可能会回滚",
+                    e, self.gas_limit
+                );
+                return Ok(self.gas_limit);
+            }
+            Err(e) => return Err(e),
+        };
+
+        Ok(self.capped_gas_limit(gas_estimate))
     }
 
-    pub fn gas_limit(&self) -> U256 {
-        self.gas_limit
+    /// 核对节点返回的估算值是否可信
+    ///
+    /// 分发调用带有calldata并执行合约代码，消耗必然超过普通转账的21000；
+    /// 低于下限时返回 `Implausible`，超过上限时截断到上限。
+    fn check_gas_estimate(&self, estimate: U256) -> Result<U256, GasEstimationError> {
+        let min = self
+            .gas_estimate_min
+            .unwrap_or_default()
+            .max(U256::from(INTRINSIC_GAS + 1));
+        if estimate < min {
+            warn!("节点返回的Gas估算值 {} 低于可信下限 {}", estimate, min);
+            return Err(GasEstimationError::Implausible { estimate, min });
+        }
+
+        match self.gas_estimate_max {
+            Some(max) if estimate > max => {
+                warn!(
+                    "节点返回的Gas估算值 {} 超过可信上限 {}，按上限发送",
+                    estimate, max
+                );
+                Ok(max)
+            }
+            _ => Ok(estimate),
+        }
+    }
+
+    /// 按Gas策略给估算值加缓冲，结果不低于估算值
+    ///
+    /// 不截断到GAS_LIMIT_CEILING，批次大小按它判断是否能放进Gas预算。
+    pub(crate) fn buffered_gas(&self, gas_estimate: U256) -> U256 {
+        match self.gas_strategy {
+            GasStrategy::EstimateExact => gas_estimate,
+            // 20% buffer，向上取整；不先乘120，异常大的估算值只会饱和而不会溢出
+            _ => {
+                let (fifth, remainder) = gas_estimate.div_mod(U256::from(5));
+                let buffer = if remainder.is_zero() {
+                    fifth
+                } else {
+                    fifth + 1
+                };
+                gas_estimate.saturating_add(buffer)
+            }
+        }
+    }
+
+    /// 交易使用的Gas限制：加缓冲后超过GAS_LIMIT_CEILING时截断到上限
+    pub(crate) fn capped_gas_limit(&self, gas_estimate: U256) -> U256 {
+        let buffered = self.buffered_gas(gas_estimate);
+        match self.gas_limit_ceiling {
+            Some(ceiling) if buffered > ceiling => {
+                if gas_estimate > ceiling {
+                    warn!(
+                        "Gas估算值 {} 超过GAS_LIMIT_CEILING {}，按上限发送，交易可能因Gas不足失败",
+                        gas_estimate, ceiling
+                    );
+                } else {
+                    warn!(
+                        "加缓冲后的Gas限制 {} 超过GAS_LIMIT_CEILING，按上限 {} 发送",
+                        buffered, ceiling
+                    );
+                }
+                ceiling
+            }
+            _ => buffered,
+        }
+    }
+
+    /// 发送分发交易并等待确认，返回结构化的结果
+    ///
+    /// 如果上次运行留下了仍在节点中的未确认交易，直接等待它而不重新发送。
+    pub async fn distribute_and_confirm(&self) -> Result<DistributionResult> {
+        self.distribute_with_options(DistributeOptions::default())
+            .await
+    }
+
+    /// 使用单次覆盖的参数发送分发交易并等待确认
+    ///
+    /// 用于临时调整Gas限制或价格而不重启服务；定时任务始终使用配置值。
+    /// 今天已分发或存在未确认的交易时不会发送新交易，覆盖参数也不会生效。
+    #[instrument(
+        name = "distribute",
+        skip_all,
+        fields(contract = ?self.contract.address(), trigger = %options.trigger)
+    )]
+    pub async fn distribute_with_options(
+        &self,
+        options: DistributeOptions,
+    ) -> Result<DistributionResult> {
+        let start_time = Instant::now();
+        let trigger = options.trigger;
+        let result = self.run_distribution(options).await;
+        self.log_summary(trigger, result.as_ref(), start_time.elapsed());
+        result
+    }
+
+    /// 单次分发的完整流程，由 `distribute_with_options` 在结束后输出摘要
+    async fn run_distribution(&self, options: DistributeOptions) -> Result<DistributionResult> {
+        self.validate_options(&options).await?;
+        let start_time = Instant::now();
+
+        // 接收地址列表超出单笔交易的Gas预算时分批发送
+        if let Some(plan) = self.plan_batches().await? {
+            let mut result = self.distribute_batches(plan, &options, start_time).await?;
+            result.verified = self.verify_if_enabled(result.block_number).await;
+            return Ok(result);
+        }
+
+        let (tx_hash, attempts) = self.send_or_resume(&options).await?;
+        let receipt = self.wait_for_confirmation(tx_hash).await?;
+
+        if receipt.status != Some(U64::from(1)) {
+            self.log_receipt_logs(&receipt);
+            let trace = match ContractDebugger::new(self.clone())
+                .trace_failure(tx_hash)
+                .await
+            {
+                Ok(trace) => {
+                    warn!("失败交易诊断 {:?}: {}", tx_hash, trace);
+                    Some(Box::new(trace))
+                }
+                Err(e) => {
+                    warn!("无法诊断失败交易 {:?}: {}", tx_hash, e);
+                    None
+                }
+            };
+            return Err(DistributorError::TransactionFailed {
+                tx_hash,
+                block_number: receipt.block_number,
+                trace,
+            });
+        }
+
+        let effective_gas_price = receipt.effective_gas_price;
+        let total_fee = receipt_fee(&receipt);
+
+        let verified = self.verify_if_enabled(receipt.block_number).await;
+        if verified == Some(false) {
+            self.log_receipt_logs(&receipt);
+        }
+        if let Some(store) = &self.state {
+            if let Err(e) = store.record_verification(
+                self.contract.address(),
+                tx_hash,
+                receipt.block_hash,
+                verified,
+            ) {
+                warn!("写入状态文件失败: {}", e);
+            }
+        }
+
+        Ok(DistributionResult {
+            tx_hash,
+            block_number: receipt.block_number,
+            gas_used: receipt.gas_used,
+            effective_gas_price,
+            total_fee,
+            total_fee_eth: total_fee.map(format_ether),
+            events: receipt.logs.iter().map(EmittedEvent::from).collect(),
+            attempts,
+            elapsed_ms: start_time.elapsed().as_millis() as u64,
+            verified,
+            trigger: options.trigger,
+            batches: Vec::new(),
+        })
+    }
+
+    /// 每次分发结束时在 `distribution_summary` 目标下输出一行结构化摘要，成功和失败都输出，供日志系统解析
+    fn log_summary(
+        &self,
+        trigger: Trigger,
+        result: std::result::Result<&DistributionResult, &DistributorError>,
+        elapsed: Duration,
+    ) {
+        let contract = self.contract.address();
+        let duration_ms = elapsed.as_millis() as u64;
+        match result {
+            Ok(result) => info!(
+                target: "distribution_summary",
+                status = "success",
+                contract = ?contract,
+                chain_id = self.chain_id,
+                trigger = %trigger,
+                tx_hash = ?result.tx_hash,
+                block = result.block_number.map(|block| block.as_u64()),
+                gas_used = result.gas_used.map(|gas| gas.low_u64()),
+                cost_eth = result.total_fee_eth.as_deref(),
+                duration_ms,
+                "分发结束"
+            ),
+            Err(e) => {
+                let (status, tx_hash) = match e {
+                    DistributorError::Deadline(DeadlineError::PendingPastDeadline {
+                        tx_hash,
+                        ..
+                    }) => ("timeout", Some(*tx_hash)),
+                    DistributorError::Deadline(DeadlineError::BeforeSend { .. }) => {
+                        ("timeout", None)
+                    }
+                    DistributorError::TransactionFailed { tx_hash, .. } => {
+                        ("failed", Some(*tx_hash))
+                    }
+                    e if e.is_skipped() => ("skipped", None),
+                    _ => ("failed", self.pending_transaction()),
+                };
+                let block = match e {
+                    DistributorError::TransactionFailed { block_number, .. } => {
+                        block_number.map(|block| block.as_u64())
+                    }
+                    _ => None,
+                };
+                info!(
+                    target: "distribution_summary",
+                    status,
+                    contract = ?contract,
+                    chain_id = self.chain_id,
+                    trigger = %trigger,
+                    tx_hash = tx_hash.map(debug),
+                    block,
+                    duration_ms,
+                    error = %e,
+                    "分发结束"
+                )
+            }
+        }
+    }
+
+    /// 结果异常时输出解码后的收据日志，便于排查合约实际做了什么
+    fn log_receipt_logs(&self, receipt: &TransactionReceipt) {
+        let logs = decode_receipt_logs(receipt, &self.event_abis());
+        if logs.is_empty() {
+            warn!("交易 {:?} 没有产生日志", receipt.transaction_hash);
+        }
+        for log in logs {
+            warn!("交易日志 {}", log);
+        }
+    }
+
+    /// 开启校验时读取 `lastDistributionTime()`，读取失败时只记录警告
+    async fn verify_if_enabled(&self, block: Option<U64>) -> Option<bool> {
+        if !self.verify_distribution {
+            return None;
+        }
+        match self.verify_last_distribution(block).await {
+            Ok(verified) => Some(verified),
+            Err(e) => {
+                warn!("读取lastDistributionTime失败，无法校验分发结果: {}", e);
+                None
+            }
+        }
+    }
+
+    /// 校验每个奖励合约的 `lastDistributionTime()` 已更新到调度时区的今天
+    ///
+    /// 交易成功不代表合约真的执行了分发（例如合约内部判断后直接返回），
+    /// 未更新时记录警告并返回 `false`。
+    pub async fn verify_last_distribution(&self, block: Option<U64>) -> Result<bool> {
+        let targets = self.reward_contracts();
+        let today = self.today();
+
+        let mut verified = true;
+        for target in targets {
+            let mut call =
+                RewardsContractABI::new(target, self.client.clone()).last_distribution_time();
+            if let Some(block) = block {
+                call = call.block(block);
+            }
+            let timestamp = call
+                .call()
+                .await
+                .map_err(|e| contract_call_error("读取lastDistributionTime", e))?;
+
+            let date = i64::try_from(timestamp)
+                .ok()
+                .and_then(|secs| chrono::DateTime::from_timestamp(secs, 0))
+                .map(|time| self.schedule_timezone.date_of(time));
+            if date == Some(today) {
+                info!(
+                    "合约 {:?} 的lastDistributionTime已更新: {}",
+                    target, timestamp
+                );
+            } else {
+                warn!(
+                    "交易成功但合约 {:?} 的lastDistributionTime未更新到今天 ({})，合约可能跳过了分发",
+                    target, timestamp
+                );
+                verified = false;
+            }
+        }
+
+        Ok(verified)
+    }
+
+    /// 查询区块范围内奖励合约发出的分发事件
+    ///
+    /// 按 `log_block_range` 分段查询；节点因范围过大或结果过多（如超过10000条）拒绝时，
+    /// 把分段范围减半后重试。
+    pub async fn fetch_distribution_events(
+        &self,
+        from_block: U64,
+        to_block: U64,
+    ) -> Result<Vec<Log>> {
+        let topic = self.distribution_event.ok_or_else(|| {
+            DistributorError::Config("未配置DISTRIBUTION_EVENT_SIGNATURE".to_string())
+        })?;
+        let addresses = self.reward_contracts();
+
+        let mut logs = Vec::new();
+        let mut start = from_block.as_u64();
+        let end = to_block.as_u64();
+        let mut range = self.log_block_range;
+
+        while start <= end {
+            let chunk_end = start.saturating_add(range - 1).min(end);
+            let filter = Filter::new()
+                .address(addresses.clone())
+                .topic0(topic)
+                .from_block(start)
+                .to_block(chunk_end);
+
+            match self.client.get_logs(&filter).await {
+                Ok(mut chunk) => {
+                    logs.append(&mut chunk);
+                    start = chunk_end + 1;
+                }
+                Err(e) if range > 1 && is_log_limit_error(&e.to_string()) => {
+                    range /= 2;
+                    warn!(
+                        "eth_getLogs超出节点限制 ({})，缩小查询范围到 {} 个区块",
+                        e, range
+                    );
+                }
+                Err(e) => return Err(DistributorError::rpc("查询分发事件", e)),
+            }
+        }
+
+        Ok(logs)
+    }
+
+    /// 时间戳之后（含）的第一个区块，用二分查找按区块时间定位
+    pub async fn first_block_since(&self, timestamp: u64) -> Result<U64> {
+        let latest = self
+            .client
+            .get_block_number()
+            .await
+            .map_err(|e| DistributorError::rpc("获取最新区块号", e))?;
+
+        let (mut low, mut high) = (0u64, latest.as_u64());
+        while low < high {
+            let mid = low + (high - low) / 2;
+            let block = self
+                .client
+                .get_block(mid)
+                .await
+                .map_err(|e| DistributorError::rpc("查询区块", e))?
+                .ok_or_else(|| anyhow::anyhow!("节点未返回区块 {}", mid))?;
+
+            if block.timestamp < U256::from(timestamp) {
+                low = mid + 1;
+            } else {
+                high = mid;
+            }
+        }
+
+        Ok(low.into())
+    }
+
+    /// 按平均出块时间估算时间戳之后的第一个区块
+    ///
+    /// 只查询最新区块和往前 `BLOCK_TIME_SAMPLE` 个区块，比 `first_block_since` 的二分查找少得多；
+    /// 出块时间不均匀时估算会有偏差，因此多往前留出 `BLOCK_ESTIMATE_MARGIN` 的时间跨度，
+    /// 调用方需要再按区块时间戳过滤。
+    pub async fn estimate_block_since(&self, timestamp: u64) -> Result<U64> {
+        let latest = self
+            .client
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|e| DistributorError::rpc("获取最新区块", e))?
+            .ok_or_else(|| anyhow::anyhow!("节点未返回最新区块"))?;
+        let latest_number = latest
+            .number
+            .ok_or_else(|| anyhow::anyhow!("最新区块缺少区块号"))?
+            .as_u64();
+
+        let sample_number = latest_number.saturating_sub(BLOCK_TIME_SAMPLE);
+        let sample = self
+            .client
+            .get_block(sample_number)
+            .await
+            .map_err(|e| DistributorError::rpc("查询区块", e))?
+            .ok_or_else(|| anyhow::anyhow!("节点未返回区块 {}", sample_number))?;
+
+        let elapsed = latest.timestamp.saturating_sub(sample.timestamp).as_u64();
+        let blocks = latest_number - sample_number;
+        if blocks == 0 || elapsed == 0 {
+            // 链太短或时间戳不变（如本地开发链），从创世区块开始扫描
+            return Ok(U64::zero());
+        }
+        let block_time = elapsed as f64 / blocks as f64;
+
+        let span = latest.timestamp.as_u64().saturating_sub(timestamp) as f64;
+        let back = (span * (1.0 + BLOCK_ESTIMATE_MARGIN) / block_time).ceil() as u64;
+        Ok(latest_number.saturating_sub(back).into())
+    }
+
+    /// 从链上分发事件重建分发记录，不依赖本地状态文件
+    ///
+    /// 分段查询 `from_block` 到最新区块的分发事件，逐笔关联交易收据取得Gas用量和交易费用；
+    /// 区块时间戳按区块号缓存，同一区块的多条事件只查询一次。Multicall一笔交易分发多个合约时，
+    /// 这些记录共享同一笔交易的Gas用量和费用。结果按区块和日志顺序排列。
+    pub async fn distribution_history(
+        &self,
+        from_block: U64,
+    ) -> Result<Vec<ChainDistributionRecord>> {
+        let latest = self
+            .client
+            .get_block_number()
+            .await
+            .map_err(|e| DistributorError::rpc("获取最新区块号", e))?;
+        let mut logs = self.fetch_distribution_events(from_block, latest).await?;
+        logs.sort_by_key(|log| (log.block_number, log.log_index));
+
+        let mut timestamps: HashMap<U64, DateTime<Utc>> = HashMap::new();
+        let mut receipts: HashMap<H256, Option<TransactionReceipt>> = HashMap::new();
+        let mut records = Vec::with_capacity(logs.len());
+
+        for log in logs {
+            let (Some(block_number), Some(tx_hash)) = (log.block_number, log.transaction_hash)
+            else {
+                continue;
+            };
+
+            let timestamp = match timestamps.get(&block_number) {
+                Some(timestamp) => *timestamp,
+                None => {
+                    let block = self
+                        .client
+                        .get_block(block_number)
+                        .await
+                        .map_err(|e| DistributorError::rpc("查询区块", e))?
+                        .ok_or_else(|| anyhow::anyhow!("节点未返回区块 {}", block_number))?;
+                    let timestamp = i64::try_from(block.timestamp.as_u64())
+                        .ok()
+                        .and_then(|secs| DateTime::from_timestamp(secs, 0))
+                        .ok_or_else(|| anyhow::anyhow!("区块 {} 的时间戳无效", block_number))?;
+                    timestamps.insert(block_number, timestamp);
+                    timestamp
+                }
+            };
+
+            if let Entry::Vacant(entry) = receipts.entry(tx_hash) {
+                let receipt = self
+                    .client
+                    .get_transaction_receipt(tx_hash)
+                    .await
+                    .map_err(|e| DistributorError::rpc("查询交易收据", e))?;
+                entry.insert(receipt);
+            }
+            let receipt = receipts[&tx_hash].as_ref();
+
+            let (amount, recipients) = self
+                .distribution_event_abi
+                .as_ref()
+                .map(|event| decode_distribution_event(event, &log))
+                .unwrap_or_default();
+
+            records.push(ChainDistributionRecord {
+                timestamp,
+                block_number,
+                tx_hash,
+                contract: log.address,
+                amount,
+                recipients,
+                gas_used: receipt.and_then(|receipt| receipt.gas_used),
+                fee: receipt.and_then(receipt_fee),
+            });
+        }
+
+        Ok(records)
+    }
+
+    /// 需要分发的奖励合约：配置了Multicall时为所有目标合约
+    pub(crate) fn reward_contracts(&self) -> Vec<Address> {
+        match &self.multicall {
+            Some(multicall) => multicall.targets.clone(),
+            None => vec![self.contract.address()],
+        }
+    }
+
+    /// 在截止时间内完成整个分发流程（发送和确认）
+    ///
+    /// 超时时区分交易是否已发送，已发送的交易会保留，下次运行继续等待其确认。
+    pub async fn distribute_and_confirm_within(
+        &self,
+        deadline: Duration,
+    ) -> Result<DistributionResult> {
+        self.distribute_with_options_within(DistributeOptions::default(), deadline)
+            .await
+    }
+
+    /// 使用单次覆盖的参数，在截止时间内完成整个分发流程
+    pub async fn distribute_with_options_within(
+        &self,
+        options: DistributeOptions,
+        deadline: Duration,
+    ) -> Result<DistributionResult> {
+        let trigger = options.trigger;
+        match tokio::time::timeout(deadline, self.distribute_with_options(options)).await {
+            Ok(result) => result,
+            Err(_) => {
+                let err = match self.pending_transaction() {
+                    Some(tx_hash) => DeadlineError::PendingPastDeadline { deadline, tx_hash },
+                    None => DeadlineError::BeforeSend { deadline },
+                }
+                .into();
+                // 超时时分发流程被取消，没有输出摘要，在这里补上
+                self.log_summary(trigger, Err(&err), deadline);
+                Err(err)
+            }
+        }
+    }
+
+    /// 检查覆盖参数：Gas限制不低于21000且不超过区块Gas上限（能获取时），价格不为0
+    async fn validate_options(&self, options: &DistributeOptions) -> Result<()> {
+        if let Some(gas_limit) = options.gas_limit {
+            if gas_limit < U256::from(INTRINSIC_GAS) {
+                return Err(DistributorError::InvalidOptions(format!(
+                    "Gas限制 {} 低于交易的固有消耗 {}",
+                    gas_limit, INTRINSIC_GAS
+                )));
+            }
+
+            match self.client.get_block(BlockNumber::Latest).await {
+                Ok(Some(block)) if gas_limit > block.gas_limit => {
+                    return Err(DistributorError::InvalidOptions(format!(
+                        "Gas限制 {} 超过区块Gas上限 {}",
+                        gas_limit, block.gas_limit
+                    )));
+                }
+                Ok(_) => {}
+                Err(e) => warn!("获取区块Gas上限失败: {}，跳过该项检查", e),
+            }
+        }
+
+        if options.gas_price.is_some_and(|price| price.is_zero()) {
+            return Err(DistributorError::InvalidOptions(
+                "Gas价格不能为0".to_string(),
+            ));
+        }
+
+        if let (Some(priority_fee), Some(gas_price)) = (options.priority_fee, options.gas_price) {
+            if priority_fee > gas_price {
+                return Err(DistributorError::InvalidOptions(format!(
+                    "小费 {} 不能高于Gas价格 {}",
+                    priority_fee, gas_price
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// 已发送但尚未确认的交易哈希
+    pub fn pending_transaction(&self) -> Option<H256> {
+        *self.pending_tx.lock().unwrap()
+    }
+
+    fn set_pending_transaction(&self, tx_hash: Option<H256>) {
+        *self.pending_tx.lock().unwrap() = tx_hash;
+    }
+
+    /// 锁定签名账户的nonce分配，持锁期间其他实例不会发送交易
+    pub(crate) async fn lock_nonce(&self) -> tokio::sync::MutexGuard<'_, Option<U256>> {
+        self.next_nonce.lock().await
+    }
+
+    pub(crate) fn recipients_source(&self) -> Option<&RecipientsSource> {
+        self.recipients.as_ref()
+    }
+
+    pub(crate) fn retry_policy(&self) -> &RetryPolicy {
+        &self.retry_policy
+    }
+
+    pub(crate) fn is_merkle_mode(&self) -> bool {
+        self.merkle_output.is_some()
+    }
+
+    pub(crate) fn batch_size(&self) -> Option<usize> {
+        self.batch_size
+    }
+
+    pub(crate) fn batch_gas_budget(&self) -> Option<U256> {
+        self.batch_gas_budget
+    }
+
+    pub(crate) fn state_store(&self) -> Option<&StateStore> {
+        self.state.as_deref()
+    }
+
+    /// 共享的状态文件，供调度器等读取进行中的交易
+    pub fn shared_state_store(&self) -> Option<Arc<StateStore>> {
+        self.state.clone()
+    }
+
+    /// 解码收据日志时使用的合约ABI
+    pub(crate) fn event_abis(&self) -> Vec<&ethers::abi::Abi> {
+        vec![self.contract.abi()]
+    }
+
+    pub(crate) fn trace_supported(&self) -> Option<bool> {
+        *self.trace_supported.lock().unwrap()
+    }
+
+    pub(crate) fn set_trace_supported(&self, supported: bool) {
+        *self.trace_supported.lock().unwrap() = Some(supported);
+    }
+
+    /// 检查上次运行遗留的未确认交易是否仍需等待
+    async fn resumable_pending_transaction(&self) -> Result<Option<H256>> {
+        let Some(tx_hash) = self.pending_transaction() else {
+            return Ok(None);
+        };
+
+        let tx = self
+            .client
+            .get_transaction(tx_hash)
+            .await
+            .map_err(|e| DistributorError::rpc("查询交易", e))?;
+        if tx.is_some() {
+            warn!(
+                "检测到上次运行遗留的未确认交易 {:?}，继续等待确认而不重新发送",
+                tx_hash
+            );
+            Ok(Some(tx_hash))
+        } else {
+            warn!("上次运行的交易 {:?} 已不在节点中，将重新发送", tx_hash);
+            self.set_pending_transaction(None);
+            self.record_status(tx_hash, RecordStatus::Failed);
+            Ok(None)
+        }
+    }
+
+    /// 查找内存池中与本次分发等价（目标地址和调用数据相同）的未确认交易
+    ///
+    /// 只有 `pending` nonce 大于 `latest` nonce 时才会查询；节点不支持
+    /// `txpool_content` 时退回检查已记录的未确认交易。
+    pub async fn find_pending_distribution(&self) -> Result<Option<Transaction>> {
+        if !self.has_pending_nonce().await? {
+            return Ok(None);
+        }
+        let (to, call_data) = self.distribution_call().await?;
+        self.find_pending_call(to, &call_data).await
+    }
+
+    /// 签名账户是否有未确认的交易（`pending` nonce 大于 `latest` nonce）
+    async fn has_pending_nonce(&self) -> Result<bool> {
+        let address = self.client.address();
+        let latest = self
+            .client
+            .get_transaction_count(address, Some(BlockNumber::Latest.into()))
+            .await
+            .map_err(|e| DistributorError::rpc("获取nonce", e))?;
+        let pending = self
+            .client
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| DistributorError::rpc("获取pending nonce", e))?;
+
+        if pending <= latest {
+            return Ok(false);
+        }
+
+        info!(
+            "账户有 {} 笔未确认交易，检查其中是否已有分发交易",
+            pending - latest
+        );
+        Ok(true)
+    }
+
+    /// 在内存池中查找目标地址和调用数据都与给定调用相同的未确认交易
+    async fn find_pending_call(
+        &self,
+        to: Address,
+        call_data: &Bytes,
+    ) -> Result<Option<Transaction>> {
+        let address = self.client.address();
+        let is_distribution = |tx: &Transaction| tx.to == Some(to) && tx.input == *call_data;
+
+        match self.client.txpool_content().await {
+            Ok(content) => Ok(content
+                .pending
+                .get(&address)
+                .into_iter()
+                .flat_map(|txs| txs.values())
+                .find(|tx| is_distribution(tx))
+                .cloned()),
+            Err(e) => {
+                warn!(
+                    "节点不支持txpool_content ({})，改为检查已记录的未确认交易",
+                    e
+                );
+                let Some(tx_hash) = self.pending_transaction() else {
+                    return Ok(None);
+                };
+                Ok(self
+                    .client
+                    .get_transaction(tx_hash)
+                    .await
+                    .map_err(|e| DistributorError::rpc("查询交易", e))?
+                    .filter(|tx| tx.block_number.is_none() && is_distribution(tx)))
+            }
+        }
+    }
+
+    /// 查找并接管内存池中已有的分发交易，后续等待它确认而不是重新发送
+    ///
+    /// `trigger` 是接管后写入状态文件的触发方式。
+    pub async fn recover_pending_distribution(&self, trigger: Trigger) -> Result<Option<H256>> {
+        let Some(tx) = self.find_pending_distribution().await? else {
+            return Ok(None);
+        };
+        Ok(Some(self.adopt_pending(tx, trigger)))
+    }
+
+    /// 接管内存池中的未确认交易：记录为待确认交易并写入状态文件
+    fn adopt_pending(&self, tx: Transaction, trigger: Trigger) -> H256 {
+        warn!(
+            "内存池中已有等价的分发交易 {:?} (nonce {})，等待它确认而不重新发送",
+            tx.hash, tx.nonce
+        );
+        self.set_pending_transaction(Some(tx.hash));
+
+        if let Some(store) = &self.state {
+            let record = DistributionRecord {
+                tx_hash: tx.hash,
+                nonce: Some(tx.nonce),
+                timestamp: Utc::now(),
+                status: RecordStatus::Pending,
+                trigger,
+                block_hash: None,
+                verified: None,
+                gas_used: None,
+                fee: None,
+            };
+            if let Err(e) = store.record_attempt(self.contract.address(), record) {
+                warn!("写入状态文件失败: {}", e);
+            }
+        }
+
+        tx.hash
+    }
+
+    /// 状态文件中今天（调度时区）已确认的分发交易
+    ///
+    /// 今天的分批分发尚未全部完成时，已确认的单个批次不算作今天已分发。
+    pub fn confirmed_today(&self) -> Option<H256> {
+        let state = self.state.as_ref()?.contract(self.contract.address());
+        let today = self.today();
+        if state
+            .batch_progress
+            .is_some_and(|progress| progress.date == today && !progress.is_complete())
+        {
+            return None;
+        }
+
+        let record = state.last_confirmed?;
+        (self.schedule_timezone.date_of(record.timestamp) == today).then_some(record.tx_hash)
+    }
+
+    /// 重新核对状态文件中今天已确认的分发是否仍在链上
+    ///
+    /// 收据消失、收据所在区块已不在主链上，或确认时校验通过的 `lastDistributionTime()`
+    /// 现在没有更新到今天时，视为分发已被重组移除，作废本地记录，本次运行重新分发。
+    /// 查询失败时保留本地记录。
+    async fn revalidate_confirmed_today(&self) {
+        let Some(store) = &self.state else {
+            return;
+        };
+        let Some(record) = store.contract(self.contract.address()).last_confirmed else {
+            return;
+        };
+        if self.confirmed_today() != Some(record.tx_hash) {
+            return;
+        }
+
+        let reason = match self.client.get_transaction_receipt(record.tx_hash).await {
+            Ok(None) => Some("节点上已没有该交易的收据".to_string()),
+            Ok(Some(receipt)) if receipt.status != Some(U64::from(1)) => {
+                Some("交易现在的执行结果为失败".to_string())
+            }
+            Ok(Some(receipt)) => match self.is_canonical(&receipt).await {
+                Ok(false) => Some(format!(
+                    "收据所在区块 {:?} 已不在主链上",
+                    receipt.block_number
+                )),
+                Ok(true) => {
+                    if record.block_hash.is_some() && record.block_hash != receipt.block_hash {
+                        info!(
+                            "今天的分发 {:?} 已被重新打包到区块 {:?}",
+                            record.tx_hash, receipt.block_number
+                        );
+                    }
+                    None
+                }
+                Err(e) => {
+                    warn!("核对今天的分发所在区块失败，沿用本地记录: {}", e);
+                    return;
+                }
+            },
+            Err(e) => {
+                warn!("核对今天的分发交易失败，沿用本地记录: {}", e);
+                return;
+            }
+        };
+
+        let reason = match reason {
+            Some(reason) => reason,
+            None if self.verify_distribution && record.verified == Some(true) => {
+                match self.verify_last_distribution(None).await {
+                    Ok(true) => return,
+                    Ok(false) => "链上lastDistributionTime已不是今天".to_string(),
+                    Err(e) => {
+                        warn!("重新读取lastDistributionTime失败，沿用本地记录: {}", e);
+                        return;
+                    }
+                }
+            }
+            None => return,
+        };
+
+        warn!(
+            "今天已确认的分发 {:?} 不再反映在链上 ({})，可能发生了链重组，作废本地记录并重新分发",
+            record.tx_hash, reason
+        );
+        if let Err(e) = store.invalidate_confirmed(self.contract.address(), record.tx_hash) {
+            warn!("写入状态文件失败: {}", e);
+        }
+    }
+
+    /// 状态文件中最近一次确认成功的分发时间
+    pub fn last_confirmed_at(&self) -> Option<DateTime<Utc>> {
+        let record = self
+            .state
+            .as_ref()?
+            .contract(self.contract.address())
+            .last_confirmed?;
+        Some(record.timestamp)
+    }
+
+    /// 把收据中的Gas用量和交易费用写入对应的交易记录
+    fn record_fee(&self, receipt: &TransactionReceipt) {
+        if let Some(store) = &self.state {
+            if let Err(e) = store.record_fee(
+                self.contract.address(),
+                receipt.transaction_hash,
+                receipt.gas_used,
+                receipt_fee(receipt),
+            ) {
+                warn!("写入状态文件失败: {}", e);
+            }
+        }
+    }
+
+    fn record_status(&self, tx_hash: H256, status: RecordStatus) {
+        if let Some(store) = &self.state {
+            if let Err(e) = store.record_status(self.contract.address(), tx_hash, status) {
+                warn!("写入状态文件失败: {}", e);
+            }
+        }
+    }
+
+    /// 分发调用的目标地址和调用数据
+    ///
+    /// 配置了Multicall时，把对每个目标合约的 `distributeDailyRewards()` 聚合成一次
+    /// `aggregate3` 调用（不允许单个失败，保证全部成功或全部回滚）。
+    /// 配置了calldata覆盖、接收地址URL或接收地址文件时，对每个目标合约都使用同一份calldata；
+    /// 接收地址每次调用都会重新获取或读取。
+    pub async fn distribution_call(&self) -> Result<(Address, Bytes)> {
+        let call_data = self.reward_call_data(true).await?;
+        self.target_call(call_data)
+    }
+
+    /// 对单个奖励合约的调用数据
+    ///
+    /// Merkle模式下 `write_proofs` 为 `false` 时只计算Merkle根，不写出证明文件，供启动探测等只读场景使用。
+    pub(crate) async fn reward_call_data(&self, write_proofs: bool) -> Result<Bytes> {
+        if self.call_data_override.is_none() {
+            if let Some(remote) = &self.remote_recipients {
+                return self.remote_root_call(remote, write_proofs).await;
+            }
+        }
+        Ok(match (&self.call_data_override, &self.recipients) {
+            (Some(call_data), _) => call_data.clone(),
+            (None, Some(source)) => {
+                let list = source.load()?;
+                info!(
+                    "从 {} 读取到 {} 个接收地址{}",
+                    source.path.display(),
+                    list.recipients.len(),
+                    if list.amounts.is_some() {
+                        "（含数量）"
+                    } else {
+                        ""
+                    }
+                );
+                match &self.merkle_output {
+                    Some(output_path) => {
+                        self.merkle_root_call(&list, write_proofs.then_some(output_path.as_path()))?
+                    }
+                    None => list.encode_call(),
+                }
+            }
+            (None, None) => self
+                .contract
+                .distribute_daily_rewards()
+                .calldata()
+                .ok_or_else(|| DistributorError::Calldata("distributeDailyRewards".into()))?,
+        })
+    }
+
+    /// 从URL获取接收地址并构建Merkle树，返回 `distributeDailyRewards(bytes32)` 的调用数据
+    async fn remote_root_call(
+        &self,
+        remote: &RemoteRecipients,
+        write_proofs: bool,
+    ) -> Result<Bytes> {
+        let tree = remote.merkle_tree().await?;
+        match self.merkle_output.as_deref().filter(|_| write_proofs) {
+            Some(output_path) => {
+                tree.write_proofs(output_path)?;
+                info!(
+                    "Merkle根: {:?}，总金额: {}，证明已写入 {}",
+                    tree.root(),
+                    tree.total_amount(),
+                    output_path.display()
+                );
+            }
+            None => info!(
+                "Merkle根: {:?}，总金额: {}",
+                tree.root(),
+                tree.total_amount()
+            ),
+        }
+        Ok(encode_root_call(tree.root()))
+    }
+
+    /// 构建Merkle树并写出证明文件，返回 `setDailyRoot(root, totalAmount)` 的调用数据
+    fn merkle_root_call(&self, list: &RecipientList, output_path: Option<&Path>) -> Result<Bytes> {
+        let tree = MerkleTree::build(list)?;
+        if let Some(output_path) = output_path {
+            tree.write_proofs(output_path)?;
+            info!(
+                "Merkle根: {:?}，总金额: {}，证明已写入 {}",
+                tree.root(),
+                tree.total_amount(),
+                output_path.display()
+            );
+        }
+
+        self.contract
+            .set_daily_root(tree.root().0, tree.total_amount())
+            .calldata()
+            .ok_or_else(|| DistributorError::Calldata("setDailyRoot".into()))
+    }
+
+    /// 把对奖励合约的调用数据转换为实际发送的目标地址和调用数据（按需通过Multicall聚合）
+    pub(crate) fn target_call(&self, call_data: Bytes) -> Result<(Address, Bytes)> {
+        let Some(multicall) = &self.multicall else {
+            return Ok((self.contract.address(), call_data));
+        };
+
+        let calls = multicall
+            .targets
+            .iter()
+            .map(|target| Call3 {
+                target: *target,
+                allow_failure: false,
+                call_data: call_data.clone(),
+            })
+            .collect();
+
+        let aggregated = Multicall3::new(multicall.address, self.client.clone())
+            .aggregate_3(calls)
+            .calldata()
+            .ok_or_else(|| DistributorError::Calldata("Multicall".into()))?;
+
+        Ok((multicall.address, aggregated))
+    }
+
+    /// 构建交易，按交易类型使用legacy或EIP-1559的费用字段
+    ///
+    /// `min_nonce` 为本进程记录的下一个可用nonce。
+    #[instrument(
+        name = "build",
+        skip_all,
+        fields(gas_limit = %gas_limit, nonce = Empty, fee_mode = Empty)
+    )]
+    pub(crate) async fn build_transaction(
+        &self,
+        to: Address,
+        call_data: Bytes,
+        gas_limit: U256,
+        options: &DistributeOptions,
+        min_nonce: Option<U256>,
+    ) -> Result<TypedTransaction, DistributorError> {
+        // 本进程已发送但尚未打包的交易不计入节点的nonce，取两者中较大的
+        let nonce = self
+            .client
+            .get_transaction_count(self.client.address(), None)
+            .await
+            .map_err(|e| DistributorError::rpc("获取nonce", e))?
+            .max(min_nonce.unwrap_or_default());
+        Span::current().record("nonce", display(nonce));
+
+        let fee_mode = self.fee_mode.resolve(self.client.as_ref()).await?;
+        Span::current().record("fee_mode", display(fee_mode));
+        let tx_request = match fee_mode {
+            FeeMode::Legacy => {
+                let (mut gas_price, source) = self.get_gas_price(options).await?;
+                if source.is_suggested() {
+                    gas_price = self.fee_escalation.initial(gas_price);
+                }
+                if let Some(cap) = self.max_gas_price.filter(|cap| gas_price > *cap) {
+                    return Err(DistributorError::GasPriceExceedsCap {
+                        price: gas_price,
+                        cap,
+                    });
+                }
+                info!(
+                    "使用Gas价格: {} wei ({} gwei)，来源: {}",
+                    gas_price,
+                    ethers::utils::format_units(gas_price, "gwei").unwrap_or_default(),
+                    source
+                );
+
+                TransactionRequest {
+                    to: Some(to.into()),
+                    value: Some(U256::zero()),
+                    gas: Some(gas_limit),
+                    gas_price: Some(gas_price),
+                    data: Some(call_data),
+                    nonce: Some(nonce),
+                    chain_id: Some(self.chain_id.into()),
+                    ..Default::default()
+                }
+                .into()
+            }
+            FeeMode::Eip1559 => {
+                let (mut max_fee, mut priority_fee, source) =
+                    self.get_eip1559_fees(options).await?;
+                if source.is_suggested() {
+                    max_fee = self.fee_escalation.initial(max_fee);
+                    priority_fee = self.fee_escalation.initial(priority_fee);
+                }
+                // maxFeePerGas只是上限，实际费用通常更低，因此限制在上限内而不是拒绝发送
+                if let Some(cap) = self.max_gas_price.filter(|cap| max_fee > *cap) {
+                    warn!("maxFeePerGas {} 超过上限 {}，已限制为上限", max_fee, cap);
+                    max_fee = cap;
+                    priority_fee = priority_fee.min(cap);
+                }
+                info!(
+                    "使用EIP-1559费用: maxFeePerGas {} gwei，maxPriorityFeePerGas {} gwei，来源: {}",
+                    ethers::utils::format_units(max_fee, "gwei").unwrap_or_default(),
+                    ethers::utils::format_units(priority_fee, "gwei").unwrap_or_default(),
+                    source
+                );
+
+                Eip1559TransactionRequest {
+                    to: Some(to.into()),
+                    value: Some(U256::zero()),
+                    gas: Some(gas_limit),
+                    max_fee_per_gas: Some(max_fee),
+                    max_priority_fee_per_gas: Some(priority_fee),
+                    data: Some(call_data),
+                    nonce: Some(nonce),
+                    chain_id: Some(self.chain_id.into()),
+                    ..Default::default()
+                }
+                .into()
+            }
+        };
+
+        Ok(tx_request)
+    }
+
+    /// 用配置的Gas限制模拟执行分发调用
+    pub async fn simulate(&self) -> Result<(), SimulationError> {
+        let (to, call_data) = self
+            .distribution_call()
+            .await
+            .map_err(|e| SimulationError::Transport(e.to_string()))?;
+        self.simulate_call(to, call_data, self.gas_limit).await
+    }
+
+    /// 通过 `eth_call` 模拟执行，回滚时解码回滚原因
+    pub(crate) async fn simulate_call(
+        &self,
+        to: Address,
+        call_data: Bytes,
+        gas_limit: U256,
+    ) -> Result<(), SimulationError> {
+        let tx_request = TransactionRequest {
+            to: Some(to.into()),
+            data: Some(call_data),
+            from: Some(self.simulation_sender()),
+            gas: Some(gas_limit),
+            ..Default::default()
+        };
+
+        let typed_tx: TypedTransaction = tx_request.into();
+        self.client
+            .call(&typed_tx, None)
+            .await
+            .map(|_| ())
+            .map_err(|e| match e.as_error_response() {
+                Some(rpc_err) if rpc_err.is_revert() || rpc_err.code == 3 => {
+                    SimulationError::Reverted {
+                        reason: decode_revert_reason(rpc_err),
+                    }
+                }
+                _ => SimulationError::Transport(e.to_string()),
+            })
+    }
+
+    /// Gas估算
+    #[instrument(name = "estimate", skip_all, fields(to = ?to, gas_estimate = Empty))]
+    pub(crate) async fn estimate_gas(
+        &self,
+        to: Address,
+        call_data: Bytes,
+    ) -> Result<U256, GasEstimationError> {
+        let tx_request = TransactionRequest {
+            to: Some(to.into()),
+            data: Some(call_data),
+            from: Some(self.simulation_sender()),
+            ..Default::default()
+        };
+
+        let typed_tx: TypedTransaction = tx_request.into();
+        let gas = self
+            .client
+            .estimate_gas(&typed_tx, None)
+            .await
+            .map_err(|e| match e.as_error_response() {
+                Some(rpc_err) if rpc_err.is_revert() || rpc_err.code == 3 => {
+                    GasEstimationError::Reverted {
+                        reason: decode_revert_reason(rpc_err),
+                    }
+                }
+                _ => GasEstimationError::Transport(e.to_string()),
+            })?;
+        Span::current().record("gas_estimate", display(gas));
+        Ok(gas)
+    }
+
+    /// 本次运行覆盖或配置的固定Gas价格
+    fn fixed_gas_price(&self, options: &DistributeOptions) -> Option<(U256, GasPriceSource)> {
+        options
+            .gas_price
+            .map(|price| (price, GasPriceSource::Override))
+            .or_else(|| self.gas_price.map(|price| (price, GasPriceSource::Config)))
+    }
+
+    /// 获取Gas价格，节点不可用时按 ON_GAS_PRICE_UNAVAILABLE 处理
+    async fn get_gas_price(&self, options: &DistributeOptions) -> Result<(U256, GasPriceSource)> {
+        if let Some(fixed) = self.fixed_gas_price(options) {
+            return Ok(fixed);
+        }
+
+        match self.client.get_gas_price().await {
+            Ok(network_price) => {
+                self.last_known_fees.set_gas_price(network_price);
+                Ok((network_price, GasPriceSource::Node))
+            }
+            Err(e) => match self.gas_price_fallback {
+                GasPriceFallback::UseDefault => {
+                    warn!("获取节点Gas价格失败: {}，使用默认价格", e);
+                    Ok((U256::from(DEFAULT_GAS_PRICE), GasPriceSource::Default))
+                }
+                GasPriceFallback::UseLastKnown => match self.last_known_fees.gas_price() {
+                    Some(price) => {
+                        warn!(
+                            "获取节点Gas价格失败: {}，使用最近一次获取的价格 {} wei",
+                            e, price
+                        );
+                        Ok((price, GasPriceSource::LastKnown))
+                    }
+                    None => Err(DistributorError::GasPriceUnavailable {
+                        reason: format!("{}，且本进程还没有成功获取过Gas价格", e),
+                    }),
+                },
+                GasPriceFallback::Abort => Err(DistributorError::GasPriceUnavailable {
+                    reason: e.to_string(),
+                }),
+            },
+        }
+    }
+
+    /// 获取EIP-1559费用 `(maxFeePerGas, maxPriorityFeePerGas)`
+    ///
+    /// 配置或覆盖了Gas价格时把它作为 `maxFeePerGas` 的上限；节点不可用时按 ON_GAS_PRICE_UNAVAILABLE 处理。
+    async fn get_eip1559_fees(
+        &self,
+        options: &DistributeOptions,
+    ) -> Result<(U256, U256, GasPriceSource)> {
+        let fixed = self.fixed_gas_price(options);
+        let estimate = match &self.fee_history {
+            Some(fee_history) => fee_history.estimate(self.client.as_ref()).await,
+            None => self
+                .client
+                .estimate_eip1559_fees(None)
+                .await
+                .map_err(|e| DistributorError::rpc("获取EIP-1559费用", e)),
+        };
+        let (max_fee, priority_fee, source) = match estimate {
+            Ok((max_fee, priority_fee)) => {
+                self.last_known_fees.set_eip1559(max_fee, priority_fee);
+                match fixed {
+                    Some((cap, source)) => (cap, priority_fee.min(cap), source),
+                    None => (max_fee, priority_fee, GasPriceSource::Node),
+                }
+            }
+            Err(e) => match (self.gas_price_fallback, fixed) {
+                // 配置了固定价格时只缺少小费，按默认小费处理
+                (GasPriceFallback::UseDefault, _) | (_, Some(_)) => {
+                    warn!("获取节点EIP-1559费用失败: {}，使用默认费用", e);
+                    let (max_fee, source) =
+                        fixed.unwrap_or((U256::from(DEFAULT_GAS_PRICE), GasPriceSource::Default));
+                    (
+                        max_fee,
+                        U256::from(DEFAULT_PRIORITY_FEE).min(max_fee),
+                        source,
+                    )
+                }
+                (GasPriceFallback::UseLastKnown, None) => match self.last_known_fees.eip1559() {
+                    Some((max_fee, priority_fee)) => {
+                        warn!(
+                            "获取节点EIP-1559费用失败: {}，使用最近一次获取的费用 (maxFeePerGas {} wei，maxPriorityFeePerGas {} wei)",
+                            e, max_fee, priority_fee
+                        );
+                        (max_fee, priority_fee, GasPriceSource::LastKnown)
+                    }
+                    None => {
+                        return Err(DistributorError::GasPriceUnavailable {
+                            reason: format!("{}，且本进程还没有成功获取过EIP-1559费用", e),
+                        })
+                    }
+                },
+                (GasPriceFallback::Abort, None) => {
+                    return Err(DistributorError::GasPriceUnavailable {
+                        reason: e.to_string(),
+                    })
+                }
+            },
+        };
+
+        Ok(match options.priority_fee {
+            Some(priority_fee) => (max_fee, priority_fee.min(max_fee), source),
+            None => (max_fee, priority_fee, source),
+        })
+    }
+
+    /// 等待交易确认
+    #[instrument(
+        name = "confirm",
+        skip(self),
+        fields(block_number = Empty, gas_used = Empty, total_fee = Empty, status = Empty)
+    )]
+    pub async fn wait_for_confirmation(&self, tx_hash: H256) -> Result<TransactionReceipt> {
+        info!("等待交易确认: {:?}", tx_hash);
+
+        let timeout = match self.confirmation_strategy {
+            ConfirmationStrategy::Finalized => FINALIZED_CONFIRMATION_TIMEOUT,
+            _ => Duration::from_secs(300), // 5分钟超时
+        };
+        let start_time = std::time::Instant::now();
+        let mut poll_interval = self.confirmation_poll.initial;
+
+        loop {
+            if start_time.elapsed() > timeout {
+                return Err(DistributorError::ConfirmationTimeout { tx_hash, timeout });
+            }
+
+            let receipt = match self.client.get_transaction_receipt(tx_hash).await {
+                Ok(receipt) => receipt,
+                Err(e) => {
+                    let kind = ErrorKind::classify(&e);
+                    if !kind.is_retryable() {
+                        return Err(DistributorError::rpc("查询交易收据", e));
+                    }
+                    warn!("查询交易收据失败 ({})，稍后重试: {}", kind, e);
+                    None
+                }
+            };
+
+            let receipt = match receipt {
+                Some(receipt) if self.reorg_protection => match self.is_canonical(&receipt).await {
+                    Ok(true) => Some(receipt),
+                    Ok(false) => {
+                        warn!(
+                            "收据所在区块 {:?} ({:?}) 已不在主链上，可能发生了重组，继续等待",
+                            receipt.block_number, receipt.block_hash
+                        );
+                        None
+                    }
+                    Err(e) => {
+                        warn!("核对收据所在区块失败，稍后重试: {}", e);
+                        None
+                    }
+                },
+                receipt => receipt,
+            };
+
+            let receipt = match (receipt, self.confirmation_strategy) {
+                (Some(receipt), ConfirmationStrategy::Count(confirmations))
+                    if confirmations > 1 =>
+                {
+                    match self.confirmation_depth(&receipt).await {
+                        Ok(depth) if depth >= confirmations => Some(receipt),
+                        Ok(depth) => {
+                            info!("交易已打包，确认数 {}/{}", depth, confirmations);
+                            None
+                        }
+                        Err(e) => {
+                            warn!("查询最新区块失败，稍后重试: {}", e);
+                            None
+                        }
+                    }
+                }
+                (Some(receipt), ConfirmationStrategy::Finalized) => {
+                    match self.finalized_block_number().await {
+                        Ok(Some(finalized))
+                            if receipt.block_number.is_some_and(|block| block <= finalized) =>
+                        {
+                            Some(receipt)
+                        }
+                        Ok(finalized) => {
+                            info!(
+                                "交易已打包在区块 {:?}，等待最终确认 (当前finalized区块: {:?})",
+                                receipt.block_number, finalized
+                            );
+                            None
+                        }
+                        Err(e) => {
+                            warn!("查询finalized区块失败，稍后重试: {}", e);
+                            None
+                        }
+                    }
+                }
+                (receipt, _) => receipt,
+            };
+
+            match receipt {
+                Some(receipt) => {
+                    let span = Span::current();
+                    span.record("block_number", receipt.block_number.map(|n| n.as_u64()));
+                    span.record("gas_used", receipt.gas_used.map(display));
+                    span.record("status", receipt.status.map(|s| s.as_u64()));
+                    if let Some(fee) = receipt_fee(&receipt) {
+                        span.record("total_fee", display(fee));
+                        info!(
+                            "实际交易费用: {} wei ({} ETH)，Gas使用量 {:?} × 实际Gas价格 {} gwei",
+                            fee,
+                            format_ether(fee),
+                            receipt.gas_used,
+                            receipt
+                                .effective_gas_price
+                                .and_then(|price| ethers::utils::format_units(price, "gwei").ok())
+                                .unwrap_or_default()
+                        );
+                    }
+                    self.set_pending_transaction(None);
+                    // 失败上链的交易同样消耗Gas
+                    self.record_spend(&receipt);
+                    self.record_fee(&receipt);
+                    if receipt.status == Some(U64::from(1)) {
+                        info!("交易执行成功");
+                        self.record_status(tx_hash, RecordStatus::Confirmed);
+                    } else {
+                        warn!("交易执行失败");
+                        self.record_status(tx_hash, RecordStatus::Failed);
+                    }
+                    return Ok(receipt);
+                }
+                None => {
+                    tokio::time::sleep(poll_interval).await;
+                    poll_interval = self.confirmation_poll.next(poll_interval);
+                }
+            }
+        }
+    }
+
+    /// 收据所在区块到最新区块的确认数（含收据所在区块）
+    async fn confirmation_depth(&self, receipt: &TransactionReceipt) -> Result<usize> {
+        let Some(block_number) = receipt.block_number else {
+            return Ok(0);
+        };
+        let latest = self
+            .client
+            .get_block_number()
+            .await
+            .map_err(|e| DistributorError::rpc("获取最新区块号", e))?;
+        Ok(latest
+            .checked_sub(block_number)
+            .map_or(0, |depth| depth.as_usize() + 1))
+    }
+
+    /// 节点标记为 `finalized` 的最新区块号
+    async fn finalized_block_number(&self) -> Result<Option<U64>> {
+        let block = self
+            .client
+            .get_block(BlockNumber::Finalized)
+            .await
+            .map_err(|e| DistributorError::rpc("获取finalized区块", e))?;
+        Ok(block.and_then(|block| block.number))
+    }
+
+    /// 重新获取收据区块号对应的主链区块，比较区块哈希是否一致
+    async fn is_canonical(&self, receipt: &TransactionReceipt) -> Result<bool> {
+        let Some(block_number) = receipt.block_number else {
+            return Ok(false);
+        };
+
+        let block = self
+            .client
+            .get_block(block_number)
+            .await
+            .map_err(|e| DistributorError::rpc("查询区块", e))?;
+
+        Ok(block.is_some_and(|block| block.hash.is_some() && block.hash == receipt.block_hash))
+    }
+
+    /// 访问器方法
+    pub fn client_address(&self) -> Address {
+        self.client.address()
+    }
+
+    /// 模拟执行和Gas估算使用的 `from` 地址：配置了SIMULATE_AS时为该地址，否则为签名账户
+    pub fn simulation_sender(&self) -> Address {
+        self.simulate_as.unwrap_or_else(|| self.client.address())
+    }
+
+    pub fn contract_address(&self) -> Address {
+        self.contract.address()
+    }
+
+    pub fn inner_contract(&self) -> &RewardsContractABI<DistributorClient> {
+        &self.contract
+    }
+
+    pub fn gas_limit(&self) -> U256 {
+        self.gas_limit
+    }
+
+    pub fn gas_price(&self) -> Option<U256> {
+        self.gas_price
+    }
+
+    pub fn chain_id(&self) -> u64 {
+        self.chain_id
+    }
+
+    pub fn configured_fees(&self) -> ConfiguredFees {
+        ConfiguredFees {
+            gas_limit: self.gas_limit,
+            gas_price: self.gas_price,
+            chain_id: self.chain_id,
+            tx_type: self.fee_mode.tx_type(),
+        }
+    }
+}
+
+/// 从JSON-RPC错误中解码回滚原因
+///
+/// 依次尝试 `Error(string)`、`Panic(uint256)`，都失败时返回原始回滚数据或错误消息。
+pub(crate) fn decode_revert_reason(err: &JsonRpcError) -> String {
+    if let Some(reason) = String::from_rpc_response(err) {
+        return reason;
+    }
+
+    match err.as_revert_data() {
+        Some(data) if !data.is_empty() => decode_revert_data(&data),
+        _ => err.message.clone(),
+    }
+}
+
+/// 只读合约调用的错误：中间件错误按RPC错误分类，其他（如返回值解码失败）保留原始信息
+fn contract_call_error(operation: &str, err: ContractError<DistributorClient>) -> DistributorError {
+    match err {
+        ContractError::MiddlewareError { e } => DistributorError::rpc(operation, e),
+        other => anyhow::anyhow!("{}失败: {}", operation, other).into(),
+    }
+}
+
+/// 解码回滚数据中的 `Error(string)` 或 `Panic(uint256)`
+pub(crate) fn decode_revert_data(data: &Bytes) -> String {
+    if let Some(reason) = String::decode_with_selector(data) {
+        return reason;
+    }
+
+    if data.starts_with(&PANIC_SELECTOR) && data.len() >= 36 {
+        format!("Panic(0x{:x})", U256::from_big_endian(&data[4..36]))
+    } else {
+        format!("未知的回滚数据: {}", data)
+    }
+}
+
+/// 以有限的并发度分发多个奖励合约，按输入顺序返回每个合约的结果
+///
+/// 每个合约单独受 `deadline` 约束，使用同一份 `options`。共享签名账户的合约在发送时串行分配nonce，
+/// 等待确认等其他步骤并发执行。
+pub async fn distribute_concurrently(
+    contracts: &[RewardsContract],
+    max_concurrent: usize,
+    deadline: Duration,
+    options: &DistributeOptions,
+) -> Vec<(Address, Result<DistributionResult>)> {
+    let semaphore = Arc::new(tokio::sync::Semaphore::new(max_concurrent.max(1)));
+    let mut tasks = tokio::task::JoinSet::new();
+    for (index, contract) in contracts.iter().cloned().enumerate() {
+        let semaphore = semaphore.clone();
+        let options = options.clone();
+        tasks.spawn(async move {
+            let _permit = semaphore.acquire_owned().await;
+            info!("开始分发合约 {:?}", contract.contract_address());
+            (
+                index,
+                contract
+                    .distribute_with_options_within(options, deadline)
+                    .await,
+            )
+        });
+    }
+
+    let mut results: Vec<Option<Result<DistributionResult>>> =
+        contracts.iter().map(|_| None).collect();
+    while let Some(joined) = tasks.join_next().await {
+        match joined {
+            Ok((index, result)) => results[index] = Some(result),
+            Err(e) => warn!("分发任务异常退出: {}", e),
+        }
+    }
+
+    contracts
+        .iter()
+        .zip(results)
+        .map(|(contract, result)| {
+            let result = result.unwrap_or_else(|| Err(anyhow::anyhow!("分发任务异常退出").into()));
+            (contract.contract_address(), result)
+        })
+        .collect()
+}
+
+/// 一个合约一次分发的结果，通过 `DailyScheduler::with_event_sender` 设置的通道发送
+///
+/// 供嵌入使用时以代码而不是日志观察每次分发的结果，例如导出指标或自定义通知。
+#[derive(Debug)]
+pub struct DistributionEvent {
+    /// 分发结束的时间
+    pub timestamp: DateTime<Utc>,
+    pub contract: Address,
+    pub trigger: Trigger,
+    pub result: Result<DistributionResult>,
+}
+
+/// 多个合约一次分发的结果，保留每个合约各自的成功或失败
+pub struct DistributionReport {
+    pub results: Vec<(Address, Result<DistributionResult>)>,
+}
+
+/// 单个合约的分发结果摘要，用于通知
+#[derive(Debug, Clone, Serialize)]
+pub struct ContractOutcome {
+    pub contract: Address,
+    /// 成功、跳过、回滚、超时、余额不足或失败
+    pub outcome: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<H256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl DistributionReport {
+    pub fn new(results: Vec<(Address, Result<DistributionResult>)>) -> Self {
+        Self { results }
+    }
+
+    pub fn succeeded(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.is_ok())
+            .count()
+    }
+
+    /// 按配置跳过（未发送交易）的合约数
+    pub fn skipped(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|(_, result)| result.as_ref().is_err_and(|e| e.is_skipped()))
+            .count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.results.len() - self.succeeded() - self.skipped()
+    }
+
+    /// 按结果分类计数，如 `3 成功，1 回滚，1 超时`
+    pub fn summary(&self) -> String {
+        let mut counts: Vec<(&'static str, usize)> = Vec::new();
+        for outcome in self.outcomes() {
+            match counts
+                .iter_mut()
+                .find(|(label, _)| *label == outcome.outcome)
+            {
+                Some((_, count)) => *count += 1,
+                None => counts.push((outcome.outcome, 1)),
+            }
+        }
+        counts.sort_by_key(|(label, _)| OUTCOME_ORDER.iter().position(|order| order == label));
+        counts
+            .iter()
+            .map(|(label, count)| format!("{} {}", count, label))
+            .collect::<Vec<_>>()
+            .join("，")
+    }
+
+    /// 每个合约的结果摘要，顺序与输入一致
+    pub fn outcomes(&self) -> Vec<ContractOutcome> {
+        self.results
+            .iter()
+            .map(|(contract, result)| match result {
+                Ok(result) => ContractOutcome {
+                    contract: *contract,
+                    outcome: "成功",
+                    tx_hash: Some(result.tx_hash),
+                    error: None,
+                },
+                Err(e) => ContractOutcome {
+                    contract: *contract,
+                    outcome: failure_label(e),
+                    tx_hash: None,
+                    error: Some(e.to_string()),
+                },
+            })
+            .collect()
+    }
+
+    /// 把每个合约的结果作为 `DistributionEvent` 发送到 `events`，返回任务的执行结果
+    ///
+    /// 错误本身随事件发出，返回的是保留描述和是否跳过的 `DistributorError::Forwarded`；
+    /// 有多个失败时返回第一个。通道已满或已关闭时丢弃事件并记录警告，不阻塞分发任务。
+    pub fn forward_events(
+        self,
+        events: &mpsc::Sender<DistributionEvent>,
+        trigger: Trigger,
+    ) -> Result<Vec<DistributionResult>> {
+        let mut results = Vec::new();
+        let mut first_error = None;
+        for (contract, result) in self.results {
+            match &result {
+                Ok(result) => results.push(result.clone()),
+                Err(e) if first_error.is_none() => {
+                    first_error = Some(DistributorError::Forwarded {
+                        message: e.to_string(),
+                        skipped: e.is_skipped(),
+                    })
+                }
+                Err(_) => {}
+            }
+            let event = DistributionEvent {
+                timestamp: Utc::now(),
+                contract,
+                trigger,
+                result,
+            };
+            if let Err(e) = events.try_send(event) {
+                warn!("分发事件未能发送 ({:?}): {}", contract, e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(results),
+        }
+    }
+}
+
+/// 收据中的实际交易费用：gasUsed × effectiveGasPrice（wei），节点未返回时为 `None`
+pub(crate) fn receipt_fee(receipt: &TransactionReceipt) -> Option<U256> {
+    receipt
+        .gas_used
+        .zip(receipt.effective_gas_price)
+        .map(|(gas_used, price)| gas_used * price)
+}
+
+/// 把事件签名解析为ABI事件，签名不带 `event` 前缀时补上；无法解析时返回 `None`
+fn parse_distribution_event(signature: &str) -> Option<abi::Event> {
+    let signature = signature.trim();
+    let declaration = if signature.starts_with("event ") {
+        signature.to_string()
+    } else {
+        format!("event {}", signature)
+    };
+    abi::parse_abi(&[declaration.as_str()])
+        .ok()?
+        .events()
+        .next()
+        .cloned()
+}
+
+/// 从分发事件中取出金额和接收地址数
+///
+/// 参数名含 `amount` 或 `total` 的整数作为金额；参数名含 `recipient` 或 `count` 的整数作为接收地址数，
+/// 为数组时取数组长度。签名没有参数名时两者都为 `None`。
+fn decode_distribution_event(event: &abi::Event, log: &Log) -> (Option<U256>, Option<U256>) {
+    let Ok(parsed) = event.parse_log(abi::RawLog {
+        topics: log.topics.clone(),
+        data: log.data.to_vec(),
+    }) else {
+        return (None, None);
+    };
+
+    let mut amount = None;
+    let mut recipients = None;
+    for param in parsed.params {
+        let name = param.name.to_lowercase();
+        match param.value {
+            abi::Token::Uint(value)
+                if amount.is_none() && (name.contains("amount") || name.contains("total")) =>
+            {
+                amount = Some(value);
+            }
+            abi::Token::Uint(value)
+                if recipients.is_none()
+                    && (name.contains("recipient") || name.contains("count")) =>
+            {
+                recipients = Some(value);
+            }
+            abi::Token::Array(values) if recipients.is_none() && name.contains("recipient") => {
+                recipients = Some(U256::from(values.len()));
+            }
+            _ => {}
+        }
+    }
+    (amount, recipients)
+}
+
+/// 汇总中各类结果的排列顺序
+const OUTCOME_ORDER: [&str; 6] = ["成功", "跳过", "回滚", "超时", "余额不足", "失败"];
+
+/// 失败的大致类别，便于汇总时一眼看出问题所在
+fn failure_label(err: &DistributorError) -> &'static str {
+    match err {
+        DistributorError::Reverted { .. }
+        | DistributorError::TransactionFailed { .. }
+        | DistributorError::GasEstimation(GasEstimationError::Reverted { .. }) => "回滚",
+        DistributorError::Deadline(_) | DistributorError::ConfirmationTimeout { .. } => "超时",
+        DistributorError::InsufficientFunds { .. }
+        | DistributorError::InsufficientGasFunds { .. }
+        | DistributorError::InsufficientRewardTokens { .. } => "余额不足",
+        DistributorError::PreflightSkipped { .. } => "跳过",
+        DistributorError::BatchFailed { source, .. } => failure_label(source),
+        _ => "失败",
+    }
+}
+
+/// `RewardsContract` 的构建器
+///
+/// 合约地址和客户端是必需的；链ID未设置时使用签名钱包的链ID，
+/// 其余选项与 `RewardsContract::with_*` 的默认值一致。
+#[derive(Default)]
+pub struct RewardsContractBuilder {
+    address: Option<Address>,
+    client: Option<Arc<DistributorClient>>,
+    gas_limit: Option<U256>,
+    gas_price: Option<U256>,
+    chain_id: Option<u64>,
+    retry_policy: Option<RetryPolicy>,
+    confirmation_strategy: Option<ConfirmationStrategy>,
+}
+
+impl RewardsContractBuilder {
+    /// 奖励合约地址
+    pub fn address(mut self, address: Address) -> Self {
+        self.address = Some(address);
+        self
+    }
+
+    /// 带签名钱包的客户端
+    pub fn client(mut self, client: Arc<DistributorClient>) -> Self {
+        self.client = Some(client);
+        self
+    }
+
+    /// Gas限制，默认 500000
+    pub fn gas_limit(mut self, gas_limit: U256) -> Self {
+        self.gas_limit = Some(gas_limit);
+        self
+    }
+
+    /// 固定Gas价格，不设置时使用节点建议价格
+    pub fn gas_price(mut self, gas_price: U256) -> Self {
+        self.gas_price = Some(gas_price);
+        self
+    }
+
+    /// 链ID，不设置时使用签名钱包的链ID
+    pub fn chain_id(mut self, chain_id: u64) -> Self {
+        self.chain_id = Some(chain_id);
+        self
+    }
+
+    /// RPC调用的重试策略
+    pub fn retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = Some(retry_policy);
+        self
+    }
+
+    /// 确认所需的区块数，默认 1
+    pub fn confirmations(mut self, confirmations: usize) -> Self {
+        self.confirmation_strategy = Some(match confirmations {
+            0 | 1 => ConfirmationStrategy::Inclusion,
+            n => ConfirmationStrategy::Count(n),
+        });
+        self
+    }
+
+    /// 交易确认的判定方式，默认收据出现即视为确认
+    pub fn confirmation_strategy(mut self, strategy: ConfirmationStrategy) -> Self {
+        self.confirmation_strategy = Some(strategy);
+        self
+    }
+
+    /// 创建合约实例，缺少地址或客户端时返回配置错误
+    pub fn build(self) -> Result<RewardsContract> {
+        let address = self
+            .address
+            .ok_or_else(|| DistributorError::Config("RewardsContractBuilder缺少合约地址".into()))?;
+        let client = self
+            .client
+            .ok_or_else(|| DistributorError::Config("RewardsContractBuilder缺少客户端".into()))?;
+        let chain_id = self.chain_id.unwrap_or_else(|| client.signer().chain_id());
+
+        let mut contract = RewardsContract::new(
+            address,
+            client,
+            self.gas_limit
+                .unwrap_or_else(|| U256::from(DEFAULT_GAS_LIMIT)),
+            self.gas_price,
+            chain_id,
+        );
+        if let Some(retry_policy) = self.retry_policy {
+            contract = contract.with_retry_policy(retry_policy);
+        }
+        if let Some(strategy) = self.confirmation_strategy {
+            contract = contract.with_confirmation_strategy(strategy);
+        }
+        Ok(contract)
+    }
+}
+
+/// 等待最终确认的超时时间；合并后的以太坊需要约两个epoch（约13分钟）
+const FINALIZED_CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// 构建器未设置Gas限制时的默认值，与 GAS_LIMIT 的默认值一致
+const DEFAULT_GAS_LIMIT: u64 = 500_000;
+
+/// 节点无法提供Gas价格时使用的默认价格（30 gwei）
+const DEFAULT_GAS_PRICE: u64 = 30_000_000_000;
+
+/// 节点无法提供EIP-1559费用时使用的默认小费（1.5 gwei）
+const DEFAULT_PRIORITY_FEE: u64 = 1_500_000_000;
+
+/// 节点是否因查询范围过大或结果过多拒绝了 `eth_getLogs`
+fn is_log_limit_error(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    lower.contains("10000")
+        || lower.contains("block range")
+        || lower.contains("range is too large")
+        || lower.contains("too many")
+        || lower.contains("limit exceeded")
+        || lower.contains("response size")
+}
+
+/// 默认单次 `eth_getLogs` 查询的区块范围
+const DEFAULT_LOG_BLOCK_RANGE: u64 = 2_000;
+
+/// 估算平均出块时间时往前取样的区块数
+const BLOCK_TIME_SAMPLE: u64 = 1_000;
+
+/// 按出块时间估算起始区块时额外多扫描的时间比例
+const BLOCK_ESTIMATE_MARGIN: f64 = 0.1;
+
+/// 普通交易的固有Gas消耗
+pub(crate) const INTRINSIC_GAS: u64 = 21_000;
+
+/// `Panic(uint256)` 的函数选择器
+const PANIC_SELECTOR: [u8; 4] = [0x4e, 0x48, 0x7b, 0x71];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{
+        distribution_reply, in_timezone, mined_receipt, test_block, test_contract,
+        test_contract_address, MockNode, MockReply, TEST_CHAIN_ID,
+    };
+    use chrono::Timelike;
+    use ethers::abi::{encode, Token};
+    use ethers::utils::{id, keccak256};
+    use serde_json::{json, Value};
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    /// 只调用不访问节点的方法时使用的地址，不会真正建立连接
+    const UNUSED_RPC_URL: &str = "http://127.0.0.1:9";
+
+    fn call_data() -> Bytes {
+        Bytes::from(vec![0x12, 0x34, 0x56, 0x78])
+    }
+
+    /// `Error(string)` 编码的回滚数据
+    fn revert_data(reason: &str) -> Value {
+        let mut data = id("Error(string)")[..4].to_vec();
+        data.extend(encode(&[Token::String(reason.to_string())]));
+        Value::String(Bytes::from(data).to_string())
+    }
+
+    /// `eth_estimateGas` 总是返回节点内部错误的模拟节点
+    async fn failing_estimate_node() -> MockNode {
+        MockNode::start(|method, _, _| match method {
+            "eth_estimateGas" => MockReply::error(-32603, "internal error"),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn resolve_gas_limit_aborts_when_estimation_reverts() {
+        let node = MockNode::start(|method, _, _| match method {
+            "eth_estimateGas" => MockReply::Error {
+                code: 3,
+                message: "execution reverted: already distributed today".to_string(),
+                data: Some(revert_data("already distributed today")),
+            },
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        // 开启回退也不能掩盖回滚
+        let contract = test_contract(node.url()).with_gas_estimation_fallback(true);
+
+        let err = contract
+            .resolve_gas_limit(test_contract_address(), call_data())
+            .await
+            .unwrap_err();
+
+        match err {
+            DistributorError::GasEstimation(GasEstimationError::Reverted { reason }) => {
+                assert_eq!(reason, "already distributed today")
+            }
+            other => panic!("应返回估算回滚错误，实际: {:?}", other),
+        }
+        assert_eq!(node.count("eth_estimateGas"), 1);
+    }
+
+    #[tokio::test]
+    async fn resolve_gas_limit_aborts_on_transport_error_by_default() {
+        let node = failing_estimate_node().await;
+        let contract = test_contract(node.url());
+
+        let err = contract
+            .resolve_gas_limit(test_contract_address(), call_data())
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                DistributorError::GasEstimation(GasEstimationError::Transport(_))
+            ),
+            "应返回估算传输错误，实际: {:?}",
+            err
+        );
+        assert!(err.kind().is_retryable());
+    }
+
+    #[tokio::test]
+    async fn resolve_gas_limit_falls_back_to_configured_limit_when_enabled() {
+        let node = failing_estimate_node().await;
+        let contract = test_contract(node.url()).with_gas_estimation_fallback(true);
+
+        let gas_limit = contract
+            .resolve_gas_limit(test_contract_address(), call_data())
+            .await
+            .unwrap();
+
+        assert_eq!(gas_limit, U256::from(DEFAULT_GAS_LIMIT));
+        // 回退前先用完重试次数
+        assert_eq!(node.count("eth_estimateGas"), 3);
+    }
+
+    #[tokio::test]
+    async fn resolve_gas_limit_buffers_successful_estimate() {
+        let node = MockNode::start(|method, _, _| match method {
+            "eth_estimateGas" => MockReply::Result(json!("0x186a0")),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let contract = test_contract(node.url()).with_gas_estimation_fallback(true);
+
+        let gas_limit = contract
+            .resolve_gas_limit(test_contract_address(), call_data())
+            .await
+            .unwrap();
+
+        assert_eq!(gas_limit, U256::from(120_000));
+        let calls = node.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].params[0]["data"], json!("0x12345678"));
+    }
+
+    #[tokio::test]
+    async fn resolve_gas_limit_retries_transport_errors_before_aborting() {
+        let node = failing_estimate_node().await;
+        let contract = test_contract(node.url()).with_gas_estimation_retries(4);
+
+        let err = contract
+            .resolve_gas_limit(test_contract_address(), call_data())
+            .await
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            DistributorError::GasEstimation(GasEstimationError::Transport(_))
+        ));
+        assert_eq!(node.count("eth_estimateGas"), 4);
+    }
+
+    #[tokio::test]
+    async fn resolve_gas_limit_recovers_after_transient_errors() {
+        let node = MockNode::start(|method, _, nth| match method {
+            "eth_estimateGas" if nth < 2 => MockReply::error(-32603, "internal error"),
+            "eth_estimateGas" => MockReply::Result(json!("0x186a0")),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let contract = test_contract(node.url());
+
+        let gas_limit = contract
+            .resolve_gas_limit(test_contract_address(), call_data())
+            .await
+            .unwrap();
+
+        assert_eq!(gas_limit, U256::from(120_000));
+        assert_eq!(node.count("eth_estimateGas"), 3);
+    }
+
+    #[test]
+    fn buffered_gas_adds_twenty_percent_rounded_up() {
+        let contract = test_contract(UNUSED_RPC_URL);
+
+        assert_eq!(contract.buffered_gas(U256::zero()), U256::zero());
+        assert_eq!(
+            contract.buffered_gas(U256::from(100_000)),
+            U256::from(120_000)
+        );
+        assert_eq!(
+            contract.buffered_gas(U256::from(100_001)),
+            U256::from(120_002)
+        );
+        assert_eq!(contract.buffered_gas(U256::from(1)), U256::from(2));
+    }
+
+    #[test]
+    fn buffered_gas_saturates_instead_of_overflowing() {
+        let contract = test_contract(UNUSED_RPC_URL);
+
+        assert_eq!(contract.buffered_gas(U256::MAX), U256::MAX);
+        assert_eq!(contract.buffered_gas(U256::MAX - 1), U256::MAX);
+        assert_eq!(contract.buffered_gas(U256::MAX / 10 * 9), U256::MAX);
+    }
+
+    #[test]
+    fn buffered_gas_is_unchanged_for_exact_estimates() {
+        let contract = test_contract(UNUSED_RPC_URL).with_gas_strategy(GasStrategy::EstimateExact);
+
+        assert_eq!(
+            contract.buffered_gas(U256::from(100_001)),
+            U256::from(100_001)
+        );
+        assert_eq!(contract.buffered_gas(U256::MAX), U256::MAX);
+    }
+
+    #[test]
+    fn capped_gas_limit_clamps_to_ceiling() {
+        let ceiling = U256::from(150_000);
+        let contract = test_contract(UNUSED_RPC_URL).with_gas_limit_ceiling(Some(ceiling));
+
+        assert_eq!(contract.capped_gas_limit(U256::zero()), U256::zero());
+        assert_eq!(
+            contract.capped_gas_limit(U256::from(100_000)),
+            U256::from(120_000)
+        );
+        assert_eq!(contract.capped_gas_limit(U256::from(125_000)), ceiling);
+        assert_eq!(contract.capped_gas_limit(U256::from(130_000)), ceiling);
+        assert_eq!(contract.capped_gas_limit(U256::from(200_000)), ceiling);
+        assert_eq!(contract.capped_gas_limit(U256::MAX), ceiling);
+
+        let uncapped = test_contract(UNUSED_RPC_URL);
+        assert_eq!(uncapped.capped_gas_limit(U256::MAX), U256::MAX);
+    }
+
+    #[test]
+    fn check_gas_estimate_rejects_estimates_at_or_below_intrinsic_gas() {
+        let contract = test_contract(UNUSED_RPC_URL);
+        let min = U256::from(INTRINSIC_GAS + 1);
+
+        for estimate in [0, 1, INTRINSIC_GAS] {
+            match contract.check_gas_estimate(U256::from(estimate)) {
+                Err(GasEstimationError::Implausible {
+                    estimate: rejected,
+                    min: reported_min,
+                }) => {
+                    assert_eq!(rejected, U256::from(estimate));
+                    assert_eq!(reported_min, min);
+                }
+                other => panic!("估算值 {} 应被拒绝，实际: {:?}", estimate, other),
+            }
+        }
+        assert_eq!(contract.check_gas_estimate(min).unwrap(), min);
+        assert_eq!(contract.check_gas_estimate(U256::MAX).unwrap(), U256::MAX);
+    }
+
+    #[test]
+    fn check_gas_estimate_applies_configured_bounds() {
+        let contract = test_contract(UNUSED_RPC_URL)
+            .with_gas_estimate_bounds(Some(U256::from(50_000)), Some(U256::from(1_000_000)));
+
+        assert!(matches!(
+            contract.check_gas_estimate(U256::from(49_999)),
+            Err(GasEstimationError::Implausible { min, .. }) if min == U256::from(50_000)
+        ));
+        assert_eq!(
+            contract.check_gas_estimate(U256::from(300_000)).unwrap(),
+            U256::from(300_000)
+        );
+        assert_eq!(
+            contract.check_gas_estimate(U256::from(2_000_000)).unwrap(),
+            U256::from(1_000_000)
+        );
+        assert_eq!(
+            contract.check_gas_estimate(U256::MAX).unwrap(),
+            U256::from(1_000_000)
+        );
+
+        // 配置的下限低于固有Gas时仍按固有Gas判断
+        let lenient =
+            test_contract(UNUSED_RPC_URL).with_gas_estimate_bounds(Some(U256::from(1_000)), None);
+        assert!(lenient
+            .check_gas_estimate(U256::from(INTRINSIC_GAS))
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn deadline_with_pending_transaction_keeps_it_for_next_run() {
+        let mined = Arc::new(AtomicBool::new(false));
+        let node_mined = mined.clone();
+        let node = MockNode::start(move |method, params, _| {
+            distribution_reply(method, params, &node_mined)
+        })
+        .await;
+        let contract = test_contract(node.url()).with_confirmation_poll(ConfirmationPoll {
+            initial: Duration::from_millis(20),
+            max: Duration::from_millis(20),
+            multiplier: 1.0,
+        });
+
+        let err = contract
+            .distribute_with_options_within(DistributeOptions::default(), Duration::from_secs(1))
+            .await
+            .unwrap_err();
+
+        let tx_hash = match err {
+            DistributorError::Deadline(DeadlineError::PendingPastDeadline { tx_hash, .. }) => {
+                tx_hash
+            }
+            other => panic!("应返回交易仍在等待确认，实际: {:?}", other),
+        };
+        assert_eq!(contract.pending_transaction(), Some(tx_hash));
+        assert_eq!(node.count("eth_sendRawTransaction"), 1);
+
+        // 下次运行时交易已打包，继续等待同一笔交易而不是重新发送
+        mined.store(true, Ordering::SeqCst);
+        let result = contract
+            .distribute_with_options_within(DistributeOptions::default(), Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        assert_eq!(result.tx_hash, tx_hash);
+        assert_eq!(result.attempts, 0);
+        assert_eq!(node.count("eth_sendRawTransaction"), 1);
+        assert_eq!(contract.pending_transaction(), None);
+    }
+
+    #[tokio::test]
+    async fn deadline_before_send_sends_nothing() {
+        let mined = AtomicBool::new(false);
+        let node = MockNode::start(move |method, params, _| match method {
+            // Gas估算迟迟没有返回，截止时间在发送前到达
+            "eth_estimateGas" => MockReply::delayed(
+                Duration::from_secs(5),
+                distribution_reply(method, params, &mined),
+            ),
+            _ => distribution_reply(method, params, &mined),
+        })
+        .await;
+        let contract = test_contract(node.url());
+
+        let err = contract
+            .distribute_with_options_within(
+                DistributeOptions::default(),
+                Duration::from_millis(300),
+            )
+            .await
+            .unwrap_err();
+
+        assert!(
+            matches!(
+                err,
+                DistributorError::Deadline(DeadlineError::BeforeSend { .. })
+            ),
+            "应返回交易尚未发送，实际: {:?}",
+            err
+        );
+        assert!(node.count("eth_estimateGas") > 0);
+        assert_eq!(contract.pending_transaction(), None);
+        // 超时后流程已被取消，之后也不会再发送
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(node.count("eth_sendRawTransaction"), 0);
+    }
+
+    /// 直接签名发送的传统交易，Gas价格1 gwei
+    fn legacy_request() -> TypedTransaction {
+        TransactionRequest::new()
+            .to(test_contract_address())
+            .data(call_data())
+            .gas(100_000)
+            .gas_price(1_000_000_000u64)
+            .nonce(0)
+            .chain_id(TEST_CHAIN_ID)
+            .into()
+    }
+
+    /// 第 `nth` 次发送的原始交易
+    fn sent_transaction(node: &MockNode, nth: usize) -> (H256, TypedTransaction) {
+        let call = node
+            .calls()
+            .into_iter()
+            .filter(|call| call.method == "eth_sendRawTransaction")
+            .nth(nth)
+            .unwrap();
+        let raw: Bytes = serde_json::from_value(call.params[0].clone()).unwrap();
+        let (tx, _) = TypedTransaction::decode_signed(&ethers::utils::rlp::Rlp::new(&raw)).unwrap();
+        (H256(keccak256(&raw)), tx)
+    }
+
+    #[tokio::test]
+    async fn send_signed_waits_for_already_known_transaction() {
+        let node = MockNode::start(|method, _, _| match method {
+            "eth_sendRawTransaction" => MockReply::error(-32000, "already known"),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let contract = test_contract(node.url());
+
+        let tx_hash = contract
+            .send_signed(legacy_request(), Instant::now())
+            .await
+            .unwrap();
+
+        // 节点已有这笔交易，返回本地计算的哈希等待确认，不重新发送
+        assert_eq!(node.count("eth_sendRawTransaction"), 1);
+        assert_eq!(tx_hash, sent_transaction(&node, 0).0);
+    }
+
+    #[tokio::test]
+    async fn send_signed_bumps_fees_when_replacement_is_underpriced() {
+        let node = MockNode::start(|method, params, nth| match method {
+            "eth_sendRawTransaction" if nth == 0 => {
+                MockReply::error(-32000, "replacement transaction underpriced")
+            }
+            "eth_sendRawTransaction" => {
+                let raw: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+                MockReply::Result(json!(H256(keccak256(&raw))))
+            }
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let contract = test_contract(node.url());
+
+        let tx_hash = contract
+            .send_signed(legacy_request(), Instant::now())
+            .await
+            .unwrap();
+
+        assert_eq!(node.count("eth_sendRawTransaction"), 2);
+        let (first_hash, first) = sent_transaction(&node, 0);
+        let (second_hash, second) = sent_transaction(&node, 1);
+        assert_eq!(tx_hash, second_hash);
+        assert_ne!(tx_hash, first_hash);
+        // 加价10%并多加1 wei，nonce不变
+        assert_eq!(first.gas_price(), Some(U256::from(1_000_000_000u64)));
+        assert_eq!(second.gas_price(), Some(U256::from(1_100_000_001u64)));
+        assert_eq!(second.nonce(), first.nonce());
+    }
+
+    #[tokio::test]
+    async fn send_signed_stops_bumping_after_max_bumps() {
+        let node = MockNode::start(|method, _, _| match method {
+            "eth_sendRawTransaction" => {
+                MockReply::error(-32000, "replacement transaction underpriced")
+            }
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let contract = test_contract(node.url()).with_fee_escalation(FeeEscalation {
+            max_bumps: 2,
+            ..FeeEscalation::default()
+        });
+
+        let err = contract
+            .send_signed(legacy_request(), Instant::now())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::ReplacementUnderpriced);
+        assert_eq!(node.count("eth_sendRawTransaction"), 3);
+    }
+
+    #[tokio::test]
+    async fn send_signed_returns_mined_pending_transaction_on_nonce_too_low() {
+        let node = MockNode::start(|method, params, _| match method {
+            "eth_sendRawTransaction" => MockReply::error(-32000, "nonce too low"),
+            "eth_getTransactionReceipt" => MockReply::Result(mined_receipt(&params[0])),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let contract = test_contract(node.url());
+        let previous = H256::repeat_byte(0xaa);
+        contract.set_pending_transaction(Some(previous));
+
+        let tx_hash = contract
+            .send_signed(legacy_request(), Instant::now())
+            .await
+            .unwrap();
+
+        // nonce被上一笔已打包的分发交易占用，直接使用它而不是重新发送
+        assert_eq!(tx_hash, previous);
+        assert_eq!(node.count("eth_sendRawTransaction"), 1);
+        assert_eq!(node.count("eth_getTransactionReceipt"), 1);
+    }
+
+    #[tokio::test]
+    async fn send_signed_returns_nonce_too_low_without_mined_pending_transaction() {
+        let node = MockNode::start(|method, _, _| match method {
+            "eth_sendRawTransaction" => MockReply::error(-32000, "nonce too low"),
+            "eth_getTransactionReceipt" => MockReply::Result(Value::Null),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let contract = test_contract(node.url());
+        contract.set_pending_transaction(Some(H256::repeat_byte(0xaa)));
+
+        let err = contract
+            .send_signed(legacy_request(), Instant::now())
+            .await
+            .unwrap_err();
+
+        // 交给上层重试重新获取nonce
+        assert_eq!(err.kind(), ErrorKind::NonceTooLow);
+        assert_eq!(node.count("eth_sendRawTransaction"), 1);
+        assert_eq!(node.count("eth_getTransactionReceipt"), 1);
+    }
+
+    #[tokio::test]
+    async fn send_signed_redetects_fee_mode_after_unsupported_tx_type() {
+        let node = MockNode::start(|method, _, nth| match method {
+            // 第一次检测时链支持EIP-1559，之后切换到了不支持的节点
+            "eth_getBlockByNumber" if nth == 0 => {
+                MockReply::Result(test_block(100, 0, Some(1_000_000_000)))
+            }
+            "eth_getBlockByNumber" => MockReply::Result(test_block(101, 0, None)),
+            "eth_sendRawTransaction" => MockReply::error(-32000, "transaction type not supported"),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let contract = test_contract(node.url()).with_tx_type(TxType::Auto);
+        let client = contract.client.clone();
+        assert_eq!(
+            contract.fee_mode.resolve(client.as_ref()).await.unwrap(),
+            FeeMode::Eip1559
+        );
+
+        let err = contract
+            .send_signed(legacy_request(), Instant::now())
+            .await
+            .unwrap_err();
+
+        assert_eq!(err.kind(), ErrorKind::UnsupportedTxType);
+        assert_eq!(node.count("eth_sendRawTransaction"), 1);
+        // 缓存的检测结果已清除，下次构建交易时重新查询最新区块
+        assert_eq!(
+            contract.fee_mode.resolve(client.as_ref()).await.unwrap(),
+            FeeMode::Legacy
+        );
+        assert_eq!(node.count("eth_getBlockByNumber"), 2);
+    }
+
+    #[test]
+    fn confirmed_today_uses_schedule_timezone() {
+        // 选一个当前日期与UTC不同的时区，以及在UTC是今天、在该时区不是今天的确认时间
+        let now = Utc::now();
+        let utc_today = now.date_naive();
+        let (tz, confirmed_at) = if now.hour() >= 12 {
+            (
+                "Etc/GMT-14",
+                utc_today.and_hms_opt(0, 0, 1).unwrap().and_utc(),
+            )
+        } else {
+            (
+                "Etc/GMT+12",
+                utc_today.and_hms_opt(23, 59, 59).unwrap().and_utc(),
+            )
+        };
+        let path =
+            std::env::temp_dir().join(format!("confirmed-today-{}.json", std::process::id()));
+        let state = Arc::new(StateStore::load(&path).unwrap());
+        let tx_hash = H256::repeat_byte(0x11);
+        state
+            .record_attempt(
+                test_contract_address(),
+                DistributionRecord {
+                    tx_hash,
+                    nonce: None,
+                    timestamp: confirmed_at,
+                    status: RecordStatus::Pending,
+                    trigger: Trigger::Cron,
+                    block_hash: None,
+                    verified: None,
+                    gas_used: None,
+                    fee: None,
+                },
+            )
+            .unwrap();
+        state
+            .record_status(test_contract_address(), tx_hash, RecordStatus::Confirmed)
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        let (utc, local) = in_timezone(tz, move || {
+            let contract = test_contract(UNUSED_RPC_URL).with_state_store(Some(state));
+            let local = contract
+                .clone()
+                .with_schedule_timezone(ScheduleTimezone::Local);
+            (contract.confirmed_today(), local.confirmed_today())
+        });
+
+        assert_eq!(utc, Some(tx_hash));
+        assert_eq!(local, None);
+    }
+
+    #[tokio::test]
+    async fn send_call_retry_adopts_equivalent_pending_transaction() {
+        let pool_tx = H256::repeat_byte(0x77);
+        let signer = test_contract(UNUSED_RPC_URL).client.address();
+        let sent = AtomicBool::new(false);
+        let mined = AtomicBool::new(false);
+        let node = MockNode::start(move |method, params, _| match method {
+            // 节点收到了交易但响应失败，交易已进入内存池
+            "eth_sendRawTransaction" => {
+                sent.store(true, Ordering::SeqCst);
+                MockReply::Status {
+                    status: 502,
+                    retry_after: None,
+                }
+            }
+            "eth_getTransactionCount" if params[1] == "pending" && sent.load(Ordering::SeqCst) => {
+                MockReply::Result(json!("0x1"))
+            }
+            "txpool_content" => MockReply::Result(json!({
+                "pending": {
+                    format!("{:?}", signer): {
+                        "0": {
+                            "hash": pool_tx,
+                            "nonce": "0x0",
+                            "blockHash": null,
+                            "blockNumber": null,
+                            "transactionIndex": null,
+                            "from": format!("{:?}", signer),
+                            "to": format!("{:?}", test_contract_address()),
+                            "value": "0x0",
+                            "gasPrice": "0x3b9aca00",
+                            "gas": "0x186a0",
+                            "input": call_data(),
+                            "v": "0x1",
+                            "r": "0x1",
+                            "s": "0x1",
+                        }
+                    }
+                },
+                "queued": {},
+            })),
+            _ => distribution_reply(method, params, &mined),
+        })
+        .await;
+        let contract = test_contract(node.url());
+
+        let (tx_hash, attempts) = contract
+            .send_call(
+                test_contract_address(),
+                call_data(),
+                &DistributeOptions::default(),
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(tx_hash, pool_tx);
+        assert_eq!(attempts, 2);
+        assert_eq!(node.count("eth_sendRawTransaction"), 1);
+        assert_eq!(contract.pending_transaction(), Some(pool_tx));
+    }
+
+    #[tokio::test]
+    async fn send_call_retry_resends_without_equivalent_pending_transaction() {
+        let mined = AtomicBool::new(false);
+        let node = MockNode::start(move |method, params, nth| match method {
+            // 第一次发送没有到达节点，内存池中没有等价的交易
+            "eth_sendRawTransaction" if nth == 0 => MockReply::Status {
+                status: 502,
+                retry_after: None,
+            },
+            _ => distribution_reply(method, params, &mined),
+        })
+        .await;
+        let contract = test_contract(node.url());
+
+        let (tx_hash, attempts) = contract
+            .send_call(
+                test_contract_address(),
+                call_data(),
+                &DistributeOptions::default(),
+                false,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(node.count("eth_sendRawTransaction"), 2);
+        assert_eq!(node.count("txpool_content"), 0);
+        assert_eq!(tx_hash, sent_transaction(&node, 1).0);
     }
 }
@@ -1,93 +1,1419 @@
-use crate::contract::RewardsContract;
-use anyhow::Result;
+use crate::contract::{
+    decode_revert_data, decode_revert_reason, DistributeOptions, DistributionResult,
+    RewardsContract,
+};
+use crate::error::DistributorError;
+use crate::history::{ExecutionOutcome, ExecutionRecord};
+use crate::monitor::{recent_costs, runway, RunwayThresholds};
+use crate::preflight::CallProbe;
+use crate::preflight::ProxyInfo;
+use crate::provider::DistributorClient;
+use crate::state::ImplementationRecord;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use ethers::abi::{self, Abi, Event, RawLog, Token};
 use ethers::prelude::*;
 use ethers::types::transaction::eip2718::TypedTransaction;
-use std::sync::Arc;
-use tracing::info;
+use ethers::utils::{format_ether, id, keccak256, to_checksum};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use tracing::{error, info, warn};
+
+/// 最新区块的时间戳落后超过这个秒数时，认为节点可能没有同步到最新
+const STALE_BLOCK_SECS: i64 = 300;
+
+/// 内置的常见事件，合约ABI中没有的日志按这些事件尝试解码
+fn common_events() -> Abi {
+    abi::parse_abi(&[
+        "event Transfer(address indexed from, address indexed to, uint256 value)",
+        "event Approval(address indexed owner, address indexed spender, uint256 value)",
+        "event OwnershipTransferred(address indexed previousOwner, address indexed newOwner)",
+        "event Paused(address account)",
+        "event Unpaused(address account)",
+    ])
+    .expect("内置事件ABI有效")
+}
+
+/// 失败交易诊断信息的来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureTraceSource {
+    /// `debug_traceTransaction` 的 `callTracer` 结果
+    Trace,
+    /// 节点不支持追踪时，在失败区块上重新模拟执行
+    Simulation,
+}
+
+/// 已上链但执行失败的交易的诊断结果
+#[derive(Debug, Clone, Serialize)]
+pub struct FailureTrace {
+    pub tx_hash: H256,
+    pub source: FailureTraceSource,
+    pub revert_reason: Option<String>,
+    /// 最深一层失败调用的目标合约
+    pub failing_target: Option<Address>,
+    /// 最深一层失败调用的函数选择器
+    pub failing_selector: Option<Bytes>,
+}
+
+impl fmt::Display for FailureTrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "回滚原因: {}",
+            self.revert_reason.as_deref().unwrap_or("未知")
+        )?;
+        if let Some(target) = self.failing_target {
+            write!(f, "，失败调用: {:?}", target)?;
+        }
+        if let Some(selector) = &self.failing_selector {
+            write!(f, "，选择器: {}", selector)?;
+        }
+        let source = match self.source {
+            FailureTraceSource::Trace => "debug_traceTransaction",
+            FailureTraceSource::Simulation => "重新模拟",
+        };
+        write!(f, " (来源: {})", source)
+    }
+}
+
+/// 解码后的一条收据日志，无法识别的事件保留原始topics和data
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedLog {
+    /// 日志在收据中的序号
+    pub index: usize,
+    pub address: Address,
+    /// 事件签名，如 `Transfer(address,address,uint256)`，无法识别时为 `None`
+    pub event: Option<String>,
+    pub params: Vec<DecodedParam>,
+    pub topics: Vec<H256>,
+    pub data: Bytes,
+}
+
+/// 事件的一个参数，`value` 已格式化（地址为校验和格式，数量附带科学计数法）
+#[derive(Debug, Clone, Serialize)]
+pub struct DecodedParam {
+    pub name: String,
+    pub indexed: bool,
+    pub value: String,
+}
+
+impl fmt::Display for DecodedLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {} ", self.index, to_checksum(&self.address, None))?;
+        let Some(event) = &self.event else {
+            write!(f, "未知事件")?;
+            for (i, topic) in self.topics.iter().enumerate() {
+                write!(f, "\n    topic{}: {:?}", i, topic)?;
+            }
+            return write!(f, "\n    data: {}", self.data);
+        };
+
+        write!(f, "{}", event)?;
+        for param in &self.params {
+            let indexed = if param.indexed { " (indexed)" } else { "" };
+            write!(f, "\n    {}{}: {}", param.name, indexed, param.value)?;
+        }
+        Ok(())
+    }
+}
+
+/// 解码收据中的全部日志
+///
+/// 依次按传入的ABI和内置常见事件（ERC20 Transfer/Approval、Ownable、Pausable）匹配，
+/// topic0和indexed参数数量都一致且能解码时采用；都不匹配的日志原样保留。
+pub fn decode_receipt_logs(receipt: &TransactionReceipt, abis: &[&Abi]) -> Vec<DecodedLog> {
+    let common_events = common_events();
+    receipt
+        .logs
+        .iter()
+        .enumerate()
+        .map(|(index, log)| {
+            let decoded = abis
+                .iter()
+                .copied()
+                .chain(std::iter::once(&common_events))
+                .flat_map(|abi| abi.events())
+                .find_map(|event| decode_log(event, log));
+
+            let (event, params) = match decoded {
+                Some((event, params)) => (Some(event), params),
+                None => (None, Vec::new()),
+            };
+            DecodedLog {
+                index,
+                address: log.address,
+                event,
+                params,
+                topics: log.topics.clone(),
+                data: log.data.clone(),
+            }
+        })
+        .collect()
+}
+
+fn decode_log(event: &Event, log: &Log) -> Option<(String, Vec<DecodedParam>)> {
+    if log.topics.first() != Some(&event.signature()) {
+        return None;
+    }
+    let parsed = event
+        .parse_log(RawLog {
+            topics: log.topics.clone(),
+            data: log.data.to_vec(),
+        })
+        .ok()?;
+
+    let params = event
+        .inputs
+        .iter()
+        .zip(parsed.params)
+        .map(|(input, param)| DecodedParam {
+            name: param.name,
+            indexed: input.indexed,
+            value: format_token(&param.value),
+        })
+        .collect();
+    let signature = format!(
+        "{}({})",
+        event.name,
+        event
+            .inputs
+            .iter()
+            .map(|input| input.kind.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    );
+    Some((signature, params))
+}
+
+/// 格式化事件参数：地址使用校验和格式，较大的整数附带科学计数法便于阅读
+fn format_token(token: &Token) -> String {
+    match token {
+        Token::Address(address) => to_checksum(address, None),
+        Token::Uint(value) => humanize_amount(*value),
+        Token::Array(tokens) | Token::FixedArray(tokens) | Token::Tuple(tokens) => format!(
+            "[{}]",
+            tokens
+                .iter()
+                .map(format_token)
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+        other => other.to_string(),
+    }
+}
+
+/// 代币精度未知，数量统一附带科学计数法，如 `1500000000000000000 (1.5e18)`
+fn humanize_amount(value: U256) -> String {
+    let digits = value.to_string();
+    if digits.len() <= 6 {
+        return digits;
+    }
+
+    let fraction = digits[1..5].trim_end_matches('0');
+    let mantissa = if fraction.is_empty() {
+        digits[..1].to_string()
+    } else {
+        format!("{}.{}", &digits[..1], fraction)
+    };
+    format!("{} ({}e{})", digits, mantissa, digits.len() - 1)
+}
+
+/// 单个账户的状态覆盖
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AccountOverride {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub balance: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<Bytes>,
+    /// 只覆盖列出的存储槽，其余保持链上状态
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub state_diff: BTreeMap<H256, H256>,
+}
+
+/// `eth_call` 第三个参数的状态覆盖，以地址为键
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct StateOverrides(BTreeMap<Address, AccountOverride>);
+
+impl StateOverrides {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 覆盖ETH余额
+    pub fn balance(mut self, address: Address, amount: U256) -> Self {
+        self.account(address).balance = Some(amount);
+        self
+    }
+
+    /// 覆盖合约代码
+    pub fn code(mut self, address: Address, code: Bytes) -> Self {
+        self.account(address).code = Some(code);
+        self
+    }
+
+    /// 覆盖单个存储槽
+    pub fn storage(mut self, address: Address, slot: H256, value: H256) -> Self {
+        self.account(address).state_diff.insert(slot, value);
+        self
+    }
+
+    /// 覆盖ERC20代币余额
+    ///
+    /// `balances_slot` 是代币合约中 `mapping(address => uint256)` 余额映射的存储槽序号
+    /// （OpenZeppelin ERC20通常为0）。
+    pub fn erc20_balance(
+        self,
+        token: Address,
+        holder: Address,
+        balances_slot: u64,
+        amount: U256,
+    ) -> Self {
+        let slot = keccak256(abi::encode(&[
+            Token::Address(holder),
+            Token::Uint(balances_slot.into()),
+        ]));
+        self.storage(token, H256(slot), H256::from_uint(&amount))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    fn account(&mut self, address: Address) -> &mut AccountOverride {
+        self.0.entry(address).or_default()
+    }
+}
+
+/// 诊断步骤的结果，按严重程度排序
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Pass,
+    Warn,
+    Fail,
+}
+
+impl fmt::Display for StepStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepStatus::Pass => write!(f, "✅ 通过"),
+            StepStatus::Warn => write!(f, "⚠️ 警告"),
+            StepStatus::Fail => write!(f, "❌ 失败"),
+        }
+    }
+}
+
+/// 一个诊断步骤的结果和详细信息
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosisStep {
+    /// 步骤序号，从1开始
+    pub step: u8,
+    pub name: &'static str,
+    pub status: StepStatus,
+    pub details: Vec<String>,
+}
+
+impl DiagnosisStep {
+    fn new(step: u8, name: &'static str) -> Self {
+        Self {
+            step,
+            name,
+            status: StepStatus::Pass,
+            details: Vec::new(),
+        }
+    }
+
+    fn pass(&mut self, detail: impl Into<String>) {
+        self.details.push(detail.into());
+    }
+
+    /// 记录警告，已失败的步骤保持失败
+    fn warn(&mut self, detail: impl Into<String>) {
+        if self.status == StepStatus::Pass {
+            self.status = StepStatus::Warn;
+        }
+        self.details.push(detail.into());
+    }
+
+    fn fail(&mut self, detail: impl Into<String>) {
+        self.status = StepStatus::Fail;
+        self.details.push(detail.into());
+    }
+}
+
+/// `diagnose` 的结果，每个步骤各自记录通过、警告或失败，一个步骤失败不影响后续步骤
+#[derive(Debug, Clone, Serialize)]
+pub struct DiagnosisReport {
+    pub steps: Vec<DiagnosisStep>,
+}
+
+impl DiagnosisReport {
+    /// 是否有步骤失败
+    pub fn has_failures(&self) -> bool {
+        self.steps
+            .iter()
+            .any(|step| step.status == StepStatus::Fail)
+    }
+
+    /// 失败的步骤
+    pub fn failures(&self) -> impl Iterator<Item = &DiagnosisStep> {
+        self.steps
+            .iter()
+            .filter(|step| step.status == StepStatus::Fail)
+    }
+
+    /// 诊断命令的退出码：全部通过为0，有警告为1，有失败为2
+    pub fn exit_code(&self) -> i32 {
+        match self.steps.iter().map(|step| step.status).max() {
+            Some(StepStatus::Fail) => 2,
+            Some(StepStatus::Warn) => 1,
+            _ => 0,
+        }
+    }
+
+    /// 按步骤记录日志，失败记为错误，警告记为警告
+    pub fn log(&self) {
+        for step in &self.steps {
+            let details = step.details.join("；");
+            match step.status {
+                StepStatus::Pass => {
+                    info!("{}. {} {}: {}", step.step, step.name, step.status, details)
+                }
+                StepStatus::Warn => {
+                    warn!("{}. {} {}: {}", step.step, step.name, step.status, details)
+                }
+                StepStatus::Fail => {
+                    error!("{}. {} {}: {}", step.step, step.name, step.status, details)
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for DiagnosisReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for step in &self.steps {
+            writeln!(f, "{}. {} {}", step.step, step.name, step.status)?;
+            for detail in &step.details {
+                writeln!(f, "   - {}", detail)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 一笔已上链分发交易的Gas数据
+#[derive(Debug, Clone, Serialize)]
+pub struct GasObservation {
+    pub tx_hash: H256,
+    /// 执行记录中该次执行的开始时间
+    pub started_at: DateTime<Utc>,
+    pub gas_used: U256,
+    /// 交易的Gas限制，无法获取交易时为空
+    pub gas_limit: Option<U256>,
+    /// Gas限制中未用完的比例（百分比）
+    pub headroom_percent: Option<f64>,
+}
+
+/// `gas_report` 的结果：当前估算、历史实际用量和建议的GAS_LIMIT
+#[derive(Debug, Clone, Serialize)]
+pub struct GasReport {
+    /// 用当前calldata估算的Gas，估算失败时为空
+    pub estimate: Option<U256>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub estimate_error: Option<String>,
+    /// 估算值按Gas策略加缓冲（不超过GAS_LIMIT_CEILING）后的Gas限制
+    pub buffered_limit: Option<U256>,
+    /// 配置的GAS_LIMIT
+    pub configured_limit: U256,
+    /// 最近成功分发的交易，按时间先后排列；没有执行记录时为空
+    pub observations: Vec<GasObservation>,
+    pub max_observed: Option<U256>,
+    pub safety_factor: f64,
+    /// 历史最大用量（没有历史时为估算值）× 安全系数，两者都没有时为空
+    pub recommended_gas_limit: Option<U256>,
+}
+
+impl fmt::Display for GasReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let or_none =
+            |value: Option<U256>| value.map_or("-".to_string(), |value| value.to_string());
+        match (&self.estimate, &self.estimate_error) {
+            (Some(estimate), _) => writeln!(
+                f,
+                "当前估算: {}，加缓冲后: {}，配置的GAS_LIMIT: {}",
+                estimate,
+                or_none(self.buffered_limit),
+                self.configured_limit
+            )?,
+            (None, error) => writeln!(
+                f,
+                "当前估算失败: {}，配置的GAS_LIMIT: {}",
+                error.as_deref().unwrap_or("未知错误"),
+                self.configured_limit
+            )?,
+        }
+
+        if self.observations.is_empty() {
+            writeln!(f, "没有历史分发记录，只根据当前估算给出建议")?;
+        } else {
+            writeln!(
+                f,
+                "{:<66}  {:<25}  {:>12}  {:>12}  {:>8}  {:>10}",
+                "交易", "时间", "实际用量", "Gas限制", "余量", "对比估算"
+            )?;
+            for observation in &self.observations {
+                let versus_estimate = self
+                    .estimate
+                    .filter(|estimate| !estimate.is_zero())
+                    .map(|estimate| {
+                        let ratio =
+                            observation.gas_used.low_u128() as f64 / estimate.low_u128() as f64;
+                        format!("{:+.1}%", (ratio - 1.0) * 100.0)
+                    })
+                    .unwrap_or_else(|| "-".to_string());
+                writeln!(
+                    f,
+                    "{:<66}  {:<25}  {:>12}  {:>12}  {:>8}  {:>10}",
+                    format!("{:?}", observation.tx_hash),
+                    observation
+                        .started_at
+                        .format("%Y-%m-%d %H:%M:%S UTC")
+                        .to_string(),
+                    observation.gas_used,
+                    or_none(observation.gas_limit),
+                    observation
+                        .headroom_percent
+                        .map_or("-".to_string(), |percent| format!("{:.1}%", percent)),
+                    versus_estimate
+                )?;
+            }
+            writeln!(f, "历史最大用量: {}", or_none(self.max_observed))?;
+        }
+
+        match self.recommended_gas_limit {
+            Some(limit) => write!(
+                f,
+                "建议GAS_LIMIT: {} (安全系数 {})",
+                limit, self.safety_factor
+            ),
+            None => write!(f, "没有估算值和历史记录，无法给出建议"),
+        }
+    }
+}
+
+/// `value × factor`，向上取整；系数按千分之一精度计算
+fn scale_gas(value: U256, factor: f64) -> U256 {
+    let permille = U256::from((factor * 1000.0).ceil() as u64);
+    let (quotient, remainder) = value.saturating_mul(permille).div_mod(U256::from(1000));
+    if remainder.is_zero() {
+        quotient
+    } else {
+        quotient + 1
+    }
+}
 
 pub struct ContractDebugger {
     contract: RewardsContract,
+    /// 执行记录，用于估算余额可维持的天数
+    history: Vec<ExecutionRecord>,
+    runway_thresholds: RunwayThresholds,
+    /// 每天的分发次数，把可运行次数换算成天数
+    runs_per_day: f64,
 }
 
 impl ContractDebugger {
     pub fn new(contract: RewardsContract) -> Self {
-        Self { contract }
+        Self {
+            contract,
+            history: Vec::new(),
+            runway_thresholds: RunwayThresholds::default(),
+            runs_per_day: 1.0,
+        }
+    }
+
+    /// 诊断时按执行记录中最近几次分发的平均花费估算余额可维持的天数，低于阈值时记为警告或失败
+    ///
+    /// `runs_per_day` 为调度每天的分发次数，用于把可运行次数换算成天数。
+    pub fn with_runway(
+        mut self,
+        history: Vec<ExecutionRecord>,
+        thresholds: RunwayThresholds,
+        runs_per_day: f64,
+    ) -> Self {
+        self.history = history;
+        self.runway_thresholds = thresholds;
+        self.runs_per_day = runs_per_day;
+        self
     }
 
-    /// 执行完整的合约诊断
-    pub async fn diagnose(&self) -> Result<()> {
+    /// 执行完整的合约诊断，返回每个步骤的结果
+    ///
+    /// 依次检查RPC连接、区块同步、签名账户余额、合约字节码、权限、暂停状态和Gas估算，
+    /// 最后模拟执行分发调用。某个步骤失败时记录后继续执行后续步骤。
+    /// 指定 `block` 时在该区块的状态上模拟执行，用于排查历史上的失败。
+    pub async fn diagnose(&self, block: Option<BlockId>) -> DiagnosisReport {
         info!("=== 开始合约诊断 ===");
 
-        // 8. 模拟执行
+        let fees = self.contract.configured_fees();
+        info!(
+            "费用配置: 链ID {}，交易类型 {:?}，Gas限制 {}，Gas价格 {}",
+            fees.chain_id,
+            fees.tx_type,
+            fees.gas_limit,
+            fees.gas_price
+                .map(|price| format!("{} wei", price))
+                .unwrap_or_else(|| "未配置（使用节点建议价格）".to_string())
+        );
+
+        let steps = vec![
+            self.check_rpc().await,
+            self.check_sync().await,
+            self.check_funds().await,
+            self.check_bytecode().await,
+            self.check_owner().await,
+            self.check_paused().await,
+            self.check_gas_estimate().await,
+            self.check_simulation(block).await,
+        ];
+
+        info!("=== 诊断完成 ===");
+        DiagnosisReport { steps }
+    }
+
+    /// 1. 节点是否可连接，链ID是否与配置一致
+    async fn check_rpc(&self) -> DiagnosisStep {
+        let mut step = DiagnosisStep::new(1, "RPC连接和链ID");
+        info!("1. 检查RPC连接和链ID...");
+        let configured = self.contract.chain_id();
+        match self.contract.client.get_chainid().await {
+            Ok(chain_id) if chain_id == U256::from(configured) => {
+                step.pass(format!("节点链ID {}，与配置一致", chain_id))
+            }
+            Ok(chain_id) => step.fail(format!(
+                "节点链ID {} 与配置的CHAIN_ID {} 不一致",
+                chain_id, configured
+            )),
+            Err(e) => step.fail(format!("无法连接RPC节点: {}", e)),
+        }
+        step
+    }
+
+    /// 2. 最新区块高度，以及节点是否同步到最新
+    async fn check_sync(&self) -> DiagnosisStep {
+        let mut step = DiagnosisStep::new(2, "区块高度和同步状态");
+        info!("2. 检查区块高度和同步状态...");
+        let client = &self.contract.client;
+        match client.get_block(BlockNumber::Latest).await {
+            Ok(Some(latest)) => {
+                let number = latest.number.unwrap_or_default();
+                let age = Utc::now().timestamp() - latest.timestamp.low_u64() as i64;
+                if age > STALE_BLOCK_SECS {
+                    step.warn(format!(
+                        "最新区块 {} 产生于 {} 秒前，节点可能落后",
+                        number, age
+                    ));
+                } else {
+                    step.pass(format!("最新区块 {}（{} 秒前）", number, age.max(0)));
+                }
+            }
+            Ok(None) => step.fail("节点没有返回最新区块"),
+            Err(e) => step.fail(format!("获取最新区块失败: {}", e)),
+        }
+        match client.syncing().await {
+            Ok(SyncingStatus::IsFalse) => step.pass("节点已同步"),
+            Ok(SyncingStatus::IsSyncing(progress)) => step.warn(format!(
+                "节点正在同步: 当前区块 {}，最高区块 {}",
+                progress.current_block, progress.highest_block
+            )),
+            Err(e) => step.warn(format!("查询同步状态失败: {}", e)),
+        }
+        step
+    }
+
+    /// 3. 签名账户余额是否足以支付本次运行所有分发交易的最多花费，以及余额可维持的天数
+    async fn check_funds(&self) -> DiagnosisStep {
+        let mut step = DiagnosisStep::new(3, "签名账户余额和预计花费");
+        info!("3. 检查签名账户余额和预计花费...");
+        let account = self.contract.client_address();
+        let balance = match self.contract.client.get_balance(account, None).await {
+            Ok(balance) => balance,
+            Err(e) => {
+                step.fail(format!("查询签名账户 {:?} 余额失败: {}", account, e));
+                return step;
+            }
+        };
+        step.pass(format!(
+            "签名账户 {:?} 余额 {} ETH",
+            account,
+            format_ether(balance)
+        ));
+
+        let gas_limit = self.contract.gas_limit();
+        let transactions = self.contract.reward_contracts().len().max(1);
+        let estimated = match self
+            .contract
+            .max_gas_cost(gas_limit, &DistributeOptions::default())
+            .await
+        {
+            Ok(per_tx) => {
+                let total = per_tx.saturating_mul(U256::from(transactions));
+                if balance < per_tx {
+                    step.fail(format!(
+                        "余额不足以支付一笔分发交易的最多花费 {} ETH",
+                        format_ether(per_tx)
+                    ));
+                } else if balance < total {
+                    step.warn(format!(
+                        "余额不足以支付全部 {} 笔分发交易的最多花费 {} ETH",
+                        transactions,
+                        format_ether(total)
+                    ));
+                } else {
+                    step.pass(format!(
+                        "本次运行最多花费 {} ETH（{} 笔交易，每笔Gas限制 {}）",
+                        format_ether(total),
+                        transactions,
+                        gas_limit
+                    ));
+                }
+                Some(total)
+            }
+            Err(e) => {
+                step.warn(format!("无法估算运行花费: {}", e));
+                None
+            }
+        };
+
+        // 没有执行记录时按估算的最多花费计算，结果偏保守
+        let costs = recent_costs(&self.history);
+        let (days, basis) = match runway(balance, &costs, self.runs_per_day) {
+            Some(days) => (Some(days), format!("最近 {} 次分发的平均花费", costs.len())),
+            None => (
+                estimated.and_then(|total| runway(balance, &[total], self.runs_per_day)),
+                "估算的每次运行最多花费".to_string(),
+            ),
+        };
+        match days {
+            Some(days) => {
+                let detail = format!("按{}，余额约可维持 {:.1} 天的分发", basis, days);
+                if days < self.runway_thresholds.fail_days {
+                    step.fail(detail);
+                } else if days < self.runway_thresholds.warn_days {
+                    step.warn(detail);
+                } else {
+                    step.pass(detail);
+                }
+            }
+            None => step.warn("没有执行记录也无法估算花费，无法计算余额可维持的天数"),
+        }
+        step
+    }
+
+    /// 4. 每个奖励合约是否有字节码、是否为代理合约、分发函数是否存在
+    async fn check_bytecode(&self) -> DiagnosisStep {
+        let mut step = DiagnosisStep::new(4, "合约字节码");
+        info!("4. 检查合约字节码...");
+        for contract in self.contract.reward_contracts() {
+            match self.contract.contract_code(contract).await {
+                Ok(code) if code.is_empty() => step.fail(format!("{:?} 上没有合约代码", contract)),
+                Ok(code) => step.pass(format!("{:?} 字节码 {} 字节", contract, code.len())),
+                Err(e) => step.fail(format!("获取 {:?} 的字节码失败: {}", contract, e)),
+            }
+        }
+
+        match self.check_proxies().await {
+            Ok(proxies) => {
+                for proxy in proxies {
+                    step.pass(format!(
+                        "{:?} 是EIP-1967代理合约，实现合约 {:?}",
+                        proxy.proxy, proxy.implementation
+                    ));
+                }
+            }
+            Err(e) => step.warn(format!("代理合约检查失败: {}", e)),
+        }
+
+        match self.contract.preflight().await {
+            Ok(report) => {
+                for call in report.calls {
+                    let detail = format!(
+                        "{:?} 选择器 {}: {}",
+                        call.contract, call.selector, call.probe
+                    );
+                    match call.probe {
+                        CallProbe::FunctionMissing { .. } => step.fail(detail),
+                        _ => step.pass(detail),
+                    }
+                }
+            }
+            Err(e) => step.fail(format!("探测分发函数失败: {}", e)),
+        }
+        step
+    }
+
+    /// 5. 合约有 `owner()` 时，签名账户是否为owner
+    ///
+    /// 分发函数不一定有权限限制，所以owner不是签名账户时只记为警告。
+    async fn check_owner(&self) -> DiagnosisStep {
+        let mut step = DiagnosisStep::new(5, "权限检查");
+        info!("5. 检查合约owner...");
+        let account = self.contract.client_address();
+        for contract in self.contract.reward_contracts() {
+            match self.view_call(contract, "owner()").await {
+                Ok(Some(data)) => {
+                    let owner = Address::from_slice(&data[12..32]);
+                    if owner == account {
+                        step.pass(format!("{:?} 的owner是签名账户", contract));
+                    } else {
+                        step.warn(format!(
+                            "{:?} 的owner是 {:?}，不是签名账户 {:?}，分发函数有权限限制时会回滚",
+                            contract, owner, account
+                        ));
+                    }
+                }
+                Ok(None) => step.pass(format!("{:?} 没有owner()，跳过权限检查", contract)),
+                Err(e) => step.warn(format!("{:?}: {}", contract, e)),
+            }
+        }
+        step
+    }
+
+    /// 6. 合约有 `paused()` 时是否已暂停
+    async fn check_paused(&self) -> DiagnosisStep {
+        let mut step = DiagnosisStep::new(6, "暂停状态");
+        info!("6. 检查合约暂停状态...");
+        for contract in self.contract.reward_contracts() {
+            match self.view_call(contract, "paused()").await {
+                Ok(Some(data)) if data[..32].iter().any(|byte| *byte != 0) => {
+                    step.fail(format!("{:?} 已暂停，分发会回滚", contract))
+                }
+                Ok(Some(_)) => step.pass(format!("{:?} 未暂停", contract)),
+                Ok(None) => step.pass(format!("{:?} 没有paused()，跳过暂停检查", contract)),
+                Err(e) => step.warn(format!("{:?}: {}", contract, e)),
+            }
+        }
+        step
+    }
+
+    /// 7. 估算分发调用的Gas，输出按Gas策略加缓冲后的Gas限制
+    async fn check_gas_estimate(&self) -> DiagnosisStep {
+        let mut step = DiagnosisStep::new(7, "Gas估算");
+        info!("7. 估算Gas...");
+        let (to, call_data) = match self.contract.distribution_call().await {
+            Ok(call) => call,
+            Err(e) => {
+                step.fail(format!("构建分发调用失败: {}", e));
+                return step;
+            }
+        };
+        match self.contract.estimate_gas(to, call_data).await {
+            Ok(estimate) => {
+                step.pass(format!(
+                    "估算值 {}，加缓冲后的Gas限制 {}",
+                    estimate,
+                    self.contract.capped_gas_limit(estimate)
+                ));
+                let configured = self.contract.gas_limit();
+                if estimate > configured {
+                    step.warn(format!(
+                        "估算值超过配置的GAS_LIMIT {}，使用固定Gas限制时交易会因Gas不足失败",
+                        configured
+                    ));
+                }
+            }
+            Err(e) => step.fail(format!("Gas估算失败: {}", e)),
+        }
+        step
+    }
+
+    /// 8. 模拟执行分发调用
+    async fn check_simulation(&self, block: Option<BlockId>) -> DiagnosisStep {
+        let mut step = DiagnosisStep::new(8, "模拟交易执行");
         info!("8. 模拟交易执行...");
-        if let Err(e) = self.simulate_transaction().await {
-            info!("模拟执行失败: {}", e);
-        } else {
-            info!("模拟执行成功");
+        match self.simulate_transaction(block).await {
+            Ok(()) => step.pass(match block {
+                Some(block) => format!("在区块 {:?} 上模拟执行成功", block),
+                None => "模拟执行成功".to_string(),
+            }),
+            Err(e) => step.fail(e.to_string()),
         }
+        step
+    }
 
-        info!("=== 诊断完成 ===");
-        Ok(())
+    /// 以签名账户身份调用无参数的view函数
+    ///
+    /// 回滚或返回数据不足32字节时（通常是合约没有该函数）返回 `None`，其他RPC错误返回错误。
+    async fn view_call(&self, contract: Address, signature: &str) -> Result<Option<Bytes>> {
+        let tx: TypedTransaction = TransactionRequest {
+            to: Some(contract.into()),
+            data: Some(id(signature).to_vec().into()),
+            from: Some(self.contract.client_address()),
+            ..Default::default()
+        }
+        .into();
+        match self.contract.client.call(&tx, None).await {
+            Ok(data) if data.len() >= 32 => Ok(Some(data)),
+            Ok(_) => Ok(None),
+            Err(e)
+                if e.as_error_response()
+                    .is_some_and(|err| err.is_revert() || err.code == 3) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(anyhow!("调用 {} 失败: {}", signature, e)),
+        }
+    }
+
+    /// 检查每个奖励合约是否为EIP-1967代理合约，输出代理和实现合约地址
+    ///
+    /// 实现合约地址或字节码与上次诊断时（记录在状态文件中）不同时发出警告：合约升级可能改变了分发函数的行为。
+    pub async fn check_proxies(&self) -> Result<Vec<ProxyInfo>> {
+        let mut proxies = Vec::new();
+        for contract in self.contract.reward_contracts() {
+            let Some(proxy) = self.contract.proxy_info(contract).await? else {
+                info!("合约 {:?} 不是EIP-1967代理合约", contract);
+                continue;
+            };
+            info!(
+                "合约 {:?} 是EIP-1967代理合约，实现合约 {:?} (字节码哈希 {:?})，管理员 {}",
+                proxy.proxy,
+                proxy.implementation,
+                proxy.code_hash,
+                proxy
+                    .admin
+                    .map(|admin| format!("{:?}", admin))
+                    .unwrap_or_else(|| "未设置（可能是UUPS代理）".to_string())
+            );
+
+            if let Some(state) = self.contract.state_store() {
+                let previous = state.record_implementation(
+                    contract,
+                    ImplementationRecord {
+                        address: proxy.implementation,
+                        code_hash: proxy.code_hash,
+                        seen_at: Utc::now(),
+                    },
+                )?;
+                match previous {
+                    Some(previous) if previous.address != proxy.implementation => warn!(
+                        "⚠️ 合约 {:?} 的实现合约已变更: 上次诊断 ({}) 时为 {:?}，现在为 {:?}，分发函数的行为可能已改变",
+                        contract, previous.seen_at, previous.address, proxy.implementation
+                    ),
+                    Some(previous) if previous.code_hash != proxy.code_hash => warn!(
+                        "⚠️ 合约 {:?} 的实现合约 {:?} 字节码已变更 (上次诊断: {})，分发函数的行为可能已改变",
+                        contract, proxy.implementation, previous.seen_at
+                    ),
+                    Some(_) => info!("实现合约与上次诊断时相同"),
+                    None => info!("首次记录实现合约，之后的诊断会检查是否变更"),
+                }
+            }
+            proxies.push(proxy);
+        }
+        Ok(proxies)
+    }
+
+    /// 模拟执行分发调用，`block` 为 `None` 时使用最新区块
+    ///
+    /// 成功时输出返回数据和该区块上的Gas消耗，失败时输出解码后的回滚原因。
+    pub async fn simulate_transaction(&self, block: Option<BlockId>) -> Result<()> {
+        match block {
+            Some(block) => info!("尝试在区块 {:?} 上模拟distributeDailyRewards调用...", block),
+            None => info!("尝试模拟distributeDailyRewards调用..."),
+        }
+
+        let (to, call_data) = self.contract.distribution_call().await?;
+        let tx_request = TransactionRequest {
+            to: Some(to.into()),
+            data: Some(call_data),
+            from: Some(self.contract.simulation_sender()),
+            gas: Some(self.contract.gas_limit()),
+            ..Default::default()
+        };
+        let typed_tx: TypedTransaction = tx_request.into();
+
+        let client = &self.contract.client;
+        match client.call(&typed_tx, block).await {
+            Ok(data) => {
+                info!("✅ 模拟调用成功，返回数据: {}", data);
+                match client.estimate_gas(&typed_tx, block).await {
+                    Ok(gas) => info!("模拟执行消耗Gas: {}", gas),
+                    Err(e) => warn!("无法估算该区块上的Gas消耗: {}", e),
+                }
+                Ok(())
+            }
+            Err(e) => {
+                let rpc_err = e.as_error_response();
+                if rpc_err.is_some_and(|err| is_state_unavailable(&err.message)) {
+                    return Err(anyhow!(
+                        "节点没有区块 {:?} 的历史状态（可能不是归档节点，状态已被裁剪）: {}",
+                        block,
+                        e
+                    ));
+                }
+
+                let reason = match rpc_err {
+                    Some(err) => decode_revert_reason(err),
+                    None => e.to_string(),
+                };
+                info!("❌ 模拟调用失败: {}", reason);
+                Err(anyhow!("模拟失败: {}", reason))
+            }
+        }
     }
 
-    async fn simulate_transaction(&self) -> Result<()> {
-        info!("尝试模拟distributeDailyRewards调用...");
+    /// 获取交易收据并输出解码后的全部日志
+    pub async fn inspect_transaction(&self, tx_hash: H256) -> Result<Vec<DecodedLog>> {
+        let receipt = self
+            .contract
+            .client
+            .get_transaction_receipt(tx_hash)
+            .await?
+            .ok_or_else(|| {
+                anyhow!(
+                    "节点中找不到交易 {:?} 的收据（交易不存在或尚未上链）",
+                    tx_hash
+                )
+            })?;
+
+        let status = match receipt.status {
+            Some(status) if status == U64::from(1) => "成功",
+            Some(_) => "失败",
+            None => "未知",
+        };
+        info!(
+            "交易 {:?}: 状态 {}，区块 {:?}，Gas使用量 {:?}，日志 {} 条",
+            tx_hash,
+            status,
+            receipt.block_number,
+            receipt.gas_used,
+            receipt.logs.len()
+        );
 
-        // 模拟调用
-        let call_data = self
+        let logs = decode_receipt_logs(&receipt, &self.contract.event_abis());
+        for log in &logs {
+            info!("{}", log);
+        }
+        Ok(logs)
+    }
+
+    /// 交易所在区块的前一个区块，用于在交易执行前的状态上重新模拟
+    pub async fn block_before_tx(&self, tx_hash: H256) -> Result<BlockId> {
+        let block_number = self
             .contract
-            .inner_contract()
-            .distribute_daily_rewards()
-            .calldata()
-            .ok_or_else(|| anyhow::anyhow!("无法生成调用数据"))?;
+            .client
+            .get_transaction(tx_hash)
+            .await?
+            .ok_or_else(|| anyhow!("节点中找不到交易 {:?}", tx_hash))?
+            .block_number
+            .ok_or_else(|| anyhow!("交易 {:?} 尚未上链", tx_hash))?;
+
+        Ok(BlockId::from(block_number.as_u64().saturating_sub(1)))
+    }
 
+    /// 带状态覆盖模拟执行分发调用，用于回答“如果合约有更多余额是否会成功”之类的问题
+    ///
+    /// ethers的类型化接口不支持状态覆盖参数，这里直接发送原始的 `eth_call` 请求。
+    pub async fn simulate_with_overrides(&self, overrides: StateOverrides) -> Result<()> {
+        info!("尝试带状态覆盖模拟distributeDailyRewards调用...");
+        info!("状态覆盖: {}", serde_json::to_string(&overrides)?);
+
+        let (to, call_data) = self.contract.distribution_call().await?;
         let tx_request = TransactionRequest {
-            to: Some(self.contract.contract_address().into()),
+            to: Some(to.into()),
             data: Some(call_data),
-            from: Some(self.contract.client_address()),
+            from: Some(self.contract.simulation_sender()),
             gas: Some(self.contract.gas_limit()),
             ..Default::default()
         };
 
-        // Convert TransactionRequest to TypedTransaction
-        let typed_tx: TypedTransaction = tx_request.into();
+        let result: Result<Bytes, ProviderError> = self
+            .contract
+            .client
+            .provider()
+            .request("eth_call", (tx_request, BlockNumber::Latest, &overrides))
+            .await;
 
-        match self.contract.client.call(&typed_tx, None).await {
+        match result {
             Ok(_) => {
                 info!("✅ 模拟调用成功");
                 Ok(())
             }
+            Err(e) if is_override_rejected(&e) => Err(anyhow!(
+                "该RPC节点不支持eth_call状态覆盖 (not supported by this endpoint): {}",
+                e
+            )),
             Err(e) => {
-                info!("❌ 模拟调用失败: {}", e);
-                Err(anyhow::anyhow!("模拟失败: {}", e))
+                let reason = match RpcError::as_error_response(&e) {
+                    Some(err) => decode_revert_reason(err),
+                    None => e.to_string(),
+                };
+                info!("❌ 模拟调用失败: {}", reason);
+                Err(anyhow!("模拟失败: {}", reason))
             }
         }
     }
 
-    /// 手动执行一次分发（用于测试）
-    pub async fn manual_distribute(&self) -> Result<()> {
-        info!("=== 手动执行分发 ===");
+    /// 诊断已上链但执行失败的交易
+    ///
+    /// 节点支持 `debug_traceTransaction` 时（探测一次后缓存）用 `callTracer` 找到最深一层
+    /// 失败的调用；否则在交易所在区块上重新模拟执行，取回滚原因。
+    pub async fn trace_failure(&self, tx_hash: H256) -> Result<FailureTrace> {
+        if self.contract.trace_supported() != Some(false) {
+            match self.trace_with_call_tracer(tx_hash).await {
+                Ok(trace) => {
+                    self.contract.set_trace_supported(true);
+                    return Ok(trace);
+                }
+                Err(e) => {
+                    if e.as_error_response().is_some_and(|err| {
+                        err.code == -32601 || is_method_unavailable(&err.message)
+                    }) {
+                        info!("节点不支持debug_traceTransaction，改为重新模拟执行");
+                        self.contract.set_trace_supported(false);
+                    } else {
+                        warn!("debug_traceTransaction失败: {}，改为重新模拟执行", e);
+                    }
+                }
+            }
+        }
 
-        match self.contract.distribute_daily_rewards().await {
-            Ok(tx_hash) => {
-                info!("交易发送成功: {:?}", tx_hash);
+        self.resimulate_at_block(tx_hash).await
+    }
 
-                // 等待确认
-                match self.contract.wait_for_confirmation(tx_hash).await {
-                    Ok(receipt) => {
-                        info!("交易确认成功: 区块 {:?}", receipt.block_number);
-                    }
-                    Err(e) => {
-                        info!("等待确认失败: {}", e);
-                    }
+    async fn trace_with_call_tracer(
+        &self,
+        tx_hash: H256,
+    ) -> Result<FailureTrace, <DistributorClient as Middleware>::Error> {
+        let options = GethDebugTracingOptions {
+            tracer: Some(GethDebugTracerType::BuiltInTracer(
+                GethDebugBuiltInTracerType::CallTracer,
+            )),
+            ..Default::default()
+        };
+        let trace = self
+            .contract
+            .client
+            .debug_trace_transaction(tx_hash, options)
+            .await?;
+
+        let frame = match &trace {
+            GethTrace::Known(GethTraceFrame::CallTracer(frame)) => Some(frame),
+            _ => None,
+        };
+        let failing = frame.and_then(deepest_failing_call);
+
+        Ok(FailureTrace {
+            tx_hash,
+            source: FailureTraceSource::Trace,
+            revert_reason: failing.and_then(|frame| {
+                frame
+                    .output
+                    .as_ref()
+                    .filter(|output| !output.is_empty())
+                    .map(decode_revert_data)
+                    .or_else(|| frame.error.clone())
+            }),
+            failing_target: failing
+                .and_then(|frame| frame.to.as_ref())
+                .and_then(|to| to.as_address())
+                .copied(),
+            failing_selector: failing
+                .filter(|frame| frame.input.len() >= 4)
+                .map(|frame| Bytes::from(frame.input[..4].to_vec())),
+        })
+    }
+
+    /// 用原交易的参数在其所在区块上执行 `eth_call`
+    async fn resimulate_at_block(&self, tx_hash: H256) -> Result<FailureTrace> {
+        let client = &self.contract.client;
+        let tx = client
+            .get_transaction(tx_hash)
+            .await?
+            .ok_or_else(|| anyhow!("节点中找不到交易 {:?}", tx_hash))?;
+
+        let tx_request = TransactionRequest {
+            from: Some(tx.from),
+            to: tx.to.map(Into::into),
+            gas: Some(tx.gas),
+            value: Some(tx.value),
+            data: Some(tx.input.clone()),
+            ..Default::default()
+        };
+        let typed_tx: TypedTransaction = tx_request.into();
+        let block = tx.block_number.map(|number| BlockId::from(number.as_u64()));
+
+        let revert_reason = match client.call(&typed_tx, block).await {
+            Ok(_) => None,
+            Err(e) => Some(match e.as_error_response() {
+                Some(err) => decode_revert_reason(err),
+                None => e.to_string(),
+            }),
+        };
+
+        Ok(FailureTrace {
+            tx_hash,
+            source: FailureTraceSource::Simulation,
+            revert_reason,
+            failing_target: tx.to,
+            failing_selector: (tx.input.len() >= 4).then(|| Bytes::from(tx.input[..4].to_vec())),
+        })
+    }
+
+    /// Gas用量报告：用当前calldata估算，再对比 `history` 中最近 `samples` 次成功分发的实际用量
+    ///
+    /// 实际用量和交易的Gas限制从交易收据中读取，读取失败的交易跳过（单笔分发时退回到执行记录中的用量）。
+    /// 建议的GAS_LIMIT为历史最大用量和当前估算中较大者 × `safety_factor`。
+    pub async fn gas_report(
+        &self,
+        history: &[ExecutionRecord],
+        samples: usize,
+        safety_factor: f64,
+    ) -> GasReport {
+        let (estimate, estimate_error) = match self.contract.distribution_call().await {
+            Ok((to, call_data)) => match self.contract.estimate_gas(to, call_data).await {
+                Ok(estimate) => (Some(estimate), None),
+                Err(e) => (None, Some(e.to_string())),
+            },
+            Err(e) => (None, Some(e.to_string())),
+        };
+
+        let successes: Vec<&ExecutionRecord> = history
+            .iter()
+            .filter(|record| {
+                record.outcome == ExecutionOutcome::Success && !record.tx_hashes.is_empty()
+            })
+            .collect();
+        let skip = successes.len().saturating_sub(samples);
+        let mut observations = Vec::new();
+        for record in &successes[skip..] {
+            for &tx_hash in &record.tx_hashes {
+                if let Some(observation) = self.observe_gas(record, tx_hash).await {
+                    observations.push(observation);
                 }
             }
+        }
+
+        let max_observed = observations
+            .iter()
+            .map(|observation| observation.gas_used)
+            .max();
+        let recommended_gas_limit = max_observed
+            .into_iter()
+            .chain(estimate)
+            .max()
+            .map(|gas| scale_gas(gas, safety_factor));
+        GasReport {
+            buffered_limit: estimate.map(|estimate| self.contract.capped_gas_limit(estimate)),
+            estimate,
+            estimate_error,
+            configured_limit: self.contract.gas_limit(),
+            observations,
+            max_observed,
+            safety_factor,
+            recommended_gas_limit,
+        }
+    }
+
+    /// 读取一笔分发交易的实际用量和Gas限制
+    async fn observe_gas(&self, record: &ExecutionRecord, tx_hash: H256) -> Option<GasObservation> {
+        let client = &self.contract.client;
+        let gas_used = match client.get_transaction_receipt(tx_hash).await {
+            Ok(Some(receipt)) => receipt.gas_used,
+            Ok(None) => None,
+            Err(e) => {
+                warn!("获取交易 {:?} 的收据失败: {}", tx_hash, e);
+                None
+            }
+        };
+        // 执行记录中的用量是本次执行所有交易的合计，只有一笔交易时才能代表该交易
+        let gas_used = gas_used.or(record.gas_used.filter(|_| record.tx_hashes.len() == 1))?;
+        let gas_limit = match client.get_transaction(tx_hash).await {
+            Ok(tx) => tx.map(|tx| tx.gas),
+            Err(e) => {
+                warn!("获取交易 {:?} 失败: {}", tx_hash, e);
+                None
+            }
+        };
+        let headroom_percent = gas_limit.filter(|limit| !limit.is_zero()).map(|limit| {
+            let used = gas_used.low_u128() as f64 / limit.low_u128() as f64;
+            (1.0 - used) * 100.0
+        });
+        Some(GasObservation {
+            tx_hash,
+            started_at: record.started_at,
+            gas_used,
+            gas_limit,
+            headroom_percent,
+        })
+    }
+
+    /// 手动执行一次分发（用于测试）
+    pub async fn manual_distribute(
+        &self,
+        options: DistributeOptions,
+    ) -> std::result::Result<DistributionResult, DistributorError> {
+        info!("=== 手动执行分发 ===");
+
+        let result = match self.contract.distribute_with_options(options).await {
+            Ok(result) => result,
             Err(e) => {
-                info!("交易发送失败: {}", e);
+                error!("分发失败: {}", e);
                 return Err(e);
             }
+        };
+
+        info!(
+            "交易确认成功: {:?}，区块 {:?}，Gas使用量 {:?}，交易费用 {} ETH",
+            result.tx_hash,
+            result.block_number,
+            result.gas_used,
+            result.total_fee_eth.as_deref().unwrap_or("未知")
+        );
+        let tx_hashes: Vec<H256> = match result.batches.is_empty() {
+            true => vec![result.tx_hash],
+            false => result.batches.iter().map(|batch| batch.tx_hash).collect(),
+        };
+        for tx_hash in tx_hashes {
+            if let Err(e) = self.inspect_transaction(tx_hash).await {
+                warn!("无法解码交易 {:?} 的日志: {}", tx_hash, e);
+            }
+        }
+        match serde_json::to_string(&result) {
+            Ok(json) => info!("结果: {}", json),
+            Err(e) => warn!("序列化分发结果失败: {}", e),
         }
+        Ok(result)
+    }
+}
 
-        Ok(())
+/// 调用树中最深一层带有错误的调用
+fn deepest_failing_call(frame: &CallFrame) -> Option<&CallFrame> {
+    frame
+        .calls
+        .iter()
+        .flatten()
+        .filter_map(deepest_failing_call)
+        .last()
+        .or_else(|| frame.error.is_some().then_some(frame))
+}
+
+fn is_method_unavailable(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    lower.contains("method not found")
+        || lower.contains("does not exist")
+        || lower.contains("not available")
+        || lower.contains("not supported")
+}
+
+/// 节点是否因为不认识状态覆盖参数而拒绝请求
+fn is_override_rejected(err: &ProviderError) -> bool {
+    let Some(err) = RpcError::as_error_response(err) else {
+        return false;
+    };
+    let lower = err.message.to_lowercase();
+    err.code == -32602
+        || lower.contains("too many arguments")
+        || lower.contains("state override")
+        || lower.contains("invalid params")
+}
+
+/// 节点是否因为缺少历史状态（非归档节点）而无法执行
+fn is_state_unavailable(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    lower.contains("missing trie node")
+        || lower.contains("state is not available")
+        || lower.contains("state histories")
+        || lower.contains("pruned")
+        || lower.contains("historical state")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_block, test_contract, MockNode, MockReply, TEST_CHAIN_ID};
+    use serde_json::{json, Value};
+
+    /// 各项检查都能通过的节点响应
+    fn healthy_reply(method: &str) -> MockReply {
+        MockReply::Result(match method {
+            "eth_chainId" => json!(format!("{:#x}", TEST_CHAIN_ID)),
+            "eth_getBlockByNumber" => test_block(100, Utc::now().timestamp(), Some(1_000_000_000)),
+            "eth_syncing" => json!(false),
+            "eth_getBalance" => json!("0xde0b6b3a7640000"),
+            "eth_gasPrice" => json!("0x3b9aca00"),
+            "eth_getCode" => json!("0x6080604052"),
+            "eth_getStorageAt" => json!(format!("{:?}", H256::zero())),
+            "eth_call" => json!("0x"),
+            "eth_estimateGas" => json!("0x5208"),
+            _ => return MockReply::error(-32601, "method not found"),
+        })
+    }
+
+    /// 在 `reply` 返回 `None` 的方法上使用健康节点响应，执行一次诊断
+    async fn diagnose_with(
+        reply: impl Fn(&str) -> Option<MockReply> + Send + Sync + 'static,
+    ) -> DiagnosisReport {
+        let node = MockNode::start(move |method: &str, _: &Value, _| {
+            reply(method).unwrap_or_else(|| healthy_reply(method))
+        })
+        .await;
+        ContractDebugger::new(test_contract(node.url()))
+            .diagnose(None)
+            .await
+    }
+
+    fn statuses(report: &DiagnosisReport) -> Vec<StepStatus> {
+        report.steps.iter().map(|step| step.status).collect()
+    }
+
+    #[tokio::test]
+    async fn diagnose_exits_zero_when_every_step_passes() {
+        let report = diagnose_with(|_| None).await;
+
+        assert_eq!(statuses(&report), vec![StepStatus::Pass; 8], "{}", report);
+        assert!(!report.has_failures());
+        assert_eq!(report.exit_code(), 0);
+    }
+
+    #[tokio::test]
+    async fn diagnose_exits_one_when_only_warnings() {
+        // 最新区块产生于一小时前，节点可能落后只记为警告
+        let report = diagnose_with(|method| {
+            (method == "eth_getBlockByNumber").then(|| {
+                MockReply::Result(test_block(
+                    100,
+                    Utc::now().timestamp() - 3600,
+                    Some(1_000_000_000),
+                ))
+            })
+        })
+        .await;
+
+        assert_eq!(report.steps[1].status, StepStatus::Warn, "{}", report);
+        assert!(!report.has_failures(), "{}", report);
+        assert_eq!(report.exit_code(), 1);
+    }
+
+    #[tokio::test]
+    async fn diagnose_exits_two_when_any_step_fails() {
+        // 链ID不一致是失败；同时有警告时仍按最严重的失败计算退出码
+        let report = diagnose_with(|method| match method {
+            "eth_chainId" => Some(MockReply::Result(json!("0x1"))),
+            "eth_syncing" => Some(MockReply::error(-32603, "internal error")),
+            _ => None,
+        })
+        .await;
+
+        assert_eq!(report.steps[0].status, StepStatus::Fail, "{}", report);
+        assert_eq!(report.steps[1].status, StepStatus::Warn, "{}", report);
+        assert_eq!(
+            report.failures().map(|step| step.name).collect::<Vec<_>>(),
+            vec!["RPC连接和链ID"]
+        );
+        assert_eq!(report.exit_code(), 2);
     }
 }
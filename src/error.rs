@@ -0,0 +1,489 @@
+use crate::contract::{decode_revert_reason, DeadlineError, GasEstimationError};
+use crate::debug::FailureTrace;
+use crate::gas::is_tx_type_unsupported;
+use crate::merkle::MerkleError;
+use crate::preflight::PreflightCheck;
+use crate::recipients::RecipientsError;
+use crate::signer::SignerError;
+use chrono::{DateTime, Utc};
+use ethers::providers::{JsonRpcError, MiddlewareError};
+use ethers::signers::WalletError;
+use ethers::types::{Address, Bytes, H256, U256, U64};
+use serde::Serialize;
+use std::fmt;
+use std::time::Duration;
+use thiserror::Error;
+use tokio_cron_scheduler::JobSchedulerError;
+
+/// 库中公开方法的返回类型
+pub type Result<T, E = DistributorError> = std::result::Result<T, E>;
+
+/// 底层错误（ethers等），保留在错误链中
+pub type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// RPC错误的分类，决定是否值得重试
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum ErrorKind {
+    /// 网络或传输层错误（连接重置、超时等）
+    Transport,
+    /// 节点限流
+    RateLimited,
+    /// nonce冲突（nonce过高等）
+    NonceConflict,
+    /// nonce过低：该nonce已被使用，可能是我们之前的交易已经打包
+    NonceTooLow,
+    /// 节点已收到同一笔交易，交易已在内存池中
+    AlreadyKnown,
+    /// 替换同nonce交易时费用加价不足
+    ReplacementUnderpriced,
+    /// 账户余额不足以支付Gas
+    InsufficientFunds,
+    /// 节点不支持该交易类型（如在不支持EIP-1559的链上发送1559交易）
+    UnsupportedTxType,
+    /// 合约执行回滚
+    Reverted {
+        reason: String,
+    },
+    Unknown,
+}
+
+impl ErrorKind {
+    /// 根据JSON-RPC错误码和消息分类
+    pub fn from_rpc_error(code: i64, message: &str) -> Self {
+        let lower = message.to_lowercase();
+
+        if code == 3 || lower.contains("revert") {
+            ErrorKind::Reverted {
+                reason: message.to_string(),
+            }
+        } else if code == 429 || code == -32005 || is_rate_limit_message(&lower) {
+            ErrorKind::RateLimited
+        } else if lower.contains("insufficient funds") {
+            ErrorKind::InsufficientFunds
+        } else if is_tx_type_unsupported(&lower) {
+            ErrorKind::UnsupportedTxType
+        } else if lower.contains("already known")
+            || lower.contains("known transaction")
+            || lower.contains("already imported")
+        {
+            ErrorKind::AlreadyKnown
+        } else if lower.contains("replacement transaction underpriced")
+            || lower.contains("replacement fee too low")
+        {
+            ErrorKind::ReplacementUnderpriced
+        } else if lower.contains("nonce too low") || lower.contains("oldnonce") {
+            ErrorKind::NonceTooLow
+        } else if lower.contains("nonce too high") {
+            ErrorKind::NonceConflict
+        } else {
+            ErrorKind::Unknown
+        }
+    }
+
+    /// 对ethers中间件返回的错误分类
+    ///
+    /// 节点返回了JSON-RPC错误响应时按错误码和消息分类，否则视为传输层错误。
+    pub fn classify<E: MiddlewareError>(err: &E) -> Self {
+        match err.as_error_response() {
+            Some(rpc_err) => Self::from_json_rpc_error(rpc_err),
+            None if is_rate_limit_message(&err.to_string().to_lowercase()) => {
+                ErrorKind::RateLimited
+            }
+            None => ErrorKind::Transport,
+        }
+    }
+
+    fn from_json_rpc_error(rpc_err: &JsonRpcError) -> Self {
+        match Self::from_rpc_error(rpc_err.code, &rpc_err.message) {
+            ErrorKind::Reverted { .. } => ErrorKind::Reverted {
+                reason: decode_revert_reason(rpc_err),
+            },
+            kind => kind,
+        }
+    }
+
+    /// 传输错误和限流值得退避重试
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ErrorKind::Transport | ErrorKind::RateLimited)
+    }
+}
+
+fn is_rate_limit_message(message: &str) -> bool {
+    message.contains("429")
+        || message.contains("too many requests")
+        || message.contains("rate limit")
+        || message.contains("limit exceeded")
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorKind::Transport => write!(f, "传输错误"),
+            ErrorKind::RateLimited => write!(f, "节点限流"),
+            ErrorKind::NonceConflict => write!(f, "nonce冲突"),
+            ErrorKind::NonceTooLow => write!(f, "nonce过低"),
+            ErrorKind::AlreadyKnown => write!(f, "交易已在内存池中"),
+            ErrorKind::ReplacementUnderpriced => write!(f, "替换交易加价不足"),
+            ErrorKind::InsufficientFunds => write!(f, "余额不足"),
+            ErrorKind::UnsupportedTxType => write!(f, "不支持的交易类型"),
+            ErrorKind::Reverted { reason } => write!(f, "执行回滚: {}", reason),
+            ErrorKind::Unknown => write!(f, "未知错误"),
+        }
+    }
+}
+
+/// 分发过程中的错误
+#[derive(Debug, Error)]
+pub enum DistributorError {
+    /// 环境变量缺失或格式错误
+    #[error("配置错误: {0}")]
+    Config(String),
+    /// 单次分发的覆盖参数无效
+    #[error("无效的分发参数: {0}")]
+    InvalidOptions(String),
+    /// 私钥无效或签名失败
+    #[error("签名错误: {0}")]
+    Signer(#[from] SignerError),
+    /// RPC调用失败，附带错误分类
+    #[error("{operation}失败 ({kind}): {source}")]
+    Rpc {
+        operation: String,
+        kind: ErrorKind,
+        #[source]
+        source: BoxError,
+    },
+    /// Gas估算失败
+    #[error(transparent)]
+    GasEstimation(#[from] GasEstimationError),
+    /// 合约执行回滚（模拟、估算或发送时由节点报告）
+    #[error("合约执行回滚: {}", .reason.as_deref().unwrap_or("未知原因"))]
+    Reverted {
+        /// 解码后的回滚原因，无法解码时为 `None`
+        reason: Option<String>,
+        #[source]
+        source: Option<BoxError>,
+    },
+    /// 交易已上链但执行失败
+    #[error(
+        "交易已上链但执行失败，哈希: {tx_hash:?}，区块: {block_number:?}{}",
+        .trace.as_ref().map(|trace| format!("，{}", trace)).unwrap_or_default()
+    )]
+    TransactionFailed {
+        tx_hash: H256,
+        block_number: Option<U64>,
+        /// 失败原因诊断，节点无法提供时为 `None`
+        trace: Option<Box<FailureTrace>>,
+    },
+    /// 分批分发中某一批失败，之前的批次已确认并记录在状态文件中
+    #[error(
+        "第{batch}/{total}批分发失败 (已确认 {completed} 批，重试时从未完成的批次继续): {source}"
+    )]
+    BatchFailed {
+        batch: usize,
+        total: usize,
+        completed: usize,
+        #[source]
+        source: Box<DistributorError>,
+    },
+    /// Gas价格超过配置的上限，交易未发送
+    #[error("Gas价格 {price} wei 超过上限 {cap} wei，未发送交易")]
+    GasPriceExceedsCap { price: U256, cap: U256 },
+    /// 无法获取Gas价格，且 ON_GAS_PRICE_UNAVAILABLE 不允许使用默认价格，交易未发送
+    #[error("无法获取Gas价格，未发送交易: {reason}")]
+    GasPriceUnavailable { reason: String },
+    /// 交易在等待时间内未确认
+    #[error("交易确认超时 ({timeout:?}): {tx_hash:?}")]
+    ConfirmationTimeout { tx_hash: H256, timeout: Duration },
+    /// 账户余额不足以支付Gas
+    #[error("{operation}失败 (余额不足): {source}")]
+    InsufficientFunds {
+        operation: String,
+        #[source]
+        source: BoxError,
+    },
+    /// 奖励合约持有的奖励代币不足，交易未发送
+    #[error(
+        "奖励合约 {contract:?} 的奖励代币 {token:?} 余额不足: 持有 {have}，需要 {need}，未发送交易"
+    )]
+    InsufficientRewardTokens {
+        contract: Address,
+        token: Address,
+        have: U256,
+        need: U256,
+    },
+    /// 签名账户的余额不足以支付本次交易的Gas费用，交易未发送
+    #[error("签名账户 {account:?} 余额 {have} wei 不足以支付Gas费用 {need} wei，未发送交易")]
+    InsufficientGasFunds {
+        account: Address,
+        have: U256,
+        need: U256,
+    },
+    /// 当天的Gas花费已达到 MAX_SPEND_PER_DAY，交易未发送
+    #[error(
+        "今天的Gas花费 {spent} wei 加上本次最多 {need} wei 将超过每日上限 {budget} wei，未发送交易"
+    )]
+    SpendBudgetExceeded {
+        spent: U256,
+        need: U256,
+        budget: U256,
+    },
+    /// 距离上一次成功分发不足MIN_INTERVAL_HOURS，交易未发送
+    #[error("距离上一次分发 ({last}) 不足最小间隔，{next} 之前不会再次分发")]
+    TooSoon {
+        last: DateTime<Utc>,
+        next: DateTime<Utc>,
+    },
+    /// 发送前检查未通过，按 `notify-and-skip` 配置跳过了本次分发
+    #[error("{check}未通过，已跳过本次分发: {source}")]
+    PreflightSkipped {
+        check: PreflightCheck,
+        #[source]
+        source: Box<DistributorError>,
+    },
+    /// 启动检查发现奖励合约上不存在分发函数
+    #[error("奖励合约 {contract:?} 上不存在分发函数 (选择器 {selector}): {reason}")]
+    FunctionNotFound {
+        contract: Address,
+        selector: Bytes,
+        reason: String,
+    },
+    /// 无法按ABI编码调用数据，重试不会改变结果
+    #[error("无法生成{0}调用数据")]
+    Calldata(String),
+    /// 接收地址文件无效
+    #[error(transparent)]
+    Recipients(#[from] RecipientsError),
+    /// Merkle模式下构建Merkle树或写出证明失败
+    #[error(transparent)]
+    Merkle(#[from] MerkleError),
+    /// 单次运行超过截止时间
+    #[error(transparent)]
+    Deadline(#[from] DeadlineError),
+    /// 任务执行超过 JOB_TIMEOUT_SECS，已中止等待；已广播的交易不受影响，由下次运行恢复
+    #[error("{name}执行超时 (已运行 {elapsed:?})，已发送的交易: {tx_hashes:?}")]
+    JobTimeout {
+        name: String,
+        elapsed: Duration,
+        tx_hashes: Vec<H256>,
+    },
+    /// 原始错误已随 `DistributionEvent` 发出，这里只保留描述和是否为按配置跳过
+    #[error("{message}")]
+    Forwarded { message: String, skipped: bool },
+    /// 调度器创建、添加任务或启停失败
+    #[error("调度器错误: {0}")]
+    Scheduler(#[from] JobSchedulerError),
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl From<WalletError> for DistributorError {
+    fn from(err: WalletError) -> Self {
+        DistributorError::Signer(err.into())
+    }
+}
+
+impl DistributorError {
+    /// 从ethers中间件错误构造，自动分类
+    ///
+    /// 余额不足和回滚会分别构造为 `InsufficientFunds`、`Reverted`，其他情况为 `Rpc`。
+    pub fn rpc<E: MiddlewareError + 'static>(operation: impl Into<String>, err: E) -> Self {
+        let operation = operation.into();
+        match ErrorKind::classify(&err) {
+            ErrorKind::InsufficientFunds => DistributorError::InsufficientFunds {
+                operation,
+                source: Box::new(err),
+            },
+            ErrorKind::Reverted { reason } => DistributorError::Reverted {
+                reason: Some(reason),
+                source: Some(Box::new(err)),
+            },
+            kind => DistributorError::Rpc {
+                operation,
+                kind,
+                source: Box::new(err),
+            },
+        }
+    }
+
+    /// 是否为按配置跳过的分发（未发送交易，不视为失败）
+    pub fn is_skipped(&self) -> bool {
+        match self {
+            DistributorError::PreflightSkipped { .. } | DistributorError::TooSoon { .. } => true,
+            DistributorError::BatchFailed { source, .. } => source.is_skipped(),
+            DistributorError::Forwarded { skipped, .. } => *skipped,
+            _ => false,
+        }
+    }
+
+    /// 错误分类，用于决定是否重试
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            DistributorError::Rpc { kind, .. } => kind.clone(),
+            DistributorError::InsufficientFunds { .. }
+            | DistributorError::InsufficientGasFunds { .. } => ErrorKind::InsufficientFunds,
+            DistributorError::Reverted { reason, .. } => ErrorKind::Reverted {
+                reason: reason.clone().unwrap_or_default(),
+            },
+            DistributorError::BatchFailed { source, .. } => source.kind(),
+            // Gas不足不会因为重试而改变
+            DistributorError::GasEstimation(e) if e.is_out_of_gas() => ErrorKind::Unknown,
+            DistributorError::GasEstimation(GasEstimationError::Reverted { reason }) => {
+                ErrorKind::Reverted {
+                    reason: reason.clone(),
+                }
+            }
+            DistributorError::GasEstimation(GasEstimationError::Transport(_)) => {
+                ErrorKind::Transport
+            }
+            _ => ErrorKind::Unknown,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::HttpTransportError;
+    use ethers::abi::{encode, Token};
+    use ethers::providers::ProviderError;
+    use ethers::types::Bytes;
+    use ethers::utils::id;
+
+    fn json_rpc_error(code: i64, message: &str, data: Option<serde_json::Value>) -> ProviderError {
+        HttpTransportError::JsonRpc(JsonRpcError {
+            code,
+            message: message.to_string(),
+            data,
+        })
+        .into()
+    }
+
+    #[test]
+    fn rpc_error_codes_and_messages_map_to_kinds() {
+        let reverted = |reason: &str| ErrorKind::Reverted {
+            reason: reason.to_string(),
+        };
+        let cases = [
+            (
+                3,
+                "execution reverted: not owner",
+                reverted("execution reverted: not owner"),
+            ),
+            (-32000, "execution reverted", reverted("execution reverted")),
+            (-32005, "request limit reached", ErrorKind::RateLimited),
+            (429, "Too Many Requests", ErrorKind::RateLimited),
+            (
+                -32000,
+                "daily request count exceeded, request rate limited",
+                ErrorKind::RateLimited,
+            ),
+            (
+                -32603,
+                "Your app has exceeded its compute units per second capacity (429)",
+                ErrorKind::RateLimited,
+            ),
+            (
+                -32000,
+                "insufficient funds for gas * price + value: have 0 want 1000",
+                ErrorKind::InsufficientFunds,
+            ),
+            (
+                -32000,
+                "transaction type not supported",
+                ErrorKind::UnsupportedTxType,
+            ),
+            (-32000, "already known", ErrorKind::AlreadyKnown),
+            (-32010, "Known transaction: 0xabc", ErrorKind::AlreadyKnown),
+            (
+                -32000,
+                "replacement transaction underpriced",
+                ErrorKind::ReplacementUnderpriced,
+            ),
+            (
+                -32000,
+                "nonce too low: next nonce 5, tx nonce 4",
+                ErrorKind::NonceTooLow,
+            ),
+            (-32000, "OldNonce", ErrorKind::NonceTooLow),
+            (-32000, "nonce too high", ErrorKind::NonceConflict),
+            (
+                -32601,
+                "the method eth_foo does not exist",
+                ErrorKind::Unknown,
+            ),
+        ];
+        for (code, message, expected) in cases {
+            assert_eq!(
+                ErrorKind::from_rpc_error(code, message),
+                expected,
+                "{} {}",
+                code,
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn classify_decodes_revert_reason_from_error_data() {
+        let mut data = id("Error(string)").to_vec();
+        data.extend(encode(&[Token::String(
+            "already distributed today".to_string(),
+        )]));
+        let err = json_rpc_error(
+            3,
+            "execution reverted: already distributed today",
+            Some(serde_json::Value::String(Bytes::from(data).to_string())),
+        );
+
+        assert_eq!(
+            ErrorKind::classify(&err),
+            ErrorKind::Reverted {
+                reason: "already distributed today".to_string()
+            }
+        );
+    }
+
+    #[test]
+    fn classify_errors_without_rpc_response() {
+        let transport = ProviderError::CustomError("connection reset by peer".to_string());
+        assert_eq!(ErrorKind::classify(&transport), ErrorKind::Transport);
+        assert!(ErrorKind::classify(&transport).is_retryable());
+
+        let rate_limited: ProviderError =
+            HttpTransportError::RateLimited { retry_after: None }.into();
+        assert_eq!(ErrorKind::classify(&rate_limited), ErrorKind::RateLimited);
+        assert!(ErrorKind::classify(&rate_limited).is_retryable());
+    }
+
+    #[test]
+    fn rpc_constructor_picks_variant_by_kind() {
+        let err = DistributorError::rpc(
+            "发送交易",
+            json_rpc_error(-32000, "insufficient funds", None),
+        );
+        assert!(matches!(err, DistributorError::InsufficientFunds { .. }));
+        assert!(!err.kind().is_retryable());
+
+        let err = DistributorError::rpc("模拟执行", json_rpc_error(3, "execution reverted", None));
+        assert!(matches!(err, DistributorError::Reverted { .. }));
+
+        let err = DistributorError::rpc("发送交易", json_rpc_error(-32000, "nonce too low", None));
+        assert_eq!(err.kind(), ErrorKind::NonceTooLow);
+        assert!(!err.kind().is_retryable());
+    }
+
+    #[test]
+    fn gas_estimation_errors_are_retried_only_for_transport_failures() {
+        let transport = DistributorError::from(GasEstimationError::Transport("timeout".into()));
+        assert!(transport.kind().is_retryable());
+
+        let reverted = DistributorError::from(GasEstimationError::Reverted {
+            reason: "paused".into(),
+        });
+        assert!(!reverted.kind().is_retryable());
+
+        // 调用数据编码失败与节点无关，重试不会改变结果
+        let calldata = DistributorError::Calldata("distributeDailyRewards".into());
+        assert!(!calldata.kind().is_retryable());
+    }
+}
@@ -0,0 +1,430 @@
+use crate::error::{DistributorError, Result};
+use crate::scheduler::ScheduleTimezone;
+use anyhow::anyhow;
+use ethers::prelude::*;
+use serde::Serialize;
+use std::str::FromStr;
+use std::sync::Mutex;
+use tracing::info;
+
+/// 配置的交易类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TxType {
+    /// 根据最新区块是否带有 `baseFeePerGas` 自动判断
+    Auto,
+    /// 传统交易，使用 `gasPrice`（默认）
+    Legacy,
+    /// EIP-1559交易，使用 `maxFeePerGas` / `maxPriorityFeePerGas`
+    Eip1559,
+}
+
+impl FromStr for TxType {
+    type Err = DistributorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "auto" => Ok(TxType::Auto),
+            "legacy" => Ok(TxType::Legacy),
+            "eip1559" | "1559" => Ok(TxType::Eip1559),
+            other => Err(DistributorError::Config(format!(
+                "无效的TX_TYPE: {}，可选值: auto, legacy, eip1559",
+                other
+            ))),
+        }
+    }
+}
+
+/// 实际使用的费用构造方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeeMode {
+    Legacy,
+    Eip1559,
+}
+
+impl std::fmt::Display for FeeMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FeeMode::Legacy => write!(f, "legacy"),
+            FeeMode::Eip1559 => write!(f, "EIP-1559"),
+        }
+    }
+}
+
+/// 根据区块是否带有 `baseFeePerGas` 判断链是否支持EIP-1559
+pub fn detect_fee_mode<TX>(block: &Block<TX>) -> FeeMode {
+    if block.base_fee_per_gas.is_some() {
+        FeeMode::Eip1559
+    } else {
+        FeeMode::Legacy
+    }
+}
+
+/// 节点是否因不支持该交易类型而拒绝交易
+pub fn is_tx_type_unsupported(message: &str) -> bool {
+    let lower = message.to_lowercase();
+    lower.contains("transaction type not supported") || lower.contains("tx type not supported")
+}
+
+/// 按 `TX_TYPE` 确定费用构造方式，自动检测的结果会被缓存
+#[derive(Debug)]
+pub struct FeeModeDetector {
+    tx_type: TxType,
+    cached: Mutex<Option<FeeMode>>,
+}
+
+impl FeeModeDetector {
+    pub fn new(tx_type: TxType) -> Self {
+        Self {
+            tx_type,
+            cached: Mutex::new(None),
+        }
+    }
+
+    pub fn tx_type(&self) -> TxType {
+        self.tx_type
+    }
+
+    /// 返回费用构造方式，自动模式下首次调用时查询最新区块
+    pub async fn resolve<M: Middleware + 'static>(&self, client: &M) -> Result<FeeMode> {
+        match self.tx_type {
+            TxType::Legacy => return Ok(FeeMode::Legacy),
+            TxType::Eip1559 => return Ok(FeeMode::Eip1559),
+            TxType::Auto => {}
+        }
+
+        if let Some(mode) = *self.cached.lock().unwrap() {
+            return Ok(mode);
+        }
+
+        let block = client
+            .get_block(BlockNumber::Latest)
+            .await
+            .map_err(|e| DistributorError::rpc("检测交易类型时获取最新区块", e))?
+            .ok_or_else(|| anyhow!("检测交易类型时节点未返回最新区块"))?;
+        let mode = detect_fee_mode(&block);
+        info!("检测到链的交易类型: {} (区块 {:?})", mode, block.number);
+
+        *self.cached.lock().unwrap() = Some(mode);
+        Ok(mode)
+    }
+
+    /// 清除缓存的检测结果，下次使用时重新检测
+    pub fn invalidate(&self) {
+        *self.cached.lock().unwrap() = None;
+    }
+}
+
+/// 替换交易加价的曲线
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EscalationCurve {
+    /// 每次按固定比例加价
+    Flat,
+    /// 越接近截止时间加价越多，到截止时间时为固定比例的3倍
+    Time,
+}
+
+impl FromStr for EscalationCurve {
+    type Err = DistributorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "flat" => Ok(EscalationCurve::Flat),
+            "time" => Ok(EscalationCurve::Time),
+            other => Err(DistributorError::Config(format!(
+                "无效的FEE_ESCALATION_CURVE: {}，可选值: flat, time",
+                other
+            ))),
+        }
+    }
+}
+
+/// 节点接受替换交易的最低加价比例（geth默认10%）
+pub const MIN_BUMP_PERCENT: u64 = 10;
+
+/// 替换交易的费用加价策略
+///
+/// 紧急的分发可以设置较高的首次加价和加价比例，尽快打包；不急的分发保持默认，慢慢加价。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FeeEscalation {
+    /// 首次发送时在节点建议费用上额外增加的百分比，固定Gas价格时不生效
+    pub initial_premium_percent: u64,
+    /// 每次替换的加价百分比，不低于节点要求的10%
+    pub bump_percent: u64,
+    /// 单次发送中最多加价替换的次数
+    pub max_bumps: u32,
+    /// 加价后费用的上限，未设置时使用 MAX_GAS_PRICE
+    pub max_price: Option<U256>,
+    pub curve: EscalationCurve,
+    /// 时间曲线的时间窗口，通常为单次运行的截止时间
+    pub window: std::time::Duration,
+}
+
+impl Default for FeeEscalation {
+    fn default() -> Self {
+        Self {
+            initial_premium_percent: 0,
+            bump_percent: MIN_BUMP_PERCENT,
+            max_bumps: 3,
+            max_price: None,
+            curve: EscalationCurve::Flat,
+            window: std::time::Duration::from_secs(4 * 3600),
+        }
+    }
+}
+
+impl FeeEscalation {
+    /// 首次发送的费用
+    pub fn initial(&self, fee: U256) -> U256 {
+        fee * (100 + self.initial_premium_percent) / 100
+    }
+
+    /// 已发送 `elapsed` 后的加价百分比
+    pub fn bump_percent_at(&self, elapsed: std::time::Duration) -> u64 {
+        let base = self.bump_percent.max(MIN_BUMP_PERCENT);
+        match self.curve {
+            EscalationCurve::Flat => base,
+            EscalationCurve::Time => {
+                let progress = if self.window.is_zero() {
+                    1.0
+                } else {
+                    (elapsed.as_secs_f64() / self.window.as_secs_f64()).min(1.0)
+                };
+                base + (base as f64 * 2.0 * progress) as u64
+            }
+        }
+    }
+
+    /// 按加价百分比提高费用，多加1 wei避免取整后仍低于节点要求
+    pub fn bump(&self, fee: U256, percent: u64) -> U256 {
+        fee * (100 + percent) / 100 + 1
+    }
+}
+
+/// 按 `eth_feeHistory` 计算EIP-1559费用的参数
+///
+/// 小费取最近 `block_count` 个区块中每个区块 `reward_percentile` 分位小费的中位数，
+/// `maxFeePerGas = 下一个区块的baseFee × base_fee_multiplier + 小费`。
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct FeeHistoryConfig {
+    pub block_count: u64,
+    /// 0到100之间的百分位
+    pub reward_percentile: f64,
+    /// baseFee的放大倍数，留出之后几个区块baseFee上涨的余量
+    pub base_fee_multiplier: f64,
+}
+
+impl FeeHistoryConfig {
+    /// 调用 `eth_feeHistory` 计算 `(maxFeePerGas, maxPriorityFeePerGas)`
+    pub async fn estimate<M: Middleware + 'static>(&self, client: &M) -> Result<(U256, U256)> {
+        let history = client
+            .fee_history(
+                self.block_count,
+                BlockNumber::Latest,
+                &[self.reward_percentile],
+            )
+            .await
+            .map_err(|e| DistributorError::rpc("获取费用历史", e))?;
+        // 最后一项是下一个区块的baseFee
+        let base_fee = *history
+            .base_fee_per_gas
+            .last()
+            .ok_or_else(|| anyhow!("eth_feeHistory没有返回baseFee"))?;
+        let mut rewards: Vec<U256> = history
+            .reward
+            .iter()
+            .filter_map(|block| block.first().copied())
+            .collect();
+        if rewards.is_empty() {
+            return Err(anyhow!("eth_feeHistory没有返回小费数据").into());
+        }
+        rewards.sort();
+        let priority_fee = rewards[rewards.len() / 2];
+        let max_fee = scale_fee(base_fee, self.base_fee_multiplier).saturating_add(priority_fee);
+        info!(
+            "按最近 {} 个区块的费用历史: baseFee {} wei，{}分位小费中位数 {} wei，maxFeePerGas {} wei",
+            rewards.len(),
+            base_fee,
+            self.reward_percentile,
+            priority_fee,
+            max_fee
+        );
+        Ok((max_fee, priority_fee))
+    }
+}
+
+/// `fee × multiplier`，倍数按千分之一精度计算
+fn scale_fee(fee: U256, multiplier: f64) -> U256 {
+    fee.saturating_mul(U256::from((multiplier * 1000.0).round() as u64)) / 1000
+}
+
+/// 每日Gas花费上限，按调度时区的日期统计，跨天后重新计算
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpendBudget {
+    /// 每天所有分发交易（含失败上链的交易）的Gas花费上限，单位wei
+    pub max_per_day: U256,
+    pub timezone: ScheduleTimezone,
+}
+
+/// 无法从节点获取Gas价格时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GasPriceFallback {
+    /// 使用内置默认价格（legacy 30 gwei，EIP-1559小费 1.5 gwei）
+    #[default]
+    UseDefault,
+    /// 不发送交易，按失败处理
+    Abort,
+    /// 使用本进程最近一次成功获取的价格，还没有成功获取过时中止
+    UseLastKnown,
+}
+
+impl FromStr for GasPriceFallback {
+    type Err = DistributorError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "use-default" => Ok(GasPriceFallback::UseDefault),
+            "abort" => Ok(GasPriceFallback::Abort),
+            "use-last-known" => Ok(GasPriceFallback::UseLastKnown),
+            other => Err(DistributorError::Config(format!(
+                "无效的ON_GAS_PRICE_UNAVAILABLE: {}，可选值: use-default, abort, use-last-known",
+                other
+            ))),
+        }
+    }
+}
+
+/// 最近一次从节点成功获取的费用，所有克隆共享
+#[derive(Debug, Default)]
+pub struct LastKnownFees {
+    gas_price: Mutex<Option<U256>>,
+    /// `(maxFeePerGas, maxPriorityFeePerGas)`
+    eip1559: Mutex<Option<(U256, U256)>>,
+}
+
+impl LastKnownFees {
+    pub fn gas_price(&self) -> Option<U256> {
+        *self.gas_price.lock().unwrap()
+    }
+
+    pub fn set_gas_price(&self, price: U256) {
+        *self.gas_price.lock().unwrap() = Some(price);
+    }
+
+    pub fn eip1559(&self) -> Option<(U256, U256)> {
+        *self.eip1559.lock().unwrap()
+    }
+
+    pub fn set_eip1559(&self, max_fee: U256, priority_fee: U256) {
+        *self.eip1559.lock().unwrap() = Some((max_fee, priority_fee));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_block, test_client, MockNode, MockReply};
+    use serde_json::Value;
+
+    /// 区块100的节点，`base_fee` 为空时模拟不支持EIP-1559的链
+    async fn block_node(base_fee: Option<u64>) -> MockNode {
+        MockNode::start(move |method, _, _| match method {
+            "eth_getBlockByNumber" => MockReply::Result(test_block(100, 0, base_fee)),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await
+    }
+
+    fn block(base_fee: Option<u64>) -> Block<H256> {
+        serde_json::from_value::<Block<H256>>(test_block(100, 0, base_fee)).unwrap()
+    }
+
+    #[test]
+    fn detect_fee_mode_uses_base_fee_presence() {
+        assert_eq!(detect_fee_mode(&block(None)), FeeMode::Legacy);
+        assert_eq!(
+            detect_fee_mode(&block(Some(1_000_000_000))),
+            FeeMode::Eip1559
+        );
+        // baseFee降到0仍然是1559区块
+        assert_eq!(detect_fee_mode(&block(Some(0))), FeeMode::Eip1559);
+    }
+
+    #[tokio::test]
+    async fn auto_detects_legacy_chain() {
+        let node = block_node(None).await;
+        let detector = FeeModeDetector::new(TxType::Auto);
+
+        let mode = detector.resolve(test_client(node.url(), 0).as_ref()).await;
+
+        assert_eq!(mode.unwrap(), FeeMode::Legacy);
+    }
+
+    #[tokio::test]
+    async fn auto_detects_eip1559_chain_and_caches_result() {
+        let node = block_node(Some(1_000_000_000)).await;
+        let client = test_client(node.url(), 0);
+        let detector = FeeModeDetector::new(TxType::Auto);
+
+        assert_eq!(
+            detector.resolve(client.as_ref()).await.unwrap(),
+            FeeMode::Eip1559
+        );
+        assert_eq!(
+            detector.resolve(client.as_ref()).await.unwrap(),
+            FeeMode::Eip1559
+        );
+        assert_eq!(node.count("eth_getBlockByNumber"), 1);
+
+        detector.invalidate();
+        assert_eq!(
+            detector.resolve(client.as_ref()).await.unwrap(),
+            FeeMode::Eip1559
+        );
+        assert_eq!(node.count("eth_getBlockByNumber"), 2);
+    }
+
+    #[tokio::test]
+    async fn configured_tx_type_skips_detection() {
+        let node = block_node(Some(1_000_000_000)).await;
+        let client = test_client(node.url(), 0);
+
+        let legacy = FeeModeDetector::new(TxType::Legacy);
+        assert_eq!(
+            legacy.resolve(client.as_ref()).await.unwrap(),
+            FeeMode::Legacy
+        );
+        let eip1559 = FeeModeDetector::new(TxType::Eip1559);
+        assert_eq!(
+            eip1559.resolve(client.as_ref()).await.unwrap(),
+            FeeMode::Eip1559
+        );
+        assert_eq!(node.count("eth_getBlockByNumber"), 0);
+    }
+
+    #[tokio::test]
+    async fn detection_fails_without_latest_block() {
+        let node = MockNode::start(|method, _, _| match method {
+            "eth_getBlockByNumber" => MockReply::Result(Value::Null),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let detector = FeeModeDetector::new(TxType::Auto);
+
+        let result = detector.resolve(test_client(node.url(), 0).as_ref()).await;
+
+        assert!(result.is_err());
+        // 失败的检测不会被缓存
+        assert!(detector.cached.lock().unwrap().is_none());
+    }
+
+    #[test]
+    fn tx_type_parses_config_values() {
+        assert_eq!("auto".parse::<TxType>().unwrap(), TxType::Auto);
+        assert_eq!(" legacy ".parse::<TxType>().unwrap(), TxType::Legacy);
+        assert_eq!("1559".parse::<TxType>().unwrap(), TxType::Eip1559);
+        assert!("eip4844".parse::<TxType>().is_err());
+    }
+}
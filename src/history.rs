@@ -0,0 +1,259 @@
+use crate::error::{DistributorError, Result};
+use crate::state::Trigger;
+use anyhow::anyhow;
+use chrono::{DateTime, Utc};
+use ethers::types::{Address, H256, U256, U64};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::warn;
+
+/// 默认保留的执行记录数
+pub const DEFAULT_HISTORY_CAPACITY: usize = 100;
+
+/// 一次执行的结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionOutcome {
+    Success,
+    /// 没有执行分发：重叠执行、暂停、跳过日期，或按配置跳过
+    Skipped,
+    Failed,
+    /// 超过 JOB_TIMEOUT_SECS 后停止等待
+    TimedOut,
+}
+
+impl fmt::Display for ExecutionOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExecutionOutcome::Success => write!(f, "成功"),
+            ExecutionOutcome::Skipped => write!(f, "跳过"),
+            ExecutionOutcome::Failed => write!(f, "失败"),
+            ExecutionOutcome::TimedOut => write!(f, "超时"),
+        }
+    }
+}
+
+/// 一次执行的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExecutionRecord {
+    pub trigger: Trigger,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub outcome: ExecutionOutcome,
+    /// 本次执行发送的交易（包括分批分发的批次）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tx_hashes: Vec<H256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// 已上链交易的Gas用量合计
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_used: Option<U256>,
+    /// 已上链交易的交易费用合计（wei）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<U256>,
+}
+
+/// 从链上分发事件重建的一次分发
+#[derive(Debug, Clone, Serialize)]
+pub struct ChainDistributionRecord {
+    /// 事件所在区块的时间
+    pub timestamp: DateTime<Utc>,
+    pub block_number: U64,
+    pub tx_hash: H256,
+    /// 发出事件的奖励合约
+    pub contract: Address,
+    /// 分发金额，事件签名中没有名为amount/total的参数时为空
+    pub amount: Option<U256>,
+    /// 接收地址数，事件签名中没有名为recipient/count的参数时为空
+    pub recipients: Option<U256>,
+    pub gas_used: Option<U256>,
+    /// 交易费用（wei）
+    pub fee: Option<U256>,
+}
+
+/// 最近若干次执行的内存记录，超过容量时丢弃最早的记录
+///
+/// 设置了快照文件时每次记录后写入全部记录，供 `history` 命令读取；不会在启动时加载。
+#[derive(Debug)]
+pub struct ExecutionHistory {
+    capacity: usize,
+    records: Mutex<VecDeque<ExecutionRecord>>,
+    snapshot_path: Option<PathBuf>,
+}
+
+impl Default for ExecutionHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl ExecutionHistory {
+    /// 最多保留 `capacity` 条记录（至少1条）
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            records: Mutex::new(VecDeque::new()),
+            snapshot_path: None,
+        }
+    }
+
+    /// 每次记录后把全部记录写入 `path`（JSON数组）
+    pub fn with_snapshot_path(mut self, path: Option<PathBuf>) -> Self {
+        self.snapshot_path = path;
+        self
+    }
+
+    /// 以已有的记录（如执行记录文件中的记录）开始，超过容量时只保留最近的记录
+    pub fn with_records(self, records: Vec<ExecutionRecord>) -> Self {
+        {
+            let mut current = self.records.lock().unwrap();
+            let skip = records.len().saturating_sub(self.capacity);
+            current.extend(records.into_iter().skip(skip));
+        }
+        self
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// 追加一条记录，超过容量时丢弃最早的记录
+    pub fn record(&self, record: ExecutionRecord) {
+        let snapshot = {
+            let mut records = self.records.lock().unwrap();
+            while records.len() >= self.capacity {
+                records.pop_front();
+            }
+            records.push_back(record);
+            self.snapshot_path.as_ref().map(|_| records.iter().cloned().collect::<Vec<_>>())
+        };
+        if let (Some(path), Some(snapshot)) = (&self.snapshot_path, snapshot) {
+            if let Err(e) = write_history_file(path, &snapshot) {
+                warn!("{}", e);
+            }
+        }
+    }
+
+    /// 当前记录的快照，按执行开始的先后排列
+    pub fn snapshot(&self) -> Vec<ExecutionRecord> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+}
+
+/// 读取执行记录文件，文件不存在时为空
+pub fn read_history_file(path: &Path) -> Result<Vec<ExecutionRecord>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(anyhow!("读取 {} 失败: {}", path.display(), e).into()),
+    };
+    serde_json::from_str(&content)
+        .map_err(|e| DistributorError::Other(anyhow!("解析执行记录文件 {} 失败: {}", path.display(), e)))
+}
+
+fn write_history_file(path: &Path, records: &[ExecutionRecord]) -> Result<()> {
+    let content = serde_json::to_vec_pretty(records)
+        .map_err(|e| DistributorError::Other(anyhow!("序列化执行记录失败: {}", e)))?;
+    std::fs::write(path, content)
+        .map_err(|e| anyhow!("写入执行记录文件 {} 失败: {}", path.display(), e).into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(minute: u32, trigger: Trigger) -> ExecutionRecord {
+        let started_at = DateTime::parse_from_rfc3339(&format!("2024-06-01T06:{:02}:00Z", minute))
+            .unwrap()
+            .with_timezone(&Utc);
+        ExecutionRecord {
+            trigger,
+            started_at,
+            finished_at: started_at + chrono::Duration::seconds(30),
+            outcome: ExecutionOutcome::Success,
+            tx_hashes: vec![H256::from_low_u64_be(minute as u64)],
+            error: None,
+            gas_used: None,
+            fee: None,
+        }
+    }
+
+    fn minutes(records: &[ExecutionRecord]) -> Vec<u32> {
+        use chrono::Timelike;
+        records
+            .iter()
+            .map(|record| record.started_at.minute())
+            .collect()
+    }
+
+    #[test]
+    fn records_are_appended_in_order() {
+        let history = ExecutionHistory::new(10);
+        history.record(record(1, Trigger::Startup));
+        history.record(record(2, Trigger::Cron));
+        history.record(record(3, Trigger::Manual));
+
+        let snapshot = history.snapshot();
+        assert_eq!(minutes(&snapshot), vec![1, 2, 3]);
+        assert_eq!(
+            snapshot
+                .iter()
+                .map(|record| record.trigger)
+                .collect::<Vec<_>>(),
+            vec![Trigger::Startup, Trigger::Cron, Trigger::Manual]
+        );
+    }
+
+    #[test]
+    fn capacity_evicts_oldest_records() {
+        let history = ExecutionHistory::new(3);
+        for minute in 1..=5 {
+            history.record(record(minute, Trigger::Cron));
+        }
+
+        assert_eq!(minutes(&history.snapshot()), vec![3, 4, 5]);
+        history.record(record(6, Trigger::Manual));
+        assert_eq!(minutes(&history.snapshot()), vec![4, 5, 6]);
+    }
+
+    #[test]
+    fn zero_capacity_keeps_latest_record() {
+        let history = ExecutionHistory::new(0);
+        history.record(record(1, Trigger::Cron));
+        history.record(record(2, Trigger::Cron));
+
+        assert_eq!(history.capacity(), 1);
+        assert_eq!(minutes(&history.snapshot()), vec![2]);
+    }
+
+    #[test]
+    fn loaded_records_keep_most_recent_within_capacity() {
+        let history = ExecutionHistory::new(2).with_records(
+            (1..=4)
+                .map(|minute| record(minute, Trigger::Cron))
+                .collect(),
+        );
+        assert_eq!(minutes(&history.snapshot()), vec![3, 4]);
+
+        history.record(record(5, Trigger::Manual));
+        assert_eq!(minutes(&history.snapshot()), vec![4, 5]);
+    }
+
+    #[test]
+    fn snapshot_file_follows_eviction() {
+        let path =
+            std::env::temp_dir().join(format!("history-snapshot-{}.json", std::process::id()));
+        let history = ExecutionHistory::new(2).with_snapshot_path(Some(path.clone()));
+        for minute in 1..=3 {
+            history.record(record(minute, Trigger::Cron));
+        }
+
+        let saved = read_history_file(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(minutes(&saved), vec![2, 3]);
+        assert_eq!(saved[1].tx_hashes, vec![H256::from_low_u64_be(3)]);
+    }
+}
@@ -0,0 +1,208 @@
+use crate::contract::DistributionResult;
+use crate::error::DistributorError;
+use crate::history::{ExecutionHistory, ExecutionOutcome, ExecutionRecord};
+use crate::notify::{Notification, Notifier, Severity};
+use crate::state::{StateStore, Trigger};
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc, Weekday};
+use ethers::types::U256;
+use futures::FutureExt;
+use std::fmt;
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::sync::Arc;
+use tracing::error;
+
+/// 一次执行的上下文，传给每个钩子
+#[derive(Debug, Clone)]
+pub struct RunContext {
+    /// 执行名称，如 `每日任务`、`手动触发`
+    pub name: String,
+    pub trigger: Trigger,
+    /// 获取执行锁的时间；获取执行锁之前就跳过的执行为跳过的时间
+    pub started_at: DateTime<Utc>,
+}
+
+impl RunContext {
+    pub fn new(name: impl Into<String>, trigger: Trigger) -> Self {
+        Self {
+            name: name.into(),
+            trigger,
+            started_at: Utc::now(),
+        }
+    }
+}
+
+/// 没有执行分发的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// 调度器已暂停
+    Paused,
+    /// 日历中排除的日期（节假日、周末）
+    ExcludedDate {
+        date: NaiveDate,
+        reason: &'static str,
+    },
+    /// HOLIDAYS_PATH中的假日
+    Holiday(NaiveDate),
+    /// 不在SCHEDULE_DAYS允许的星期
+    DayDisabled { date: NaiveDate, weekday: Weekday },
+    /// 上一次执行尚未结束
+    Overlapping,
+    /// 调度器正在关闭
+    ShuttingDown,
+    /// 分发流程按配置跳过（例如启动检查策略为 notify-and-skip）
+    Distribution(String),
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SkipReason::Paused => write!(f, "scheduler paused"),
+            SkipReason::ExcludedDate { date, reason } => write!(f, "{} ({})", reason, date),
+            SkipReason::Holiday(date) => write!(f, "holiday ({})", date),
+            SkipReason::DayDisabled { date, weekday } => {
+                write!(f, "{} not in SCHEDULE_DAYS ({})", weekday, date)
+            }
+            SkipReason::Overlapping => write!(f, "previous run still in progress"),
+            SkipReason::ShuttingDown => write!(f, "scheduler shutting down"),
+            SkipReason::Distribution(message) => write!(f, "{}", message),
+        }
+    }
+}
+
+/// 每日任务执行的生命周期钩子，默认实现什么都不做
+///
+/// cron/间隔触发、手动触发、一次性任务和 `run_exclusive` 的执行都会调用。钩子按注册顺序
+/// 依次执行，panic会被捕获并记录日志，不影响任务和其他钩子。
+#[async_trait]
+pub trait JobHooks: Send + Sync {
+    /// 获取执行锁后、开始执行前
+    async fn on_start(&self, _ctx: &RunContext) {}
+
+    /// 执行成功，`results` 为各合约的分发结果，任务不返回结果时为空
+    async fn on_success(&self, _ctx: &RunContext, _results: &[DistributionResult]) {}
+
+    /// 执行失败或超时
+    async fn on_failure(&self, _ctx: &RunContext, _error: &DistributorError) {}
+
+    /// 没有执行分发，未获取执行锁就跳过时不会先调用 `on_start`
+    async fn on_skip(&self, _ctx: &RunContext, _reason: &SkipReason) {}
+}
+
+/// 执行一个钩子，捕获其中的panic
+pub(crate) async fn guarded(event: &str, hook: impl Future<Output = ()>) {
+    if AssertUnwindSafe(hook).catch_unwind().await.is_err() {
+        error!("{}钩子发生panic，已忽略", event);
+    }
+}
+
+/// 每日任务的返回值，成功时交给 `on_success` 的分发结果
+pub trait IntoRunResults {
+    fn into_run_results(self) -> Vec<DistributionResult>;
+}
+
+impl IntoRunResults for () {
+    fn into_run_results(self) -> Vec<DistributionResult> {
+        Vec::new()
+    }
+}
+
+impl IntoRunResults for DistributionResult {
+    fn into_run_results(self) -> Vec<DistributionResult> {
+        vec![self]
+    }
+}
+
+impl IntoRunResults for Vec<DistributionResult> {
+    fn into_run_results(self) -> Vec<DistributionResult> {
+        self
+    }
+}
+
+/// 把每次执行写入执行记录，交易和Gas数据取自状态文件中本次执行期间发送的交易
+pub struct HistoryHook {
+    history: Arc<ExecutionHistory>,
+    state: Option<Arc<StateStore>>,
+}
+
+impl HistoryHook {
+    pub fn new(history: Arc<ExecutionHistory>, state: Option<Arc<StateStore>>) -> Self {
+        Self { history, state }
+    }
+
+    fn record(&self, ctx: &RunContext, outcome: ExecutionOutcome, error: Option<String>) {
+        let records = self
+            .state
+            .as_ref()
+            .map(|state| state.records_since(ctx.started_at))
+            .unwrap_or_default();
+        let sum = |values: Vec<U256>| {
+            (!values.is_empty()).then(|| values.into_iter().fold(U256::zero(), |a, b| a + b))
+        };
+        self.history.record(ExecutionRecord {
+            trigger: ctx.trigger,
+            started_at: ctx.started_at,
+            finished_at: Utc::now(),
+            outcome,
+            tx_hashes: records.iter().map(|record| record.tx_hash).collect(),
+            error,
+            gas_used: sum(records.iter().filter_map(|record| record.gas_used).collect()),
+            fee: sum(records.iter().filter_map(|record| record.fee).collect()),
+        });
+    }
+}
+
+#[async_trait]
+impl JobHooks for HistoryHook {
+    async fn on_success(&self, ctx: &RunContext, _results: &[DistributionResult]) {
+        self.record(ctx, ExecutionOutcome::Success, None);
+    }
+
+    async fn on_failure(&self, ctx: &RunContext, error: &DistributorError) {
+        let outcome = match error {
+            DistributorError::JobTimeout { .. } => ExecutionOutcome::TimedOut,
+            _ => ExecutionOutcome::Failed,
+        };
+        self.record(ctx, outcome, Some(error.to_string()));
+    }
+
+    async fn on_skip(&self, ctx: &RunContext, reason: &SkipReason) {
+        self.record(ctx, ExecutionOutcome::Skipped, Some(reason.to_string()));
+    }
+}
+
+/// 任务执行超时时发送严重告警，假日跳过分发时发送通知
+pub struct NotificationHook {
+    notifier: Notifier,
+}
+
+impl NotificationHook {
+    pub fn new(notifier: Notifier) -> Self {
+        Self { notifier }
+    }
+}
+
+#[async_trait]
+impl JobHooks for NotificationHook {
+    async fn on_failure(&self, _ctx: &RunContext, error: &DistributorError) {
+        if let DistributorError::JobTimeout { .. } = error {
+            self.notifier
+                .notify(Notification::new(Severity::Critical, "任务执行超时", error.to_string()))
+                .await;
+        }
+    }
+
+    async fn on_skip(&self, ctx: &RunContext, reason: &SkipReason) {
+        // 假日不分发是有意为之，但仍需通知，让当天没有分发有据可查
+        if let SkipReason::Holiday(date) = reason {
+            self.notifier
+                .notify(Notification::new(
+                    Severity::Info,
+                    "假日跳过每日奖励分发",
+                    format!("{} 在假日文件中，已跳过{}", date, ctx.name),
+                ))
+                .await;
+        }
+    }
+}
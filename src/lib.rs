@@ -1,7 +1,169 @@
+pub mod admin;
+pub mod batch;
+pub mod build_info;
 pub mod config;
 pub mod contract;
+pub mod debug;
+pub mod error;
+pub mod gas;
+pub mod history;
+pub mod hooks;
+pub mod merkle;
+pub mod monitor;
+pub mod nonce;
+pub mod notify;
+pub mod preflight;
+pub mod provider;
+pub mod recipients;
+pub mod remote_recipients;
+pub mod retry;
 pub mod scheduler;
+pub mod signer;
+pub mod state;
+pub mod telemetry;
+#[cfg(test)]
+mod test_utils;
+pub mod transport;
 
-pub use config::Config;
-pub use contract::RewardsContract;
-pub use scheduler::DailyScheduler;
\ No newline at end of file
+pub use admin::{AdminClient, AdminServer, AdminStatus, ScheduledOnce};
+pub use config::{
+    ChainTarget, ConfirmationStrategy, Config, ContractAddress, DistributionMode, GasStrategy,
+    ScheduleMode,
+};
+pub use contract::{
+    distribute_concurrently, ContractOutcome, DistributeOptions, DistributionEvent,
+    DistributionReport, DistributionResult, RewardsContract, RewardsContractBuilder,
+};
+pub use error::{DistributorError, ErrorKind};
+pub use gas::{FeeEscalation, FeeHistoryConfig, GasPriceFallback, SpendBudget, TxType};
+pub use history::{ChainDistributionRecord, ExecutionHistory, ExecutionOutcome, ExecutionRecord};
+pub use hooks::{HistoryHook, IntoRunResults, JobHooks, RunContext, SkipReason};
+pub use monitor::{runway, BalanceMonitor, DeadManSwitch, DistributionVerifier, RunwayThresholds};
+pub use nonce::NonceGap;
+pub use notify::{Notification, Notifier, Severity};
+pub use preflight::{
+    CallProbe, PreflightCheck, PreflightPolicies, PreflightPolicy, PreflightReport, ProxyInfo,
+};
+pub use retry::{ConfirmationPoll, RetryPolicy};
+pub use scheduler::{
+    CronSchedule, DailyScheduler, DstPolicy, JobHandle, JobInfo, ScheduleCalendar, ScheduleTimezone,
+    TriggerHandle,
+};
+pub use state::{StateStore, Trigger};
+pub use telemetry::init_tracing;
+use error::Result;
+use provider::{build_transport, provider_with_transport, resolve_contract_address};
+use signer::DistributorSigner;
+use std::sync::Arc;
+
+/// 按配置创建签名客户端、加载状态文件并构建奖励合约实例
+///
+/// 使用AWS KMS签名时会请求KMS获取签名地址。
+pub async fn build_contract(config: &Config) -> Result<RewardsContract> {
+    let mut contracts = build_signer_contracts(config).await?;
+    Ok(contracts.remove(0))
+}
+
+/// 为主签名账户和 ADDITIONAL_PRIVATE_KEYS 中的每个账户各构建一个奖励合约实例，第一个使用主签名账户
+///
+/// 所有实例共用RPC限速和状态文件，每个签名账户单独分配nonce。
+pub async fn build_signer_contracts(config: &Config) -> Result<Vec<RewardsContract>> {
+    let transport = build_transport(config)?;
+    let provider = provider_with_transport(config, transport.clone());
+    // ENS名称只在这里解析一次，之后整个进程使用解析出的地址
+    let contract_address = resolve_contract_address(&provider, &config.contract_address).await?;
+    if let ContractAddress::Ens(name) = &config.contract_address {
+        tracing::info!("ENS名称 {} 解析为合约地址 {:?}", name, contract_address);
+    }
+    let signer = DistributorSigner::from_config(&config.signer, config.chain_id).await?;
+    let client = Arc::new(ethers::middleware::SignerMiddleware::new(provider, signer));
+    let state = Arc::new(StateStore::load(&config.state_path)?);
+
+    let mut builder = RewardsContract::builder()
+        .address(contract_address)
+        .client(client)
+        .gas_limit(config.gas_limit)
+        .chain_id(config.chain_id)
+        .confirmation_strategy(config.confirmation_strategy);
+    if let Some(gas_price) = config.gas_price {
+        builder = builder.gas_price(gas_price);
+    }
+
+    let contract = builder
+        .build()?
+        .with_gas_strategy(config.gas_strategy)
+        .with_tx_type(config.tx_type)
+        .with_max_gas_price(config.max_gas_price)
+        .with_gas_price_fallback(config.gas_price_fallback)
+        .with_fee_escalation(config.fee_escalation.clone())
+        .with_fee_history(config.fee_history)
+        .with_spend_budget(config.max_spend_per_day.map(|max_per_day| SpendBudget {
+            max_per_day,
+            timezone: config.schedule_timezone,
+        }))
+        .with_gas_estimation_fallback(config.gas_estimation_fallback)
+        .with_gas_estimation_retries(config.gas_estimation_retries)
+        .with_gas_estimate_bounds(config.gas_estimate_min, config.gas_estimate_max)
+        .with_gas_limit_ceiling(config.gas_limit_ceiling)
+        .with_simulate_before_send(config.simulate_before_send)
+        .with_simulate_as(config.simulate_as)
+        .with_min_interval(config.min_interval)
+        .with_schedule_timezone(config.schedule_timezone)
+        .with_preflight_policies(config.preflight_policies)
+        .with_reorg_protection(config.reorg_protection)
+        .with_confirmation_poll(config.confirmation_poll)
+        .with_verify_distribution(config.verify_distribution)
+        .with_distribution_event(config.distribution_event_signature.as_deref())
+        .with_log_block_range(config.log_block_range)
+        .with_multicall(config.multicall.clone())
+        .with_call_data(config.call_selector.clone())
+        .with_recipients(config.recipients.clone())
+        .with_remote_recipients(config.remote_recipients.clone())
+        .with_batching(config.batch_size, config.batch_gas_budget)
+        .with_token_balance_check(config.token_balance_check.clone())
+        .with_allowance_check(config.allowance_check.clone())
+        .with_merkle_output(
+            (config.distribution_mode == DistributionMode::Merkle
+                || config.remote_recipients.is_some())
+            .then(|| config.merkle_output_path.clone()),
+        )
+        .with_state_store(Some(state));
+
+    let mut contracts = vec![contract.clone()];
+    for signer in &config.additional_signers {
+        let signer = DistributorSigner::from_config(signer, config.chain_id).await?;
+        let provider = provider_with_transport(config, transport.clone());
+        let client = Arc::new(ethers::middleware::SignerMiddleware::new(provider, signer));
+        contracts.push(contract.for_signer(client));
+    }
+    Ok(contracts)
+}
+
+/// 为 CHAIN_TARGETS_PATH 中的每个分发目标构建奖励合约实例
+///
+/// 每个目标使用自己的RPC节点和状态文件，签名客户端按目标的链ID签名，与主链的实例互不共享nonce。
+pub async fn build_chain_target_contracts(config: &Config) -> Result<Vec<RewardsContract>> {
+    let mut contracts = Vec::with_capacity(config.chain_targets.len());
+    for target in &config.chain_targets {
+        let contract = build_contract(&config.for_chain_target(target)).await?;
+        tracing::info!(
+            "分发目标 {} (链ID {}): 合约 {:?}，节点 {}",
+            target.name,
+            target.chain_id,
+            target.contract_address,
+            provider::redact_url(&target.rpc_url)
+        );
+        contracts.push(contract);
+    }
+    Ok(contracts)
+}
+
+/// 不经过调度器执行一次完整的分发：构建客户端和合约，发送并等待确认
+///
+/// 只分发 CONTRACT_ADDRESS，整个流程受 RUN_DEADLINE_SECS 约束。
+pub async fn run_once(config: &Config) -> Result<DistributionResult> {
+    let contract = build_contract(config).await?;
+    contract
+        .distribute_and_confirm_within(config.run_deadline)
+        .await
+}
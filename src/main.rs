@@ -1,111 +1,1429 @@
 use anyhow::Result;
+use clap::{Parser, Subcommand};
+use daily_rewards_distributor::build_info;
+use daily_rewards_distributor::config::{parse_gas_limit, parse_gas_price};
+use daily_rewards_distributor::debug::{ContractDebugger, StateOverrides};
+use daily_rewards_distributor::admin::STATUS_RECENT_RUNS;
+use daily_rewards_distributor::history::read_history_file;
+use daily_rewards_distributor::monitor::{recent_costs, runway};
+use daily_rewards_distributor::provider::{redact_url, resolve_contract_address};
+use daily_rewards_distributor::scheduler::{
+    read_holidays_file, read_one_shot_file, write_one_shot_file,
+};
+use daily_rewards_distributor::telemetry;
+use daily_rewards_distributor::{
+    build_chain_target_contracts, build_signer_contracts, distribute_concurrently, AdminClient,
+    AdminServer, AdminStatus, BalanceMonitor, ChainDistributionRecord, Config, ContractAddress,
+    CronSchedule, DailyScheduler, DeadManSwitch, DistributeOptions, DistributionEvent,
+    DistributionReport, DistributionResult, DistributionVerifier, DistributorError,
+    ExecutionHistory, ExecutionRecord, HistoryHook, JobHandle, JobHooks, Notification, Notifier,
+    RewardsContract, RunContext, ScheduleCalendar, ScheduleMode, Severity, SkipReason, Trigger,
+    TriggerHandle,
+};
 use ethers::prelude::*;
-use std::sync::Arc;
-use tracing::{error, info};
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tracing::{error, info, warn};
 
-mod config;
-mod contract;
-mod scheduler;
-mod debug;
+/// 运行中的服务检查暂停标记文件的间隔
+const PAUSE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
 
-use config::Config;
-use contract::RewardsContract;
-use scheduler::DailyScheduler;
-use debug::ContractDebugger;
+#[derive(Parser)]
+#[command(about = "每日奖励分发服务", version = build_info::LONG_VERSION)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+    /// 调度器启动后立即执行一次分发（等同于 RUN_ON_STARTUP=true）
+    #[arg(long, global = true)]
+    run_now: bool,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// 启动定时分发服务（默认）
+    Run,
+    /// 用 eth_call 模拟分发调用，不发送交易
+    Simulate {
+        /// 覆盖账户的ETH余额，格式为 地址=数量(ETH)，可重复使用
+        #[arg(long = "override-balance", value_name = "ADDR=AMOUNT", value_parser = parse_balance_override)]
+        override_balance: Vec<(Address, U256)>,
+    },
+    /// 诊断合约调用，可在历史区块上模拟执行；全部通过时退出码为0，有警告为1，有失败为2
+    Diagnose {
+        /// 在指定区块的状态上模拟执行
+        #[arg(long, conflicts_with = "at_tx")]
+        at_block: Option<u64>,
+        /// 在指定交易执行前（所在区块的前一个区块）的状态上模拟执行
+        #[arg(long)]
+        at_tx: Option<H256>,
+        /// 诊断结束后在最后一行输出JSON格式的诊断报告
+        #[arg(long)]
+        json: bool,
+        /// 与 distribute-now --dry-run 相同的语义：诊断本身从不发送交易，加上该参数时在输出中明确确认
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// 获取交易收据并输出解码后的全部日志
+    InspectTx {
+        /// 交易哈希
+        tx_hash: H256,
+    },
+    /// 暂停运行中服务的定时任务，合约升级等期间使用；配置了ADMIN_LISTEN_ADDR时通过管理接口立即生效，否则创建暂停标记文件
+    Pause,
+    /// 恢复运行中服务的定时任务，不会补执行暂停期间错过的触发；未配置ADMIN_LISTEN_ADDR时删除暂停标记文件
+    Resume,
+    /// 输出运行中服务的状态：是否暂停、下次触发时间、已注册的任务和最近几次执行；
+    /// 未配置ADMIN_LISTEN_ADDR时按暂停标记文件和执行记录文件输出
+    Status,
+    /// 安排运行中的服务在指定时间额外执行一次分发，不影响定时任务；配置了ADMIN_LISTEN_ADDR时通过管理接口注册并确认，否则写入一次性任务文件
+    ScheduleOnce {
+        /// 执行时间（RFC 3339），如 2024-07-01T18:00:00Z
+        #[arg(long)]
+        at: chrono::DateTime<chrono::Utc>,
+    },
+    /// 输出运行中的服务最近的执行记录（配置了ADMIN_LISTEN_ADDR时通过管理接口查询，否则读取执行记录文件）；指定 --days 时从链上分发事件重建
+    History {
+        /// 只输出最近的若干条
+        #[arg(long)]
+        limit: Option<usize>,
+        /// 查询最近若干天链上的分发事件，不依赖本地状态（需要配置DISTRIBUTION_EVENT_SIGNATURE）
+        #[arg(long)]
+        days: Option<u32>,
+        /// 以JSON格式输出链上分发记录（与 --days 一起使用）
+        #[arg(long, requires = "days")]
+        json: bool,
+    },
+    /// 输出签名账户的地址和当前链上的ETH余额
+    Balance {
+        /// 持续刷新余额，直到按 Ctrl+C 退出
+        #[arg(long)]
+        watch: bool,
+        /// 刷新间隔（秒）
+        #[arg(long, default_value_t = 15, requires = "watch")]
+        interval: u64,
+    },
+    /// 检查签名账户是否有状态文件中没有记录的待处理nonce
+    RepairNonce {
+        /// 用0金额的自转账交易替换占用这些nonce的交易
+        #[arg(long)]
+        fix: bool,
+    },
+    /// 对比当前Gas估算和最近几次分发的实际用量，给出建议的GAS_LIMIT
+    GasReport {
+        /// 读取执行记录中最近的若干次成功分发
+        #[arg(long, default_value_t = 10)]
+        samples: usize,
+        /// 建议的GAS_LIMIT为最大用量乘以该系数
+        #[arg(long, default_value_t = 1.2, value_parser = parse_safety_factor)]
+        safety_factor: f64,
+        /// 以JSON格式输出报告
+        #[arg(long)]
+        json: bool,
+    },
+    /// 立即执行一次分发并等待确认，可临时覆盖Gas参数；发送前检查分发函数并要求确认
+    DistributeNow {
+        /// 覆盖Gas限制（设置后不再估算）
+        #[arg(long, value_parser = parse_gas_limit)]
+        gas_limit: Option<U256>,
+        /// 覆盖Gas价格，如 30gwei（EIP-1559交易中作为maxFeePerGas）
+        #[arg(long, value_parser = parse_gas_price)]
+        gas_price: Option<U256>,
+        /// 覆盖EIP-1559小费maxPriorityFeePerGas，如 2gwei
+        #[arg(long, value_parser = parse_gas_price)]
+        priority_fee: Option<U256>,
+        /// 跳过发送前的模拟执行
+        #[arg(long)]
+        skip_simulation: bool,
+        /// 不询问确认直接发送，非交互环境（如脚本）中必须使用
+        #[arg(long)]
+        yes: bool,
+        /// 只做发送前检查和模拟执行，不发送交易
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // 初始化日志
-    tracing_subscriber::fmt::init();
-
-    // 加载配置
+    // 解析命令行参数，加载 .env 和配置后初始化日志（OTLP导出地址可以写在 .env 中）
+    let cli = Cli::parse();
     dotenv::dotenv().ok();
     let config = Config::from_env()?;
+    telemetry::init_tracing(&config)?;
+    info!("每日奖励分发服务 {}", build_info::LONG_VERSION);
+
+    let result = run(cli, config).await;
+    telemetry::shutdown();
+    result
+}
+
+async fn run(cli: Cli, mut config: Config) -> Result<()> {
+    config.run_on_startup |= cli.run_now;
+
+    // 暂停、恢复、查看状态、安排一次性执行和查看执行记录只操作管理接口或本地文件，不需要连接节点
+    let admin = config
+        .admin_listen_addr
+        .map(|addr| AdminClient::new(addr, config.admin_token.clone()));
+    match cli.command {
+        Some(Command::Pause) if admin.is_some() => {
+            let status = admin.unwrap().pause().await?;
+            info!("运行中的服务已暂停定时任务");
+            print_status(&status);
+            return Ok(());
+        }
+        Some(Command::Resume) if admin.is_some() => {
+            let status = admin.unwrap().resume().await?;
+            info!("运行中的服务已恢复定时任务，暂停期间错过的触发不会补执行");
+            print_status(&status);
+            return Ok(());
+        }
+        Some(Command::ScheduleOnce { at }) if admin.is_some() => {
+            let scheduled = admin.unwrap().schedule_once(at).await?;
+            info!(
+                "运行中的服务已安排一次性任务 {}，执行时间: {}",
+                scheduled.id,
+                scheduled
+                    .next_run
+                    .map_or_else(|| "未知".to_string(), |time| time.to_rfc3339())
+            );
+            return Ok(());
+        }
+        Some(Command::Status) => {
+            match admin {
+                Some(admin) => print_status(&admin.status().await?),
+                None => {
+                    info!("未配置ADMIN_LISTEN_ADDR，按暂停标记文件和执行记录文件输出");
+                    let paused = config.pause_file.exists();
+                    println!("定时任务: {}", if paused { "已暂停" } else { "未暂停" });
+                    let records = read_history_file(&config.history_path)?;
+                    let skip = records.len().saturating_sub(STATUS_RECENT_RUNS);
+                    print_runs(&records[skip..]);
+                }
+            }
+            return Ok(());
+        }
+        Some(Command::History { limit, days: None, .. }) if admin.is_some() => {
+            let records = admin.unwrap().history(limit).await?;
+            if records.is_empty() {
+                info!("运行中的服务还没有执行记录");
+            }
+            for record in &records {
+                info!("{}", serde_json::to_string(record)?);
+            }
+            return Ok(());
+        }
+        Some(Command::Pause) => {
+            std::fs::write(&config.pause_file, chrono::Utc::now().to_rfc3339())?;
+            info!(
+                "已创建暂停标记文件 {}，运行中的服务将在 {} 秒内暂停定时任务",
+                config.pause_file.display(),
+                PAUSE_CHECK_INTERVAL.as_secs()
+            );
+            return Ok(());
+        }
+        Some(Command::Resume) => {
+            match std::fs::remove_file(&config.pause_file) {
+                Ok(()) => info!(
+                    "已删除暂停标记文件 {}，运行中的服务将在 {} 秒内恢复定时任务",
+                    config.pause_file.display(),
+                    PAUSE_CHECK_INTERVAL.as_secs()
+                ),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    info!("暂停标记文件 {} 不存在，服务未暂停", config.pause_file.display())
+                }
+                Err(e) => return Err(e.into()),
+            }
+            return Ok(());
+        }
+        Some(Command::ScheduleOnce { at }) => {
+            if at <= chrono::Utc::now() {
+                anyhow::bail!("执行时间 {} 已经过去", at.to_rfc3339());
+            }
+            let mut times = read_one_shot_file(&config.schedule_once_file)?;
+            times.insert(at);
+            write_one_shot_file(&config.schedule_once_file, &times)?;
+            info!(
+                "已将 {} 写入一次性任务文件 {}，运行中的服务将在 {} 秒内注册",
+                at.to_rfc3339(),
+                config.schedule_once_file.display(),
+                PAUSE_CHECK_INTERVAL.as_secs()
+            );
+            return Ok(());
+        }
+        Some(Command::History { limit, days: None, .. }) => {
+            let records = read_history_file(&config.history_path)?;
+            if records.is_empty() {
+                info!("执行记录文件 {} 中没有记录", config.history_path.display());
+                return Ok(());
+            }
+            let skip = limit.map_or(0, |limit| records.len().saturating_sub(limit));
+            for record in &records[skip..] {
+                info!("{}", serde_json::to_string(record)?);
+            }
+            return Ok(());
+        }
+        _ => {}
+    }
 
-    info!("启动每日奖励分发服务...");
     info!("合约地址: {}", config.contract_address);
-    info!("RPC URL: {}", config.rpc_url);
+    info!("RPC节点: {}", redact_url(&config.rpc_url));
+    if let Some(call_data) = &config.call_selector {
+        info!("使用自定义calldata: {}", call_data);
+    }
+    if let Some(recipients) = &config.recipients {
+        info!("接收地址文件: {}", recipients.path.display());
+    }
+    if let Some(remote) = &config.remote_recipients {
+        info!("接收地址URL: {}", redact_url(&remote.url));
+    }
+    if let Some(multicall) = &config.multicall {
+        info!(
+            "Multicall聚合分发: {:?}，目标合约: {:?}",
+            multicall.address, multicall.targets
+        );
+    }
+
+    // 创建客户端、加载状态文件并构建合约实例，配置了多个签名账户时每个账户一个实例
+    let signers = build_signer_contracts(&config).await?;
+    let rewards_contract = signers[0].clone();
+    info!("状态文件: {}", config.state_path.display());
+
+    match cli.command.unwrap_or(Command::Run) {
+        Command::Run => run_service(&config, signers).await,
+        Command::Pause | Command::Resume | Command::Status | Command::ScheduleOnce { .. } | Command::History { days: None, .. } => {
+            unreachable!("暂停、恢复、查看状态、安排一次性执行和查看执行记录在连接节点前已处理")
+        }
+        Command::History {
+            limit,
+            days: Some(days),
+            json,
+        } => {
+            let since = chrono::Utc::now() - chrono::Duration::days(i64::from(days));
+            let from_block = rewards_contract
+                .estimate_block_since(since.timestamp().max(0) as u64)
+                .await?;
+            info!("从区块 {} 开始查询最近 {} 天的链上分发事件", from_block, days);
+
+            let mut records: Vec<_> = rewards_contract
+                .distribution_history(from_block)
+                .await?
+                .into_iter()
+                .filter(|record| record.timestamp >= since)
+                .collect();
+            if let Some(limit) = limit {
+                records.drain(..records.len().saturating_sub(limit));
+            }
+
+            if json {
+                println!("{}", serde_json::to_string(&records)?);
+            } else if records.is_empty() {
+                info!("最近 {} 天链上没有分发事件", days);
+            } else {
+                print_chain_history(&records);
+            }
+            Ok(())
+        }
+        Command::Simulate { override_balance } => {
+            let overrides = override_balance
+                .into_iter()
+                .fold(StateOverrides::new(), |overrides, (address, amount)| {
+                    overrides.balance(address, amount)
+                });
+            ContractDebugger::new(rewards_contract)
+                .simulate_with_overrides(overrides)
+                .await
+        }
+        Command::Diagnose {
+            at_block,
+            at_tx,
+            json,
+            dry_run,
+        } => {
+            if dry_run {
+                info!("--dry-run: 诊断只执行eth_call和只读查询，不会发送任何交易");
+            } else {
+                info!("诊断只执行eth_call和只读查询，不会发送任何交易");
+            }
+            let debugger = ContractDebugger::new(rewards_contract).with_runway(
+                read_history_file(&config.history_path)?,
+                config.runway_thresholds,
+                config.daily_schedule.runs_per_day(chrono::Utc::now()),
+            );
+            let block = match (at_block, at_tx) {
+                (Some(number), _) => Some(BlockId::from(number)),
+                (None, Some(tx_hash)) => {
+                    debugger.inspect_transaction(tx_hash).await?;
+                    Some(debugger.block_before_tx(tx_hash).await?)
+                }
+                (None, None) => None,
+            };
+            let report = debugger.diagnose(block).await;
+            report.log();
+            if json {
+                println!("{}", serde_json::to_string(&report)?);
+            }
+            let code = report.exit_code();
+            if code != 0 {
+                if report.has_failures() {
+                    let failed: Vec<_> = report.failures().map(|step| step.name).collect();
+                    error!("诊断未通过: {}", failed.join("、"));
+                } else {
+                    warn!("诊断通过，但有警告");
+                }
+                telemetry::shutdown();
+                std::process::exit(code);
+            }
+            info!("诊断全部通过");
+            Ok(())
+        }
+        Command::InspectTx { tx_hash } => {
+            ContractDebugger::new(rewards_contract)
+                .inspect_transaction(tx_hash)
+                .await?;
+            Ok(())
+        }
+        Command::Balance { watch, interval } => {
+            let mut ticker = tokio::time::interval(Duration::from_secs(interval.max(1)));
+            loop {
+                ticker.tick().await;
+                // 执行记录中的花费属于发送分发的主签名账户
+                let costs = recent_costs(&read_history_file(&config.history_path)?);
+                let runs_per_day = config.daily_schedule.runs_per_day(chrono::Utc::now());
+                for (index, contract) in signers.iter().enumerate() {
+                    let address = contract.client_address();
+                    let balance = contract.client.get_balance(address, None).await?;
+                    let runway_text = runway(balance, &costs, runs_per_day)
+                        .filter(|_| index == 0)
+                        .map(|days| format!("，约可维持 {:.1} 天的分发", days))
+                        .unwrap_or_default();
+                    info!(
+                        "签名账户 {:?} 余额: {} ETH (链ID {}){}",
+                        address,
+                        ethers::utils::format_ether(balance),
+                        config.chain_id,
+                        runway_text
+                    );
+                }
+                if !watch {
+                    return Ok(());
+                }
+            }
+        }
+        Command::RepairNonce { fix } => {
+            let Some(gap) = rewards_contract.detect_nonce_gap().await? else {
+                info!("没有发现状态文件中未记录的待处理nonce");
+                return Ok(());
+            };
+            warn!("发现nonce缺口: {}", gap);
+            if !fix {
+                info!("使用 --fix 发送0金额的自转账交易替换这些nonce上的交易");
+                return Ok(());
+            }
+            let tx_hashes = rewards_contract.repair_nonce_gap(&gap).await?;
+            info!("已发送 {} 笔自转账交易: {:?}", tx_hashes.len(), tx_hashes);
+            Ok(())
+        }
+        Command::GasReport {
+            samples,
+            safety_factor,
+            json,
+        } => {
+            let history = read_history_file(&config.history_path)?;
+            let report = ContractDebugger::new(rewards_contract)
+                .gas_report(&history, samples, safety_factor)
+                .await;
+            if json {
+                println!("{}", serde_json::to_string(&report)?);
+            } else {
+                println!("{}", report);
+            }
+            Ok(())
+        }
+        Command::DistributeNow {
+            gas_limit,
+            gas_price,
+            priority_fee,
+            skip_simulation,
+            yes,
+            dry_run,
+        } => {
+            let report = rewards_contract.preflight().await?;
+            report.log();
+            report.ensure_callable()?;
+
+            let debugger = ContractDebugger::new(rewards_contract.clone());
+            if dry_run {
+                debugger.simulate_transaction(None).await?;
+                info!("--dry-run: 发送前检查完成，未发送交易");
+                return Ok(());
+            }
+            if !yes && !confirm(&format!(
+                "确认由签名账户 {:?} 向链ID {} 发送分发交易？",
+                rewards_contract.client_address(),
+                config.chain_id
+            ))? {
+                info!("已取消，未发送交易");
+                return Ok(());
+            }
+
+            let options = DistributeOptions {
+                gas_limit,
+                gas_price,
+                priority_fee,
+                skip_simulation,
+                trigger: Trigger::ManualCli,
+            };
+            // 与运行中的服务写同一个执行记录文件，先读入已有记录，避免覆盖
+            let history = ExecutionHistory::new(config.history_capacity)
+                .with_snapshot_path(Some(config.history_path.clone()))
+                .with_records(read_history_file(&config.history_path)?);
+            let hook = HistoryHook::new(Arc::new(history), rewards_contract.shared_state_store());
+            let ctx = RunContext::new("命令行手动执行", Trigger::ManualCli);
+
+            let result = debugger.manual_distribute(options).await;
+            match &result {
+                Ok(result) => hook.on_success(&ctx, std::slice::from_ref(result)).await,
+                Err(e) if e.is_skipped() => {
+                    hook.on_skip(&ctx, &SkipReason::Distribution(e.to_string()))
+                        .await
+                }
+                Err(e) => hook.on_failure(&ctx, e).await,
+            }
+            result?;
+            Ok(())
+        }
+    }
+}
+
+/// 输出管理接口返回的服务状态
+fn print_status(status: &AdminStatus) {
+    let time = |time: Option<chrono::DateTime<chrono::Utc>>| {
+        time.map_or_else(|| "-".to_string(), |time| time.to_rfc3339())
+    };
+    println!("版本: {}", status.version);
+    println!("定时任务: {}", if status.paused { "已暂停" } else { "运行中" });
+    println!("下次触发时间(UTC): {}", time(status.next_run));
+    if !status.upcoming_runs.is_empty() {
+        println!("之后{}次分发时间(UTC，已跳过排除日期):", status.upcoming_runs.len());
+        for run in &status.upcoming_runs {
+            println!("  {}", run.to_rfc3339());
+        }
+    }
+    for job in &status.jobs {
+        println!("  {:<24} {:<24} 下次触发: {}", job.name, job.cron, time(job.next_run));
+    }
+    print_runs(&status.recent_runs);
+}
+
+/// 以表格输出最近的执行记录
+fn print_runs(records: &[ExecutionRecord]) {
+    if records.is_empty() {
+        println!("最近执行: 无");
+        return;
+    }
+    println!("最近执行:");
+    println!("  {:<20} {:<14} {:<6} {:>8}  交易/错误", "开始时间(UTC)", "触发方式", "结果", "耗时(秒)");
+    for record in records {
+        let detail = match &record.error {
+            Some(error) => error.clone(),
+            None => record
+                .tx_hashes
+                .iter()
+                .map(|tx_hash| format!("{:?}", tx_hash))
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        println!(
+            "  {:<20} {:<14} {:<6} {:>8}  {}",
+            record.started_at.format("%Y-%m-%d %H:%M:%S"),
+            record.trigger.to_string(),
+            record.outcome.to_string(),
+            (record.finished_at - record.started_at).num_seconds(),
+            detail
+        );
+    }
+}
+
+/// 以表格输出链上分发记录
+fn print_chain_history(records: &[ChainDistributionRecord]) {
+    let optional = |value: Option<U256>| value.map_or_else(|| "-".to_string(), |value| value.to_string());
+    println!(
+        "{:<17} {:<66} {:>28} {:>10} {:>22}",
+        "时间(UTC)", "交易哈希", "金额", "接收地址数", "交易费用(ETH)"
+    );
+    for record in records {
+        println!(
+            "{:<17} {:<66} {:>28} {:>10} {:>22}",
+            record.timestamp.format("%Y-%m-%d %H:%M"),
+            format!("{:?}", record.tx_hash),
+            optional(record.amount),
+            optional(record.recipients),
+            record.fee.map_or_else(|| "-".to_string(), ethers::utils::format_ether)
+        );
+    }
+}
 
-    // 创建以太坊客户端
-    let provider = Provider::<Http>::try_from(&config.rpc_url)?;
-    let wallet: LocalWallet = config
-        .private_key
-        .parse::<LocalWallet>()?
-        .with_chain_id(config.chain_id);
-    let client = SignerMiddleware::new(provider, wallet);
-    let client = Arc::new(client);
+/// 解析安全系数，不能小于1
+fn parse_safety_factor(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(factor) if factor.is_finite() && factor >= 1.0 => Ok(factor),
+        _ => Err(format!("无效的安全系数: {}，应为不小于1的数", s)),
+    }
+}
+
+/// 在终端中询问是否继续，标准输入不是终端时返回错误，需要使用 --yes
+fn confirm(prompt: &str) -> Result<bool> {
+    use std::io::{IsTerminal, Write};
+
+    if !std::io::stdin().is_terminal() {
+        anyhow::bail!("标准输入不是终端，无法确认；使用 --yes 跳过确认");
+    }
+    eprint!("{} [y/N] ", prompt);
+    std::io::stderr().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim().to_lowercase().as_str(), "y" | "yes"))
+}
+
+/// 启动定时分发服务，直到收到退出信号
+async fn run_service(config: &Config, signers: Vec<RewardsContract>) -> Result<()> {
+    let rewards_contract = signers[0].clone();
+    info!("启动每日奖励分发服务...");
+
+    // 启动检查：分发函数不存在时直接退出，而不是每天晚上报一次回滚
+    match rewards_contract.preflight().await {
+        Ok(report) => {
+            report.log();
+            report.ensure_callable()?;
+        }
+        Err(e) => warn!("启动检查失败: {}", e),
+    }
+
+    // 启动恢复：接管内存池中已有的分发交易，避免下次运行重复发送
+    match rewards_contract.recover_pending_distribution(Trigger::Startup).await {
+        Ok(Some(tx_hash)) => info!("已接管未确认的分发交易: {:?}", tx_hash),
+        Ok(None) => {}
+        Err(e) => warn!("检查未确认的分发交易失败: {}", e),
+    }
+
+    // 余额监控：低于阈值时发送通知
+    let notifier = Notifier::new(config.notify_webhook_url.clone());
+
+    // 检查崩溃留下的nonce缺口，只报告不修复，修复需要手动执行 repair-nonce --fix
+    match rewards_contract.detect_nonce_gap().await {
+        Ok(Some(gap)) => {
+            warn!("发现nonce缺口: {}，后续交易可能排在这些交易之后", gap);
+            let notification = Notification::new(
+                Severity::Warning,
+                "签名账户存在未记录的待处理交易",
+                format!("{}。可执行 repair-nonce --fix 用自转账交易替换", gap),
+            )
+            .with_details(&gap);
+            notifier.notify(notification).await;
+        }
+        Ok(None) => {}
+        Err(e) => warn!("检查nonce缺口失败: {}", e),
+    }
+    if config.min_balance_warn.is_some() || config.min_balance_critical.is_some() {
+        let monitor = Arc::new(BalanceMonitor::new(
+            rewards_contract.clone(),
+            notifier.clone(),
+            config.min_balance_warn,
+            config.min_balance_critical,
+        )
+        .with_history_path(Some(config.history_path.clone()))
+        .with_runs_per_day(config.daily_schedule.runs_per_day(chrono::Utc::now())));
+        monitor.spawn(config.balance_check_interval);
+        info!("余额监控已启动，检查间隔: {:?}", config.balance_check_interval);
+    }
 
-    // 创建合约实例
-    let rewards_contract = RewardsContract::new(config.contract_address, client.clone(),config.gas_limit,
-        config.gas_price,config.chain_id);
+    // 死人开关：长时间没有成功分发时告警，兜住服务卡住、什么都没做的情况
+    let dead_man_switch = config.dead_man_switch.map(|max_silence| {
+        let last_success = rewards_contract
+            .last_confirmed_at()
+            .unwrap_or_else(chrono::Utc::now);
+        let switch = Arc::new(DeadManSwitch::new(
+            notifier.clone(),
+            max_silence,
+            config.last_success_path.clone(),
+            last_success,
+        ));
+        switch.clone().spawn(Duration::from_secs(15 * 60));
+        info!("死人开关已启动，阈值: {:?}，上次成功: {}", max_silence, last_success);
+        switch
+    });
+
+    // 执行成功后的生命周期钩子：死人开关记录成功时间
+    let hooks: Vec<Arc<dyn JobHooks>> = dead_man_switch
+        .into_iter()
+        .map(|switch| switch as Arc<dyn JobHooks>)
+        .collect();
+
+    // 假日文件有无效的行时不启动，避免在应跳过的日子分发
+    let holidays = match &config.holidays_path {
+        Some(path) => {
+            let holidays = read_holidays_file(path)?;
+            info!("已从 {} 读取 {} 个假日", path.display(), holidays.len());
+            holidays
+        }
+        None => Default::default(),
+    };
 
     // 创建调度器
-    let mut scheduler = DailyScheduler::new().await?;
-
-    // 添加每日任务
-    let contract_clone = rewards_contract.clone();
-    scheduler
-        .add_daily_job(move || {
-            let contract = contract_clone.clone();
-            async move { distribute_daily_rewards(contract).await }
+    let scheduler = DailyScheduler::new().await?.with_calendar(
+        ScheduleCalendar::new(config.skip_dates.iter().copied(), config.skip_weekends)
+            .with_days(config.schedule_days.clone())
+            .with_holidays(holidays),
+    )
+    .with_schedule(config.daily_schedule.clone())
+    .with_jitter(config.schedule_jitter)
+    .with_state_store(rewards_contract.shared_state_store())
+    .with_job_timeout(config.job_timeout)
+    .with_shutdown_grace(config.shutdown_grace)
+    .with_history(
+        ExecutionHistory::new(config.history_capacity)
+            .with_snapshot_path(Some(config.history_path.clone())),
+    )
+    .with_notifier(notifier.clone())
+    .with_hooks(hooks);
+
+    // 分发事件校验：每小时查询当天的分发事件，独立于收据处理
+    let verifier = config.distribution_event_signature.as_ref().map(|_| {
+        Arc::new(DistributionVerifier::new(
+            rewards_contract.clone(),
+            notifier.clone(),
+            config.event_verify_grace,
+        ))
+    });
+    if let Some(verifier) = verifier.clone() {
+        scheduler
+            .add_hourly_job("分发事件校验", move || {
+                let verifier = verifier.clone();
+                async move { verifier.check().await.map(|_| ()) }
+            })
+            .await?;
+    }
+
+    // 其他链上的分发目标：各自连接自己的节点，同样在启动时检查分发函数并接管未确认的交易
+    let chain_targets = build_chain_target_contracts(config).await?;
+    for (target, contract) in config.chain_targets.iter().zip(&chain_targets) {
+        match contract.preflight().await {
+            Ok(report) => {
+                report.log();
+                report
+                    .ensure_callable()
+                    .map_err(|e| anyhow::anyhow!("分发目标 {}: {}", target.name, e))?;
+            }
+            Err(e) => warn!("分发目标 {} 启动检查失败: {}", target.name, e),
+        }
+        match contract.recover_pending_distribution(Trigger::Startup).await {
+            Ok(Some(tx_hash)) => info!("分发目标 {} 已接管未确认的分发交易: {:?}", target.name, tx_hash),
+            Ok(None) => {}
+            Err(e) => warn!("分发目标 {} 检查未确认的分发交易失败: {}", target.name, e),
+        }
+    }
+
+    // 添加每日任务，奖励合约按顺序轮流分配给各签名账户，同一账户的合约共享nonce分配；
+    // 其他链上的目标使用各自的签名客户端，在同一次执行中一起分发
+    let contracts: Arc<Vec<RewardsContract>> = Arc::new(
+        std::iter::once(rewards_contract.clone())
+            .chain(
+                config
+                    .additional_contracts
+                    .iter()
+                    .enumerate()
+                    .map(|(i, address)| signers[(i + 1) % signers.len()].for_address(*address)),
+            )
+            .chain(chain_targets)
+            .collect(),
+    );
+    if contracts.len() > 1 {
+        info!(
+            "共 {} 个奖励合约，{} 个签名账户，{} 个其他链上的分发目标，最大并发数: {}",
+            contracts.len(),
+            signers.len(),
+            config.chain_targets.len(),
+            config.max_concurrent_distributions
+        );
+        for contract in contracts.iter() {
+            info!(
+                "合约 {:?} (链ID {}) 由 {:?} 发送",
+                contract.contract_address(),
+                contract.chain_id(),
+                contract.client_address()
+            );
+        }
+    }
+    let notifier_clone = notifier.clone();
+    let run_deadline = config.run_deadline;
+    let max_concurrent = config.max_concurrent_distributions;
+    // 配置为失败即退出时，任务把错误交给主循环，由主循环关闭调度器并退出
+    let (fatal_tx, mut fatal_rx) = tokio::sync::mpsc::unbounded_channel();
+    let fatal_tx = config.exit_on_distribution_failure.then_some(fatal_tx);
+    // 重新加载配置时替换为应用了新配置的实例，之后的执行使用新实例
+    let active_contracts = Arc::new(RwLock::new(contracts.clone()));
+    // 定时任务、启动补执行和启动时执行共用的分发流程
+    let daily_run = {
+        let contracts = active_contracts.clone();
+        let events = scheduler.event_sender();
+        Arc::new(move |trigger: Trigger| {
+            let contracts = contracts.read().unwrap().clone();
+            let notifier = notifier_clone.clone();
+            let verifier = verifier.clone();
+            let fatal_tx = fatal_tx.clone();
+            let events = events.clone();
+            async move {
+                let results = distribute_daily_rewards(
+                    &contracts,
+                    notifier,
+                    run_deadline,
+                    max_concurrent,
+                    trigger,
+                    events.as_ref(),
+                )
+                .await
+                .inspect_err(|e| {
+                    // 启动时执行只用于验证流程，失败不停止服务；按配置跳过的分发也不算失败
+                    if let Some(fatal_tx) =
+                        fatal_tx.filter(|_| trigger != Trigger::Startup && !e.is_skipped())
+                    {
+                        let _ = fatal_tx.send(e.to_string());
+                    }
+                })?;
+                // 分发完成后立即核对一次链上事件
+                if let Some(verifier) = verifier {
+                    if let Err(e) = verifier.check().await {
+                        warn!("校验分发事件失败: {}", e);
+                    }
+                }
+                Ok(results)
+            }
         })
-        .await?;
-
-    // 可选：添加测试任务（每分钟执行一次，用于测试）
-    // 在生产环境中可以注释掉这部分
-    // #[cfg(debug_assertions)]
-    // {
-    //     let contract_test = rewards_contract.clone();
-    //     scheduler
-    //         .add_test_job(move || {
-    //             let contract = contract_test.clone();
-    //             async move {
-    //                 info!("执行测试任务 - 检查合约状态");
-    //                 let _ = distribute_daily_rewards(contract).await;
-    //                 Ok(())
-    //             }
-    //         })
-    //         .await?;
-    // }
-
-    // 启动调度器
+    };
+    let scheduled_run = {
+        let daily_run = daily_run.clone();
+        move || daily_run(Trigger::Cron)
+    };
+    let daily_job = add_distribution_job(
+        &scheduler,
+        config.schedule_mode,
+        &config.daily_schedule,
+        scheduled_run.clone(),
+    )
+    .await?;
+
+    // 补执行停机期间错过的分发，在调度器启动前完成，不会与定时任务重叠
+    if let Some(window) = config.catchup_window {
+        // 取各合约中最早的成功记录，任一合约错过都需要补执行，已分发的合约会自动跳过
+        let last_success = contracts
+            .iter()
+            .filter_map(|contract| contract.last_confirmed_at())
+            .min();
+        if let Some(missed) = scheduler.missed_run(last_success, window) {
+            let timezone = scheduler.timezone();
+            info!(
+                "停机期间错过了 {} 的定时分发 (最近一次成功: {})，在启动调度器前立即补执行",
+                timezone.format(missed),
+                last_success.map(|time| timezone.format(time)).unwrap_or_default()
+            );
+            if let Err(e) = scheduler
+                .run_exclusive("补执行", Trigger::Catchup, daily_run(Trigger::Catchup))
+                .await
+            {
+                error!("补执行分发失败: {}", e);
+            }
+        }
+    }
+
+    // 启动调度器，暂停标记文件存在时定时任务触发后跳过
+    scheduler.watch_pause_file(config.pause_file.clone(), PAUSE_CHECK_INTERVAL);
     scheduler.start().await?;
+    let scheduler = Arc::new(scheduler);
 
-    info!(
-        "调度器已启动，下次执行时间: {}",
-        DailyScheduler::next_midnight()
-    );
-    info!("按 Ctrl+C 退出服务");
+    // 管理接口：查询状态、暂停和恢复、安排一次性执行和立即分发，暂停状态和一次性任务同步到文件，重启后保持
+    if let Some(addr) = config.admin_listen_addr {
+        let daily_run = daily_run.clone();
+        let contracts = active_contracts.clone();
+        AdminServer::new(scheduler.clone())
+            .with_token(config.admin_token.clone())
+            .with_pause_file(Some(config.pause_file.clone()))
+            .with_one_shot_task(move || daily_run(Trigger::OneShot))
+            .with_schedule_once_file(Some(config.schedule_once_file.clone()))
+            // 与distribute-now命令相同，使用主签名账户和主奖励合约
+            .with_contract(move || contracts.read().unwrap()[0].clone())
+            .spawn(addr)?;
+    }
+
+    // schedule-once 写入的一次性执行，与定时任务共用执行锁
+    scheduler.watch_one_shot_file(config.schedule_once_file.clone(), PAUSE_CHECK_INTERVAL, {
+        let daily_run = daily_run.clone();
+        move || daily_run(Trigger::OneShot)
+    });
+
+    // 测试模式：按TEST_CRON执行启动检查和模拟，开启TEST_MODE_SEND时才经由手动触发实际发送
+    if config.test_mode {
+        warn!("==================================================");
+        warn!(
+            "测试模式已开启: 按 {} 执行测试任务，{}",
+            config.test_cron,
+            if config.test_mode_send {
+                "会实际发送分发交易 (TEST_MODE_SEND=true)"
+            } else {
+                "只检查和模拟，不发送交易"
+            }
+        );
+        warn!("生产环境请设置 TEST_MODE=false");
+        warn!("==================================================");
+        let contracts = active_contracts.clone();
+        let trigger = config.test_mode_send.then(|| scheduler.trigger_handle());
+        scheduler
+            .add_job("测试任务", &config.test_cron, move || {
+                let contracts = contracts.read().unwrap().clone();
+                let trigger = trigger.clone();
+                async move { run_test_job(&contracts, trigger).await }
+            })
+            .await?;
+    }
+
+    scheduler.log_upcoming_runs(5);
+    if let Some(next_run) = scheduler.next_run().await {
+        info!("下次任务触发时间: {}", scheduler.timezone().format(next_run));
+    }
+    if scheduler.is_paused() {
+        warn!(
+            "调度器处于暂停状态，执行 resume 命令或删除 {} 后恢复",
+            config.pause_file.display()
+        );
+    }
 
-    // 保持程序运行
-    tokio::signal::ctrl_c().await?;
-    info!("收到退出信号，正在关闭服务...");
+    // 启动时执行：与定时任务共用执行锁，结果同样记录日志、发送通知并写入状态文件
+    if config.run_on_startup {
+        let scheduler = scheduler.clone();
+        let daily_run = daily_run.clone();
+        tokio::spawn(async move {
+            info!("已开启启动时执行，立即执行一次分发");
+            if let Err(e) = scheduler
+                .run_exclusive("启动时执行", Trigger::Startup, daily_run(Trigger::Startup))
+                .await
+            {
+                error!("启动时执行分发失败，调度器继续按计划运行: {}", e);
+            }
+        });
+    }
+
+    // SIGUSR1/SIGHUP：立即执行一次分发，与定时任务共用执行锁和超时，今天已确认的分发不会重复发送
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        for (kind, name) in [
+            (SignalKind::user_defined1(), "SIGUSR1"),
+            (SignalKind::hangup(), "SIGHUP"),
+        ] {
+            let trigger = scheduler.trigger_handle();
+            let mut signal = signal(kind)?;
+            tokio::spawn(async move {
+                while signal.recv().await.is_some() {
+                    info!("收到{}，手动触发一次分发", name);
+                    // 执行结果由每日任务自己记录日志和发送通知，这里不等待
+                    drop(trigger.trigger_now());
+                }
+            });
+        }
+        info!(
+            "发送SIGUSR1或SIGHUP可立即执行一次分发 (kill -HUP {})",
+            std::process::id()
+        );
+    }
+    // SIGUSR2：重新读取 .env 和环境变量，热更新可以不重启生效的配置
+    #[cfg(unix)]
+    {
+        let mut usr2 = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::user_defined2())?;
+        let mut current = config.clone();
+        let contracts = active_contracts.clone();
+        let notifier = notifier.clone();
+        let scheduler = scheduler.clone();
+        let mut daily_job = daily_job;
+        tokio::spawn(async move {
+            while usr2.recv().await.is_some() {
+                info!("收到SIGUSR2，重新加载配置");
+                if let Err(e) = reload_config(
+                    &mut current,
+                    &contracts,
+                    &notifier,
+                    &scheduler,
+                    &mut daily_job,
+                    &scheduled_run,
+                )
+                .await
+                {
+                    error!("重新加载配置失败，继续使用原配置: {}", e);
+                }
+            }
+        });
+        info!("发送SIGUSR2可重新加载配置 (kill -USR2 {})", std::process::id());
+    }
+    info!("按 Ctrl+C 或发送SIGTERM退出服务，进行中的分发会在 {} 秒内完成后再退出", config.shutdown_grace.as_secs());
+
+    // 保持程序运行，直到收到退出信号或分发失败（开启 EXIT_ON_DISTRIBUTION_FAILURE 时）
+    let fatal = tokio::select! {
+        signal = shutdown_signal() => {
+            signal?;
+            info!("收到退出信号，正在关闭服务...");
+            None
+        }
+        Some(e) = fatal_rx.recv() => {
+            error!("分发失败且配置了EXIT_ON_DISTRIBUTION_FAILURE，正在关闭服务...");
+            Some(e)
+        }
+    };
 
     scheduler.shutdown().await?;
     info!("服务已关闭");
 
-    Ok(())
+    match fatal {
+        Some(e) => Err(anyhow::anyhow!("每日奖励分发失败: {}", e)),
+        None => Ok(()),
+    }
 }
 
-async fn distribute_daily_rewards(contract: RewardsContract) -> Result<()> {
-    info!("开始分发每日奖励...");
+#[tracing::instrument(skip_all, fields(contracts = contracts.len(), trigger = %trigger))]
+async fn distribute_daily_rewards(
+    contracts: &[RewardsContract],
+    notifier: Notifier,
+    deadline: Duration,
+    max_concurrent: usize,
+    trigger: Trigger,
+    events: Option<&tokio::sync::mpsc::Sender<DistributionEvent>>,
+) -> Result<Vec<DistributionResult>, DistributorError> {
+    match trigger {
+        Trigger::Cron => info!("开始分发每日奖励..."),
+        _ => info!("开始分发每日奖励 ({})...", trigger),
+    }
 
-    // 调用分发奖励函数
-    match contract.distribute_daily_rewards().await {
-        Ok(tx_hash) => {
-            info!("每日奖励分发成功! 交易哈希: {:?}", tx_hash);
+    // 发送并等待确认，每个合约的流程受截止时间约束
+    let options = DistributeOptions {
+        trigger,
+        ..Default::default()
+    };
+    let report = DistributionReport::new(
+        distribute_concurrently(contracts, max_concurrent, deadline, &options).await,
+    );
+    let multiple = report.results.len() > 1;
 
-            // 等待交易确认
-            if let Ok(receipt) = contract.wait_for_confirmation(tx_hash).await {
-                info!("交易已确认，区块号: {:?}", receipt.block_number);
-                info!("Gas使用量: {:?}", receipt.gas_used);
+    for (address, result) in &report.results {
+        let subject = if multiple {
+            format!(" ({:?})", address)
+        } else {
+            String::new()
+        };
+        match result {
+            Ok(result) => {
+                info!("每日奖励分发成功{}! 交易哈希: {:?}", subject, result.tx_hash);
+                info!("交易已确认，区块号: {:?}", result.block_number);
+                info!("Gas使用量: {:?}", result.gas_used);
+                if let (Some(fee), Some(fee_eth)) = (result.total_fee, &result.total_fee_eth) {
+                    info!("交易费用: {} wei ({} ETH)", fee, fee_eth);
+                }
+                if result.verified == Some(false) {
+                    let notification = Notification::new(
+                        Severity::Warning,
+                        "分发交易成功但链上状态未更新",
+                        format!(
+                            "交易 {:?} 已确认，但lastDistributionTime未更新到今天，合约可能跳过了分发",
+                            result.tx_hash
+                        ),
+                    )
+                    .with_details(result);
+                    notifier.notify(notification).await;
+                } else if !multiple {
+                    notifier.notify(success_notification(result, trigger)).await;
+                }
+            }
+            Err(e) if e.is_skipped() => {
+                warn!("已跳过每日奖励分发{}: {}", subject, e);
+                if !multiple {
+                    notifier.notify(failure_notification(e, trigger)).await;
+                }
+            }
+            Err(e) => {
+                error!("分发每日奖励失败{}: {}", subject, e);
+                // 多个合约时失败汇总到一条通知里，避免一次运行发出多条告警
+                if !multiple {
+                    notifier.notify(failure_notification(e, trigger)).await;
+                }
             }
         }
-        Err(e) => {
-            error!("分发每日奖励失败: {}", e);
-            return Err(e);
+    }
+
+    if multiple {
+        let summary = report.summary();
+        info!("{} 个合约分发完成: {}", report.results.len(), summary);
+        if report.failed() > 0 {
+            let notification = Notification::new(
+                Severity::Critical,
+                format!(
+                    "每日奖励部分分发失败{} ({}/{})",
+                    trigger_label(trigger),
+                    report.failed(),
+                    report.results.len()
+                ),
+                summary,
+            )
+            .with_details(report.outcomes());
+            notifier.notify(notification).await;
+        } else if report.skipped() > 0 {
+            let notification = Notification::new(
+                Severity::Warning,
+                format!(
+                    "每日奖励部分合约跳过分发{} ({}/{})",
+                    trigger_label(trigger),
+                    report.skipped(),
+                    report.results.len()
+                ),
+                summary,
+            )
+            .with_details(report.outcomes());
+            notifier.notify(notification).await;
+        } else {
+            let total_fee = report
+                .results
+                .iter()
+                .filter_map(|(_, result)| result.as_ref().ok()?.total_fee)
+                .fold(U256::zero(), |total, fee| total.saturating_add(fee));
+            let notification = Notification::new(
+                Severity::Info,
+                format!(
+                    "每日奖励分发成功{} ({}个合约)",
+                    trigger_label(trigger),
+                    report.results.len()
+                ),
+                format!(
+                    "{}，总费用 {} ETH",
+                    summary,
+                    ethers::utils::format_ether(total_fee)
+                ),
+            )
+            .with_details(report.outcomes());
+            notifier.notify(notification).await;
+        }
+    }
+
+    match events {
+        Some(events) => report.forward_events(events, trigger),
+        None => report.results.into_iter().map(|(_, result)| result).collect(),
+    }
+}
+
+/// 测试任务：对每个合约执行启动检查和模拟；传入手动触发句柄时改为实际执行一次分发
+async fn run_test_job(contracts: &[RewardsContract], trigger: Option<TriggerHandle>) -> Result<()> {
+    if let Some(trigger) = trigger {
+        info!("测试任务: 发送分发交易");
+        return Ok(trigger
+            .trigger_now()
+            .await
+            .map_err(|_| anyhow::anyhow!("调度器已关闭"))??);
+    }
+    for contract in contracts {
+        info!("测试任务: 检查合约 {:?}", contract.contract_address());
+        contract.preflight().await?.log();
+        contract
+            .simulate()
+            .await
+            .map_err(|e| anyhow::anyhow!("模拟执行失败: {}", e))?;
+        info!("测试任务: 模拟执行成功，未发送交易");
+    }
+    Ok(())
+}
+
+/// 按调度方式添加分发任务：按 `schedule` 触发的每日任务或按间隔触发的任务，并记录下次分发时间
+async fn add_distribution_job<F, Fut>(
+    scheduler: &DailyScheduler,
+    mode: ScheduleMode,
+    schedule: &CronSchedule,
+    task: F,
+) -> Result<JobHandle>
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<Vec<DistributionResult>, DistributorError>> + Send + 'static,
+{
+    let handle = match mode {
+        ScheduleMode::Cron => {
+            let handle = scheduler.add_daily_job_on(schedule.clone(), task).await?;
+            // 与注册任务使用同一个cron表达式和时区计算，日志时间就是实际触发时间
+            if let Some(next_run) = schedule.next_after(chrono::Utc::now()) {
+                info!("分发计划: {}，下次分发时间: {}", schedule, schedule.timezone().format(next_run));
+            }
+            handle
+        }
+        ScheduleMode::Interval(interval) => {
+            let handle = scheduler.add_interval_job(interval, task).await?;
+            if let Some(next_run) = scheduler.next_run_for(&handle).await {
+                info!("下次分发时间: {}", scheduler.timezone().format(next_run));
+            }
+            handle
+        }
+    };
+    Ok(handle)
+}
+
+/// 重新读取 .env（覆盖已有的环境变量）和环境变量，应用可以热更新的配置
+///
+/// Gas策略、Gas价格上限、通知Webhook地址、调度方式、每日任务的cron表达式和假日文件立即生效，进行中的执行不受影响；
+/// RPC节点、签名账户、链ID、合约地址、分发目标、调度时区和SCHEDULE_DAYS的修改只记录警告，需要重启服务。
+async fn reload_config<F, Fut>(
+    current: &mut Config,
+    contracts: &RwLock<Arc<Vec<RewardsContract>>>,
+    notifier: &Notifier,
+    scheduler: &DailyScheduler,
+    daily_job: &mut JobHandle,
+    task: &F,
+) -> Result<()>
+where
+    F: Fn() -> Fut + Clone + Send + Sync + 'static,
+    Fut: Future<Output = Result<Vec<DistributionResult>, DistributorError>> + Send + 'static,
+{
+    // dotenv::dotenv() 不覆盖已有的环境变量，重新加载时只能逐项读取后设置
+    #[allow(deprecated)]
+    let vars = dotenv::dotenv_iter();
+    if let Ok(vars) = vars {
+        for var in vars {
+            let (key, value) = var?;
+            std::env::set_var(key, value);
         }
     }
+    let new = Config::from_env()?;
+    // 假日文件无效时整个重新加载失败，其他配置也不应用
+    let holidays = match &new.holidays_path {
+        Some(path) => Some(read_holidays_file(path)?),
+        None => None,
+    };
+    if let Some(holidays) = holidays {
+        info!("已重新读取假日文件，共 {} 个假日", holidays.len());
+        scheduler.set_holidays(holidays);
+    } else if current.holidays_path.is_some() {
+        scheduler.set_holidays(Default::default());
+        info!("已取消HOLIDAYS_PATH，不再按假日跳过分发");
+    }
+    current.holidays_path = new.holidays_path.clone();
+
+    // ENS名称在下面重新解析，不需要重启；十六进制地址或未开启重新解析时仍需重启
+    let ens_refresh = current.ens_refresh_on_reload
+        && matches!(new.contract_address, ContractAddress::Ens(_));
+    let restart_needed: Vec<&str> = [
+        ("RPC_URL", new.rpc_url != current.rpc_url),
+        (
+            "签名账户",
+            new.signer != current.signer || new.additional_signers != current.additional_signers,
+        ),
+        ("CHAIN_ID", new.chain_id != current.chain_id),
+        (
+            "CONTRACT_ADDRESS",
+            !ens_refresh && new.contract_address != current.contract_address,
+        ),
+        ("ADDITIONAL_CONTRACTS", new.additional_contracts != current.additional_contracts),
+        ("CHAIN_TARGETS_PATH", new.chain_targets != current.chain_targets),
+        ("SCHEDULE_TIMEZONE", new.schedule_timezone != current.schedule_timezone),
+        ("SCHEDULE_DAYS", new.schedule_days != current.schedule_days),
+    ]
+    .into_iter()
+    .filter_map(|(name, changed)| changed.then_some(name))
+    .collect();
+    if !restart_needed.is_empty() {
+        warn!("{} 已修改，需要重启服务才能生效", restart_needed.join(", "));
+    }
+
+    if new.gas_strategy != current.gas_strategy || new.max_gas_price != current.max_gas_price {
+        let updated = contracts
+            .read()
+            .unwrap()
+            .iter()
+            .map(|contract| {
+                // 分发目标单独设置了Gas价格上限时保持不变
+                let max_gas_price = current
+                    .chain_targets
+                    .iter()
+                    .find(|target| {
+                        target.chain_id == contract.chain_id()
+                            && target.contract_address == contract.contract_address()
+                    })
+                    .and_then(|target| target.max_gas_price)
+                    .or(new.max_gas_price);
+                contract
+                    .clone()
+                    .with_gas_strategy(new.gas_strategy)
+                    .with_max_gas_price(max_gas_price)
+            })
+            .collect();
+        *contracts.write().unwrap() = Arc::new(updated);
+        current.gas_strategy = new.gas_strategy;
+        current.max_gas_price = new.max_gas_price;
+        info!(
+            "已更新Gas策略: {:?}，Gas价格上限: {:?}",
+            new.gas_strategy, new.max_gas_price
+        );
+    }
+
+    if ens_refresh {
+        if let ContractAddress::Ens(name) = &new.contract_address {
+            refresh_ens_contract(name, current.chain_id, contracts).await;
+            current.contract_address = new.contract_address.clone();
+        }
+    }
+    current.ens_refresh_on_reload = new.ens_refresh_on_reload;
+
+    if new.notify_webhook_url != current.notify_webhook_url {
+        notifier.set_webhook_url(new.notify_webhook_url.clone());
+        current.notify_webhook_url = new.notify_webhook_url;
+        info!("已更新通知Webhook地址");
+    }
 
+    let cron_changed = new.daily_schedule.expr() != current.daily_schedule.expr()
+        || new.daily_schedule.dst_policy() != current.daily_schedule.dst_policy();
+    if new.schedule_mode != current.schedule_mode
+        || (new.schedule_mode == ScheduleMode::Cron && cron_changed)
+    {
+        // 调度时区需要重启才能生效，新的cron表达式仍按当前时区计算
+        let schedule = new.daily_schedule.clone().with_timezone(current.schedule_timezone);
+        // 先添加新任务再移除旧任务，替换过程中不会漏掉触发
+        let handle = add_distribution_job(scheduler, new.schedule_mode, &schedule, task.clone()).await?;
+        scheduler.remove_job(daily_job).await?;
+        *daily_job = handle;
+        current.schedule_mode = new.schedule_mode;
+        current.daily_schedule = schedule;
+        info!("已按新的调度方式重新注册分发任务: {:?}", new.schedule_mode);
+    }
     Ok(())
 }
+
+/// 重新解析ENS名称，地址变化时把主链上旧地址的奖励合约替换为新地址，之后的分发使用新地址
+///
+/// 解析失败时只记录警告，继续使用原来的地址。
+async fn refresh_ens_contract(
+    name: &str,
+    chain_id: u64,
+    contracts: &RwLock<Arc<Vec<RewardsContract>>>,
+) {
+    let primary = contracts.read().unwrap()[0].clone();
+    let old = primary.contract_address();
+    let ens = ContractAddress::Ens(name.to_string());
+    let address = match resolve_contract_address(primary.client.inner(), &ens).await {
+        Ok(address) => address,
+        Err(e) => {
+            warn!("重新解析ENS名称 {} 失败，继续使用 {:?}: {}", name, old, e);
+            return;
+        }
+    };
+    if address == old {
+        info!("ENS名称 {} 仍解析为 {:?}", name, old);
+        return;
+    }
+
+    let updated = contracts
+        .read()
+        .unwrap()
+        .iter()
+        .map(|contract| {
+            if contract.chain_id() == chain_id && contract.contract_address() == old {
+                contract.for_address(address)
+            } else {
+                contract.clone()
+            }
+        })
+        .collect();
+    *contracts.write().unwrap() = Arc::new(updated);
+    info!(
+        "ENS名称 {} 的解析地址已变化: {:?} -> {:?}，之后的分发使用新地址",
+        name, old, address
+    );
+}
+
+/// 等待退出信号：Ctrl+C，unix下还包括SIGTERM（systemd、Kubernetes停止服务时发送）
+async fn shutdown_signal() -> std::io::Result<()> {
+    #[cfg(unix)]
+    {
+        let mut terminate =
+            tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())?;
+        tokio::select! {
+            signal = tokio::signal::ctrl_c() => signal,
+            _ = terminate.recv() => Ok(()),
+        }
+    }
+    #[cfg(not(unix))]
+    tokio::signal::ctrl_c().await
+}
+
+/// 通知标题中标注非定时触发的方式，定时触发时为空
+fn trigger_label(trigger: Trigger) -> String {
+    match trigger {
+        Trigger::Cron => String::new(),
+        _ => format!("（{}）", trigger),
+    }
+}
+
+/// 单个合约分发成功的通知，附上完整的分发结果
+fn success_notification(result: &DistributionResult, trigger: Trigger) -> Notification {
+    let block = result
+        .block_number
+        .map_or_else(|| "-".to_string(), |block| block.to_string());
+    Notification::new(
+        Severity::Info,
+        format!("每日奖励分发成功{}", trigger_label(trigger)),
+        match &result.total_fee_eth {
+            Some(fee_eth) => format!(
+                "交易 {:?} 已在区块 {} 确认，费用 {} ETH",
+                result.tx_hash, block, fee_eth
+            ),
+            None => format!("交易 {:?} 已在区块 {} 确认", result.tx_hash, block),
+        },
+    )
+    .with_details(result)
+}
+
+/// 单个合约分发失败的通知，附上便于处理的细节
+fn failure_notification(e: &DistributorError, trigger: Trigger) -> Notification {
+    // 按 notify-and-skip 配置跳过的分发只发送警告
+    let (severity, title) = if e.is_skipped() {
+        (Severity::Warning, "每日奖励分发已跳过")
+    } else {
+        (Severity::Critical, "每日奖励分发失败")
+    };
+    let notification = Notification::new(
+        severity,
+        format!("{}{}", title, trigger_label(trigger)),
+        e.to_string(),
+    );
+    match e {
+        DistributorError::TransactionFailed {
+            trace: Some(trace), ..
+        } => notification.with_details(trace),
+        // 附上余额和所需数量，方便财务及时补充代币
+        DistributorError::InsufficientRewardTokens {
+            contract,
+            token,
+            have,
+            need,
+        } => notification.with_details(serde_json::json!({
+            "contract": contract,
+            "token": token,
+            "have": have.to_string(),
+            "need": need.to_string(),
+            "shortfall": (*need - *have).to_string(),
+        })),
+        _ => notification,
+    }
+}
+
+/// 解析 `地址=数量` 形式的余额覆盖，数量以ETH为单位
+fn parse_balance_override(value: &str) -> Result<(Address, U256), String> {
+    let (address, amount) = value
+        .split_once('=')
+        .ok_or_else(|| "格式应为 地址=数量".to_string())?;
+    let address = address
+        .trim()
+        .parse::<Address>()
+        .map_err(|_| format!("无效的地址: {}", address))?;
+    let amount = ethers::utils::parse_ether(amount.trim())
+        .map_err(|_| format!("无效的数量: {}", amount))?;
+    Ok((address, amount))
+}
@@ -0,0 +1,305 @@
+use crate::recipients::RecipientList;
+use ethers::abi::{encode, Token};
+use ethers::types::{Address, H256, U256};
+use ethers::utils::{keccak256, to_checksum};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// 叶子的ABI编码类型，与OpenZeppelin `StandardMerkleTree.of(values, ["address", "uint256"])` 一致
+const LEAF_ENCODING: [&str; 2] = ["address", "uint256"];
+
+/// 构建Merkle树或写出证明失败
+#[derive(Debug, Error)]
+pub enum MerkleError {
+    #[error("Merkle模式需要接收地址文件中的每个地址都提供数量")]
+    MissingAmounts,
+    #[error("Merkle模式的接收地址列表不能为空")]
+    Empty,
+    #[error("分发总金额溢出uint256")]
+    TotalOverflow,
+    #[error("计算出的Merkle根 {computed:?} 与数据源提供的 {expected:?} 不一致")]
+    RootMismatch { expected: H256, computed: H256 },
+    #[error("无法写入Merkle证明文件 {path}: {source}")]
+    Write {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+}
+
+/// 与OpenZeppelin `StandardMerkleTree` 兼容的Merkle树
+///
+/// 叶子为 `keccak256(keccak256(abi.encode(address, amount)))`，按哈希排序后以数组形式
+/// 存放完整二叉树，节点按排序后的子节点拼接哈希，可直接用 `MerkleProof.verify` 校验。
+#[derive(Debug, Clone)]
+pub struct MerkleTree {
+    tree: Vec<H256>,
+    /// 按输入顺序的 (地址, 数量, 叶子在 `tree` 中的位置)
+    values: Vec<(Address, U256, usize)>,
+    total_amount: U256,
+}
+
+impl MerkleTree {
+    pub fn build(list: &RecipientList) -> Result<Self, MerkleError> {
+        let amounts = list.amounts.as_ref().ok_or(MerkleError::MissingAmounts)?;
+        if list.recipients.is_empty() {
+            return Err(MerkleError::Empty);
+        }
+
+        let total_amount = amounts
+            .iter()
+            .try_fold(U256::zero(), |total, amount| total.checked_add(*amount))
+            .ok_or(MerkleError::TotalOverflow)?;
+
+        let mut hashed: Vec<(usize, H256)> = list
+            .recipients
+            .iter()
+            .zip(amounts)
+            .map(|(address, amount)| leaf_hash(*address, *amount))
+            .enumerate()
+            .collect();
+        hashed.sort_by_key(|(_, hash)| *hash);
+
+        let leaf_count = hashed.len();
+        let mut tree = vec![H256::zero(); 2 * leaf_count - 1];
+        let mut tree_indices = vec![0; leaf_count];
+        for (position, (value_index, hash)) in hashed.into_iter().enumerate() {
+            let tree_index = tree.len() - 1 - position;
+            tree[tree_index] = hash;
+            tree_indices[value_index] = tree_index;
+        }
+        for index in (0..tree.len() - leaf_count).rev() {
+            tree[index] = hash_pair(tree[2 * index + 1], tree[2 * index + 2]);
+        }
+
+        let values = list
+            .recipients
+            .iter()
+            .zip(amounts)
+            .zip(tree_indices)
+            .map(|((address, amount), tree_index)| (*address, *amount, tree_index))
+            .collect();
+
+        Ok(Self {
+            tree,
+            values,
+            total_amount,
+        })
+    }
+
+    pub fn root(&self) -> H256 {
+        self.tree[0]
+    }
+
+    pub fn total_amount(&self) -> U256 {
+        self.total_amount
+    }
+
+    /// 第 `value_index` 个接收地址（输入顺序）的证明
+    pub fn proof(&self, value_index: usize) -> Vec<H256> {
+        let mut index = self.values[value_index].2;
+        let mut proof = Vec::new();
+        while index > 0 {
+            let sibling = if index % 2 == 1 { index + 1 } else { index - 1 };
+            proof.push(self.tree[sibling]);
+            index = (index - 1) / 2;
+        }
+        proof
+    }
+
+    /// 写出证明文件，供前端领取时使用
+    ///
+    /// 文件同时包含OpenZeppelin `StandardMerkleTree.load` 可读取的 `standard-v1` 格式字段，
+    /// 以及按地址索引的证明。先写临时文件再重命名，避免前端读到写了一半的文件。
+    pub fn write_proofs(&self, path: &Path) -> Result<(), MerkleError> {
+        let values: Vec<_> = self
+            .values
+            .iter()
+            .map(|(address, amount, tree_index)| {
+                json!({
+                    "value": [to_checksum(address, None), amount.to_string()],
+                    "treeIndex": tree_index,
+                })
+            })
+            .collect();
+        let proofs: serde_json::Map<_, _> = self
+            .values
+            .iter()
+            .enumerate()
+            .map(|(value_index, (address, amount, _))| {
+                (
+                    to_checksum(address, None),
+                    json!({
+                        "amount": amount.to_string(),
+                        "proof": self.proof(value_index),
+                    }),
+                )
+            })
+            .collect();
+        let content = json!({
+            "format": "standard-v1",
+            "leafEncoding": LEAF_ENCODING,
+            "tree": self.tree,
+            "values": values,
+            "root": self.root(),
+            "totalAmount": self.total_amount.to_string(),
+            "proofs": proofs,
+        });
+
+        let write_error = |source| MerkleError::Write {
+            path: path.to_path_buf(),
+            source,
+        };
+        let mut tmp_path = path.as_os_str().to_owned();
+        tmp_path.push(".tmp");
+        let content = serde_json::to_vec_pretty(&content).map_err(std::io::Error::from);
+        fs::write(&tmp_path, content.map_err(write_error)?).map_err(write_error)?;
+        fs::rename(&tmp_path, path).map_err(write_error)
+    }
+}
+
+/// 叶子哈希：`keccak256(bytes.concat(keccak256(abi.encode(account, amount))))`
+pub fn leaf_hash(address: Address, amount: U256) -> H256 {
+    let encoded = encode(&[Token::Address(address), Token::Uint(amount)]);
+    H256(keccak256(keccak256(encoded)))
+}
+
+/// 按字节序排序后拼接哈希，与 `MerkleProof` 的 `_hashPair` 一致
+fn hash_pair(a: H256, b: H256) -> H256 {
+    let (first, second) = if a <= b { (a, b) } else { (b, a) };
+    let mut data = [0u8; 64];
+    data[..32].copy_from_slice(first.as_bytes());
+    data[32..].copy_from_slice(second.as_bytes());
+    H256(keccak256(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn list(values: &[(&str, &str)]) -> RecipientList {
+        RecipientList {
+            recipients: values
+                .iter()
+                .map(|(address, _)| address.parse().unwrap())
+                .collect(),
+            amounts: Some(
+                values
+                    .iter()
+                    .map(|(_, amount)| U256::from_dec_str(amount).unwrap())
+                    .collect(),
+            ),
+        }
+    }
+
+    fn h256(hex: &str) -> H256 {
+        hex.parse().unwrap()
+    }
+
+    /// 按 `MerkleProof.verify` 的规则从叶子和证明还原根
+    fn process_proof(leaf: H256, proof: &[H256]) -> H256 {
+        proof
+            .iter()
+            .fold(leaf, |node, sibling| hash_pair(node, *sibling))
+    }
+
+    /// OpenZeppelin merkle-tree README中的示例：
+    /// `StandardMerkleTree.of(values, ["address", "uint256"])`
+    const OZ_EXAMPLE: [(&str, &str); 2] = [
+        (
+            "0x1111111111111111111111111111111111111111",
+            "5000000000000000000",
+        ),
+        (
+            "0x2222222222222222222222222222222222222222",
+            "2500000000000000000",
+        ),
+    ];
+
+    #[test]
+    fn root_matches_openzeppelin_standard_merkle_tree() {
+        let tree = MerkleTree::build(&list(&OZ_EXAMPLE)).unwrap();
+
+        assert_eq!(
+            tree.root(),
+            h256("0xd4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77")
+        );
+        assert_eq!(
+            tree.total_amount(),
+            U256::from_dec_str("7500000000000000000").unwrap()
+        );
+    }
+
+    #[test]
+    fn proof_matches_openzeppelin_standard_merkle_tree() {
+        let tree = MerkleTree::build(&list(&OZ_EXAMPLE)).unwrap();
+
+        assert_eq!(
+            tree.proof(0),
+            vec![h256(
+                "0xb92c48e9d7abe27fd8dfd6b5dfdbfb1c9a463f80c712b66f3a5180a090cccafc"
+            )]
+        );
+    }
+
+    #[test]
+    fn proofs_verify_for_odd_number_of_leaves() {
+        let mut values = OZ_EXAMPLE.to_vec();
+        values.push((
+            "0x3333333333333333333333333333333333333333",
+            "1000000000000000000",
+        ));
+        let list = list(&values);
+        let tree = MerkleTree::build(&list).unwrap();
+
+        for (index, (address, amount)) in list
+            .recipients
+            .iter()
+            .zip(list.amounts.as_ref().unwrap())
+            .enumerate()
+        {
+            let leaf = leaf_hash(*address, *amount);
+            assert_eq!(process_proof(leaf, &tree.proof(index)), tree.root());
+        }
+        // 叶子按哈希升序从数组末尾向前存放，最深的叶子证明最长
+        let mut proof_lengths: Vec<_> = (0..3).map(|index| tree.proof(index).len()).collect();
+        proof_lengths.sort();
+        assert_eq!(proof_lengths, vec![1, 2, 2]);
+    }
+
+    #[test]
+    fn build_rejects_missing_amounts_and_empty_lists() {
+        let mut missing = list(&OZ_EXAMPLE);
+        missing.amounts = None;
+        assert!(matches!(
+            MerkleTree::build(&missing),
+            Err(MerkleError::MissingAmounts)
+        ));
+        assert!(matches!(
+            MerkleTree::build(&list(&[])),
+            Err(MerkleError::Empty)
+        ));
+    }
+
+    #[test]
+    fn written_proofs_use_standard_v1_format() {
+        let tree = MerkleTree::build(&list(&OZ_EXAMPLE)).unwrap();
+        let path = std::env::temp_dir().join(format!("merkle-proofs-{}.json", std::process::id()));
+        tree.write_proofs(&path).unwrap();
+        let content: serde_json::Value = serde_json::from_slice(&fs::read(&path).unwrap()).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(content["format"], "standard-v1");
+        assert_eq!(content["leafEncoding"], json!(["address", "uint256"]));
+        assert_eq!(
+            content["root"],
+            "0xd4dee0beab2d53f2cc83e567171bd2820e49898130a22622b10ead383e90bd77"
+        );
+        assert_eq!(
+            content["proofs"]["0x1111111111111111111111111111111111111111"]["proof"],
+            json!(["0xb92c48e9d7abe27fd8dfd6b5dfdbfb1c9a463f80c712b66f3a5180a090cccafc"])
+        );
+    }
+}
@@ -0,0 +1,463 @@
+use crate::contract::{DistributionResult, RewardsContract};
+use crate::history::{read_history_file, ExecutionOutcome, ExecutionRecord};
+use crate::hooks::{JobHooks, RunContext};
+use crate::notify::{Notification, Notifier, Severity};
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, Utc};
+use ethers::prelude::*;
+use ethers::utils::format_ether;
+use serde_json::json;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{info, warn};
+
+/// 余额需要回升到阈值之上这个比例才解除告警，避免在阈值附近反复通知
+const HYSTERESIS_PERCENT: u64 = 10;
+
+/// 估算余额可维持天数时使用的最近成功分发次数
+pub const RUNWAY_SAMPLES: usize = 7;
+
+/// 余额可维持天数低于阈值时，诊断记为警告或失败
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunwayThresholds {
+    pub warn_days: f64,
+    pub fail_days: f64,
+}
+
+impl Default for RunwayThresholds {
+    fn default() -> Self {
+        Self {
+            warn_days: 14.0,
+            fail_days: 3.0,
+        }
+    }
+}
+
+/// 余额还能支付多少天的分发：余额 ÷ 最近几次分发的平均花费得到还能运行的次数，
+/// 再除以每天的运行次数（见 `CronSchedule::runs_per_day`）
+///
+/// 没有花费数据、平均花费为0或调度不会触发时返回 `None`，由调用方改用估算的花费。
+pub fn runway(balance: U256, recent_costs: &[U256], runs_per_day: f64) -> Option<f64> {
+    if recent_costs.is_empty() || runs_per_day <= 0.0 {
+        return None;
+    }
+    let total = recent_costs
+        .iter()
+        .fold(U256::zero(), |total, cost| total.saturating_add(*cost));
+    let average = total / U256::from(recent_costs.len());
+    if average.is_zero() {
+        return None;
+    }
+    let (runs, remainder) = balance.div_mod(average);
+    let hundredths = remainder * U256::from(100) / average;
+    let runs = runs.min(U256::from(u32::MAX)).as_u64() as f64 + hundredths.as_u64() as f64 / 100.0;
+    Some(runs / runs_per_day)
+}
+
+/// 执行记录中最近 `RUNWAY_SAMPLES` 次成功分发的交易费用
+pub fn recent_costs(history: &[ExecutionRecord]) -> Vec<U256> {
+    let mut costs: Vec<U256> = history
+        .iter()
+        .rev()
+        .filter(|record| record.outcome == ExecutionOutcome::Success)
+        .filter_map(|record| record.fee)
+        .take(RUNWAY_SAMPLES)
+        .collect();
+    costs.reverse();
+    costs
+}
+
+/// 钱包余额所处的告警级别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum BalanceLevel {
+    Normal,
+    Warning,
+    Critical,
+}
+
+/// 定期检查操作钱包余额，低于阈值时发送一次通知
+pub struct BalanceMonitor {
+    contract: RewardsContract,
+    notifier: Notifier,
+    warn_threshold: Option<U256>,
+    critical_threshold: Option<U256>,
+    level: Mutex<BalanceLevel>,
+    /// 执行记录文件，设置后告警中附上余额可维持的天数
+    history_path: Option<PathBuf>,
+    /// 每天的分发次数，把可运行次数换算成天数
+    runs_per_day: f64,
+}
+
+impl BalanceMonitor {
+    pub fn new(
+        contract: RewardsContract,
+        notifier: Notifier,
+        warn_threshold: Option<U256>,
+        critical_threshold: Option<U256>,
+    ) -> Self {
+        Self {
+            contract,
+            notifier,
+            warn_threshold,
+            critical_threshold,
+            level: Mutex::new(BalanceLevel::Normal),
+            history_path: None,
+            runs_per_day: 1.0,
+        }
+    }
+
+    /// 告警时按执行记录文件中最近几次分发的平均花费估算余额可维持的天数
+    pub fn with_history_path(mut self, path: Option<PathBuf>) -> Self {
+        self.history_path = path;
+        self
+    }
+
+    /// 每天的分发次数（默认1次），按调度计算，用于把可运行次数换算成天数
+    pub fn with_runs_per_day(mut self, runs_per_day: f64) -> Self {
+        self.runs_per_day = runs_per_day;
+        self
+    }
+
+    /// 按执行记录估算余额可维持的天数，没有记录或读取失败时为 `None`
+    fn runway_days(&self, balance: U256) -> Option<f64> {
+        let path = self.history_path.as_ref()?;
+        match read_history_file(path) {
+            Ok(history) => runway(balance, &recent_costs(&history), self.runs_per_day),
+            Err(e) => {
+                warn!("{}", e);
+                None
+            }
+        }
+    }
+
+    /// 在后台按固定间隔检查余额
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                if let Err(e) = self.check().await {
+                    warn!("检查钱包余额失败: {}", e);
+                }
+            }
+        })
+    }
+
+    /// 检查一次余额，级别升高时发送通知，回落时只记录日志
+    pub async fn check(&self) -> Result<BalanceLevel> {
+        let address = self.contract.client_address();
+        let balance = self.contract.client.get_balance(address, None).await?;
+
+        let (previous, current) = {
+            let mut level = self.level.lock().unwrap();
+            let previous = *level;
+            *level = self.next_level(previous, balance);
+            (previous, *level)
+        };
+
+        if current > previous {
+            let (severity, threshold) = match current {
+                BalanceLevel::Critical => (Severity::Critical, self.critical_threshold),
+                _ => (Severity::Warning, self.warn_threshold),
+            };
+            let threshold = threshold.unwrap_or_default();
+            let runway_days = self.runway_days(balance);
+            let runway_text = runway_days
+                .map(|days| format!("，按最近的平均花费约可维持 {:.1} 天的分发", days))
+                .unwrap_or_default();
+            let notification = Notification::new(
+                severity,
+                "操作钱包余额不足",
+                format!(
+                    "钱包 {:?} 余额 {} ETH，低于阈值 {} ETH{}",
+                    address,
+                    format_ether(balance),
+                    format_ether(threshold),
+                    runway_text
+                ),
+            )
+            .with_details(json!({
+                "address": address,
+                "balance_wei": balance,
+                "threshold_wei": threshold,
+                "runway_days": runway_days,
+                "runs_per_day": self.runs_per_day,
+            }));
+            self.notifier.notify(notification).await;
+        } else if current < previous {
+            info!(
+                "钱包余额已恢复到 {} ETH，告警级别: {:?}",
+                format_ether(balance),
+                current
+            );
+        }
+
+        Ok(current)
+    }
+
+    fn threshold(&self, level: BalanceLevel) -> Option<U256> {
+        match level {
+            BalanceLevel::Normal => None,
+            BalanceLevel::Warning => self.warn_threshold,
+            BalanceLevel::Critical => self.critical_threshold,
+        }
+    }
+
+    fn next_level(&self, current: BalanceLevel, balance: U256) -> BalanceLevel {
+        let below = |level| self.threshold(level).is_some_and(|t| balance < t);
+        let measured = if below(BalanceLevel::Critical) {
+            BalanceLevel::Critical
+        } else if below(BalanceLevel::Warning) {
+            BalanceLevel::Warning
+        } else {
+            BalanceLevel::Normal
+        };
+
+        if measured >= current {
+            return measured;
+        }
+
+        // 降级需要余额回升到当前级别阈值之上一定比例
+        let recovered = self
+            .threshold(current)
+            .is_none_or(|t| balance >= t * (100 + HYSTERESIS_PERCENT) / 100);
+        if recovered {
+            measured
+        } else {
+            current
+        }
+    }
+}
+
+/// 通过链上的分发事件独立校验每天的分发，不依赖服务自己的收据处理
+pub struct DistributionVerifier {
+    contract: RewardsContract,
+    notifier: Notifier,
+    /// 当天开始后超过这个时长仍没有分发事件才告警
+    grace: Duration,
+    /// 已经告警过的日期，每天只告警一次
+    alerted_on: Mutex<Option<NaiveDate>>,
+}
+
+impl DistributionVerifier {
+    pub fn new(contract: RewardsContract, notifier: Notifier, grace: Duration) -> Self {
+        Self {
+            contract,
+            notifier,
+            grace,
+            alerted_on: Mutex::new(None),
+        }
+    }
+
+    /// 查询今天（UTC）的分发事件并与服务记录对比，返回找到的事件数量
+    pub async fn check(&self) -> Result<usize> {
+        let now = Utc::now();
+        let today = now.date_naive();
+        let day_start = today.and_hms_opt(0, 0, 0).unwrap().and_utc();
+
+        let from_block = self
+            .contract
+            .first_block_since(day_start.timestamp() as u64)
+            .await?;
+        let to_block = self.contract.client.get_block_number().await?;
+        let events = if from_block > to_block {
+            Vec::new()
+        } else {
+            self.contract
+                .fetch_distribution_events(from_block, to_block)
+                .await?
+        };
+        let recorded = self.contract.confirmed_today();
+
+        info!(
+            "今天的分发事件: {} 条 (区块 {} - {})，服务记录的分发交易: {:?}",
+            events.len(),
+            from_block,
+            to_block,
+            recorded
+        );
+
+        if !events.is_empty() {
+            if recorded.is_none() {
+                warn!("链上已有今天的分发事件，但服务没有对应的分发记录");
+            }
+            return Ok(events.len());
+        }
+
+        let overdue = (now - day_start).to_std().unwrap_or_default() > self.grace;
+        let already_alerted = *self.alerted_on.lock().unwrap() == Some(today);
+        if overdue && !already_alerted {
+            *self.alerted_on.lock().unwrap() = Some(today);
+            let message = match recorded {
+                Some(tx_hash) => format!(
+                    "服务记录今天已确认分发交易 {:?}，但链上没有找到分发事件",
+                    tx_hash
+                ),
+                None => format!("今天已过去超过 {:?}，链上仍没有分发事件", self.grace),
+            };
+            let notification =
+                Notification::new(Severity::Critical, "未找到今天的分发事件", message)
+                    .with_details(json!({
+                        "date": today,
+                        "from_block": from_block,
+                        "to_block": to_block,
+                        "recorded_tx": recorded,
+                    }));
+            self.notifier.notify(notification).await;
+        }
+
+        Ok(0)
+    }
+}
+
+/// 死人开关：进程仍在运行但长时间没有成功分发时告警
+///
+/// 服务卡住或调度没有触发时失败路径不会执行，这里独立检查距离上次成功的时长。
+pub struct DeadManSwitch {
+    notifier: Notifier,
+    max_silence: Duration,
+    /// 最近一次成功时间的输出文件，供外部监控读取
+    path: Option<PathBuf>,
+    last_success: Mutex<DateTime<Utc>>,
+    /// 是否已经针对当前这次沉默告警过，成功后重置
+    alerted: Mutex<bool>,
+}
+
+impl DeadManSwitch {
+    /// `last_success` 为已知的最近一次成功时间，没有记录时通常使用启动时间
+    pub fn new(
+        notifier: Notifier,
+        max_silence: Duration,
+        path: Option<PathBuf>,
+        last_success: DateTime<Utc>,
+    ) -> Self {
+        let switch = Self {
+            notifier,
+            max_silence,
+            path,
+            last_success: Mutex::new(last_success),
+            alerted: Mutex::new(false),
+        };
+        switch.write_last_success(last_success);
+        switch
+    }
+
+    /// 最近一次成功分发的时间
+    pub fn last_success(&self) -> DateTime<Utc> {
+        *self.last_success.lock().unwrap()
+    }
+
+    /// 记录一次成功分发
+    pub fn record_success(&self) {
+        let now = Utc::now();
+        *self.last_success.lock().unwrap() = now;
+        *self.alerted.lock().unwrap() = false;
+        self.write_last_success(now);
+    }
+
+    /// 在后台按固定间隔检查
+    pub fn spawn(self: Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.check().await;
+            }
+        })
+    }
+
+    /// 距离上次成功超过阈值时发送一次严重告警，返回是否已超时
+    pub async fn check(&self) -> bool {
+        let last_success = self.last_success();
+        let silence = (Utc::now() - last_success).to_std().unwrap_or_default();
+        if silence <= self.max_silence {
+            return false;
+        }
+
+        let already_alerted = std::mem::replace(&mut *self.alerted.lock().unwrap(), true);
+        if !already_alerted {
+            let notification = Notification::new(
+                Severity::Critical,
+                "长时间没有成功分发",
+                format!(
+                    "距离上次成功分发 ({}) 已超过 {} 小时，服务可能已卡住或调度未触发",
+                    last_success.to_rfc3339(),
+                    self.max_silence.as_secs() / 3600
+                ),
+            )
+            .with_details(json!({
+                "last_success": last_success,
+                "silence_secs": silence.as_secs(),
+                "threshold_secs": self.max_silence.as_secs(),
+            }));
+            self.notifier.notify(notification).await;
+        }
+
+        true
+    }
+
+    fn write_last_success(&self, timestamp: DateTime<Utc>) {
+        if let Some(path) = &self.path {
+            if let Err(e) = fs::write(path, timestamp.to_rfc3339()) {
+                warn!("写入最近成功时间文件 {} 失败: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// 每日任务执行成功时记录成功时间
+#[async_trait]
+impl JobHooks for DeadManSwitch {
+    async fn on_success(&self, _ctx: &RunContext, _results: &[DistributionResult]) {
+        self.record_success();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ether(amount: u64) -> U256 {
+        U256::exp10(18) * amount
+    }
+
+    #[test]
+    fn runway_is_unknown_without_history() {
+        assert_eq!(runway(ether(1), &[], 1.0), None);
+        assert_eq!(recent_costs(&[]), Vec::<U256>::new());
+    }
+
+    #[test]
+    fn runway_from_single_run() {
+        assert_eq!(runway(ether(10), &[ether(2)], 1.0), Some(5.0));
+        // 余额不够一次运行时只剩零点几天
+        assert_eq!(runway(ether(1), &[ether(4)], 1.0), Some(0.25));
+    }
+
+    #[test]
+    fn runway_averages_recent_costs() {
+        let costs = [ether(1), ether(2), ether(3)];
+        assert_eq!(runway(ether(12), &costs, 1.0), Some(6.0));
+    }
+
+    #[test]
+    fn runway_scales_runs_by_schedule_frequency() {
+        let costs = [ether(1)];
+        // 每小时运行一次时，24次运行只够一天
+        assert_eq!(runway(ether(24), &costs, 24.0), Some(1.0));
+        // 只在工作日运行时，5次运行可以维持一周
+        let days = runway(ether(5), &costs, 5.0 / 7.0).unwrap();
+        assert!((days - 7.0).abs() < 1e-9, "{}", days);
+        // 调度不会触发时无法换算
+        assert_eq!(runway(ether(5), &costs, 0.0), None);
+    }
+
+    #[test]
+    fn runway_is_unknown_when_runs_cost_nothing() {
+        assert_eq!(runway(ether(1), &[U256::zero(), U256::zero()], 1.0), None);
+    }
+}
@@ -0,0 +1,134 @@
+use crate::contract::{DistributeOptions, RewardsContract, INTRINSIC_GAS};
+use crate::error::{DistributorError, Result};
+use ethers::prelude::*;
+use serde::Serialize;
+use std::fmt;
+use tracing::{info, warn};
+
+/// 签名账户在节点中占用、但状态文件中没有记录的nonce
+///
+/// 通常是进程在签名后、确认前崩溃留下的交易，后续交易会排在它们后面。
+#[derive(Debug, Clone, Serialize)]
+pub struct NonceGap {
+    /// 已打包的交易数（`latest`）
+    pub latest: U256,
+    /// 包含内存池的交易数（`pending`）
+    pub pending: U256,
+    /// `latest..pending` 中状态文件没有记录的nonce
+    pub untracked: Vec<U256>,
+}
+
+impl fmt::Display for NonceGap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let nonces: Vec<String> = self.untracked.iter().map(|n| n.to_string()).collect();
+        write!(
+            f,
+            "已打包nonce {}，待处理nonce {}，状态文件中没有记录的nonce: {}",
+            self.latest,
+            self.pending,
+            nonces.join(", ")
+        )
+    }
+}
+
+impl RewardsContract {
+    /// 比较节点的 `latest` 和 `pending` 交易数，找出状态文件中没有记录的待处理nonce
+    pub async fn detect_nonce_gap(&self) -> Result<Option<NonceGap>> {
+        let address = self.client_address();
+        let latest = self
+            .client
+            .get_transaction_count(address, Some(BlockNumber::Latest.into()))
+            .await
+            .map_err(|e| DistributorError::rpc("获取已打包的nonce", e))?;
+        let pending = self
+            .client
+            .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+            .await
+            .map_err(|e| DistributorError::rpc("获取待处理的nonce", e))?;
+
+        if pending <= latest {
+            return Ok(None);
+        }
+
+        let tracked = self.tracked_pending_nonces().await;
+        let mut untracked = Vec::new();
+        let mut nonce = latest;
+        while nonce < pending {
+            if !tracked.contains(&nonce) {
+                untracked.push(nonce);
+            }
+            nonce += U256::one();
+        }
+
+        Ok((!untracked.is_empty()).then_some(NonceGap {
+            latest,
+            pending,
+            untracked,
+        }))
+    }
+
+    /// 状态文件中待确认交易的nonce，没有记录nonce的（如分批分发的批次）从节点查询
+    async fn tracked_pending_nonces(&self) -> Vec<U256> {
+        let records = self
+            .state_store()
+            .map(|store| store.pending_records())
+            .unwrap_or_default();
+
+        let mut nonces = Vec::with_capacity(records.len());
+        for record in records {
+            let nonce = match record.nonce {
+                Some(nonce) => Some(nonce),
+                None => match self.client.get_transaction(record.tx_hash).await {
+                    Ok(tx) => tx.map(|tx| tx.nonce),
+                    Err(e) => {
+                        warn!("查询交易 {:?} 失败: {}", record.tx_hash, e);
+                        None
+                    }
+                },
+            };
+            nonces.extend(nonce);
+        }
+        nonces
+    }
+
+    /// 用0金额的自转账交易替换占用nonce的未知交易，返回发送的交易哈希
+    ///
+    /// 只处理 `gap.untracked` 中低于节点 `pending` 交易数的nonce，从不使用更高的nonce；
+    /// 费用使用当前Gas价格，被节点拒绝替换时按替换规则加价。
+    pub async fn repair_nonce_gap(&self, gap: &NonceGap) -> Result<Vec<H256>> {
+        let address = self.client_address();
+        // 持有nonce锁，修复期间不会发送分发交易
+        let mut next_nonce = self.lock_nonce().await;
+
+        let mut tx_hashes = Vec::with_capacity(gap.untracked.len());
+        for &nonce in gap.untracked.iter().filter(|&&nonce| nonce < gap.pending) {
+            let mut tx = self
+                .build_transaction(
+                    address,
+                    Bytes::new(),
+                    U256::from(INTRINSIC_GAS),
+                    &DistributeOptions::default(),
+                    None,
+                )
+                .await?;
+            tx.set_nonce(nonce);
+            tx.set_from(address);
+
+            match self.send_signed(tx, std::time::Instant::now()).await {
+                Ok(tx_hash) => {
+                    info!("已发送nonce {} 的自转账交易: {:?}", nonce, tx_hash);
+                    tx_hashes.push(tx_hash);
+                }
+                Err(e) => {
+                    warn!("nonce {} 的自转账交易发送失败: {}", nonce, e);
+                    *next_nonce = None;
+                    return Err(e);
+                }
+            }
+        }
+
+        // 下次发送时重新从节点获取nonce
+        *next_nonce = None;
+        Ok(tx_hashes)
+    }
+}
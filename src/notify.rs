@@ -0,0 +1,80 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::{Arc, RwLock};
+use tracing::{info, warn};
+
+/// 通知的严重程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
+
+/// 发送到Webhook的通知内容
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub severity: Severity,
+    pub title: String,
+    pub message: String,
+    /// 附加的结构化数据，例如分发结果
+    pub details: Value,
+}
+
+impl Notification {
+    pub fn new(severity: Severity, title: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            severity,
+            title: title.into(),
+            message: message.into(),
+            details: Value::Null,
+        }
+    }
+
+    pub fn with_details(mut self, details: impl Serialize) -> Self {
+        self.details = serde_json::to_value(details).unwrap_or(Value::Null);
+        self
+    }
+}
+
+/// 通过Webhook（POST JSON）发送通知；未配置URL时只写日志
+///
+/// 所有克隆共享Webhook地址，`set_webhook_url` 修改后对所有克隆生效。
+#[derive(Clone)]
+pub struct Notifier {
+    webhook_url: Arc<RwLock<Option<String>>>,
+    http: reqwest::Client,
+}
+
+impl Notifier {
+    pub fn new(webhook_url: Option<String>) -> Self {
+        Self {
+            webhook_url: Arc::new(RwLock::new(webhook_url)),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// 替换Webhook地址，用于重新加载配置
+    pub fn set_webhook_url(&self, webhook_url: Option<String>) {
+        *self.webhook_url.write().unwrap() = webhook_url;
+    }
+
+    /// 发送通知，失败只记录日志，不影响调用方
+    pub async fn notify(&self, notification: Notification) {
+        info!(
+            "通知 [{:?}] {}: {}",
+            notification.severity, notification.title, notification.message
+        );
+
+        let Some(url) = self.webhook_url.read().unwrap().clone() else {
+            return;
+        };
+
+        match self.http.post(&url).json(&notification).send().await {
+            Ok(response) if response.status().is_success() => {}
+            Ok(response) => warn!("通知Webhook返回错误状态: {}", response.status()),
+            Err(e) => warn!("发送通知失败: {}", e),
+        }
+    }
+}
@@ -0,0 +1,362 @@
+use crate::contract::{decode_revert_reason, RewardsContract};
+use crate::error::{DistributorError, Result};
+use ethers::prelude::*;
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::utils::{id, keccak256};
+use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
+use tracing::{error, info, warn};
+
+/// EIP-1967 实现地址存储槽：`bytes32(uint256(keccak256("eip1967.proxy.implementation")) - 1)`
+const EIP1967_IMPLEMENTATION_SLOT: H256 = H256([
+    0x36, 0x08, 0x94, 0xa1, 0x3b, 0xa1, 0xa3, 0x21, 0x06, 0x67, 0xc8, 0x28, 0x49, 0x2d, 0xb9, 0x8d,
+    0xca, 0x3e, 0x20, 0x76, 0xcc, 0x37, 0x35, 0xa9, 0x20, 0xa3, 0xca, 0x50, 0x5d, 0x38, 0x2b, 0xbc,
+]);
+
+/// EIP-1967 管理员地址存储槽：`bytes32(uint256(keccak256("eip1967.proxy.admin")) - 1)`
+const EIP1967_ADMIN_SLOT: H256 = H256([
+    0xb5, 0x31, 0x27, 0x68, 0x4a, 0x56, 0x8b, 0x31, 0x73, 0xae, 0x13, 0xb9, 0xf8, 0xa6, 0x01, 0x6e,
+    0x24, 0x3e, 0x63, 0xb6, 0xe8, 0xee, 0x11, 0x78, 0xd6, 0xa7, 0x17, 0x85, 0x0b, 0x5d, 0x61, 0x03,
+]);
+
+/// 明确表示函数不存在的自定义错误（EIP-2535 Diamond）
+const FUNCTION_NOT_FOUND_ERROR: &str = "FunctionNotFound(bytes4)";
+
+/// 回滚原因中表示函数不存在的常见写法（小写比较）
+const FUNCTION_NOT_FOUND_REASONS: [&str; 2] = ["function does not exist", "selector not found"];
+
+const DELEGATECALL: u8 = 0xf4;
+
+/// 发送前检查未通过时的处理方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PreflightPolicy {
+    /// 中止本次分发，按失败处理（默认）
+    #[default]
+    Abort,
+    /// 不发送交易，发送警告通知，不视为失败
+    NotifyAndSkip,
+    /// 记录警告后照常发送
+    Continue,
+}
+
+impl FromStr for PreflightPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "abort" => Ok(PreflightPolicy::Abort),
+            "notify-and-skip" => Ok(PreflightPolicy::NotifyAndSkip),
+            "continue" => Ok(PreflightPolicy::Continue),
+            other => Err(format!(
+                "未知的处理方式: {}，应为 abort、notify-and-skip 或 continue",
+                other
+            )),
+        }
+    }
+}
+
+impl fmt::Display for PreflightPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreflightPolicy::Abort => write!(f, "abort"),
+            PreflightPolicy::NotifyAndSkip => write!(f, "notify-and-skip"),
+            PreflightPolicy::Continue => write!(f, "continue"),
+        }
+    }
+}
+
+impl PreflightPolicy {
+    /// 按处理方式处理未通过的检查：中止时原样返回错误，跳过时包装为 `PreflightSkipped`
+    pub(crate) fn apply(self, check: PreflightCheck, err: DistributorError) -> Result<()> {
+        match self {
+            PreflightPolicy::Abort => Err(err),
+            PreflightPolicy::NotifyAndSkip => {
+                warn!("{}未通过，跳过本次分发: {}", check, err);
+                Err(DistributorError::PreflightSkipped {
+                    check,
+                    source: Box::new(err),
+                })
+            }
+            PreflightPolicy::Continue => {
+                warn!("{}未通过，按配置继续发送: {}", check, err);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// 发送前的检查项，按执行顺序排列
+///
+/// 先模拟执行（发现回滚的成本最低），再检查余额（签名账户的Gas费用和奖励合约的代币），最后发送。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PreflightCheck {
+    Simulation,
+    Balance,
+}
+
+impl fmt::Display for PreflightCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PreflightCheck::Simulation => write!(f, "发送前模拟执行"),
+            PreflightCheck::Balance => write!(f, "余额检查"),
+        }
+    }
+}
+
+/// 每个发送前检查项各自的处理方式
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PreflightPolicies {
+    /// 模拟执行回滚时（ON_SIMULATION_REVERT）
+    pub on_simulation_revert: PreflightPolicy,
+    /// 签名账户余额不足以支付Gas或奖励合约代币不足时（ON_INSUFFICIENT_BALANCE）
+    pub on_insufficient_balance: PreflightPolicy,
+}
+
+impl PreflightPolicies {
+    pub fn policy(&self, check: PreflightCheck) -> PreflightPolicy {
+        match check {
+            PreflightCheck::Simulation => self.on_simulation_revert,
+            PreflightCheck::Balance => self.on_insufficient_balance,
+        }
+    }
+}
+
+/// EIP-1967代理合约的信息
+#[derive(Debug, Clone, Serialize)]
+pub struct ProxyInfo {
+    pub proxy: Address,
+    pub implementation: Address,
+    /// 管理员槽中的地址，未设置时为 `None`（如UUPS代理）
+    pub admin: Option<Address>,
+    /// 实现合约字节码的keccak256
+    pub code_hash: H256,
+}
+
+/// 用 `eth_call` 探测分发函数的结果
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum CallProbe {
+    /// 模拟执行成功
+    Callable,
+    /// 模拟执行回滚，可能只是还没到分发时间，不影响启动
+    Reverts { reason: String },
+    /// 合约上没有这个函数，每次分发都会失败
+    FunctionMissing { reason: String },
+}
+
+impl fmt::Display for CallProbe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CallProbe::Callable => write!(f, "可调用"),
+            CallProbe::Reverts { reason } => write!(f, "模拟执行回滚: {}", reason),
+            CallProbe::FunctionMissing { reason } => write!(f, "分发函数不存在: {}", reason),
+        }
+    }
+}
+
+/// 单个奖励合约的探测结果
+#[derive(Debug, Clone, Serialize)]
+pub struct ContractProbe {
+    pub contract: Address,
+    /// 分发调用的函数选择器
+    pub selector: Bytes,
+    pub probe: CallProbe,
+}
+
+/// 启动前检查的结果，服务启动和 `diagnose` 共用
+#[derive(Debug, Clone, Serialize)]
+pub struct PreflightReport {
+    pub calls: Vec<ContractProbe>,
+}
+
+impl PreflightReport {
+    /// 任一合约上不存在分发函数时返回错误，其他结果（包括回滚）都视为通过
+    pub fn ensure_callable(&self) -> Result<()> {
+        for call in &self.calls {
+            if let CallProbe::FunctionMissing { reason } = &call.probe {
+                return Err(DistributorError::FunctionNotFound {
+                    contract: call.contract,
+                    selector: call.selector.clone(),
+                    reason: reason.clone(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// 记录每个合约的探测结果，函数不存在时记为错误，其他记为info
+    pub fn log(&self) {
+        for call in &self.calls {
+            match &call.probe {
+                CallProbe::FunctionMissing { .. } => {
+                    error!(
+                        "合约 {:?} 选择器 {}: {}",
+                        call.contract, call.selector, call.probe
+                    )
+                }
+                _ => info!(
+                    "合约 {:?} 选择器 {}: {}",
+                    call.contract, call.selector, call.probe
+                ),
+            }
+        }
+    }
+}
+
+impl RewardsContract {
+    /// 启动前检查：以签名账户（或SIMULATE_AS）身份 `eth_call` 每个奖励合约的分发函数
+    ///
+    /// 回滚且没有回滚数据时，再检查字节码中是否包含该选择器，区分“函数不存在”和
+    /// “函数存在但条件不满足”。EIP-1967代理合约检查其实现合约的字节码。
+    pub async fn preflight(&self) -> Result<PreflightReport> {
+        let call_data = self.reward_call_data(false).await?;
+        let mut calls = Vec::new();
+        for contract in self.reward_contracts() {
+            let probe = self.probe_call(contract, &call_data).await?;
+            calls.push(ContractProbe {
+                contract,
+                selector: call_data.get(..4).unwrap_or(&call_data).to_vec().into(),
+                probe,
+            });
+        }
+        Ok(PreflightReport { calls })
+    }
+
+    async fn probe_call(&self, contract: Address, call_data: &Bytes) -> Result<CallProbe> {
+        let code = self.contract_code(contract).await?;
+        if code.is_empty() {
+            return Ok(CallProbe::FunctionMissing {
+                reason: "地址上没有合约代码".to_string(),
+            });
+        }
+
+        let tx: TypedTransaction = TransactionRequest {
+            to: Some(contract.into()),
+            data: Some(call_data.clone()),
+            from: Some(self.simulation_sender()),
+            ..Default::default()
+        }
+        .into();
+        let rpc_err = match self.client.call(&tx, None).await {
+            Ok(_) => return Ok(CallProbe::Callable),
+            Err(e) => match e.as_error_response() {
+                Some(rpc_err) if rpc_err.is_revert() || rpc_err.code == 3 => rpc_err.clone(),
+                _ => return Err(DistributorError::rpc("探测分发函数", e)),
+            },
+        };
+
+        let revert_data = rpc_err.as_revert_data().unwrap_or_default();
+        let reason = decode_revert_reason(&rpc_err);
+        if revert_data.starts_with(&id(FUNCTION_NOT_FOUND_ERROR)) {
+            return Ok(CallProbe::FunctionMissing {
+                reason: format!("合约返回 {}", FUNCTION_NOT_FOUND_ERROR),
+            });
+        }
+        let lowercase = reason.to_lowercase();
+        if FUNCTION_NOT_FOUND_REASONS
+            .iter()
+            .any(|pattern| lowercase.contains(pattern))
+        {
+            return Ok(CallProbe::FunctionMissing { reason });
+        }
+
+        if revert_data.is_empty() && call_data.len() >= 4 {
+            let selector = &call_data[..4];
+            let (code, source) = match self.implementation_code(contract, &code).await? {
+                Some((implementation, code)) => (code, format!("实现合约 {:?}", implementation)),
+                None => (code, "合约".to_string()),
+            };
+            if !has_delegatecall(&code) && !pushes_selector(&code, selector) {
+                return Ok(CallProbe::FunctionMissing {
+                    reason: format!("回滚数据为空，且{}字节码中没有该选择器", source),
+                });
+            }
+        }
+
+        Ok(CallProbe::Reverts { reason })
+    }
+
+    pub(crate) async fn contract_code(&self, address: Address) -> Result<Bytes> {
+        self.client
+            .get_code(address, None)
+            .await
+            .map_err(|e| DistributorError::rpc("获取合约字节码", e))
+    }
+
+    /// 读取EIP-1967实现槽和管理员槽，实现槽为空时不是代理合约，返回 `None`
+    pub async fn proxy_info(&self, proxy: Address) -> Result<Option<ProxyInfo>> {
+        let implementation =
+            Address::from(self.storage_at(proxy, EIP1967_IMPLEMENTATION_SLOT).await?);
+        if implementation.is_zero() {
+            return Ok(None);
+        }
+        let admin = Address::from(self.storage_at(proxy, EIP1967_ADMIN_SLOT).await?);
+        let code = self.contract_code(implementation).await?;
+        Ok(Some(ProxyInfo {
+            proxy,
+            implementation,
+            admin: (!admin.is_zero()).then_some(admin),
+            code_hash: H256(keccak256(&code)),
+        }))
+    }
+
+    async fn storage_at(&self, address: Address, slot: H256) -> Result<H256> {
+        self.client
+            .get_storage_at(address, slot, None)
+            .await
+            .map_err(|e| DistributorError::rpc("读取EIP-1967存储槽", e))
+    }
+
+    /// 代理合约（字节码包含DELEGATECALL且EIP-1967实现槽非空）的实现地址和字节码
+    async fn implementation_code(
+        &self,
+        proxy: Address,
+        proxy_code: &Bytes,
+    ) -> Result<Option<(Address, Bytes)>> {
+        if !has_delegatecall(proxy_code) {
+            return Ok(None);
+        }
+        let implementation =
+            Address::from(self.storage_at(proxy, EIP1967_IMPLEMENTATION_SLOT).await?);
+        if implementation.is_zero() {
+            return Ok(None);
+        }
+        let code = self.contract_code(implementation).await?;
+        Ok(Some((implementation, code)))
+    }
+}
+
+/// 遍历字节码的操作码（跳过PUSH的立即数）
+fn opcodes(code: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut pc = 0;
+    std::iter::from_fn(move || {
+        let op = *code.get(pc)?;
+        let push_len = if (0x60..=0x7f).contains(&op) {
+            (op - 0x5f) as usize
+        } else {
+            0
+        };
+        let immediate = &code[(pc + 1).min(code.len())..(pc + 1 + push_len).min(code.len())];
+        pc += 1 + push_len;
+        Some((op, immediate))
+    })
+}
+
+/// 字节码中是否有DELEGATECALL，有则可能通过fallback转发调用
+fn has_delegatecall(code: &[u8]) -> bool {
+    opcodes(code).any(|(op, _)| op == DELEGATECALL)
+}
+
+/// 函数分发器是否比较了该选择器（编译器会省略选择器的前导零字节，所以按数值比较PUSH1到PUSH4）
+fn pushes_selector(code: &[u8], selector: &[u8]) -> bool {
+    let selector = u32::from_be_bytes([selector[0], selector[1], selector[2], selector[3]]);
+    opcodes(code).any(|(op, immediate)| {
+        (0x60..=0x63).contains(&op)
+            && immediate
+                .iter()
+                .fold(0u32, |value, byte| (value << 8) | *byte as u32)
+                == selector
+    })
+}
@@ -0,0 +1,103 @@
+use crate::config::{Config, ContractAddress};
+use crate::signer::DistributorSigner;
+use crate::transport::{HttpTransport, RateLimitRetryPolicy};
+use anyhow::{anyhow, Result};
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Middleware, Provider, RetryClient, RetryClientBuilder};
+use ethers::types::Address;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, AUTHORIZATION};
+use reqwest::Url;
+
+/// 带重试的RPC Provider
+pub type RpcProvider = Provider<RetryClient<HttpTransport>>;
+
+/// 用于签名和发送交易的客户端
+pub type DistributorClient = SignerMiddleware<RpcProvider, DistributorSigner>;
+
+/// 按配置构建RPC Provider
+///
+/// 自定义请求头（如鉴权）会附加到每个请求上；限流（HTTP 429、节点限流错误码）、
+/// 节点5xx和网络错误会按 `RPC_MAX_RETRIES` 自动重试，并优先遵循 `Retry-After`。
+/// 配置了 `RPC_REQUESTS_PER_SECOND` 时所有请求共享同一个令牌桶。
+pub fn build_provider(config: &Config) -> Result<RpcProvider> {
+    Ok(provider_with_transport(config, build_transport(config)?))
+}
+
+/// 按配置构建HTTP传输层，克隆后构建的多个Provider共享同一个令牌桶
+pub fn build_transport(config: &Config) -> Result<HttpTransport> {
+    let url = Url::parse(&config.rpc_url).map_err(|e| anyhow!("无效的RPC_URL: {}", e))?;
+
+    let mut headers = HeaderMap::new();
+    if let Some(auth) = &config.rpc_auth_header {
+        let mut value =
+            HeaderValue::from_str(auth).map_err(|_| anyhow!("无效的RPC_AUTH_HEADER值"))?;
+        value.set_sensitive(true);
+        headers.insert(AUTHORIZATION, value);
+    }
+    for (name, value) in &config.rpc_headers {
+        let name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| anyhow!("无效的RPC请求头名称: {}", name))?;
+        let mut value =
+            HeaderValue::from_str(value).map_err(|_| anyhow!("无效的RPC请求头 {} 的值", name))?;
+        value.set_sensitive(true);
+        headers.insert(name, value);
+    }
+
+    let client = reqwest::Client::builder()
+        .default_headers(headers)
+        .build()?;
+
+    Ok(HttpTransport::new(url, client).with_rate_limit(config.rpc_requests_per_second))
+}
+
+/// 在传输层外包一层按配置重试的RPC Provider
+pub fn provider_with_transport(config: &Config, transport: HttpTransport) -> RpcProvider {
+    // 未开启计算单元感知时，用极大的配额让退避只取决于Retry-After和初始退避时间
+    let compute_units_per_second = if config.rpc_compute_unit_aware {
+        config.rpc_compute_units_per_second
+    } else {
+        u64::MAX
+    };
+
+    let retry_client = RetryClientBuilder::default()
+        .rate_limit_retries(config.rpc_max_retries)
+        .timeout_retries(config.rpc_max_retries)
+        .initial_backoff(config.rpc_initial_backoff)
+        .compute_units_per_second(compute_units_per_second)
+        .build(transport, Box::new(RateLimitRetryPolicy));
+
+    Provider::new(retry_client)
+}
+
+/// 确定奖励合约地址，ENS名称通过节点上的ENS注册表解析
+///
+/// 链上没有ENS注册表或名称没有设置地址时返回错误，不会回退到其他地址。
+pub async fn resolve_contract_address(
+    provider: &RpcProvider,
+    contract_address: &ContractAddress,
+) -> Result<Address> {
+    let name = match contract_address {
+        ContractAddress::Address(address) => return Ok(*address),
+        ContractAddress::Ens(name) => name,
+    };
+
+    let address = provider.resolve_name(name).await.map_err(|e| {
+        anyhow!(
+            "无法通过ENS解析CONTRACT_ADDRESS {} (当前链可能不支持ENS，或该名称未设置地址): {}",
+            name,
+            e
+        )
+    })?;
+    if address.is_zero() {
+        return Err(anyhow!("ENS名称 {} 解析到零地址", name));
+    }
+    Ok(address)
+}
+
+/// 去掉RPC地址中的路径、查询参数和用户信息，只保留协议和主机，避免密钥写入日志
+pub fn redact_url(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(url) => format!("{}://{}", url.scheme(), url.host_str().unwrap_or("")),
+        Err(_) => "<无效的URL>".to_string(),
+    }
+}
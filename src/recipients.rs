@@ -0,0 +1,260 @@
+use ethers::abi::{encode, Token};
+use ethers::types::{Address, Bytes, U256};
+use ethers::utils::{id, to_checksum};
+use serde_json::Value;
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+use thiserror::Error;
+
+/// 只有地址时的分发函数签名
+const RECIPIENTS_SIGNATURE: &str = "distributeDailyRewards(address[])";
+
+/// 地址和数量都有时的分发函数签名
+const RECIPIENTS_WITH_AMOUNTS_SIGNATURE: &str = "distributeDailyRewards(address[],uint256[])";
+
+/// 接收地址列表无效
+#[derive(Debug, Error)]
+pub enum RecipientsError {
+    #[error("无法读取接收地址文件 {path}: {source}")]
+    Read {
+        path: PathBuf,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("接收地址文件格式错误 (第{line}项): {message}")]
+    Parse { line: usize, message: String },
+    #[error("接收地址不是有效的校验和格式: {0}")]
+    NotChecksummed(String),
+    #[error("接收地址不能为零地址 (第{0}项)")]
+    ZeroAddress(usize),
+    #[error("重复的接收地址: {0:?}")]
+    Duplicate(Address),
+    #[error("部分接收地址缺少数量，数量必须全部提供或全部省略")]
+    MixedAmounts,
+    #[error("接收地址列表为空 (设置 ALLOW_EMPTY_RECIPIENTS=true 允许空列表)")]
+    Empty,
+    #[error("无法从 {url} 获取接收地址: {message}")]
+    Fetch { url: String, message: String },
+}
+
+/// 接收地址文件，每次分发时重新读取，列表可以每天变化而无需重启
+#[derive(Debug, Clone)]
+pub struct RecipientsSource {
+    pub path: PathBuf,
+    /// 是否允许空列表
+    pub allow_empty: bool,
+}
+
+impl RecipientsSource {
+    pub fn load(&self) -> Result<RecipientList, RecipientsError> {
+        RecipientList::load(&self.path, self.allow_empty)
+    }
+}
+
+/// 从文件读取的接收地址及可选的数量
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecipientList {
+    pub recipients: Vec<Address>,
+    /// 与 `recipients` 一一对应，未提供数量时为 `None`
+    pub amounts: Option<Vec<U256>>,
+}
+
+impl RecipientList {
+    /// 读取并校验接收地址文件
+    ///
+    /// `.json` 文件可以是地址字符串数组，或 `{"address": .., "amount": ..}` 对象数组；
+    /// 其他文件按CSV处理，每行 `地址[,数量]`，允许表头、空行和 `#` 注释。
+    pub fn load(path: &Path, allow_empty: bool) -> Result<Self, RecipientsError> {
+        let content = fs::read_to_string(path).map_err(|source| RecipientsError::Read {
+            path: path.to_path_buf(),
+            source,
+        })?;
+
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let entries = if is_json {
+            parse_json(&content)?
+        } else {
+            parse_csv(&content)?
+        };
+
+        Self::checked(Self::from_entries(entries)?, allow_empty)
+    }
+
+    /// 从已解析的JSON数组读取并校验接收地址，格式与 `.json` 接收地址文件相同
+    pub fn from_json_items(items: &[Value], allow_empty: bool) -> Result<Self, RecipientsError> {
+        Self::checked(Self::from_entries(parse_json_items(items)?)?, allow_empty)
+    }
+
+    fn checked(list: Self, allow_empty: bool) -> Result<Self, RecipientsError> {
+        if list.recipients.is_empty() && !allow_empty {
+            return Err(RecipientsError::Empty);
+        }
+        Ok(list)
+    }
+
+    fn from_entries(entries: Vec<(usize, Address, Option<U256>)>) -> Result<Self, RecipientsError> {
+        let mut seen = HashSet::new();
+        let mut recipients = Vec::with_capacity(entries.len());
+        let mut amounts = Vec::with_capacity(entries.len());
+
+        for (line, address, amount) in entries {
+            if address.is_zero() {
+                return Err(RecipientsError::ZeroAddress(line));
+            }
+            if !seen.insert(address) {
+                return Err(RecipientsError::Duplicate(address));
+            }
+            recipients.push(address);
+            amounts.push(amount);
+        }
+
+        let amounts = if amounts.iter().all(Option::is_some) && !amounts.is_empty() {
+            Some(amounts.into_iter().flatten().collect())
+        } else if amounts.iter().all(Option::is_none) {
+            None
+        } else {
+            return Err(RecipientsError::MixedAmounts);
+        };
+
+        Ok(Self {
+            recipients,
+            amounts,
+        })
+    }
+
+    /// 按每批最多 `size` 个地址拆分，数量随地址一起拆分
+    pub fn chunks(&self, size: usize) -> Vec<RecipientList> {
+        let size = size.max(1);
+        self.recipients
+            .chunks(size)
+            .enumerate()
+            .map(|(index, recipients)| {
+                let start = index * size;
+                RecipientList {
+                    recipients: recipients.to_vec(),
+                    amounts: self
+                        .amounts
+                        .as_ref()
+                        .map(|amounts| amounts[start..start + recipients.len()].to_vec()),
+                }
+            })
+            .collect()
+    }
+
+    /// 编码为 `distributeDailyRewards(address[])` 或 `(address[],uint256[])` 调用
+    pub fn encode_call(&self) -> Bytes {
+        let addresses = Token::Array(
+            self.recipients
+                .iter()
+                .map(|address| Token::Address(*address))
+                .collect(),
+        );
+        let (signature, tokens) = match &self.amounts {
+            Some(amounts) => (
+                RECIPIENTS_WITH_AMOUNTS_SIGNATURE,
+                vec![
+                    addresses,
+                    Token::Array(amounts.iter().map(|amount| Token::Uint(*amount)).collect()),
+                ],
+            ),
+            None => (RECIPIENTS_SIGNATURE, vec![addresses]),
+        };
+
+        let mut call_data = id(signature).to_vec();
+        call_data.extend(encode(&tokens));
+        call_data.into()
+    }
+}
+
+fn parse_json(content: &str) -> Result<Vec<(usize, Address, Option<U256>)>, RecipientsError> {
+    let items: Vec<Value> = serde_json::from_str(content).map_err(|e| RecipientsError::Parse {
+        line: 0,
+        message: e.to_string(),
+    })?;
+    parse_json_items(&items)
+}
+
+fn parse_json_items(items: &[Value]) -> Result<Vec<(usize, Address, Option<U256>)>, RecipientsError> {
+    items
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let line = index + 1;
+            let parse_error = |message: &str| RecipientsError::Parse {
+                line,
+                message: message.to_string(),
+            };
+            let (address, amount) = match item {
+                Value::String(address) => (address.as_str(), None),
+                Value::Object(object) => {
+                    let address = object
+                        .get("address")
+                        .and_then(Value::as_str)
+                        .ok_or_else(|| parse_error("缺少address字段"))?;
+                    let amount = match object.get("amount") {
+                        None | Some(Value::Null) => None,
+                        Some(Value::String(amount)) => Some(parse_amount(amount, line)?),
+                        Some(Value::Number(amount)) => {
+                            Some(parse_amount(&amount.to_string(), line)?)
+                        }
+                        Some(_) => return Err(parse_error("amount应为字符串或整数")),
+                    };
+                    (address, amount)
+                }
+                _ => return Err(parse_error("应为地址字符串或包含address的对象")),
+            };
+            Ok((line, parse_address(address, line)?, amount))
+        })
+        .collect()
+}
+
+fn parse_csv(content: &str) -> Result<Vec<(usize, Address, Option<U256>)>, RecipientsError> {
+    let mut entries = Vec::new();
+    let mut first_row = true;
+    for (index, row) in content.lines().enumerate() {
+        let line = index + 1;
+        let row = row.trim();
+        if row.is_empty() || row.starts_with('#') {
+            continue;
+        }
+
+        let mut fields = row.split(',').map(str::trim);
+        let address = fields.next().unwrap_or_default();
+        // 第一行不是地址时视为表头
+        if std::mem::replace(&mut first_row, false) && !address.starts_with("0x") {
+            continue;
+        }
+        let amount = match fields.next().filter(|amount| !amount.is_empty()) {
+            Some(amount) => Some(parse_amount(amount, line)?),
+            None => None,
+        };
+        entries.push((line, parse_address(address, line)?, amount));
+    }
+    Ok(entries)
+}
+
+/// 解析地址并要求使用EIP-55校验和格式，防止手工编辑时输错
+fn parse_address(value: &str, line: usize) -> Result<Address, RecipientsError> {
+    let address = value
+        .trim()
+        .parse::<Address>()
+        .map_err(|_| RecipientsError::Parse {
+            line,
+            message: format!("无效的地址: {}", value),
+        })?;
+    if to_checksum(&address, None) != value.trim() {
+        return Err(RecipientsError::NotChecksummed(value.trim().to_string()));
+    }
+    Ok(address)
+}
+
+/// 数量为十进制整数（最小单位）
+fn parse_amount(value: &str, line: usize) -> Result<U256, RecipientsError> {
+    U256::from_dec_str(value.trim()).map_err(|_| RecipientsError::Parse {
+        line,
+        message: format!("无效的数量: {}", value),
+    })
+}
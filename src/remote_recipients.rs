@@ -0,0 +1,110 @@
+use crate::error::Result;
+use crate::merkle::{MerkleError, MerkleTree};
+use crate::provider::redact_url;
+use crate::recipients::{RecipientList, RecipientsError};
+use ethers::abi::{encode, Token};
+use ethers::types::{Bytes, H256};
+use ethers::utils::id;
+use serde_json::Value;
+use std::time::Duration;
+use tracing::info;
+
+/// 按Merkle根分发的函数签名
+const ROOT_SIGNATURE: &str = "distributeDailyRewards(bytes32)";
+
+/// 获取接收地址数据的默认超时时间
+pub const DEFAULT_FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 每次分发前从URL获取当天的接收地址和数量，计算Merkle根后调用 `distributeDailyRewards(bytes32)`
+///
+/// 响应为JSON，可以是与 `.json` 接收地址文件相同格式的数组，也可以是
+/// `{"recipients": [..], "root": "0x.."}`；提供 `root` 时与计算出的根比对，不一致时不发送。
+#[derive(Debug, Clone)]
+pub struct RemoteRecipients {
+    pub url: String,
+    pub timeout: Duration,
+}
+
+impl RemoteRecipients {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            timeout: DEFAULT_FETCH_TIMEOUT,
+        }
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// 获取并校验接收地址列表，返回列表和数据源提供的Merkle根（如有）
+    pub async fn fetch(&self) -> Result<(RecipientList, Option<H256>)> {
+        let fetch_error = |message: String| RecipientsError::Fetch {
+            url: redact_url(&self.url),
+            message,
+        };
+        let response = reqwest::Client::new()
+            .get(&self.url)
+            .timeout(self.timeout)
+            .send()
+            .await
+            .map_err(|e| fetch_error(e.without_url().to_string()))?;
+        if !response.status().is_success() {
+            return Err(fetch_error(format!("HTTP状态 {}", response.status())).into());
+        }
+        let body: Value = response
+            .json()
+            .await
+            .map_err(|e| fetch_error(e.without_url().to_string()))?;
+
+        let (items, root) = match &body {
+            Value::Array(items) => (items, None),
+            Value::Object(object) => {
+                let items = object
+                    .get("recipients")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| fetch_error("响应缺少recipients数组".to_string()))?;
+                let root = match object.get("root") {
+                    None | Some(Value::Null) => None,
+                    Some(root) => Some(
+                        root.as_str()
+                            .and_then(|root| root.parse::<H256>().ok())
+                            .ok_or_else(|| fetch_error(format!("无效的root: {}", root)))?,
+                    ),
+                };
+                (items, root)
+            }
+            _ => return Err(fetch_error("响应应为数组或包含recipients的对象".to_string()).into()),
+        };
+        // Merkle树要求非空列表，空列表在构建时报错
+        let list = RecipientList::from_json_items(items, true)?;
+        Ok((list, root))
+    }
+
+    /// 获取接收地址并构建Merkle树，数据源提供了根时校验两者一致
+    pub async fn merkle_tree(&self) -> Result<MerkleTree> {
+        let (list, expected) = self.fetch().await?;
+        info!(
+            "从 {} 获取到 {} 个接收地址",
+            redact_url(&self.url),
+            list.recipients.len()
+        );
+        let tree = MerkleTree::build(&list)?;
+        if let Some(expected) = expected.filter(|expected| *expected != tree.root()) {
+            return Err(MerkleError::RootMismatch {
+                expected,
+                computed: tree.root(),
+            }
+            .into());
+        }
+        Ok(tree)
+    }
+}
+
+/// `distributeDailyRewards(bytes32 root)` 的调用数据
+pub fn encode_root_call(root: H256) -> Bytes {
+    let mut call_data = id(ROOT_SIGNATURE).to_vec();
+    call_data.extend(encode(&[Token::FixedBytes(root.as_bytes().to_vec())]));
+    call_data.into()
+}
@@ -0,0 +1,118 @@
+use crate::error::{DistributorError, ErrorKind};
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+/// RPC调用的重试策略
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// 传输错误和限流的最大尝试次数（含首次）
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// 第 `attempt` 次失败后的退避时间（指数增长，不超过上限）
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt.saturating_sub(1));
+        self.initial_backoff
+            .saturating_mul(factor)
+            .min(self.max_backoff)
+    }
+}
+
+/// 等待交易确认时轮询收据的间隔：从 `initial` 开始每次乘以 `multiplier`，不超过 `max`
+///
+/// 出块快的链上交易能尽快确认，慢链上的长时间等待也不会频繁请求节点。
+#[derive(Debug, Clone, Copy)]
+pub struct ConfirmationPoll {
+    pub initial: Duration,
+    pub max: Duration,
+    pub multiplier: f64,
+}
+
+impl Default for ConfirmationPoll {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(15),
+            multiplier: 2.0,
+        }
+    }
+}
+
+impl ConfirmationPoll {
+    /// 当前间隔之后的下一次轮询间隔
+    pub fn next(&self, interval: Duration) -> Duration {
+        interval.mul_f64(self.multiplier).min(self.max)
+    }
+}
+
+/// 按错误分类重试异步操作，返回结果和实际尝试次数
+///
+/// - 传输错误、限流：指数退避后重试，直到 `max_attempts`
+/// - nonce冲突、nonce过低：立即重试一次（操作本身需要重新获取nonce）
+/// - 不支持的交易类型：立即重试一次（操作本身需要重新检测交易类型）
+/// - 回滚、余额不足及其他：直接失败
+pub async fn retry<T, F, Fut>(
+    policy: &RetryPolicy,
+    operation: &str,
+    mut f: F,
+) -> Result<(T, u32), DistributorError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, DistributorError>>,
+{
+    let mut attempt = 0;
+    let mut nonce_resynced = false;
+    let mut tx_type_reprobed = false;
+
+    loop {
+        attempt += 1;
+        let err = match f().await {
+            Ok(value) => return Ok((value, attempt)),
+            Err(e) => e,
+        };
+
+        match err.kind() {
+            kind if kind.is_retryable() && attempt < policy.max_attempts => {
+                let backoff = policy.backoff(attempt);
+                warn!(
+                    "{}失败 ({})，{:?}后进行第{}次尝试: {}",
+                    operation,
+                    kind,
+                    backoff,
+                    attempt + 1,
+                    err
+                );
+                tokio::time::sleep(backoff).await;
+            }
+            ErrorKind::NonceConflict | ErrorKind::NonceTooLow if !nonce_resynced => {
+                warn!(
+                    "{}遇到nonce冲突，重新同步nonce后重试一次: {}",
+                    operation, err
+                );
+                nonce_resynced = true;
+            }
+            ErrorKind::UnsupportedTxType if !tx_type_reprobed => {
+                warn!(
+                    "{}被节点拒绝（不支持的交易类型），重新检测交易类型后重试一次: {}",
+                    operation, err
+                );
+                tx_type_reprobed = true;
+            }
+            _ => return Err(err),
+        }
+    }
+}
@@ -1,70 +1,1354 @@
-use anyhow::Result;
-use chrono::{DateTime, Local, Timelike};
-use tokio_cron_scheduler::{Job, JobScheduler};
-use tracing::{info, warn};
+use crate::contract::{DistributionEvent, DistributionResult};
+use crate::error::{DistributorError, Result};
+use crate::history::{ExecutionHistory, ExecutionRecord};
+use crate::hooks::{
+    guarded, HistoryHook, IntoRunResults, JobHooks, NotificationHook, RunContext, SkipReason,
+};
+use crate::notify::Notifier;
+use crate::state::{StateStore, Trigger};
+use anyhow::anyhow;
+use chrono::{
+    DateTime, Datelike, FixedOffset, Local, LocalResult, NaiveDate, NaiveDateTime, NaiveTime,
+    TimeZone, Utc, Weekday,
+};
+use cron::Schedule;
+use ethers::types::H256;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::Duration;
+use tokio::sync::{mpsc, oneshot};
+use tokio_cron_scheduler::{Job, JobScheduler, JobSchedulerError};
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+/// 已注册的任务闭包，类型擦除后可以由cron或手动触发调用
+type JobTask = Arc<dyn Fn() -> Pin<Box<dyn Future<Output = Result<()>> + Send>> + Send + Sync>;
+
+/// 每日任务的闭包，成功时返回交给 `on_success` 钩子的分发结果
+type DailyTask = Arc<
+    dyn Fn() -> Pin<Box<dyn Future<Output = Result<Vec<DistributionResult>>> + Send>> + Send + Sync,
+>;
+
+/// 每日分发任务默认的cron表达式（秒 分 时 日 月 周，按调度时区触发）
+///
+/// 每天 06:25 执行，调度时区为UTC时为北京时间 14:25。
+pub const DEFAULT_DAILY_CRON: &str = "0 25 6 * * *";
+
+/// 关闭时默认等待进行中的每日任务结束的最长时间
+const DEFAULT_SHUTDOWN_GRACE: Duration = Duration::from_secs(120);
+
+/// 关闭时等待进行中的执行期间输出进度的间隔
+const SHUTDOWN_PROGRESS_INTERVAL: Duration = Duration::from_secs(10);
+
+/// 计算跳过日期后的下次执行时间时最多向后查找的天数
+const MAX_LOOKAHEAD_DAYS: i64 = 366;
+
+/// 解析cron表达式，5段的标准crontab格式会给出补上秒字段的提示
+pub fn parse_cron(expr: &str) -> Result<Schedule> {
+    Schedule::from_str(expr).map_err(|e| {
+        let hint = if expr.split_whitespace().count() == 5 {
+            "，需要6段 (秒 分 时 日 月 周)，5段的crontab格式请在前面加上秒字段，例如 0 "
+        } else {
+            ""
+        };
+        DistributorError::Config(format!("无效的cron表达式 \"{}\": {}{}", expr, e, hint))
+    })
+}
+
+/// 把6段cron表达式的星期字段替换为 `days`，用于按SCHEDULE_DAYS生成默认的每日cron表达式
+pub fn cron_with_days(expr: &str, days: &[Weekday]) -> String {
+    let days = days.iter().map(Weekday::to_string).collect::<Vec<_>>().join(",");
+    let mut fields: Vec<&str> = expr.split_whitespace().collect();
+    if fields.len() == 6 {
+        fields[5] = &days;
+    }
+    fields.join(" ")
+}
+
+/// cron表达式的计算时区，也用于日志中的时间和跳过日期的判断
+///
+/// 默认UTC，同样的配置在任何主机上都在同一时刻触发；`local` 使用主机时区（`TZ`）。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ScheduleTimezone {
+    #[default]
+    Utc,
+    Local,
+}
+
+impl FromStr for ScheduleTimezone {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "utc" => Ok(ScheduleTimezone::Utc),
+            "local" => Ok(ScheduleTimezone::Local),
+            other => Err(format!("未知的调度时区: {}，应为 utc 或 local", other)),
+        }
+    }
+}
+
+impl fmt::Display for ScheduleTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScheduleTimezone::Utc => write!(f, "UTC"),
+            ScheduleTimezone::Local => write!(f, "本地时区"),
+        }
+    }
+}
+
+impl ScheduleTimezone {
+    /// 某一时刻在该时区的日期
+    pub fn date_of(&self, time: DateTime<Utc>) -> NaiveDate {
+        match self {
+            ScheduleTimezone::Utc => time.date_naive(),
+            ScheduleTimezone::Local => time.with_timezone(&Local).date_naive(),
+        }
+    }
+
+    /// 按该时区格式化时间，本地时区附带UTC偏移
+    pub fn format(&self, time: DateTime<Utc>) -> String {
+        match self {
+            ScheduleTimezone::Utc => time.format("%Y-%m-%d %H:%M:%S UTC").to_string(),
+            ScheduleTimezone::Local => time
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M:%S %:z")
+                .to_string(),
+        }
+    }
+
+    /// 该时区今年是否有夏令时切换（1月和7月的UTC偏移不同）
+    pub fn observes_dst(&self) -> bool {
+        match self {
+            ScheduleTimezone::Utc => false,
+            ScheduleTimezone::Local => {
+                let year = Utc::now().year();
+                let offset = |month| {
+                    NaiveDate::from_ymd_opt(year, month, 1)
+                        .map(|date| Local.offset_from_utc_datetime(&date.and_time(NaiveTime::MIN)))
+                };
+                offset(1) != offset(7)
+            }
+        }
+    }
+
+    /// 在该时区计算cron表达式 `after` 之后的触发时间，夏令时切换当天按 `policy` 处理
+    fn upcoming<'a>(
+        &self,
+        schedule: &'a Schedule,
+        policy: DstPolicy,
+        after: DateTime<Utc>,
+    ) -> Box<dyn Iterator<Item = DateTime<Utc>> + 'a> {
+        fn local<const POLICY: u8>(
+            schedule: &Schedule,
+            after: DateTime<Utc>,
+        ) -> Box<dyn Iterator<Item = DateTime<Utc>> + '_> {
+            Box::new(
+                schedule
+                    .after(&after.with_timezone(&DstLocal::<POLICY>))
+                    .map(|time| time.with_timezone(&Utc)),
+            )
+        }
+        match (self, policy) {
+            (ScheduleTimezone::Utc, _) => Box::new(schedule.after(&after)),
+            (ScheduleTimezone::Local, DstPolicy::Skip) => local::<DST_SKIP>(schedule, after),
+            (ScheduleTimezone::Local, DstPolicy::Earliest) => local::<DST_EARLIEST>(schedule, after),
+            (ScheduleTimezone::Local, DstPolicy::Latest) => local::<DST_LATEST>(schedule, after),
+        }
+    }
+
+    /// 在该时区按cron表达式触发的任务，与 `upcoming` 使用同样的夏令时处理
+    fn job<T>(
+        &self,
+        cron: &str,
+        policy: DstPolicy,
+        run: T,
+    ) -> std::result::Result<Job, JobSchedulerError>
+    where
+        T: FnMut(Uuid, JobScheduler) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        match (self, policy) {
+            (ScheduleTimezone::Utc, _) => Job::new_async_tz(cron, Utc, run),
+            (ScheduleTimezone::Local, DstPolicy::Skip) => {
+                Job::new_async_tz(cron, DstLocal::<DST_SKIP>, run)
+            }
+            (ScheduleTimezone::Local, DstPolicy::Earliest) => {
+                Job::new_async_tz(cron, DstLocal::<DST_EARLIEST>, run)
+            }
+            (ScheduleTimezone::Local, DstPolicy::Latest) => {
+                Job::new_async_tz(cron, DstLocal::<DST_LATEST>, run)
+            }
+        }
+    }
+}
+
+/// 本地时区夏令时切换当天，cron时间不存在（春季拨快）或出现两次（秋季拨慢）时的处理方式
+///
+/// 默认 `earliest`：出现两次的时间只在第一次触发，不存在的时间顺延跳过的时长后触发
+/// （如 02:30 在拨快一小时的当天于 03:30 触发），每天都触发且从不重复触发。
+/// `latest` 在出现两次的时间的第二次触发，不存在的时间同样顺延；`skip` 这两种情况当天都不触发。
+/// cron表达式按UTC计算时没有夏令时，不受影响。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DstPolicy {
+    Skip,
+    #[default]
+    Earliest,
+    Latest,
+}
+
+impl FromStr for DstPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "skip" => Ok(DstPolicy::Skip),
+            "earliest" => Ok(DstPolicy::Earliest),
+            "latest" => Ok(DstPolicy::Latest),
+            other => Err(format!("未知的夏令时策略: {}，应为 skip、earliest 或 latest", other)),
+        }
+    }
+}
+
+impl fmt::Display for DstPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DstPolicy::Skip => write!(f, "skip"),
+            DstPolicy::Earliest => write!(f, "earliest"),
+            DstPolicy::Latest => write!(f, "latest"),
+        }
+    }
+}
+
+const DST_SKIP: u8 = 0;
+const DST_EARLIEST: u8 = 1;
+const DST_LATEST: u8 = 2;
+
+/// 按 `DstPolicy` 解析本地时间的主机时区
+///
+/// cron计算触发时间时把每个候选的本地时间交给时区换算，`Local` 对不存在和出现两次的时间都不给出
+/// 唯一结果，这样的时间会被直接跳过。这里按策略给出唯一的偏移，计算下次触发时间和调度器实际触发
+/// 使用同一个时区，两者保持一致。
+#[derive(Debug, Clone, Copy)]
+struct DstLocal<const POLICY: u8>;
+
+impl<const POLICY: u8> TimeZone for DstLocal<POLICY> {
+    type Offset = FixedOffset;
+
+    fn from_offset(_offset: &FixedOffset) -> Self {
+        DstLocal
+    }
+
+    fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+        self.offset_from_local_datetime(&local.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+        match Local.offset_from_local_datetime(local) {
+            LocalResult::Single(offset) => LocalResult::Single(offset),
+            LocalResult::Ambiguous(a, b) => {
+                // 偏移越大，同一本地时间对应的时刻越早
+                let (earliest, latest) = if a.local_minus_utc() >= b.local_minus_utc() {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                match POLICY {
+                    DST_EARLIEST => LocalResult::Single(earliest),
+                    DST_LATEST => LocalResult::Single(latest),
+                    _ => LocalResult::None,
+                }
+            }
+            LocalResult::None if POLICY == DST_SKIP => LocalResult::None,
+            // 不存在的时间按切换前的偏移换算，结果落在切换之后，即顺延跳过的时长
+            LocalResult::None => {
+                match Local
+                    .offset_from_local_datetime(&(*local - chrono::Duration::days(1)))
+                    .earliest()
+                {
+                    Some(offset) => LocalResult::Single(offset),
+                    None => LocalResult::None,
+                }
+            }
+        }
+    }
+
+    fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+        self.offset_from_utc_datetime(&utc.and_time(NaiveTime::MIN))
+    }
+
+    fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+        Local.offset_from_utc_datetime(utc)
+    }
+}
+
+/// 按指定时区计算的cron表达式
+///
+/// 注册任务、计算下次触发时间和启动日志都从这里取值，保证日志中的时间就是任务实际触发的时间。
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    expr: String,
+    schedule: Schedule,
+    timezone: ScheduleTimezone,
+    dst_policy: DstPolicy,
+}
+
+impl CronSchedule {
+    pub fn new(expr: &str, timezone: ScheduleTimezone) -> Result<Self> {
+        Ok(Self {
+            expr: expr.trim().to_string(),
+            schedule: parse_cron(expr.trim())?,
+            timezone,
+            dst_policy: DstPolicy::default(),
+        })
+    }
+
+    /// 默认的每日分发时间（`DEFAULT_DAILY_CRON`）
+    pub fn daily(timezone: ScheduleTimezone) -> Self {
+        Self::new(DEFAULT_DAILY_CRON, timezone).expect("默认的cron表达式有效")
+    }
+
+    pub fn expr(&self) -> &str {
+        &self.expr
+    }
+
+    pub fn timezone(&self) -> ScheduleTimezone {
+        self.timezone
+    }
+
+    /// 同一cron表达式在另一时区的计算
+    pub fn with_timezone(mut self, timezone: ScheduleTimezone) -> Self {
+        self.timezone = timezone;
+        self
+    }
+
+    /// 设置夏令时切换当天的处理方式（默认 `earliest`）
+    pub fn with_dst_policy(mut self, policy: DstPolicy) -> Self {
+        self.dst_policy = policy;
+        self
+    }
+
+    pub fn dst_policy(&self) -> DstPolicy {
+        self.dst_policy
+    }
+
+    /// `after` 之后的触发时间
+    pub fn upcoming(&self, after: DateTime<Utc>) -> Box<dyn Iterator<Item = DateTime<Utc>> + '_> {
+        self.timezone.upcoming(&self.schedule, self.dst_policy, after)
+    }
+
+    /// `after` 之后的第一次触发时间
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.upcoming(after).next()
+    }
+
+    /// `after` 之后一周内平均每天触发的次数，只在部分星期几触发的表达式也能按天换算
+    pub fn runs_per_day(&self, after: DateTime<Utc>) -> f64 {
+        let end = after + chrono::Duration::days(7);
+        let runs = self.upcoming(after).take_while(|time| *time <= end).count();
+        runs as f64 / 7.0
+    }
+
+    /// 按该cron表达式和时区触发的任务
+    fn job<T>(&self, run: T) -> std::result::Result<Job, JobSchedulerError>
+    where
+        T: FnMut(Uuid, JobScheduler) -> Pin<Box<dyn Future<Output = ()> + Send>>
+            + Send
+            + Sync
+            + 'static,
+    {
+        self.timezone.job(&self.expr, self.dst_policy, run)
+    }
+}
+
+impl fmt::Display for CronSchedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // 只有时区有夏令时切换时策略才会生效
+        if self.timezone.observes_dst() {
+            write!(f, "{} ({}，夏令时策略: {})", self.expr, self.timezone, self.dst_policy)
+        } else {
+            write!(f, "{} ({})", self.expr, self.timezone)
+        }
+    }
+}
+
+/// 任务的触发规则
+#[derive(Clone)]
+enum JobSchedule {
+    /// 按cron表达式触发
+    Cron(Box<CronSchedule>),
+    /// 从 `anchor`（添加任务的时间）开始每隔 `every` 触发一次
+    Interval {
+        every: Duration,
+        anchor: DateTime<Utc>,
+    },
+    /// 在指定时间触发一次
+    Once(DateTime<Utc>),
+}
+
+impl JobSchedule {
+    /// `after` 之后的触发时间
+    fn upcoming(&self, after: DateTime<Utc>) -> Box<dyn Iterator<Item = DateTime<Utc>> + '_> {
+        match self {
+            JobSchedule::Cron(schedule) => schedule.upcoming(after),
+            JobSchedule::Interval { every, anchor } => {
+                let anchor = *anchor;
+                let every_ms = every.as_millis().max(1) as i64;
+                let first = (after - anchor).num_milliseconds().max(0) / every_ms + 1;
+                Box::new(
+                    (first..).map(move |k| anchor + chrono::Duration::milliseconds(k * every_ms)),
+                )
+            }
+            JobSchedule::Once(at) => Box::new(std::iter::once(*at).filter(move |at| *at > after)),
+        }
+    }
+}
+
+/// 已注册的任务，用于列出任务和输出之后的执行时间
+struct RegisteredJob {
+    id: Uuid,
+    name: String,
+    /// cron表达式，间隔任务为 `every <秒数>s`，一次性任务为 `once <RFC 3339时间>`
+    cron: String,
+    schedule: JobSchedule,
+}
+
+/// 添加任务时返回的句柄，用于移除、替换任务和查询下次触发时间
+#[derive(Clone)]
+pub struct JobHandle {
+    id: Uuid,
+    scheduler: JobScheduler,
+}
+
+impl fmt::Debug for JobHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("JobHandle").field("id", &self.id).finish()
+    }
+}
+
+impl JobHandle {
+    pub fn id(&self) -> Uuid {
+        self.id
+    }
+
+    /// 调度器记录的下次触发时间（UTC），调度器尚未启动或任务已移除时为 `None`
+    pub async fn next_run(&self) -> Option<DateTime<Utc>> {
+        self.scheduler
+            .clone()
+            .next_tick_for_job(self.id)
+            .await
+            .ok()
+            .flatten()
+    }
+}
+
+type TriggerSender = mpsc::UnboundedSender<oneshot::Sender<Result<()>>>;
+
+/// 手动触发每日任务的句柄，可以克隆后交给其他任务使用
+#[derive(Clone)]
+pub struct TriggerHandle {
+    sender: TriggerSender,
+}
+
+impl TriggerHandle {
+    /// 请求立即执行一次每日任务，返回的接收端在执行结束后得到执行结果
+    ///
+    /// 已有执行在进行时跳过并返回 `Ok(())`；调度器已被丢弃时接收端返回 `RecvError`。
+    pub fn trigger_now(&self) -> oneshot::Receiver<Result<()>> {
+        let (reply, receiver) = oneshot::channel();
+        let _ = self.sender.send(reply);
+        receiver
+    }
+}
+
+/// `list_jobs` 返回的任务信息
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobInfo {
+    pub id: Uuid,
+    pub name: String,
+    /// cron表达式，间隔任务为 `every <秒数>s`，一次性任务为 `once <RFC 3339时间>`
+    pub cron: String,
+    /// 下次触发时间（UTC），每日任务不考虑跳过日期
+    pub next_run: Option<DateTime<Utc>>,
+}
+
+/// 不执行分发的日期（节假日、周末、SCHEDULE_DAYS之外的星期）
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleCalendar {
+    skip_dates: BTreeSet<NaiveDate>,
+    skip_weekends: bool,
+    /// HOLIDAYS_PATH中的假日，重新加载配置时整体替换，所以与调度器共享
+    holidays: Arc<RwLock<BTreeSet<NaiveDate>>>,
+    /// 允许分发的星期，为 `None` 时每天都允许
+    days: Option<Vec<Weekday>>,
+}
+
+impl ScheduleCalendar {
+    pub fn new(skip_dates: impl IntoIterator<Item = NaiveDate>, skip_weekends: bool) -> Self {
+        Self {
+            skip_dates: skip_dates.into_iter().collect(),
+            skip_weekends,
+            holidays: Arc::default(),
+            days: None,
+        }
+    }
+
+    /// 设置假日（HOLIDAYS_PATH），假日当天跳过分发并发送通知
+    pub fn with_holidays(self, holidays: BTreeSet<NaiveDate>) -> Self {
+        self.set_holidays(holidays);
+        self
+    }
+
+    /// 替换假日，之后的触发按新的假日判断
+    pub fn set_holidays(&self, holidays: BTreeSet<NaiveDate>) {
+        *self.holidays.write().unwrap() = holidays;
+    }
+
+    /// 只在这些星期分发，为 `None` 时每天都允许
+    pub fn with_days(mut self, days: Option<Vec<Weekday>>) -> Self {
+        self.days = days;
+        self
+    }
+
+    /// 该日期不执行分发的原因，需要执行时为 `None`
+    pub fn skip_reason(&self, date: NaiveDate) -> Option<SkipReason> {
+        if self.holidays.read().unwrap().contains(&date) {
+            return Some(SkipReason::Holiday(date));
+        }
+        let weekday = date.weekday();
+        if self.days.as_ref().is_some_and(|days| !days.contains(&weekday)) {
+            return Some(SkipReason::DayDisabled { date, weekday });
+        }
+        self.exclusion_reason(date)
+            .map(|reason| SkipReason::ExcludedDate { date, reason })
+    }
+
+    /// 该日期是否需要跳过，需要时返回原因
+    pub fn exclusion_reason(&self, date: NaiveDate) -> Option<&'static str> {
+        if self.skip_dates.contains(&date) {
+            Some("excluded date")
+        } else if self.skip_weekends && matches!(date.weekday(), Weekday::Sat | Weekday::Sun) {
+            Some("weekend")
+        } else {
+            None
+        }
+    }
+}
 
 pub struct DailyScheduler {
     scheduler: JobScheduler,
+    /// 每日任务的ID和闭包，供手动触发使用
+    daily_task: Arc<Mutex<Option<(Uuid, DailyTask)>>>,
+    /// `trigger_handle` 首次调用时启动的手动触发通道
+    trigger_sender: Mutex<Option<TriggerSender>>,
+    calendar: Arc<ScheduleCalendar>,
+    timezone: ScheduleTimezone,
+    /// 每日任务的触发时间，时区与 `timezone` 一致
+    daily_schedule: CronSchedule,
+    /// cron触发后随机延迟的上限，手动触发不延迟
+    jitter: Duration,
+    /// 一次性任务执行后从中移除自己，所以与任务闭包共享
+    jobs: Arc<Mutex<Vec<RegisteredJob>>>,
+    /// 每日任务的执行锁，cron触发、手动触发和启动时执行共用，保证同一时间只有一次执行
+    run_lock: Arc<RunLock>,
+    /// 暂停时所有cron任务触发后直接跳过，手动触发不受影响
+    paused: Arc<AtomicBool>,
+    /// 关闭时等待进行中的每日任务结束的最长时间
+    shutdown_grace: Duration,
+    /// 每个合约分发结束后发送 `DistributionEvent` 的通道
+    events: Option<mpsc::Sender<DistributionEvent>>,
 }
 
 impl DailyScheduler {
     pub async fn new() -> Result<Self> {
         let scheduler = JobScheduler::new().await?;
-        Ok(Self { scheduler })
+        Ok(Self {
+            scheduler,
+            daily_task: Arc::new(Mutex::new(None)),
+            trigger_sender: Mutex::new(None),
+            calendar: Arc::new(ScheduleCalendar::default()),
+            timezone: ScheduleTimezone::default(),
+            daily_schedule: CronSchedule::daily(ScheduleTimezone::default()),
+            jitter: Duration::ZERO,
+            jobs: Arc::new(Mutex::new(Vec::new())),
+            run_lock: Arc::new(RunLock::new(RunSettings::default())),
+            paused: Arc::new(AtomicBool::new(false)),
+            shutdown_grace: DEFAULT_SHUTDOWN_GRACE,
+            events: None,
+        })
+    }
+
+    /// 设置跳过日期，需要在 `add_daily_job` 之前调用
+    pub fn with_calendar(mut self, calendar: ScheduleCalendar) -> Self {
+        self.calendar = Arc::new(calendar);
+        self
+    }
+
+    /// 设置cron表达式的计算时区（默认UTC），需要在添加任务之前调用
+    pub fn with_timezone(mut self, timezone: ScheduleTimezone) -> Self {
+        self.timezone = timezone;
+        self.daily_schedule = self.daily_schedule.with_timezone(timezone);
+        self
+    }
+
+    /// 设置每日任务的cron表达式，调度时区随之改为其时区，需要在添加任务之前调用
+    pub fn with_schedule(mut self, schedule: CronSchedule) -> Self {
+        self.timezone = schedule.timezone();
+        self.daily_schedule = schedule;
+        self
+    }
+
+    /// 设置每日任务触发后随机延迟的上限（默认不延迟），需要在 `add_daily_job` 之前调用
+    ///
+    /// 延迟在任务内部执行，不修改cron表达式，`upcoming_runs` 等仍按cron时间计算。
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    /// 设置状态文件，跳过重叠执行时从中读取进行中的执行已发送的交易，需要在 `add_daily_job` 之前调用
+    pub fn with_state_store(mut self, state: Option<Arc<StateStore>>) -> Self {
+        let mut settings = self.run_lock.settings.clone();
+        settings.state = state;
+        self.run_lock = Arc::new(RunLock::new(settings));
+        self
+    }
+
+    /// 设置每次执行每日任务的超时时间（默认不限制），需要在 `add_daily_job` 之前调用
+    ///
+    /// 超时后停止等待并释放执行锁，已广播的交易不会被取消，由下次运行的待确认交易恢复处理。
+    pub fn with_job_timeout(mut self, timeout: Option<Duration>) -> Self {
+        let mut settings = self.run_lock.settings.clone();
+        settings.timeout = timeout;
+        self.run_lock = Arc::new(RunLock::new(settings));
+        self
+    }
+
+    /// 设置关闭时等待进行中的每日任务结束的最长时间（默认2分钟）
+    pub fn with_shutdown_grace(mut self, grace: Duration) -> Self {
+        self.shutdown_grace = grace;
+        self
+    }
+
+    /// 设置执行记录的容量和快照文件（默认保留100条、不写文件），需要在 `add_daily_job` 之前调用
+    pub fn with_history(mut self, history: ExecutionHistory) -> Self {
+        let mut settings = self.run_lock.settings.clone();
+        settings.history = Arc::new(history);
+        self.run_lock = Arc::new(RunLock::new(settings));
+        self
+    }
+
+    /// 添加每日任务执行的生命周期钩子，在内置的执行记录和超时通知之后按顺序调用
+    ///
+    /// 钩子对所有经过执行锁的执行生效（cron/间隔触发、手动触发、一次性任务和 `run_exclusive`）。
+    pub fn with_hooks(self, hooks: Vec<Arc<dyn JobHooks>>) -> Self {
+        self.run_lock.settings.hooks.lock().unwrap().extend(hooks);
+        self
+    }
+
+    /// 设置任务超时时发送通知的通知器，需要在 `add_daily_job` 之前调用
+    pub fn with_notifier(mut self, notifier: Notifier) -> Self {
+        let mut settings = self.run_lock.settings.clone();
+        settings.notifier = Some(notifier);
+        self.run_lock = Arc::new(RunLock::new(settings));
+        self
+    }
+
+    /// 设置发送分发事件的通道，每日任务在每个合约分发结束后发送一个 `DistributionEvent`
+    ///
+    /// 事件由任务闭包通过 `event_sender` 取得通道后用 `DistributionReport::forward_events` 发送。
+    pub fn with_event_sender(mut self, events: mpsc::Sender<DistributionEvent>) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    /// `with_event_sender` 设置的通道，未设置时为 `None`
+    pub fn event_sender(&self) -> Option<mpsc::Sender<DistributionEvent>> {
+        self.events.clone()
+    }
+
+    pub fn timezone(&self) -> ScheduleTimezone {
+        self.timezone
+    }
+
+    /// 替换日历中的假日，用于重新加载HOLIDAYS_PATH
+    pub fn set_holidays(&self, holidays: BTreeSet<NaiveDate>) {
+        self.calendar.set_holidays(holidays);
+    }
+
+    /// `add_daily_job` 使用的触发时间
+    pub fn schedule(&self) -> &CronSchedule {
+        &self.daily_schedule
+    }
+
+    /// 最近的执行记录快照，按执行开始的先后排列
+    ///
+    /// cron/间隔触发、手动触发、一次性任务和 `run_exclusive` 的执行都会记录，包括跳过的执行。
+    pub fn history(&self) -> Vec<ExecutionRecord> {
+        self.run_lock.settings.history.snapshot()
+    }
+    
+    /// 按 `with_schedule` 设置的触发时间添加每日分发任务，返回任务句柄
+    ///
+    /// 任务可以返回 `()`、`DistributionResult` 或 `Vec<DistributionResult>`，成功时交给 `on_success` 钩子。
+    pub async fn add_daily_job<F, Fut, R>(&self, task: F) -> Result<JobHandle>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<R>> + Send + 'static,
+        R: IntoRunResults + 'static,
+    {
+        self.add_daily_job_on(self.daily_schedule.clone(), task).await
+    }
+
+    /// 按指定的触发时间添加每日分发任务，返回任务句柄
+    ///
+    /// 用于重新加载配置后按新的cron表达式注册每日任务。
+    pub async fn add_daily_job_on<F, Fut, R>(
+        &self,
+        schedule: CronSchedule,
+        task: F,
+    ) -> Result<JobHandle>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<R>> + Send + 'static,
+        R: IntoRunResults + 'static,
+    {
+        let task = erase_daily_task(task);
+        let uuid = self.scheduler.add(self.daily_job(&schedule, task.clone())?).await?;
+        *self.daily_task.lock().unwrap() = Some((uuid, task));
+        info!("每日任务已添加到调度器 ({}): {}", schedule, uuid);
+        self.jobs.lock().unwrap().push(RegisteredJob {
+            id: uuid,
+            name: "每日任务".to_string(),
+            cron: schedule.expr().to_string(),
+            schedule: JobSchedule::Cron(Box::new(schedule)),
+        });
+        Ok(self.handle(uuid))
+    }
+
+    /// 添加每日分发任务并注册生命周期钩子，返回任务句柄
+    ///
+    /// 钩子与 `with_hooks` 注册的一样对所有经过执行锁的执行生效。
+    pub async fn add_daily_job_with_hooks<F, Fut, R>(
+        &self,
+        task: F,
+        hooks: Vec<Arc<dyn JobHooks>>,
+    ) -> Result<JobHandle>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<R>> + Send + 'static,
+        R: IntoRunResults + 'static,
+    {
+        self.run_lock.settings.hooks.lock().unwrap().extend(hooks);
+        self.add_daily_job(task).await
+    }
+
+    /// 按间隔执行分发任务（如每6小时），代替按cron触发的每日任务，返回任务句柄
+    ///
+    /// 从添加任务时开始计时，与每日任务一样跳过排除日期、随机延迟、在执行锁内执行，
+    /// 也可以通过 `trigger_now` 手动触发。间隔按整秒计算。
+    pub async fn add_interval_job<F, Fut, R>(&self, interval: Duration, task: F) -> Result<JobHandle>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<R>> + Send + 'static,
+        R: IntoRunResults + 'static,
+    {
+        if interval.as_secs() == 0 {
+            return Err(DistributorError::Config("任务间隔至少为1秒".to_string()));
+        }
+        let task = erase_daily_task(task);
+        let job = Job::new_repeated_async(interval, self.daily_job_run(task.clone()))?;
+        let anchor = Utc::now();
+        let uuid = self.scheduler.add(job).await?;
+        *self.daily_task.lock().unwrap() = Some((uuid, task));
+        self.jobs.lock().unwrap().push(RegisteredJob {
+            id: uuid,
+            name: "每日任务".to_string(),
+            cron: format!("every {}s", interval.as_secs()),
+            schedule: JobSchedule::Interval {
+                every: interval,
+                anchor,
+            },
+        });
+        info!("分发任务已添加到调度器，每 {} 秒执行一次: {}", interval.as_secs(), uuid);
+        Ok(self.handle(uuid))
+    }
+
+    /// 在 `at` 执行一次任务，返回任务句柄，执行后自动从调度器移除
+    ///
+    /// 用于临时补发等一次性分发：与每日任务共用执行锁和超时，不受跳过日期和随机延迟影响，
+    /// 暂停时跳过。`at` 必须晚于当前时间。
+    pub async fn add_one_shot<F, Fut, R>(&self, at: DateTime<Utc>, task: F) -> Result<JobHandle>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<R>> + Send + 'static,
+        R: IntoRunResults + 'static,
+    {
+        let now = Utc::now();
+        if at <= now {
+            return Err(DistributorError::Config(format!(
+                "一次性任务的执行时间 {} 已经过去",
+                self.timezone.format(at)
+            )));
+        }
+        // 调度器把触发时间记为添加时的整秒加上延迟的整秒数，延迟按 `at` 向上取整到秒计算，保证不会提前执行
+        let target = at.timestamp() + i64::from(at.timestamp_subsec_nanos() > 0);
+        let delay = Duration::from_secs((target - now.timestamp()) as u64);
+        let task = erase_daily_task(task);
+        let name = format!("一次性任务 {}", self.timezone.format(at));
+        let timezone = self.timezone;
+        let run_lock = self.run_lock.clone();
+        let paused = self.paused.clone();
+        let jobs = self.jobs.clone();
+        let job_name = name.clone();
+        let job = Job::new_one_shot_async(delay, move |uuid, scheduler| {
+            let task = task.clone();
+            let name = job_name.clone();
+            let run_lock = run_lock.clone();
+            let paused = paused.clone();
+            let jobs = jobs.clone();
+            Box::pin(async move {
+                jobs.lock().unwrap().retain(|job| job.id != uuid);
+                if paused.load(Ordering::SeqCst) {
+                    info!("skipped: scheduler paused ({})", name);
+                    run_lock
+                        .skip(RunContext::new(&name, Trigger::OneShot), SkipReason::Paused)
+                        .await;
+                } else {
+                    info!("开始执行{}", name);
+                    let _ = run_lock
+                        .run(&name, Trigger::OneShot, timezone, run_daily_task(&task, timezone))
+                        .await;
+                }
+                // 调度器只是不再触发执行过的一次性任务，这里把它彻底移除
+                let _ = scheduler.remove(&uuid).await;
+                info!("{}已执行并从调度器移除: {}", name, uuid);
+            })
+        })?;
+        let uuid = self.scheduler.add(job).await?;
+        self.jobs.lock().unwrap().push(RegisteredJob {
+            id: uuid,
+            name: name.clone(),
+            cron: format!("once {}", at.to_rfc3339()),
+            schedule: JobSchedule::Once(at),
+        });
+        info!("{}已添加到调度器: {}", name, uuid);
+        Ok(self.handle(uuid))
+    }
+
+    /// 每日任务的cron任务：跳过排除日期、随机延迟，然后在执行锁内执行
+    fn daily_job(&self, schedule: &CronSchedule, task: DailyTask) -> Result<Job> {
+        Ok(schedule.job(self.daily_job_run(task))?)
+    }
+
+    /// 每日任务每次触发时执行的闭包，cron任务和间隔任务共用
+    fn daily_job_run(
+        &self,
+        task: DailyTask,
+    ) -> impl FnMut(Uuid, JobScheduler) -> Pin<Box<dyn Future<Output = ()> + Send>>
+           + Send
+           + Sync
+           + 'static {
+        let calendar = self.calendar.clone();
+        let timezone = self.timezone;
+        let run_lock = self.run_lock.clone();
+        let jitter = self.jitter;
+        let paused = self.paused.clone();
+        move |_uuid, _l| {
+            let task = task.clone();
+            let calendar = calendar.clone();
+            let run_lock = run_lock.clone();
+            let paused = paused.clone();
+            Box::pin(async move {
+                if paused.load(Ordering::SeqCst) {
+                    info!("skipped: scheduler paused (每日任务)");
+                    run_lock
+                        .skip(RunContext::new("每日任务", Trigger::Cron), SkipReason::Paused)
+                        .await;
+                    return;
+                }
+                // 跳过日期与cron使用同一时区判断
+                let today = timezone.date_of(Utc::now());
+                if let Some(reason) = calendar.skip_reason(today) {
+                    info!("skipping distribution: {}", reason);
+                    run_lock
+                        .skip(RunContext::new("每日任务", Trigger::Cron), reason)
+                        .await;
+                    return;
+                }
+                // 先随机延迟再获取执行锁，延迟期间手动触发不会被阻塞
+                if !jitter.is_zero() {
+                    let delay = Duration::from_millis(rand::thread_rng().gen_range(0..=jitter.as_millis() as u64));
+                    info!("随机延迟 {:.1} 秒后执行每日任务", delay.as_secs_f64());
+                    tokio::time::sleep(delay).await;
+                }
+                let _ = run_lock
+                    .run("每日任务", Trigger::Cron, timezone, run_daily_task(&task, timezone))
+                    .await;
+            })
+        }
+    }
+
+    /// 立即执行已注册的每日任务，不等待cron触发
+    ///
+    /// 与cron触发走同一个执行路径，返回任务的执行结果，便于测试和手动触发。
+    pub async fn trigger_now(&self) -> Result<()> {
+        trigger_daily_task(&self.daily_task, &self.run_lock, self.timezone).await
+    }
+
+    /// 供外部代码（管理界面、信号处理等）手动触发每日任务的句柄
+    ///
+    /// 句柄通过通道请求执行，与cron触发共用执行锁和超时；首次调用时启动处理请求的后台任务，
+    /// 需要在 `with_*` 设置完成之后调用。
+    pub fn trigger_handle(&self) -> TriggerHandle {
+        let mut sender = self.trigger_sender.lock().unwrap();
+        let sender = sender
+            .get_or_insert_with(|| self.spawn_trigger_listener())
+            .clone();
+        TriggerHandle { sender }
+    }
+
+    fn spawn_trigger_listener(&self) -> TriggerSender {
+        let (sender, mut receiver) = mpsc::unbounded_channel::<oneshot::Sender<Result<()>>>();
+        let daily_task = self.daily_task.clone();
+        let run_lock = self.run_lock.clone();
+        let timezone = self.timezone;
+        tokio::spawn(async move {
+            while let Some(reply) = receiver.recv().await {
+                // 每次请求单独执行，执行中再次触发时与cron触发一样按重叠执行跳过
+                let daily_task = daily_task.clone();
+                let run_lock = run_lock.clone();
+                tokio::spawn(async move {
+                    let result = trigger_daily_task(&daily_task, &run_lock, timezone).await;
+                    let _ = reply.send(result);
+                });
+            }
+        });
+        sender
+    }
+
+    /// 暂停所有cron任务，触发时直接跳过；手动触发和正在进行的执行不受影响
+    pub fn pause(&self) {
+        if !self.paused.swap(true, Ordering::SeqCst) {
+            info!("调度器已暂停，定时任务触发时将跳过");
+        }
+    }
+
+    /// 恢复cron任务，暂停期间错过的触发不会补执行
+    pub fn resume(&self) {
+        if self.paused.swap(false, Ordering::SeqCst) {
+            info!("调度器已恢复");
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// 按暂停标记文件是否存在同步暂停状态，每隔 `interval` 检查一次
+    ///
+    /// 供 `pause`/`resume` 命令控制运行中的服务；启动时立即检查一次。
+    pub fn watch_pause_file(&self, path: PathBuf, interval: Duration) {
+        let paused = self.paused.clone();
+        let sync = move || {
+            let exists = path.exists();
+            if paused.swap(exists, Ordering::SeqCst) != exists {
+                if exists {
+                    info!("发现暂停标记文件 {}，调度器已暂停", path.display());
+                } else {
+                    info!("暂停标记文件 {} 已删除，调度器已恢复", path.display());
+                }
+            }
+        };
+        sync();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                sync();
+            }
+        });
+    }
+
+    /// 定期读取一次性任务文件，注册其中尚未注册的执行时间，每隔 `interval` 检查一次
+    ///
+    /// 供 `schedule-once` 命令向运行中的服务添加一次性任务；启动时立即检查一次。执行时间过去后
+    /// 从文件中删除，其中服务停机期间错过（未在本进程注册过）的记录警告，不补执行。
+    pub fn watch_one_shot_file<F, Fut, R>(self: &Arc<Self>, path: PathBuf, interval: Duration, task: F)
+    where
+        F: Fn() -> Fut + Clone + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<R>> + Send + 'static,
+        R: IntoRunResults + 'static,
+    {
+        let scheduler = self.clone();
+        tokio::spawn(async move {
+            let mut registered = BTreeSet::new();
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                scheduler.sync_one_shots(&path, &mut registered, &task).await;
+            }
+        });
+    }
+
+    async fn sync_one_shots<F, Fut, R>(
+        &self,
+        path: &Path,
+        registered: &mut BTreeSet<DateTime<Utc>>,
+        task: &F,
+    ) where
+        F: Fn() -> Fut + Clone + Send + Sync + 'static,
+        Fut: std::future::Future<Output = Result<R>> + Send + 'static,
+        R: IntoRunResults + 'static,
+    {
+        let times = match read_one_shot_file(path) {
+            Ok(times) => times,
+            Err(e) => {
+                warn!("读取一次性任务文件 {} 失败: {}", path.display(), e);
+                return;
+            }
+        };
+        let now = Utc::now();
+        let mut expired = BTreeSet::new();
+        for at in times {
+            if at <= now {
+                if !registered.remove(&at) {
+                    warn!("一次性任务 {} 的执行时间在服务未运行期间已过去，不再执行", self.timezone.format(at));
+                }
+                expired.insert(at);
+            } else if self.has_one_shot(at) {
+                // 已通过管理接口注册，文件中的记录只用于重启后报告错过的执行
+                registered.insert(at);
+            } else if !registered.contains(&at) {
+                match self.add_one_shot(at, task.clone()).await {
+                    Ok(_) => {
+                        registered.insert(at);
+                    }
+                    Err(e) => warn!("注册一次性任务 {} 失败: {}", self.timezone.format(at), e),
+                }
+            }
+        }
+        if expired.is_empty() {
+            return;
+        }
+        // 重新读取后再写回，尽量不覆盖 schedule-once 命令刚写入的时间
+        let result = read_one_shot_file(path).and_then(|mut times| {
+            times.retain(|at| !expired.contains(at));
+            write_one_shot_file(path, &times)
+        });
+        if let Err(e) = result {
+            warn!("更新一次性任务文件 {} 失败: {}", path.display(), e);
+        }
+    }
+
+    /// 是否已注册了在 `at` 执行、尚未执行的一次性任务
+    fn has_one_shot(&self, at: DateTime<Utc>) -> bool {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|job| matches!(job.schedule, JobSchedule::Once(time) if time == at))
+    }
+
+    /// 在每日任务的执行锁内运行 `run`，与cron触发和手动触发互斥
+    ///
+    /// 用于启动时执行、补执行等不经过cron的分发，以 `trigger` 写入执行记录；
+    /// 已有执行在进行时跳过并返回 `Ok(())`。
+    pub async fn run_exclusive<Fut, R>(&self, name: &str, trigger: Trigger, run: Fut) -> Result<()>
+    where
+        Fut: Future<Output = Result<R>>,
+        R: IntoRunResults,
+    {
+        let run = async move { run.await.map(IntoRunResults::into_run_results) };
+        self.run_lock.run(name, trigger, self.timezone, run).await
     }
     
-    pub async fn add_daily_job<F, Fut>(&self, task: F) -> Result<()>
+    /// 按cron表达式（6段，按调度时区触发）添加任务，返回任务句柄，失败只记录日志
+    ///
+    /// 与每日任务不同，这里的任务不受跳过日期影响。
+    pub async fn add_job<F, Fut, E>(&self, name: &str, cron: &str, task: F) -> Result<JobHandle>
     where
         F: Fn() -> Fut + Send + Sync + 'static,
-        Fut: std::future::Future<Output = Result<()>> + Send + 'static,
+        Fut: std::future::Future<Output = std::result::Result<(), E>> + Send + 'static,
+        E: std::fmt::Display,
     {
-        // 每天北京时间 14:25 点执行的Cron表达式
-        let job = Job::new_async("0 25 6 * * *", {
-            let task = std::sync::Arc::new(task);
-            move |_uuid, _l| {
+        let schedule = CronSchedule::new(cron, self.timezone)?.with_dst_policy(self.daily_schedule.dst_policy());
+        let task = erase_task(task);
+        let uuid = self.scheduler.add(self.job(name, &schedule, task)?).await?;
+        self.jobs.lock().unwrap().push(RegisteredJob {
+            id: uuid,
+            name: name.to_string(),
+            cron: cron.to_string(),
+            schedule: JobSchedule::Cron(Box::new(schedule)),
+        });
+        info!("{}已添加到调度器 ({}): {}", name, cron, uuid);
+        Ok(self.handle(uuid))
+    }
+
+    /// 普通任务的cron任务：执行失败只记录日志
+    fn job(&self, name: &str, schedule: &CronSchedule, task: JobTask) -> Result<Job> {
+        let name = name.to_string();
+        let paused = self.paused.clone();
+        let job = schedule.job(move |_uuid, _l| {
             let task = task.clone();
+            let name = name.clone();
+            let paused = paused.clone();
             Box::pin(async move {
-                info!("开始执行每日任务...");
-                let now = Local::now();
-                info!("当前时间: {}", now.format("%Y-%m-%d %H:%M:%S"));
-                
-                match (task)().await {
-                Ok(_) => info!("每日任务执行成功"),
-                Err(e) => warn!("每日任务执行失败: {}", e),
+                if paused.load(Ordering::SeqCst) {
+                    info!("skipped: scheduler paused ({})", name);
+                    return;
+                }
+                if let Err(e) = task().await {
+                    warn!("{}执行失败: {}", name, e);
                 }
             })
-            }
         })?;
-        
-        self.scheduler.add(job).await?;
-        info!("每日任务已添加到调度器");
-        Ok(())
+        Ok(job)
     }
     
-    pub async fn add_test_job<F, Fut>(&self, task: F) -> Result<()>
+    /// 添加每小时整点执行的任务，失败只记录日志
+    pub async fn add_hourly_job<F, Fut>(&self, name: &str, task: F) -> Result<JobHandle>
     where
         F: Fn() -> Fut + Send + Sync + 'static,
-        Fut: std::future::Future<Output = Result<()>> + Send + 'static,
-    {
-        // 每分钟执行一次的测试任务
-        let job = Job::new_async("0 * * * * *", {
-            let task = std::sync::Arc::new(task);
-            move |_uuid, _l| {
-                let task = task.clone();
-                Box::pin(async move {
-                    info!("执行测试任务...");
-                    match (task)().await {
-                        Ok(_) => info!("测试任务执行成功"),
-                        Err(e) => warn!("测试任务执行失败: {}", e),
-                    }
-                })
-            }
-        })?;
-        
-        self.scheduler.add(job).await?;
-        info!("测试任务已添加到调度器（每分钟执行）");
+        Fut: std::future::Future<Output = anyhow::Result<()>> + Send + 'static,
+    {
+        self.add_job(name, "0 0 * * * *", task).await
+    }
+    
+    /// 从调度器中移除任务，移除每日任务后 `trigger_now` 不再可用
+    pub async fn remove_job(&self, handle: &JobHandle) -> Result<()> {
+        let id = handle.id;
+        let name = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let index = jobs
+                .iter()
+                .position(|job| job.id == id)
+                .ok_or_else(|| DistributorError::Other(anyhow!("没有ID为 {} 的任务", id)))?;
+            jobs.remove(index).name
+        };
+        self.scheduler.remove(&id).await?;
+
+        let mut daily_task = self.daily_task.lock().unwrap();
+        if daily_task.as_ref().is_some_and(|(daily_id, _)| *daily_id == id) {
+            *daily_task = None;
+        }
+        info!("{}已从调度器移除: {}", name, id);
         Ok(())
     }
+
+    /// 用新的cron表达式和任务替换已注册的任务，返回新任务的句柄，旧句柄随之失效
+    ///
+    /// 先添加新任务再移除旧任务，替换过程中不会漏掉触发；cron表达式无效时保留原任务。
+    /// 替换每日任务时新任务仍按每日任务执行（跳过日期、随机延迟、执行锁），`trigger_now` 执行新任务。
+    pub async fn replace_job<F, Fut, E>(
+        &self,
+        handle: &JobHandle,
+        cron: &str,
+        task: F,
+    ) -> Result<JobHandle>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: std::future::Future<Output = std::result::Result<(), E>> + Send + 'static,
+        E: std::fmt::Display,
+    {
+        let schedule = CronSchedule::new(cron, self.timezone)?.with_dst_policy(self.daily_schedule.dst_policy());
+        let name = self
+            .jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|job| job.id == handle.id)
+            .map(|job| job.name.clone())
+            .ok_or_else(|| DistributorError::Other(anyhow!("没有ID为 {} 的任务", handle.id)))?;
+        let daily = self
+            .daily_task
+            .lock()
+            .unwrap()
+            .as_ref()
+            .is_some_and(|(daily_id, _)| *daily_id == handle.id);
+
+        let task = erase_task(task);
+        let daily_task = daily.then(|| daily_from_job_task(task.clone()));
+        let job = match &daily_task {
+            Some(daily_task) => self.daily_job(&schedule, daily_task.clone())?,
+            None => self.job(&name, &schedule, task)?,
+        };
+        let uuid = self.scheduler.add(job).await?;
+        if let Err(e) = self.scheduler.remove(&handle.id).await {
+            // 旧任务没能移除时撤销新任务，避免两个任务同时触发
+            let _ = self.scheduler.remove(&uuid).await;
+            return Err(e.into());
+        }
+
+        if let Some(daily_task) = daily_task {
+            *self.daily_task.lock().unwrap() = Some((uuid, daily_task));
+        }
+        if let Some(job) = self
+            .jobs
+            .lock()
+            .unwrap()
+            .iter_mut()
+            .find(|job| job.id == handle.id)
+        {
+            job.id = uuid;
+            job.cron = cron.to_string();
+            job.schedule = JobSchedule::Cron(Box::new(schedule));
+        }
+        info!("{}已替换 ({}): {} -> {}", name, cron, handle.id, uuid);
+        Ok(self.handle(uuid))
+    }
+
+    fn handle(&self, id: Uuid) -> JobHandle {
+        JobHandle {
+            id,
+            scheduler: self.scheduler.clone(),
+        }
+    }
+
+    /// 每日任务当前的触发规则，尚未注册每日任务时为 `None`
+    fn daily_schedule(&self) -> Option<JobSchedule> {
+        let daily_id = self.daily_task.lock().unwrap().as_ref()?.0;
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|job| job.id == daily_id)
+            .map(|job| job.schedule.clone())
+    }
+    
+    /// 已注册的任务及其下次触发时间，按添加顺序排列
+    pub async fn list_jobs(&self) -> Vec<JobInfo> {
+        let jobs: Vec<(Uuid, String, String, Option<DateTime<Utc>>)> = {
+            let now = Utc::now();
+            self.jobs
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|job| {
+                    let next = job.schedule.upcoming(now).next();
+                    (job.id, job.name.clone(), job.cron.clone(), next)
+                })
+                .collect()
+        };
+
+        let mut infos = Vec::with_capacity(jobs.len());
+        for (id, name, cron, computed) in jobs {
+            // 调度器启动后以调度器记录的时间为准，启动前按cron表达式计算
+            let next_run = match self.scheduler.clone().next_tick_for_job(id).await {
+                Ok(Some(next)) => Some(next),
+                _ => computed,
+            };
+            infos.push(JobInfo {
+                id,
+                name,
+                cron,
+                next_run,
+            });
+        }
+        infos
+    }
+    
+    /// 所有已注册任务中最早的下次触发时间（UTC），由调度器提供，调度器启动前按cron表达式计算
+    pub async fn next_run(&self) -> Option<DateTime<Utc>> {
+        self.list_jobs()
+            .await
+            .into_iter()
+            .filter_map(|job| job.next_run)
+            .min()
+    }
+
+    /// 指定任务的下次触发时间（UTC），任务已移除时为 `None`
+    pub async fn next_run_for(&self, handle: &JobHandle) -> Option<DateTime<Utc>> {
+        self.list_jobs()
+            .await
+            .into_iter()
+            .find(|job| job.id == handle.id)
+            .and_then(|job| job.next_run)
+    }
+    
+    /// 每日任务之后 `n` 次实际执行分发的时间，已跳过日历中排除的日期
+    ///
+    /// 尚未注册每日任务时返回空列表。
+    pub fn upcoming_runs(&self, n: usize) -> Vec<DateTime<Utc>> {
+        self.upcoming_runs_after(Utc::now(), n)
+    }
+
+    /// 每日任务 `after` 之后 `n` 次实际执行分发的时间
+    fn upcoming_runs_after(&self, after: DateTime<Utc>, n: usize) -> Vec<DateTime<Utc>> {
+        let Some(schedule) = self.daily_schedule() else {
+            return Vec::new();
+        };
+        let limit = after + chrono::Duration::days(MAX_LOOKAHEAD_DAYS);
+        schedule
+            .upcoming(after)
+            .take_while(|time| *time < limit)
+            .filter(|time| {
+                self.calendar
+                    .skip_reason(self.timezone.date_of(*time))
+                    .is_none()
+            })
+            .take(n)
+            .collect()
+    }
+    
+    /// 启动时检查停机期间是否错过了每日任务
+    ///
+    /// 返回 `window` 内最近一次应执行（未被日历排除）、且晚于 `last_success` 的触发时间。
+    /// 没有成功记录时无法判断是否错过，不补执行；间隔任务从进程启动开始计时，没有错过的触发。
+    pub fn missed_run(
+        &self,
+        last_success: Option<DateTime<Utc>>,
+        window: Duration,
+    ) -> Option<DateTime<Utc>> {
+        let JobSchedule::Cron(schedule) = self.daily_schedule()? else {
+            return None;
+        };
+        let last_success = last_success?;
+        let now = Utc::now();
+        let window_start = now - chrono::Duration::from_std(window).ok()?;
+
+        schedule
+            .upcoming(window_start.max(last_success))
+            .take_while(|time| *time <= now)
+            .filter(|time| {
+                self.calendar
+                    .skip_reason(self.timezone.date_of(*time))
+                    .is_none()
+            })
+            .last()
+    }
+    
+    /// 记录每个已注册任务之后 `n` 次的触发时间，便于启动时确认cron表达式符合预期
+    pub fn log_upcoming_runs(&self, n: usize) {
+        let now = Utc::now();
+        for job in self.jobs.lock().unwrap().iter() {
+            let times: Vec<String> = job
+                .schedule
+                .upcoming(now)
+                .take(n)
+                .map(|time| self.timezone.format(time))
+                .collect();
+            info!("{} 之后{}次触发时间: {}", job.name, times.len(), times.join(", "));
+        }
+        if self.daily_task.lock().unwrap().is_some() {
+            let runs: Vec<String> = self
+                .upcoming_runs(n)
+                .iter()
+                .map(|time| self.timezone.format(*time))
+                .collect();
+            info!("跳过排除日期后，之后{}次分发时间: {}", runs.len(), runs.join(", "));
+        }
+    }
     
     pub async fn start(&self) -> Result<()> {
         self.scheduler.start().await?;
@@ -72,22 +1356,1152 @@ impl DailyScheduler {
         Ok(())
     }
     
-    pub async fn shutdown(&mut self) -> Result<()> {
-        self.scheduler.shutdown().await?;
+    /// 停止触发新的执行并关闭调度器，然后最多等待 `with_shutdown_grace` 设置的时间让进行中的每日任务结束
+    ///
+    /// 超过等待时间时直接返回：已发送的交易在发送时已写入状态文件，下次启动后继续确认。
+    pub async fn shutdown(&self) -> Result<()> {
+        self.run_lock.shutting_down.store(true, Ordering::SeqCst);
+        self.scheduler.clone().shutdown().await?;
+        self.run_lock
+            .wait_idle(self.shutdown_grace, self.timezone)
+            .await;
         info!("调度器已关闭");
         Ok(())
     }
-    
-    pub fn next_midnight() -> DateTime<Local> {
-        let now = Local::now();
-        let today = now.date_naive();
-        let scheduled_time = today.and_hms_opt(6, 25, 0).unwrap().and_local_timezone(Local).unwrap();
+}
+
+/// 把返回任意错误类型的任务转换为统一的任务闭包
+fn erase_task<F, Fut, E>(task: F) -> JobTask
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = std::result::Result<(), E>> + Send + 'static,
+    E: fmt::Display,
+{
+    let task = Arc::new(task);
+    Arc::new(move || {
+        let task = task.clone();
+        Box::pin(async move {
+            task()
+                .await
+                .map_err(|e| DistributorError::Other(anyhow!("{}", e)))
+        })
+    })
+}
+
+/// 把返回分发结果的任务转换为统一的每日任务闭包
+fn erase_daily_task<F, Fut, R>(task: F) -> DailyTask
+where
+    F: Fn() -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<R>> + Send + 'static,
+    R: IntoRunResults + 'static,
+{
+    Arc::new(move || {
+        let run = task();
+        Box::pin(async move { run.await.map(IntoRunResults::into_run_results) })
+    })
+}
+
+/// 替换每日任务时，把不返回分发结果的任务转换为每日任务闭包
+fn daily_from_job_task(task: JobTask) -> DailyTask {
+    Arc::new(move || {
+        let run = task();
+        Box::pin(async move { run.await.map(|()| Vec::new()) })
+    })
+}
+
+/// 读取一次性任务文件（每行一个RFC 3339时间，忽略空行和 `#` 开头的行），文件不存在时为空
+pub fn read_one_shot_file(path: &Path) -> Result<BTreeSet<DateTime<Utc>>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeSet::new()),
+        Err(e) => return Err(anyhow!("读取 {} 失败: {}", path.display(), e).into()),
+    };
+    content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            DateTime::parse_from_rfc3339(line)
+                .map(|at| at.with_timezone(&Utc))
+                .map_err(|e| DistributorError::Config(format!("一次性任务文件中的时间 \"{}\" 无效: {}", line, e)))
+        })
+        .collect()
+}
 
-        if now < scheduled_time {
-            scheduled_time
+/// 读取假日文件：每行一个 `YYYY-MM-DD` 日期，忽略空行和 `#` 之后的注释
+///
+/// 所有无效的行一起报告，附带行号。
+pub fn read_holidays_file(path: &Path) -> Result<BTreeSet<NaiveDate>> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| DistributorError::Config(format!("读取假日文件 {} 失败: {}", path.display(), e)))?;
+    let mut holidays = BTreeSet::new();
+    let mut invalid = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let date = line.split('#').next().unwrap_or_default().trim();
+        if date.is_empty() {
+            continue;
+        }
+        match NaiveDate::parse_from_str(date, "%Y-%m-%d") {
+            Ok(date) => {
+                holidays.insert(date);
+            }
+            Err(_) => invalid.push(format!("第{}行 \"{}\"", index + 1, date)),
+        }
+    }
+    if !invalid.is_empty() {
+        return Err(DistributorError::Config(format!(
+            "假日文件 {} 中的日期无效 (应为 YYYY-MM-DD): {}",
+            path.display(),
+            invalid.join("，")
+        )));
+    }
+    Ok(holidays)
+}
+
+/// 写入一次性任务文件，没有待执行的时间时删除文件
+pub fn write_one_shot_file(path: &Path, times: &BTreeSet<DateTime<Utc>>) -> Result<()> {
+    let result = if times.is_empty() {
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(()),
+            result => result,
+        }
+    } else {
+        let content: String = times.iter().map(|at| format!("{}\n", at.to_rfc3339())).collect();
+        std::fs::write(path, content)
+    };
+    result.map_err(|e| anyhow!("写入 {} 失败: {}", path.display(), e).into())
+}
+
+/// 手动触发已注册的每日任务，尚未注册时返回错误
+async fn trigger_daily_task(
+    daily_task: &Mutex<Option<(Uuid, DailyTask)>>,
+    run_lock: &RunLock,
+    timezone: ScheduleTimezone,
+) -> Result<()> {
+    let task = daily_task
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|(_, task)| task.clone())
+        .ok_or_else(|| DistributorError::Other(anyhow!("尚未注册每日任务")))?;
+
+    info!("手动触发每日任务");
+    run_lock
+        .run("手动触发", Trigger::Manual, timezone, run_daily_task(&task, timezone))
+        .await
+}
+
+/// 执行每日任务并记录结果
+async fn run_daily_task(
+    task: &DailyTask,
+    timezone: ScheduleTimezone,
+) -> Result<Vec<DistributionResult>> {
+    info!("开始执行每日任务...");
+    info!("当前时间: {}", timezone.format(Utc::now()));
+
+    let result = task().await;
+    match &result {
+        Ok(_) => info!("每日任务执行成功"),
+        Err(e) => warn!("每日任务执行失败: {}", e),
+    }
+    result
+}
+
+/// 执行锁的配置
+#[derive(Clone, Default)]
+struct RunSettings {
+    /// 每次执行（包括跳过的执行）的记录
+    history: Arc<ExecutionHistory>,
+    /// 用户注册的生命周期钩子，与调度器的各个设置共享
+    hooks: Arc<Mutex<Vec<Arc<dyn JobHooks>>>>,
+    /// 跳过重叠执行或超时时从中读取已发送的交易
+    state: Option<Arc<StateStore>>,
+    /// 单次执行的超时时间
+    timeout: Option<Duration>,
+    /// 超时时发送通知
+    notifier: Option<Notifier>,
+}
+
+impl RunSettings {
+    /// 内置的执行记录和超时通知钩子，以及用户注册的钩子
+    fn hooks(&self) -> Vec<Arc<dyn JobHooks>> {
+        let mut hooks: Vec<Arc<dyn JobHooks>> = vec![Arc::new(HistoryHook::new(
+            self.history.clone(),
+            self.state.clone(),
+        ))];
+        if let Some(notifier) = &self.notifier {
+            hooks.push(Arc::new(NotificationHook::new(notifier.clone())));
+        }
+        hooks.extend(self.hooks.lock().unwrap().iter().cloned());
+        hooks
+    }
+}
+
+/// 每日任务的执行锁，记录进行中的执行，跳过重叠执行时说明原因
+struct RunLock {
+    lock: tokio::sync::Mutex<()>,
+    /// 持有锁的执行名称和开始时间
+    current: Mutex<Option<(String, DateTime<Utc>)>>,
+    /// 调度器正在关闭，不再开始新的执行
+    shutting_down: AtomicBool,
+    settings: RunSettings,
+}
+
+impl RunLock {
+    fn new(settings: RunSettings) -> Self {
+        Self {
+            lock: tokio::sync::Mutex::new(()),
+            current: Mutex::new(None),
+            shutting_down: AtomicBool::new(false),
+            settings,
+        }
+    }
+
+    /// 持有执行锁时运行 `run`，上一次执行尚未结束时跳过；超时后返回 `JobTimeout` 并释放锁
+    ///
+    /// 每次调用都会调用生命周期钩子：跳过时 `on_skip`，否则 `on_start` 后按结果调用
+    /// `on_success`、`on_failure` 或 `on_skip`（分发流程按配置跳过）。
+    async fn run<Fut>(
+        &self,
+        name: &str,
+        trigger: Trigger,
+        timezone: ScheduleTimezone,
+        run: Fut,
+    ) -> Result<()>
+    where
+        Fut: Future<Output = Result<Vec<DistributionResult>>>,
+    {
+        if self.shutting_down.load(Ordering::SeqCst) {
+            info!("{}已跳过: 调度器正在关闭", name);
+            self.skip(RunContext::new(name, trigger), SkipReason::ShuttingDown)
+                .await;
+            return Ok(());
+        }
+        let Ok(_guard) = self.lock.try_lock() else {
+            self.log_skipped(name, timezone);
+            self.skip(RunContext::new(name, trigger), SkipReason::Overlapping)
+                .await;
+            return Ok(());
+        };
+        let ctx = RunContext::new(name, trigger);
+        *self.current.lock().unwrap() = Some((name.to_string(), ctx.started_at));
+
+        let hooks = self.settings.hooks();
+        for hook in &hooks {
+            guarded("on_start", hook.on_start(&ctx)).await;
+        }
+        let result = self.run_with_timeout(name, ctx.started_at, run).await;
+        match &result {
+            Ok(results) => {
+                for hook in &hooks {
+                    guarded("on_success", hook.on_success(&ctx, results)).await;
+                }
+            }
+            Err(e) if e.is_skipped() => {
+                let reason = SkipReason::Distribution(e.to_string());
+                for hook in &hooks {
+                    guarded("on_skip", hook.on_skip(&ctx, &reason)).await;
+                }
+            }
+            Err(e) => {
+                for hook in &hooks {
+                    guarded("on_failure", hook.on_failure(&ctx, e)).await;
+                }
+            }
+        }
+        result.map(|_| ())
+    }
+
+    /// 没有获取执行锁就跳过的执行，调用 `on_skip` 钩子
+    async fn skip(&self, ctx: RunContext, reason: SkipReason) {
+        for hook in self.settings.hooks() {
+            guarded("on_skip", hook.on_skip(&ctx, &reason)).await;
+        }
+    }
+
+    async fn run_with_timeout<Fut>(
+        &self,
+        name: &str,
+        started_at: DateTime<Utc>,
+        run: Fut,
+    ) -> Result<Vec<DistributionResult>>
+    where
+        Fut: Future<Output = Result<Vec<DistributionResult>>>,
+    {
+        let Some(timeout) = self.settings.timeout else {
+            return run.await;
+        };
+        match tokio::time::timeout(timeout, run).await {
+            Ok(result) => result,
+            Err(_) => {
+                let err = DistributorError::JobTimeout {
+                    name: name.to_string(),
+                    elapsed: (Utc::now() - started_at).to_std().unwrap_or(timeout),
+                    tx_hashes: self.pending_since(started_at),
+                };
+                error!("{}，已释放执行锁，已发送的交易由下次运行恢复", err);
+                Err(err)
+            }
+        }
+    }
+
+    /// 等待进行中的执行结束，最多等待 `grace`，期间每10秒输出一次进度
+    async fn wait_idle(&self, grace: Duration, timezone: ScheduleTimezone) {
+        if self.lock.try_lock().is_ok() {
+            return;
+        }
+        let Some((name, started_at)) = self.current.lock().unwrap().clone() else {
+            return;
+        };
+        info!(
+            "等待进行中的{} (开始于 {}) 结束，最多等待 {} 秒...",
+            name,
+            timezone.format(started_at),
+            grace.as_secs()
+        );
+
+        let acquire = self.lock.lock();
+        tokio::pin!(acquire);
+        let deadline = tokio::time::sleep(grace);
+        tokio::pin!(deadline);
+        let mut progress = tokio::time::interval(SHUTDOWN_PROGRESS_INTERVAL);
+        progress.tick().await;
+        let waiting_since = std::time::Instant::now();
+        loop {
+            tokio::select! {
+                _ = &mut acquire => {
+                    info!("{}已结束", name);
+                    return;
+                }
+                _ = &mut deadline => {
+                    let tx_hashes = self.pending_since(started_at);
+                    warn!(
+                        "等待{}超时，直接关闭；已发送的交易 {:?} 已记录在状态文件中，下次启动后继续确认",
+                        name, tx_hashes
+                    );
+                    return;
+                }
+                _ = progress.tick() => {
+                    info!("仍在等待{}结束，已等待 {} 秒", name, waiting_since.elapsed().as_secs());
+                }
+            }
+        }
+    }
+
+    /// `since` 之后发送、尚未确认的交易
+    fn pending_since(&self, since: DateTime<Utc>) -> Vec<H256> {
+        self.settings
+            .state
+            .iter()
+            .flat_map(|state| state.pending_records())
+            .filter(|record| record.timestamp >= since)
+            .map(|record| record.tx_hash)
+            .collect()
+    }
+
+    fn log_skipped(&self, name: &str, timezone: ScheduleTimezone) {
+        let Some((previous, started_at)) = self.current.lock().unwrap().clone() else {
+            warn!("{}已跳过: previous run still in progress", name);
+            return;
+        };
+        let tx_hashes = self.pending_since(started_at);
+        let tx = if tx_hashes.is_empty() {
+            "尚未发送交易".to_string()
         } else {
-            let tomorrow = today.succ_opt().unwrap();
-            tomorrow.and_hms_opt(6, 25, 0).unwrap().and_local_timezone(Local).unwrap()
+            format!(
+                "tx {}",
+                tx_hashes
+                    .iter()
+                    .map(|hash| format!("{:?}", hash))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        };
+        warn!(
+            "{}已跳过: previous run still in progress ({}开始于 {}，{})",
+            name,
+            previous,
+            timezone.format(started_at),
+            tx
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::history::ExecutionOutcome;
+    use crate::state::{DistributionRecord, RecordStatus, Trigger};
+    use crate::test_utils::{in_timezone, init_test_tracing};
+    use ethers::types::Address;
+    use std::sync::atomic::AtomicUsize;
+    use tokio::sync::{mpsc, oneshot};
+
+    #[tokio::test]
+    async fn overlapping_run_is_skipped_while_first_is_in_progress() {
+        init_test_tracing();
+        let lock = RunLock::new(RunSettings::default());
+        let runs = AtomicUsize::new(0);
+        let (started_tx, started_rx) = oneshot::channel();
+        let (release_tx, release_rx) = oneshot::channel::<()>();
+
+        let first = lock.run("第一次", Trigger::Cron, ScheduleTimezone::Utc, async {
+            runs.fetch_add(1, Ordering::SeqCst);
+            let _ = started_tx.send(());
+            let _ = release_rx.await;
+            Ok(Vec::new())
+        });
+        let second = async {
+            started_rx.await.unwrap();
+            let result = lock
+                .run("第二次", Trigger::Manual, ScheduleTimezone::Utc, async {
+                    runs.fetch_add(1, Ordering::SeqCst);
+                    Ok(Vec::new())
+                })
+                .await;
+            let _ = release_tx.send(());
+            result
+        };
+        let (first, second) = tokio::join!(first, second);
+
+        assert!(first.is_ok());
+        assert!(second.is_ok());
+        assert_eq!(runs.load(Ordering::SeqCst), 1);
+
+        let history = lock.settings.history.snapshot();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].trigger, Trigger::Manual);
+        assert_eq!(history[0].outcome, ExecutionOutcome::Skipped);
+        assert_eq!(
+            history[0].error.as_deref(),
+            Some("previous run still in progress")
+        );
+        assert_eq!(history[1].trigger, Trigger::Cron);
+        assert_eq!(history[1].outcome, ExecutionOutcome::Success);
+    }
+
+    #[tokio::test]
+    async fn lock_is_released_after_run_finishes() {
+        init_test_tracing();
+        let lock = RunLock::new(RunSettings::default());
+        let runs = AtomicUsize::new(0);
+
+        for _ in 0..2 {
+            lock.run("每日任务", Trigger::Cron, ScheduleTimezone::Utc, async {
+                runs.fetch_add(1, Ordering::SeqCst);
+                Ok(Vec::new())
+            })
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn hung_run_times_out_and_releases_lock() {
+        init_test_tracing();
+        let path =
+            std::env::temp_dir().join(format!("job-timeout-state-{}.json", std::process::id()));
+        let state = Arc::new(StateStore::load(&path).unwrap());
+        let lock = RunLock::new(RunSettings {
+            state: Some(state.clone()),
+            timeout: Some(Duration::from_millis(50)),
+            ..RunSettings::default()
+        });
+        let tx_hash = H256::repeat_byte(0xab);
+
+        let result = lock
+            .run("每日任务", Trigger::Cron, ScheduleTimezone::Utc, async {
+                // 交易已广播后节点连接挂起
+                state
+                    .record_attempt(
+                        Address::zero(),
+                        DistributionRecord {
+                            tx_hash,
+                            nonce: None,
+                            timestamp: Utc::now(),
+                            status: RecordStatus::Pending,
+                            trigger: Trigger::Cron,
+                            block_hash: None,
+                            verified: None,
+                            gas_used: None,
+                            fee: None,
+                        },
+                    )
+                    .unwrap();
+                std::future::pending::<Result<Vec<DistributionResult>>>().await
+            })
+            .await;
+        let _ = std::fs::remove_file(&path);
+
+        match result {
+            Err(DistributorError::JobTimeout { tx_hashes, .. }) => {
+                assert_eq!(tx_hashes, vec![tx_hash])
+            }
+            other => panic!("应返回任务超时错误，实际: {:?}", other),
+        }
+        let history = lock.settings.history.snapshot();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].outcome, ExecutionOutcome::TimedOut);
+
+        // 超时后执行锁已释放，下一次执行正常进行
+        let next = lock
+            .run("每日任务", Trigger::Cron, ScheduleTimezone::Utc, async {
+                Ok(Vec::new())
+            })
+            .await;
+        assert!(next.is_ok());
+        let history = lock.settings.history.snapshot();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[1].outcome, ExecutionOutcome::Success);
+    }
+
+    fn utc(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+    }
+
+    /// `from` 到 `to` 之间按策略在本地时区触发的时间
+    fn local_fires(cron: &str, policy: DstPolicy, from: &str, to: &str) -> Vec<DateTime<Utc>> {
+        let (from, to) = (utc(from), utc(to));
+        CronSchedule::new(cron, ScheduleTimezone::Local)
+            .unwrap()
+            .with_dst_policy(policy)
+            .upcoming(from)
+            .take_while(|time| *time < to)
+            .collect()
+    }
+
+    #[test]
+    fn spring_forward_fires_once_unless_skipped() {
+        // 2024-03-10 02:00 EST 拨快到 03:00 EDT，当天没有 02:30
+        let fires = |policy| {
+            in_timezone("America/New_York", move || {
+                local_fires(
+                    "0 30 2 * * *",
+                    policy,
+                    "2024-03-09T12:00:00Z",
+                    "2024-03-11T12:00:00Z",
+                )
+            })
+        };
+
+        // 顺延到 03:30 EDT
+        let shifted = vec![utc("2024-03-10T07:30:00Z"), utc("2024-03-11T06:30:00Z")];
+        assert_eq!(fires(DstPolicy::Earliest), shifted);
+        assert_eq!(fires(DstPolicy::Latest), shifted);
+        assert_eq!(fires(DstPolicy::Skip), vec![utc("2024-03-11T06:30:00Z")]);
+    }
+
+    #[test]
+    fn fall_back_never_fires_twice() {
+        // 2024-11-03 02:00 EDT 拨慢到 01:00 EST，01:30 出现两次
+        let fires = |policy| {
+            in_timezone("America/New_York", move || {
+                local_fires(
+                    "0 30 1 * * *",
+                    policy,
+                    "2024-11-02T12:00:00Z",
+                    "2024-11-04T12:00:00Z",
+                )
+            })
+        };
+
+        assert_eq!(
+            fires(DstPolicy::Earliest),
+            vec![utc("2024-11-03T05:30:00Z"), utc("2024-11-04T06:30:00Z")]
+        );
+        assert_eq!(
+            fires(DstPolicy::Latest),
+            vec![utc("2024-11-03T06:30:00Z"), utc("2024-11-04T06:30:00Z")]
+        );
+        assert_eq!(fires(DstPolicy::Skip), vec![utc("2024-11-04T06:30:00Z")]);
+    }
+
+    #[test]
+    fn utc_schedule_ignores_dst_policy() {
+        let fires = in_timezone("America/New_York", || {
+            CronSchedule::new("0 30 2 * * *", ScheduleTimezone::Utc)
+                .unwrap()
+                .with_dst_policy(DstPolicy::Skip)
+                .upcoming(utc("2024-03-09T12:00:00Z"))
+                .take(2)
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(
+            fires,
+            vec![utc("2024-03-10T02:30:00Z"), utc("2024-03-11T02:30:00Z")]
+        );
+    }
+
+    #[tokio::test]
+    async fn upcoming_runs_cross_month_boundary() {
+        init_test_tracing();
+        let scheduler = DailyScheduler::new()
+            .await
+            .unwrap()
+            .with_schedule(CronSchedule::daily(ScheduleTimezone::Utc));
+        assert!(scheduler.upcoming_runs(3).is_empty());
+        scheduler.add_daily_job(|| async { Ok(()) }).await.unwrap();
+
+        assert_eq!(
+            scheduler.upcoming_runs_after(utc("2024-01-30T12:00:00Z"), 4),
+            vec![
+                utc("2024-01-31T06:25:00Z"),
+                utc("2024-02-01T06:25:00Z"),
+                utc("2024-02-02T06:25:00Z"),
+                utc("2024-02-03T06:25:00Z"),
+            ]
+        );
+        // 闰年2月最后一天之后是3月1日
+        assert_eq!(
+            scheduler.upcoming_runs_after(utc("2024-02-28T12:00:00Z"), 2),
+            vec![utc("2024-02-29T06:25:00Z"), utc("2024-03-01T06:25:00Z")]
+        );
+    }
+
+    #[test]
+    fn month_end_cron_skips_short_months() {
+        let schedule = CronSchedule::new("0 0 0 31 * *", ScheduleTimezone::Utc).unwrap();
+
+        assert_eq!(
+            schedule
+                .upcoming(utc("2024-01-31T01:00:00Z"))
+                .take(3)
+                .collect::<Vec<_>>(),
+            vec![
+                utc("2024-03-31T00:00:00Z"),
+                utc("2024-05-31T00:00:00Z"),
+                utc("2024-07-31T00:00:00Z"),
+            ]
+        );
+    }
+
+    /// 每次触发时把 `label` 发送到 `fired` 的任务
+    fn labelled_task(
+        label: &'static str,
+        fired: mpsc::UnboundedSender<&'static str>,
+    ) -> impl Fn() -> std::future::Ready<Result<()>> + Send + Sync + 'static {
+        move || {
+            let _ = fired.send(label);
+            std::future::ready(Ok(()))
+        }
+    }
+
+    /// 等待下一次触发，最多等待5秒
+    async fn next_fire(fired: &mut mpsc::UnboundedReceiver<&'static str>) -> &'static str {
+        tokio::time::timeout(Duration::from_secs(5), fired.recv())
+            .await
+            .expect("5秒内没有任务触发")
+            .unwrap()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn removed_job_never_fires() {
+        init_test_tracing();
+        let scheduler = DailyScheduler::new().await.unwrap();
+        let (fired_tx, mut fired) = mpsc::unbounded_channel();
+        let removed = scheduler
+            .add_job(
+                "已移除",
+                "* * * * * *",
+                labelled_task("removed", fired_tx.clone()),
+            )
+            .await
+            .unwrap();
+        scheduler
+            .add_job("保留", "* * * * * *", labelled_task("kept", fired_tx))
+            .await
+            .unwrap();
+        scheduler.remove_job(&removed).await.unwrap();
+        assert!(scheduler.next_run_for(&removed).await.is_none());
+        assert_eq!(scheduler.list_jobs().await.len(), 1);
+
+        scheduler.start().await.unwrap();
+        // 保留的任务触发两次，说明调度器至少经过了两个触发时间
+        for _ in 0..2 {
+            assert_eq!(next_fire(&mut fired).await, "kept");
+        }
+        scheduler.shutdown().await.unwrap();
+        while let Ok(label) = fired.try_recv() {
+            assert_eq!(label, "kept");
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn replaced_job_never_runs_alongside_new_one() {
+        init_test_tracing();
+        let scheduler = DailyScheduler::new().await.unwrap();
+        let (fired_tx, mut fired) = mpsc::unbounded_channel();
+        let old = scheduler
+            .add_job(
+                "任务",
+                "* * * * * *",
+                labelled_task("old", fired_tx.clone()),
+            )
+            .await
+            .unwrap();
+        scheduler.start().await.unwrap();
+        assert_eq!(next_fire(&mut fired).await, "old");
+
+        let new = scheduler
+            .replace_job(&old, "* * * * * *", labelled_task("new", fired_tx))
+            .await
+            .unwrap();
+        assert_ne!(new.id(), old.id());
+        assert_eq!(scheduler.list_jobs().await.len(), 1);
+
+        // 替换前已经开始的触发可能还在路上，之后只会有新任务的触发
+        let mut labels = Vec::new();
+        while labels.iter().filter(|label| **label == "new").count() < 2 {
+            labels.push(next_fire(&mut fired).await);
+        }
+        scheduler.shutdown().await.unwrap();
+        while let Ok(label) = fired.try_recv() {
+            labels.push(label);
+        }
+        let first_new = labels.iter().position(|label| *label == "new").unwrap();
+        assert!(
+            labels[first_new..].iter().all(|label| *label == "new"),
+            "新旧任务交替触发: {:?}",
+            labels
+        );
+    }
+
+    #[tokio::test]
+    async fn replace_with_invalid_cron_keeps_original_job() {
+        init_test_tracing();
+        let scheduler = DailyScheduler::new().await.unwrap();
+        let (fired_tx, _fired) = mpsc::unbounded_channel();
+        let handle = scheduler
+            .add_job(
+                "任务",
+                "0 0 * * * *",
+                labelled_task("old", fired_tx.clone()),
+            )
+            .await
+            .unwrap();
+
+        assert!(scheduler
+            .replace_job(&handle, "0 0 * * *", labelled_task("new", fired_tx))
+            .await
+            .is_err());
+
+        let jobs = scheduler.list_jobs().await;
+        assert_eq!(jobs.len(), 1);
+        assert_eq!(jobs[0].id, handle.id());
+        assert_eq!(jobs[0].cron, "0 0 * * * *");
+    }
+
+    #[tokio::test]
+    async fn next_run_returns_earliest_across_jobs() {
+        init_test_tracing();
+        let scheduler = DailyScheduler::new().await.unwrap();
+        assert!(scheduler.next_run().await.is_none());
+        let (fired_tx, _fired) = mpsc::unbounded_channel();
+        // 先添加触发较晚的任务，确认返回的不是第一个任务的时间
+        let yearly = scheduler
+            .add_job(
+                "每年",
+                "0 0 0 1 1 *",
+                labelled_task("yearly", fired_tx.clone()),
+            )
+            .await
+            .unwrap();
+        let minutely = scheduler
+            .add_job("每分钟", "0 * * * * *", labelled_task("minutely", fired_tx))
+            .await
+            .unwrap();
+
+        let yearly_next = scheduler.next_run_for(&yearly).await.unwrap();
+        let minutely_next = scheduler.next_run_for(&minutely).await.unwrap();
+        assert!(minutely_next < yearly_next);
+        assert!(minutely_next - Utc::now() <= chrono::Duration::minutes(1));
+        assert_eq!(scheduler.next_run().await, Some(minutely_next));
+
+        scheduler.remove_job(&minutely).await.unwrap();
+        assert_eq!(scheduler.next_run().await, Some(yearly_next));
+    }
+
+    /// 在后台开始一次执行，返回执行的句柄；`body` 开始执行后才返回
+    async fn spawn_run<Fut>(
+        scheduler: &Arc<DailyScheduler>,
+        body: Fut,
+    ) -> tokio::task::JoinHandle<Result<()>>
+    where
+        Fut: Future<Output = Result<()>> + Send + 'static,
+    {
+        let (started_tx, started_rx) = oneshot::channel();
+        let scheduler = scheduler.clone();
+        let handle = tokio::spawn(async move {
+            scheduler
+                .run_exclusive("慢任务", Trigger::Manual, async move {
+                    let _ = started_tx.send(());
+                    body.await
+                })
+                .await
+        });
+        started_rx.await.unwrap();
+        handle
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shutdown_waits_for_in_flight_run_within_grace() {
+        init_test_tracing();
+        let scheduler = Arc::new(
+            DailyScheduler::new()
+                .await
+                .unwrap()
+                .with_shutdown_grace(Duration::from_secs(10)),
+        );
+        let finished = Arc::new(AtomicBool::new(false));
+        let run = spawn_run(&scheduler, {
+            let finished = finished.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(300)).await;
+                finished.store(true, Ordering::SeqCst);
+                Ok(())
+            }
+        })
+        .await;
+
+        scheduler.shutdown().await.unwrap();
+
+        assert!(
+            finished.load(Ordering::SeqCst),
+            "关闭应等待进行中的执行结束"
+        );
+        assert!(run.await.unwrap().is_ok());
+
+        // 关闭后不再开始新的执行
+        let started = Arc::new(AtomicBool::new(false));
+        let flag = started.clone();
+        scheduler
+            .run_exclusive("关闭后", Trigger::Manual, async move {
+                flag.store(true, Ordering::SeqCst);
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert!(!started.load(Ordering::SeqCst));
+        let history = scheduler.history();
+        assert_eq!(
+            history.last().and_then(|record| record.error.as_deref()),
+            Some("scheduler shutting down")
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn shutdown_gives_up_after_grace_period() {
+        init_test_tracing();
+        let grace = Duration::from_millis(200);
+        let scheduler = Arc::new(
+            DailyScheduler::new()
+                .await
+                .unwrap()
+                .with_shutdown_grace(grace),
+        );
+        let run = spawn_run(&scheduler, std::future::pending()).await;
+
+        let started = std::time::Instant::now();
+        scheduler.shutdown().await.unwrap();
+        let waited = started.elapsed();
+
+        assert!(waited >= grace, "关闭只等待了 {:?}", waited);
+        assert!(waited < Duration::from_secs(5), "关闭等待了 {:?}", waited);
+        assert!(!run.is_finished());
+        run.abort();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn logged_next_run_matches_first_fire() {
+        init_test_tracing();
+        let schedule = CronSchedule::new("*/2 * * * * *", ScheduleTimezone::Utc).unwrap();
+        let scheduler = DailyScheduler::new().await.unwrap().with_schedule(schedule);
+        let (fired_tx, mut fired) = mpsc::unbounded_channel();
+        scheduler
+            .add_daily_job(move || {
+                let _ = fired_tx.send(Utc::now());
+                async { Ok(()) }
+            })
+            .await
+            .unwrap();
+
+        // 启动日志输出的就是这个时间
+        let logged = scheduler.upcoming_runs(1)[0];
+        scheduler.start().await.unwrap();
+        let first_fire = tokio::time::timeout(Duration::from_secs(5), fired.recv())
+            .await
+            .expect("5秒内每日任务没有触发")
+            .unwrap();
+        scheduler.shutdown().await.unwrap();
+
+        let drift = (first_fire - logged).num_milliseconds().abs();
+        assert!(
+            drift < 1_000,
+            "日志中的下次执行时间 {} 与实际触发时间 {} 相差 {} 毫秒",
+            logged,
+            first_fire,
+            drift
+        );
+    }
+
+    const WEEKDAYS: [Weekday; 5] = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ];
+
+    const ALL_DAYS: [Weekday; 7] = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+        Weekday::Sat,
+        Weekday::Sun,
+    ];
+
+    fn daily_runs(expr: &str, after: &str, n: usize) -> Vec<DateTime<Utc>> {
+        CronSchedule::new(expr, ScheduleTimezone::Utc)
+            .unwrap()
+            .upcoming(utc(after))
+            .take(n)
+            .collect()
+    }
+
+    #[test]
+    fn cron_with_days_wraps_from_sunday_to_monday() {
+        let weekdays = cron_with_days(DEFAULT_DAILY_CRON, &WEEKDAYS);
+        assert_eq!(weekdays, "0 25 6 * * Mon,Tue,Wed,Thu,Fri");
+        // 2024-06-07是星期五，周末不触发
+        assert_eq!(
+            daily_runs(&weekdays, "2024-06-07T12:00:00Z", 2),
+            vec![utc("2024-06-10T06:25:00Z"), utc("2024-06-11T06:25:00Z")]
+        );
+        assert_eq!(
+            daily_runs(&weekdays, "2024-06-09T23:59:59Z", 1),
+            vec![utc("2024-06-10T06:25:00Z")]
+        );
+
+        let sunday_monday = cron_with_days(DEFAULT_DAILY_CRON, &[Weekday::Sun, Weekday::Mon]);
+        assert_eq!(
+            daily_runs(&sunday_monday, "2024-06-08T12:00:00Z", 3),
+            vec![
+                utc("2024-06-09T06:25:00Z"),
+                utc("2024-06-10T06:25:00Z"),
+                utc("2024-06-16T06:25:00Z"),
+            ]
+        );
+    }
+
+    #[test]
+    fn cron_with_all_days_fires_every_day() {
+        let every_day = cron_with_days(DEFAULT_DAILY_CRON, &ALL_DAYS);
+        let expected: Vec<_> = (0..7)
+            .map(|day| utc("2024-06-07T06:25:00Z") + chrono::Duration::days(day + 1))
+            .collect();
+
+        assert_eq!(daily_runs(&every_day, "2024-06-07T12:00:00Z", 7), expected);
+        assert_eq!(
+            daily_runs(DEFAULT_DAILY_CRON, "2024-06-07T12:00:00Z", 7),
+            expected
+        );
+    }
+
+    #[test]
+    fn calendar_days_skip_weekend_and_allow_monday() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 6, d).unwrap();
+        let calendar = ScheduleCalendar::default().with_days(Some(WEEKDAYS.to_vec()));
+
+        assert!(matches!(
+            calendar.skip_reason(date(8)),
+            Some(SkipReason::DayDisabled {
+                weekday: Weekday::Sat,
+                ..
+            })
+        ));
+        assert!(matches!(
+            calendar.skip_reason(date(9)),
+            Some(SkipReason::DayDisabled {
+                weekday: Weekday::Sun,
+                ..
+            })
+        ));
+        assert_eq!(calendar.skip_reason(date(10)), None);
+
+        // 默认每天都允许，列出全部7天与不限制相同
+        for calendar in [
+            ScheduleCalendar::default(),
+            ScheduleCalendar::default().with_days(None),
+            ScheduleCalendar::default().with_days(Some(ALL_DAYS.to_vec())),
+        ] {
+            for day in 3..=9 {
+                assert_eq!(calendar.skip_reason(date(day)), None);
+            }
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn upcoming_runs_skip_disabled_days_with_custom_cron() {
+        init_test_tracing();
+        let scheduler = DailyScheduler::new()
+            .await
+            .unwrap()
+            .with_calendar(ScheduleCalendar::default().with_days(Some(WEEKDAYS.to_vec())))
+            .with_schedule(CronSchedule::daily(ScheduleTimezone::Utc));
+        scheduler.add_daily_job(|| async { Ok(()) }).await.unwrap();
+
+        assert_eq!(
+            scheduler.upcoming_runs_after(utc("2024-06-07T12:00:00Z"), 2),
+            vec![utc("2024-06-10T06:25:00Z"), utc("2024-06-11T06:25:00Z")]
+        );
+    }
+
+    /// 节假日为2025-01-01、cron为 `expr` 时，`after` 之后前 `n` 次执行和对应的调度时区日期
+    fn runs_around_new_year(
+        expr: &'static str,
+        timezone: ScheduleTimezone,
+        n: usize,
+    ) -> Vec<(DateTime<Utc>, NaiveDate)> {
+        init_test_tracing();
+        let holiday = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        tokio::runtime::Runtime::new()
+            .unwrap()
+            .block_on(async move {
+                let scheduler = DailyScheduler::new()
+                    .await
+                    .unwrap()
+                    .with_calendar(
+                        ScheduleCalendar::default().with_holidays(BTreeSet::from([holiday])),
+                    )
+                    .with_schedule(CronSchedule::new(expr, timezone).unwrap());
+                scheduler.add_daily_job(|| async { Ok(()) }).await.unwrap();
+                scheduler
+                    .upcoming_runs_after(utc("2024-12-30T12:00:00Z"), n)
+                    .into_iter()
+                    .map(|time| (time, timezone.date_of(time)))
+                    .collect()
+            })
+    }
+
+    #[test]
+    fn holiday_is_judged_in_schedule_timezone_across_utc_date_boundary() {
+        let holiday = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let calendar = ScheduleCalendar::default().with_holidays(BTreeSet::from([holiday]));
+        // 东京08:00是UTC前一天23:00：UTC的12月31日已是东京的元旦
+        let new_year_morning = utc("2024-12-31T23:00:00Z");
+        let local_date = in_timezone("Asia/Tokyo", move || {
+            ScheduleTimezone::Local.date_of(new_year_morning)
+        });
+
+        assert_eq!(local_date, holiday);
+        assert_eq!(
+            calendar.skip_reason(local_date),
+            Some(SkipReason::Holiday(holiday))
+        );
+        assert_eq!(
+            calendar.skip_reason(ScheduleTimezone::Utc.date_of(new_year_morning)),
+            None
+        );
+
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let local = in_timezone("Asia/Tokyo", || {
+            runs_around_new_year("0 0 8 * * *", ScheduleTimezone::Local, 2)
+        });
+        assert_eq!(
+            local,
+            vec![
+                (utc("2024-12-30T23:00:00Z"), date(2024, 12, 31)),
+                (utc("2025-01-01T23:00:00Z"), date(2025, 1, 2)),
+            ]
+        );
+
+        // 同样的UTC触发时间按UTC判断时，跳过的是UTC元旦那一次
+        let utc_runs = in_timezone("Asia/Tokyo", || {
+            runs_around_new_year("0 0 23 * * *", ScheduleTimezone::Utc, 3)
+        });
+        assert_eq!(
+            utc_runs,
+            vec![
+                (utc("2024-12-30T23:00:00Z"), date(2024, 12, 30)),
+                (utc("2024-12-31T23:00:00Z"), date(2024, 12, 31)),
+                (utc("2025-01-02T23:00:00Z"), date(2025, 1, 2)),
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_per_day_follows_cron_frequency() {
+        let after = Utc.with_ymd_and_hms(2024, 3, 4, 0, 0, 0).unwrap();
+        let runs_per_day = |expr: &str| {
+            CronSchedule::new(expr, ScheduleTimezone::Utc)
+                .unwrap()
+                .runs_per_day(after)
+        };
+
+        assert_eq!(runs_per_day("0 0 9 * * *"), 1.0);
+        assert_eq!(runs_per_day("0 0 * * * *"), 24.0);
+        assert_eq!(
+            runs_per_day(&cron_with_days("0 0 9 * * *", &WEEKDAYS)),
+            5.0 / 7.0
+        );
+    }
+
+    #[test]
+    fn utc_schedule_is_identical_on_any_host_timezone() {
+        let after = utc("2024-06-01T23:30:00Z");
+        let evaluate = move |tz| {
+            in_timezone(tz, move || {
+                let schedule = CronSchedule::daily(ScheduleTimezone::Utc);
+                let next = schedule.next_after(after).unwrap();
+                (
+                    next,
+                    ScheduleTimezone::Utc.date_of(next),
+                    ScheduleTimezone::Utc.format(next),
+                )
+            })
+        };
+
+        let tokyo = evaluate("Asia/Tokyo");
+        assert_eq!(tokyo, evaluate("America/New_York"));
+        assert_eq!(tokyo, evaluate("UTC"));
+        assert_eq!(tokyo.0, utc("2024-06-02T06:25:00Z"));
+        assert_eq!(tokyo.2, "2024-06-02 06:25:00 UTC");
+
+        // 显式选择本地时区时才随主机时区变化
+        let local = move |tz| {
+            in_timezone(tz, move || {
+                CronSchedule::daily(ScheduleTimezone::Local).next_after(after)
+            })
+        };
+        assert_eq!(local("Asia/Tokyo"), Some(utc("2024-06-02T21:25:00Z")));
+        assert_eq!(local("America/New_York"), Some(utc("2024-06-02T10:25:00Z")));
+    }
+
+    #[test]
+    fn local_schedule_crosses_month_boundary_in_schedule_timezone() {
+        // 东京08:00是UTC前一天23:00，本地5月1日的触发在UTC仍是4月30日
+        let runs = in_timezone("Asia/Tokyo", || {
+            CronSchedule::new("0 0 8 * * *", ScheduleTimezone::Local)
+                .unwrap()
+                .upcoming(utc("2024-04-29T12:00:00Z"))
+                .take(3)
+                .map(|time| (time, ScheduleTimezone::Local.date_of(time)))
+                .collect::<Vec<_>>()
+        });
+
+        let date = |m, d| NaiveDate::from_ymd_opt(2024, m, d).unwrap();
+        assert_eq!(
+            runs,
+            vec![
+                (utc("2024-04-29T23:00:00Z"), date(4, 30)),
+                (utc("2024-04-30T23:00:00Z"), date(5, 1)),
+                (utc("2024-05-01T23:00:00Z"), date(5, 2)),
+            ]
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use crate::config::SignerConfig;
+use crate::error::{DistributorError, Result};
+use async_trait::async_trait;
+use ethers::signers::{LocalWallet, Signer, WalletError};
+use ethers::types::transaction::eip2718::TypedTransaction;
+use ethers::types::transaction::eip712::Eip712;
+use ethers::types::{Address, Signature};
+use thiserror::Error;
+
+#[cfg(feature = "aws-kms")]
+use ethers::signers::{AwsSigner, AwsSignerError};
+
+/// 签名交易失败
+#[derive(Debug, Error)]
+pub enum SignerError {
+    #[error(transparent)]
+    Wallet(#[from] WalletError),
+    /// KMS返回的错误较大，装箱避免撑大所有 `Result`
+    #[cfg(feature = "aws-kms")]
+    #[error("AWS KMS签名失败: {0}")]
+    AwsKms(Box<AwsSignerError>),
+}
+
+#[cfg(feature = "aws-kms")]
+impl From<AwsSignerError> for SignerError {
+    fn from(err: AwsSignerError) -> Self {
+        SignerError::AwsKms(Box::new(err))
+    }
+}
+
+/// 分发交易使用的签名器，按SIGNER_TYPE选择本地私钥或AWS KMS
+#[derive(Debug)]
+pub enum DistributorSigner {
+    Local(LocalWallet),
+    #[cfg(feature = "aws-kms")]
+    AwsKms(AwsSigner),
+}
+
+impl DistributorSigner {
+    /// 按配置创建签名器；使用AWS KMS时会请求KMS获取公钥以确定签名地址
+    pub async fn from_config(config: &SignerConfig, chain_id: u64) -> Result<Self> {
+        match config {
+            SignerConfig::Local { private_key } => Ok(DistributorSigner::Local(
+                private_key.parse::<LocalWallet>()?.with_chain_id(chain_id),
+            )),
+            #[cfg(feature = "aws-kms")]
+            SignerConfig::AwsKms { key_id, region } => {
+                let region = region
+                    .parse::<rusoto_core::Region>()
+                    .map_err(|e| DistributorError::Config(format!("无效的AWS_REGION: {}", e)))?;
+                let kms = rusoto_kms::KmsClient::new(region);
+                let signer = AwsSigner::new(kms, key_id, chain_id)
+                    .await
+                    .map_err(SignerError::from)?;
+                Ok(DistributorSigner::AwsKms(signer))
+            }
+            #[cfg(not(feature = "aws-kms"))]
+            SignerConfig::AwsKms { .. } => Err(DistributorError::Config(
+                "SIGNER_TYPE=aws-kms 需要以 --features aws-kms 编译".to_string(),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl Signer for DistributorSigner {
+    type Error = SignerError;
+
+    async fn sign_message<S: Send + Sync + AsRef<[u8]>>(
+        &self,
+        message: S,
+    ) -> std::result::Result<Signature, Self::Error> {
+        match self {
+            DistributorSigner::Local(wallet) => Ok(wallet.sign_message(message).await?),
+            #[cfg(feature = "aws-kms")]
+            DistributorSigner::AwsKms(signer) => Ok(signer.sign_message(message).await?),
+        }
+    }
+
+    async fn sign_transaction(
+        &self,
+        message: &TypedTransaction,
+    ) -> std::result::Result<Signature, Self::Error> {
+        match self {
+            DistributorSigner::Local(wallet) => Ok(wallet.sign_transaction(message).await?),
+            #[cfg(feature = "aws-kms")]
+            DistributorSigner::AwsKms(signer) => Ok(signer.sign_transaction(message).await?),
+        }
+    }
+
+    async fn sign_typed_data<T: Eip712 + Send + Sync>(
+        &self,
+        payload: &T,
+    ) -> std::result::Result<Signature, Self::Error> {
+        match self {
+            DistributorSigner::Local(wallet) => Ok(wallet.sign_typed_data(payload).await?),
+            #[cfg(feature = "aws-kms")]
+            DistributorSigner::AwsKms(signer) => Ok(signer.sign_typed_data(payload).await?),
+        }
+    }
+
+    fn address(&self) -> Address {
+        match self {
+            DistributorSigner::Local(wallet) => wallet.address(),
+            #[cfg(feature = "aws-kms")]
+            DistributorSigner::AwsKms(signer) => signer.address(),
+        }
+    }
+
+    fn chain_id(&self) -> u64 {
+        match self {
+            DistributorSigner::Local(wallet) => wallet.chain_id(),
+            #[cfg(feature = "aws-kms")]
+            DistributorSigner::AwsKms(signer) => signer.chain_id(),
+        }
+    }
+
+    fn with_chain_id<T: Into<u64>>(self, chain_id: T) -> Self {
+        match self {
+            DistributorSigner::Local(wallet) => {
+                DistributorSigner::Local(wallet.with_chain_id(chain_id))
+            }
+            #[cfg(feature = "aws-kms")]
+            DistributorSigner::AwsKms(signer) => {
+                DistributorSigner::AwsKms(signer.with_chain_id(chain_id))
+            }
+        }
+    }
+}
+
+impl From<LocalWallet> for DistributorSigner {
+    fn from(wallet: LocalWallet) -> Self {
+        DistributorSigner::Local(wallet)
+    }
+}
@@ -0,0 +1,456 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate, Utc};
+use ethers::types::{Address, H256, U256};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tracing::{info, warn};
+
+/// 触发分发的方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Trigger {
+    /// 定时任务按cron触发
+    #[default]
+    Cron,
+    /// 启动时补执行停机期间错过的定时分发
+    Catchup,
+    /// 调度器启动后立即执行一次（RUN_ON_STARTUP 或 --run-now）
+    Startup,
+    /// `schedule-once` 安排在指定时间的一次性执行
+    OneShot,
+    /// 通过 `trigger_now`、SIGUSR1或SIGHUP手动触发
+    Manual,
+    /// 通过 `distribute-now` 命令手动执行
+    #[serde(rename = "manual-cli")]
+    ManualCli,
+    /// 通过管理接口 `POST /distribute` 手动执行
+    #[serde(rename = "manual-api")]
+    ManualApi,
+}
+
+impl Trigger {
+    pub fn is_cron(&self) -> bool {
+        *self == Trigger::Cron
+    }
+}
+
+impl std::fmt::Display for Trigger {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Trigger::Cron => write!(f, "定时"),
+            Trigger::Catchup => write!(f, "补执行"),
+            Trigger::Startup => write!(f, "启动时执行"),
+            Trigger::OneShot => write!(f, "一次性执行"),
+            Trigger::Manual => write!(f, "手动触发"),
+            Trigger::ManualCli => write!(f, "命令行手动执行"),
+            Trigger::ManualApi => write!(f, "管理接口手动执行"),
+        }
+    }
+}
+
+/// 分发交易的状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordStatus {
+    /// 已发送，尚未确认
+    Pending,
+    /// 已上链且执行成功
+    Confirmed,
+    /// 已上链但执行失败，或交易已从节点中消失
+    Failed,
+}
+
+/// 一次分发交易的记录
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DistributionRecord {
+    pub tx_hash: H256,
+    pub nonce: Option<U256>,
+    pub timestamp: DateTime<Utc>,
+    pub status: RecordStatus,
+    /// 触发这次分发的方式，定时触发时不写入
+    #[serde(default, skip_serializing_if = "Trigger::is_cron")]
+    pub trigger: Trigger,
+    /// 确认时收据所在区块的哈希，下次运行时据此判断交易是否因重组离开主链
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_hash: Option<H256>,
+    /// 确认后链上 `lastDistributionTime()` 是否已更新，未开启校验时为空
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verified: Option<bool>,
+    /// 上链后收据中的Gas用量
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_used: Option<U256>,
+    /// 上链后实际支付的交易费用（wei）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fee: Option<U256>,
+}
+
+/// 分批分发的进度，失败后重试时从未完成的批次继续
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchProgress {
+    /// 分发日期（调度时区）
+    pub date: NaiveDate,
+    /// 完整接收地址列表调用数据的哈希，列表变化后旧进度作废
+    pub list_hash: H256,
+    pub batch_size: usize,
+    pub total_batches: usize,
+    /// 以批次序号（从0开始）为键的交易记录
+    pub batches: BTreeMap<usize, DistributionRecord>,
+}
+
+impl BatchProgress {
+    /// 已确认的批次数量
+    pub fn confirmed(&self) -> usize {
+        self.batches
+            .values()
+            .filter(|record| record.status == RecordStatus::Confirmed)
+            .count()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.confirmed() == self.total_batches
+    }
+}
+
+/// 单个合约的分发状态
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ContractState {
+    pub last_attempt: Option<DistributionRecord>,
+    pub last_confirmed: Option<DistributionRecord>,
+    /// 最近一次分批分发的进度
+    pub batch_progress: Option<BatchProgress>,
+    /// 诊断时记录的EIP-1967代理实现合约，用于发现合约升级
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub implementation: Option<ImplementationRecord>,
+}
+
+/// 代理合约的实现合约
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImplementationRecord {
+    pub address: Address,
+    /// 实现合约字节码的keccak256
+    pub code_hash: H256,
+    pub seen_at: DateTime<Utc>,
+}
+
+/// 当天（按调度时区）已上链交易的Gas花费，所有合约共用
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpendRecord {
+    pub date: NaiveDate,
+    /// 累计花费，单位wei
+    pub total: U256,
+    /// 已计入的交易，避免重启后重复计入
+    pub tx_hashes: Vec<H256>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateFile {
+    /// 以合约地址（十六进制字符串）为键
+    contracts: BTreeMap<String, ContractState>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spend: Option<SpendRecord>,
+}
+
+/// 持久化到JSON文件的分发状态，用于进程重启后避免重复发送
+pub struct StateStore {
+    path: PathBuf,
+    state: Mutex<StateFile>,
+}
+
+impl StateStore {
+    /// 加载状态文件，不存在时创建空状态；文件损坏时隔离后从空状态开始
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+
+        let state = match fs::read_to_string(&path) {
+            Ok(content) => match serde_json::from_str::<StateFile>(&content) {
+                Ok(state) => state,
+                Err(e) => {
+                    let quarantined = quarantine(&path)?;
+                    warn!(
+                        "状态文件 {} 已损坏: {}，已隔离到 {}，使用空状态",
+                        path.display(),
+                        e,
+                        quarantined.display()
+                    );
+                    StateFile::default()
+                }
+            },
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                info!("状态文件 {} 不存在，使用空状态", path.display());
+                StateFile::default()
+            }
+            Err(e) => {
+                return Err(e).with_context(|| format!("无法读取状态文件 {}", path.display()))
+            }
+        };
+
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// 读取某个合约的分发状态
+    pub fn contract(&self, address: Address) -> ContractState {
+        self.state
+            .lock()
+            .unwrap()
+            .contracts
+            .get(&key(address))
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// 所有合约中仍处于待确认状态的交易记录（包括分批分发的批次）
+    pub fn pending_records(&self) -> Vec<DistributionRecord> {
+        self.state
+            .lock()
+            .unwrap()
+            .contracts
+            .values()
+            .flat_map(|state| {
+                let batches = state
+                    .batch_progress
+                    .iter()
+                    .flat_map(|progress| progress.batches.values());
+                state.last_attempt.iter().chain(batches)
+            })
+            .filter(|record| record.status == RecordStatus::Pending)
+            .cloned()
+            .collect()
+    }
+
+    /// 记录一次新的发送尝试
+    pub fn record_attempt(&self, address: Address, record: DistributionRecord) -> Result<()> {
+        self.update(address, |state| state.last_attempt = Some(record))
+    }
+
+    /// 更新某笔交易的最终状态，确认成功时同时记为最近一次成功分发
+    pub fn record_status(
+        &self,
+        address: Address,
+        tx_hash: H256,
+        status: RecordStatus,
+    ) -> Result<()> {
+        self.update(address, |state| {
+            let record = match &mut state.last_attempt {
+                Some(record) if record.tx_hash == tx_hash => {
+                    record.status = status;
+                    record.clone()
+                }
+                _ => DistributionRecord {
+                    tx_hash,
+                    nonce: None,
+                    timestamp: Utc::now(),
+                    status,
+                    trigger: Trigger::Cron,
+                    block_hash: None,
+                    verified: None,
+                    gas_used: None,
+                    fee: None,
+                },
+            };
+
+            if status == RecordStatus::Confirmed {
+                state.last_confirmed = Some(record);
+            }
+        })
+    }
+
+    /// 记录已上链交易收据中的Gas用量和交易费用，需要在 `record_status` 之前调用
+    pub fn record_fee(
+        &self,
+        address: Address,
+        tx_hash: H256,
+        gas_used: Option<U256>,
+        fee: Option<U256>,
+    ) -> Result<()> {
+        self.update(address, |state| {
+            let batches = state
+                .batch_progress
+                .iter_mut()
+                .flat_map(|progress| progress.batches.values_mut());
+            for record in state.last_attempt.iter_mut().chain(batches) {
+                if record.tx_hash == tx_hash {
+                    record.gas_used = gas_used;
+                    record.fee = fee;
+                }
+            }
+        })
+    }
+
+    /// 所有合约中 `since` 之后发送的交易记录（包括分批分发的批次）
+    pub fn records_since(&self, since: DateTime<Utc>) -> Vec<DistributionRecord> {
+        self.state
+            .lock()
+            .unwrap()
+            .contracts
+            .values()
+            .flat_map(|state| {
+                let batches = state
+                    .batch_progress
+                    .iter()
+                    .flat_map(|progress| progress.batches.values());
+                state.last_attempt.iter().chain(batches)
+            })
+            .filter(|record| record.timestamp >= since)
+            .cloned()
+            .collect()
+    }
+
+    /// 合约最近一次成功分发的时间，取单笔分发和分批分发中已确认交易的最晚记录时间
+    pub fn last_distribution(&self, address: Address) -> Option<DateTime<Utc>> {
+        let state = self.contract(address);
+        let batches = state
+            .batch_progress
+            .iter()
+            .flat_map(|progress| progress.batches.values())
+            .filter(|record| record.status == RecordStatus::Confirmed);
+        state
+            .last_confirmed
+            .iter()
+            .chain(batches)
+            .map(|record| record.timestamp)
+            .max()
+    }
+
+    /// 保存分批分发的进度
+    pub fn record_batch_progress(&self, address: Address, progress: BatchProgress) -> Result<()> {
+        self.update(address, |state| state.batch_progress = Some(progress))
+    }
+
+    /// 记录最近一次成功分发所在的区块和链上校验结果，供下次运行重新核对
+    pub fn record_verification(
+        &self,
+        address: Address,
+        tx_hash: H256,
+        block_hash: Option<H256>,
+        verified: Option<bool>,
+    ) -> Result<()> {
+        self.update(address, |state| {
+            if let Some(record) = state
+                .last_confirmed
+                .as_mut()
+                .filter(|record| record.tx_hash == tx_hash)
+            {
+                record.block_hash = block_hash;
+                record.verified = verified;
+            }
+        })
+    }
+
+    /// 最近一次成功分发已不在链上（如被重组移除），作废该记录，下次运行重新分发
+    pub fn invalidate_confirmed(&self, address: Address, tx_hash: H256) -> Result<()> {
+        self.update(address, |state| {
+            if state
+                .last_confirmed
+                .as_ref()
+                .is_some_and(|record| record.tx_hash == tx_hash)
+            {
+                state.last_confirmed = None;
+            }
+            if let Some(record) = state
+                .last_attempt
+                .as_mut()
+                .filter(|record| record.tx_hash == tx_hash)
+            {
+                record.status = RecordStatus::Failed;
+            }
+        })
+    }
+
+    /// 记录代理合约当前的实现合约，返回之前记录的实现合约
+    pub fn record_implementation(
+        &self,
+        address: Address,
+        implementation: ImplementationRecord,
+    ) -> Result<Option<ImplementationRecord>> {
+        let mut previous = None;
+        self.update(address, |state| {
+            previous = state.implementation.replace(implementation);
+        })?;
+        Ok(previous)
+    }
+
+    /// `date` 当天已计入的Gas花费（wei），跨天后从0开始
+    pub fn spent_on(&self, date: NaiveDate) -> U256 {
+        self.state
+            .lock()
+            .unwrap()
+            .spend
+            .as_ref()
+            .filter(|spend| spend.date == date)
+            .map(|spend| spend.total)
+            .unwrap_or_default()
+    }
+
+    /// 计入一笔已上链交易的Gas花费，返回当天累计；同一笔交易只计入一次
+    pub fn record_spend(&self, date: NaiveDate, tx_hash: H256, amount: U256) -> Result<U256> {
+        let mut state = self.state.lock().unwrap();
+        let spend = state.spend.get_or_insert_with(SpendRecord::default);
+        if spend.date != date {
+            *spend = SpendRecord {
+                date,
+                ..Default::default()
+            };
+        }
+        if !spend.tx_hashes.contains(&tx_hash) {
+            spend.tx_hashes.push(tx_hash);
+            spend.total = spend.total.saturating_add(amount);
+        }
+        let total = spend.total;
+        self.persist(&state)?;
+        Ok(total)
+    }
+
+    fn update(&self, address: Address, f: impl FnOnce(&mut ContractState)) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        f(state.contracts.entry(key(address)).or_default());
+        self.persist(&state)
+    }
+
+    /// 先写临时文件再重命名，保证状态文件不会写到一半
+    fn persist(&self, state: &StateFile) -> Result<()> {
+        let tmp_path = with_suffix(&self.path, ".tmp");
+        let content = serde_json::to_vec_pretty(state)?;
+
+        let mut file = fs::File::create(&tmp_path)
+            .with_context(|| format!("无法创建临时状态文件 {}", tmp_path.display()))?;
+        file.write_all(&content)?;
+        file.sync_all()?;
+
+        fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("无法写入状态文件 {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+fn key(address: Address) -> String {
+    format!("{:?}", address)
+}
+
+fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+    let mut path = path.as_os_str().to_owned();
+    path.push(suffix);
+    path.into()
+}
+
+/// 把损坏的状态文件改名保存，便于事后排查
+fn quarantine(path: &Path) -> Result<PathBuf> {
+    let quarantined = with_suffix(
+        path,
+        &format!(".corrupt-{}", Utc::now().format("%Y%m%d%H%M%S")),
+    );
+
+    fs::rename(path, &quarantined)
+        .with_context(|| format!("无法隔离损坏的状态文件 {}", path.display()))?;
+    Ok(quarantined)
+}
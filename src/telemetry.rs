@@ -0,0 +1,80 @@
+use crate::config::Config;
+use std::sync::Once;
+
+/// 安装全局日志订阅器，只由二进制程序在启动时调用
+///
+/// 库中的函数不会安装订阅器，作为依赖使用时由调用方自行初始化 `tracing`。
+/// 启用 `otel` 特性且设置了 `OTEL_EXPORTER_OTLP_ENDPOINT`（或 `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`）时，
+/// 同时通过OTLP/HTTP导出链路追踪，分发流程中的 estimate、build、send、confirm 等span会带上
+/// Gas、nonce和交易哈希等属性。已经安装过全局订阅器时返回错误而不是panic。
+pub fn init_tracing(config: &Config) -> anyhow::Result<()> {
+    #[cfg(feature = "otel")]
+    if otlp_endpoint_configured() {
+        return otel::init(config);
+    }
+    #[cfg(not(feature = "otel"))]
+    let _ = config;
+
+    tracing_subscriber::fmt::try_init().map_err(|e| anyhow::anyhow!("初始化日志失败: {}", e))
+}
+
+/// 测试中使用的日志初始化，可以在每个测试开头重复调用
+///
+/// 日志写到测试框架捕获的输出中（`cargo test -- --nocapture` 时显示），只有第一次调用会安装订阅器；
+/// 其他代码已经安装过全局订阅器时忽略，不会panic。
+pub fn init_test_tracing() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| {
+        let _ = tracing_subscriber::fmt().with_test_writer().try_init();
+    });
+}
+
+/// 导出尚未发送的span，在进程退出前调用
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+#[cfg(feature = "otel")]
+fn otlp_endpoint_configured() -> bool {
+    [
+        "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+        "OTEL_EXPORTER_OTLP_ENDPOINT",
+    ]
+    .iter()
+    .any(|name| std::env::var(name).is_ok_and(|value| !value.trim().is_empty()))
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use crate::config::Config;
+    use opentelemetry::KeyValue;
+    use opentelemetry_sdk::{runtime, trace, Resource};
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+
+    pub(super) fn init(config: &Config) -> anyhow::Result<()> {
+        // 未设置 OTEL_SERVICE_NAME 时使用包名作为服务名
+        let resource = match std::env::var("OTEL_SERVICE_NAME") {
+            Ok(_) => Resource::default(),
+            Err(_) => Resource::new([KeyValue::new("service.name", env!("CARGO_PKG_NAME"))]),
+        }
+        .merge(&Resource::new([KeyValue::new(
+            "chain.id",
+            config.chain_id as i64,
+        )]));
+        // 导出地址、请求头等由exporter按OTEL_EXPORTER_OTLP_*环境变量读取
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(opentelemetry_otlp::new_exporter().http())
+            .with_trace_config(trace::config().with_resource(resource))
+            .install_batch(runtime::Tokio)?;
+
+        tracing_subscriber::registry()
+            .with(tracing_subscriber::fmt::layer())
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .try_init()?;
+        tracing::info!("已启用OpenTelemetry链路追踪导出");
+        Ok(())
+    }
+}
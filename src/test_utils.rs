@@ -0,0 +1,380 @@
+//! 单元测试共用的工具：模拟JSON-RPC节点，以及连接到它的签名客户端和合约实例
+
+use crate::contract::RewardsContract;
+use crate::provider::DistributorClient;
+use crate::retry::RetryPolicy;
+use crate::signer::DistributorSigner;
+pub(crate) use crate::telemetry::init_test_tracing;
+use crate::transport::{HttpTransport, RateLimitRetryPolicy};
+use chrono::Utc;
+use ethers::middleware::SignerMiddleware;
+use ethers::providers::{Provider, RetryClientBuilder};
+use ethers::signers::{LocalWallet, Signer};
+use ethers::types::{Address, Bloom, Bytes, H256};
+use ethers::utils::keccak256;
+use serde_json::{json, Value};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+
+/// 测试签名使用的私钥（Hardhat默认账户0，不持有真实资产）
+pub(crate) const TEST_PRIVATE_KEY: &str =
+    "ac0974bec39a17e36ba4a6b4d238ff944bacb478cbed5efcae784d7bf4f2ff80";
+
+/// 测试使用的链ID
+pub(crate) const TEST_CHAIN_ID: u64 = 31_337;
+
+/// 测试使用的奖励合约地址
+pub(crate) fn test_contract_address() -> Address {
+    Address::repeat_byte(0x42)
+}
+
+/// 高度为 `number`、时间戳为 `timestamp` 的最小区块，`base_fee` 为空时模拟不支持EIP-1559的链
+pub(crate) fn test_block(number: u64, timestamp: i64, base_fee: Option<u64>) -> Value {
+    let mut block = json!({
+        "number": format!("{:#x}", number),
+        "hash": format!("{:?}", H256::from_low_u64_be(number)),
+        "timestamp": format!("{:#x}", timestamp),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "transactions": [],
+    });
+    if let Some(base_fee) = base_fee {
+        block["baseFeePerGas"] = json!(format!("{:#x}", base_fee));
+    }
+    block
+}
+
+/// 在区块100中成功执行的交易收据
+pub(crate) fn mined_receipt(tx_hash: &Value) -> Value {
+    json!({
+        "transactionHash": tx_hash,
+        "transactionIndex": "0x0",
+        "blockHash": format!("{:?}", H256::from_low_u64_be(100)),
+        "blockNumber": "0x64",
+        "from": format!("{:?}", Address::zero()),
+        "to": format!("{:?}", test_contract_address()),
+        "cumulativeGasUsed": "0x5208",
+        "gasUsed": "0x5208",
+        "effectiveGasPrice": "0x3b9aca00",
+        "contractAddress": null,
+        "logs": [],
+        "logsBloom": format!("{:?}", Bloom::zero()),
+        "status": "0x1",
+        "type": "0x0",
+    })
+}
+
+/// 分发流程正常运行所需的节点响应；`mined` 为true前交易一直停留在交易池中
+pub(crate) fn distribution_reply(method: &str, params: &Value, mined: &AtomicBool) -> MockReply {
+    let now = Utc::now().timestamp();
+    match method {
+        "eth_chainId" => MockReply::Result(json!(format!("{:#x}", TEST_CHAIN_ID))),
+        "eth_blockNumber" => MockReply::Result(json!("0x64")),
+        "eth_getBlockByNumber" => MockReply::Result(test_block(100, now, Some(1_000_000_000))),
+        "eth_getTransactionCount" => MockReply::Result(json!("0x0")),
+        "eth_gasPrice" | "eth_maxPriorityFeePerGas" => MockReply::Result(json!("0x3b9aca00")),
+        "eth_estimateGas" => MockReply::Result(json!("0x186a0")),
+        "eth_call" => MockReply::Result(json!("0x")),
+        "eth_getCode" => MockReply::Result(json!("0x6080604052")),
+        "eth_getBalance" => MockReply::Result(json!("0xde0b6b3a7640000")),
+        "eth_sendRawTransaction" => {
+            let raw: Bytes = serde_json::from_value(params[0].clone()).unwrap();
+            MockReply::Result(json!(H256::from(keccak256(&raw))))
+        }
+        "eth_getTransactionByHash" => MockReply::Result(json!({
+            "hash": params[0],
+            "nonce": "0x0",
+            "blockHash": null,
+            "blockNumber": null,
+            "transactionIndex": null,
+            "from": format!("{:?}", Address::zero()),
+            "to": format!("{:?}", test_contract_address()),
+            "value": "0x0",
+            "gasPrice": "0x3b9aca00",
+            "gas": "0x5208",
+            "input": "0x",
+            "v": "0x1",
+            "r": "0x1",
+            "s": "0x1",
+        })),
+        "eth_getTransactionReceipt" if mined.load(Ordering::SeqCst) => {
+            MockReply::Result(mined_receipt(&params[0]))
+        }
+        "eth_getTransactionReceipt" => MockReply::Result(Value::Null),
+        _ => MockReply::error(-32601, "method not found"),
+    }
+}
+
+/// 在主机时区为 `tz` 的新线程中执行 `f`
+///
+/// chrono按线程缓存本地时区，新线程第一次使用时才读取 `TZ`；修改 `TZ` 的测试互斥执行。
+pub(crate) fn in_timezone<T: Send + 'static>(
+    tz: &str,
+    f: impl FnOnce() -> T + Send + 'static,
+) -> T {
+    static TZ_LOCK: Mutex<()> = Mutex::new(());
+    let _guard = TZ_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    std::env::set_var("TZ", tz);
+    std::thread::spawn(f).join().unwrap()
+}
+
+/// 模拟节点对一次请求的响应
+#[derive(Debug, Clone)]
+pub(crate) enum MockReply {
+    /// JSON-RPC结果
+    Result(Value),
+    /// JSON-RPC错误
+    Error {
+        code: i64,
+        message: String,
+        data: Option<Value>,
+    },
+    /// 没有JSON-RPC响应体的HTTP错误状态，`retry_after` 为 `Retry-After` 的秒数
+    Status {
+        status: u16,
+        retry_after: Option<u64>,
+    },
+    /// 等待 `delay` 后再返回 `reply`，模拟响应缓慢的节点
+    Delayed {
+        delay: Duration,
+        reply: Box<MockReply>,
+    },
+}
+
+impl MockReply {
+    pub(crate) fn error(code: i64, message: &str) -> Self {
+        MockReply::Error {
+            code,
+            message: message.to_string(),
+            data: None,
+        }
+    }
+
+    pub(crate) fn delayed(delay: Duration, reply: MockReply) -> Self {
+        MockReply::Delayed {
+            delay,
+            reply: Box::new(reply),
+        }
+    }
+}
+
+/// 模拟节点收到的一次请求
+#[derive(Debug, Clone)]
+pub(crate) struct MockCall {
+    pub method: String,
+    pub params: Value,
+    pub at: Instant,
+}
+
+type Handler = dyn Fn(&str, &Value, usize) -> MockReply + Send + Sync;
+
+struct MockState {
+    handler: Box<Handler>,
+    calls: Mutex<Vec<MockCall>>,
+}
+
+/// 监听本地端口的最小HTTP JSON-RPC节点
+///
+/// 每个请求交给处理函数决定响应，参数为方法名、参数和该方法此前被调用的次数；
+/// 所有请求按到达顺序记录。实例drop时停止接受新连接。
+pub(crate) struct MockNode {
+    url: String,
+    state: Arc<MockState>,
+    server: JoinHandle<()>,
+}
+
+impl MockNode {
+    pub(crate) async fn start<F>(handler: F) -> Self
+    where
+        F: Fn(&str, &Value, usize) -> MockReply + Send + Sync + 'static,
+    {
+        init_test_tracing();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let state = Arc::new(MockState {
+            handler: Box::new(handler),
+            calls: Mutex::new(Vec::new()),
+        });
+
+        let server_state = state.clone();
+        let server = tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(serve(stream, server_state.clone()));
+            }
+        });
+
+        Self { url, state, server }
+    }
+
+    pub(crate) fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// 按到达顺序返回收到的全部请求
+    pub(crate) fn calls(&self) -> Vec<MockCall> {
+        self.state.calls.lock().unwrap().clone()
+    }
+
+    /// 某个方法被调用的次数
+    pub(crate) fn count(&self, method: &str) -> usize {
+        self.calls()
+            .iter()
+            .filter(|call| call.method == method)
+            .count()
+    }
+}
+
+impl Drop for MockNode {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// 处理一个keep-alive连接上的所有请求
+async fn serve(stream: TcpStream, state: Arc<MockState>) -> std::io::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(());
+        }
+
+        let mut content_length = 0;
+        loop {
+            line.clear();
+            if reader.read_line(&mut line).await? == 0 {
+                return Ok(());
+            }
+            let header = line.trim_end();
+            if header.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = header.split_once(':') {
+                if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.trim().parse().unwrap_or(0);
+                }
+            }
+        }
+
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body).await?;
+        let request: Value = serde_json::from_slice(&body).unwrap_or(Value::Null);
+        let (delay, response) = respond(&state, &request);
+        if !delay.is_zero() {
+            tokio::time::sleep(delay).await;
+        }
+        writer.write_all(response.as_bytes()).await?;
+    }
+}
+
+/// 返回响应前需要等待的时间和完整的HTTP响应
+fn respond(state: &MockState, request: &Value) -> (Duration, String) {
+    let method = request["method"].as_str().unwrap_or_default();
+    let params = request.get("params").cloned().unwrap_or(Value::Null);
+    let nth = {
+        let mut calls = state.calls.lock().unwrap();
+        let nth = calls.iter().filter(|call| call.method == method).count();
+        calls.push(MockCall {
+            method: method.to_string(),
+            params: params.clone(),
+            at: Instant::now(),
+        });
+        nth
+    };
+
+    let id = request["id"].clone();
+    let mut delay = Duration::ZERO;
+    let mut reply = (state.handler)(method, &params, nth);
+    while let MockReply::Delayed {
+        delay: extra,
+        reply: inner,
+    } = reply
+    {
+        delay += extra;
+        reply = *inner;
+    }
+    let response = match reply {
+        MockReply::Result(result) => http_response(
+            200,
+            None,
+            &json!({ "jsonrpc": "2.0", "id": id, "result": result }).to_string(),
+        ),
+        MockReply::Error {
+            code,
+            message,
+            data,
+        } => {
+            let mut error = json!({ "code": code, "message": message });
+            if let Some(data) = data {
+                error["data"] = data;
+            }
+            http_response(
+                200,
+                None,
+                &json!({ "jsonrpc": "2.0", "id": id, "error": error }).to_string(),
+            )
+        }
+        MockReply::Status {
+            status,
+            retry_after,
+        } => http_response(status, retry_after, ""),
+        MockReply::Delayed { .. } => unreachable!("延迟响应已在上面展开"),
+    };
+    (delay, response)
+}
+
+fn http_response(status: u16, retry_after: Option<u64>, body: &str) -> String {
+    let retry_after = retry_after
+        .map(|secs| format!("retry-after: {}\r\n", secs))
+        .unwrap_or_default();
+    format!(
+        "HTTP/1.1 {} Mock\r\ncontent-type: application/json\r\ncontent-length: {}\r\n{}\r\n{}",
+        status,
+        body.len(),
+        retry_after,
+        body
+    )
+}
+
+/// 连接到 `url` 的签名客户端，节点限流和超时最多重试 `retries` 次
+pub(crate) fn test_client(url: &str, retries: u32) -> Arc<DistributorClient> {
+    let transport = HttpTransport::new(url.parse().unwrap(), reqwest::Client::new());
+    let retry_client = RetryClientBuilder::default()
+        .rate_limit_retries(retries)
+        .timeout_retries(retries)
+        .initial_backoff(Duration::from_millis(10))
+        .compute_units_per_second(u64::MAX)
+        .build(transport, Box::new(RateLimitRetryPolicy));
+    let wallet = TEST_PRIVATE_KEY
+        .parse::<LocalWallet>()
+        .unwrap()
+        .with_chain_id(TEST_CHAIN_ID);
+
+    Arc::new(SignerMiddleware::new(
+        Provider::new(retry_client),
+        DistributorSigner::from(wallet),
+    ))
+}
+
+/// 连接到 `url` 的奖励合约实例，应用层重试的退避缩短到毫秒级
+pub(crate) fn test_contract(url: &str) -> RewardsContract {
+    init_test_tracing();
+    RewardsContract::builder()
+        .address(test_contract_address())
+        .client(test_client(url, 0))
+        .chain_id(TEST_CHAIN_ID)
+        .retry_policy(RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(5),
+        })
+        .build()
+        .unwrap()
+}
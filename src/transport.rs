@@ -0,0 +1,310 @@
+use crate::error::ErrorKind;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use ethers::providers::{JsonRpcClient, JsonRpcError, ProviderError, RetryPolicy, RpcError};
+use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use reqwest::{StatusCode, Url};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::fmt::Debug;
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tracing::warn;
+
+/// HTTP JSON-RPC传输层
+///
+/// 与ethers自带的 `Http` 相同，但会识别HTTP 429并保留 `Retry-After` 响应头，
+/// 供 [`RateLimitRetryPolicy`] 决定退避时间。配置了请求速率时，每个请求（包括重试）
+/// 发送前都要从令牌桶中取得令牌。克隆共享请求ID和令牌桶。
+#[derive(Debug, Clone)]
+pub struct HttpTransport {
+    id: Arc<AtomicU64>,
+    client: reqwest::Client,
+    url: Url,
+    limiter: Option<Arc<RequestLimiter>>,
+}
+
+impl HttpTransport {
+    pub fn new(url: Url, client: reqwest::Client) -> Self {
+        Self {
+            id: Arc::new(AtomicU64::new(1)),
+            client,
+            url,
+            limiter: None,
+        }
+    }
+
+    /// 限制每秒请求数，允许的突发数等于每秒请求数
+    pub fn with_rate_limit(mut self, requests_per_second: Option<NonZeroU32>) -> Self {
+        self.limiter = requests_per_second.map(|rps| Arc::new(RequestLimiter::new(rps)));
+        self
+    }
+}
+
+/// 本地请求速率限制，被限速时按分钟汇总记录日志
+struct RequestLimiter {
+    limiter: DefaultDirectRateLimiter,
+    requests_per_second: NonZeroU32,
+    /// 上次记录日志的时间，以及之后被限速的请求数
+    throttled: Mutex<(Option<Instant>, u64)>,
+}
+
+impl RequestLimiter {
+    fn new(requests_per_second: NonZeroU32) -> Self {
+        Self {
+            limiter: RateLimiter::direct(Quota::per_second(requests_per_second)),
+            requests_per_second,
+            throttled: Mutex::new((None, 0)),
+        }
+    }
+
+    /// 等待直到可以发送下一个请求
+    async fn acquire(&self, method: &str) {
+        if self.limiter.check().is_ok() {
+            return;
+        }
+
+        {
+            let mut throttled = self.throttled.lock().unwrap();
+            throttled.1 += 1;
+            let should_log = throttled
+                .0
+                .is_none_or(|logged_at| logged_at.elapsed() >= THROTTLE_LOG_INTERVAL);
+            if should_log {
+                warn!(
+                    "RPC请求超过本地限速 {}/秒，{} 个请求被延迟 (最近: {})；请求持续被限速时可考虑升级节点套餐或降低轮询频率",
+                    self.requests_per_second, throttled.1, method
+                );
+                *throttled = (Some(Instant::now()), 0);
+            }
+        }
+        self.limiter.until_ready().await;
+    }
+}
+
+impl Debug for RequestLimiter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RequestLimiter")
+            .field("requests_per_second", &self.requests_per_second)
+            .finish()
+    }
+}
+
+/// 被限速时日志的最小间隔，避免确认轮询等高频请求刷屏
+const THROTTLE_LOG_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Error)]
+pub enum HttpTransportError {
+    #[error(transparent)]
+    Reqwest(#[from] reqwest::Error),
+    /// 节点返回HTTP 429
+    #[error("节点限流 (HTTP 429)，Retry-After: {retry_after:?}")]
+    RateLimited { retry_after: Option<Duration> },
+    /// 节点返回非JSON的错误状态
+    #[error("节点返回HTTP {status}: {body}")]
+    Status { status: StatusCode, body: String },
+    #[error("无法解析节点响应: {err}，响应内容: {text}")]
+    SerdeJson {
+        err: serde_json::Error,
+        text: String,
+    },
+    #[error(transparent)]
+    JsonRpc(#[from] JsonRpcError),
+}
+
+impl RpcError for HttpTransportError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            HttpTransportError::JsonRpc(err) => Some(err),
+            _ => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            HttpTransportError::SerdeJson { err, .. } => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<HttpTransportError> for ProviderError {
+    fn from(err: HttpTransportError) -> Self {
+        ProviderError::JsonRpcClientError(Box::new(err))
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for HttpTransport {
+    type Error = HttpTransportError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, Self::Error>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if let Some(limiter) = &self.limiter {
+            limiter.acquire(method).await;
+        }
+
+        let id = self.id.fetch_add(1, Ordering::SeqCst);
+        let mut payload = json!({ "jsonrpc": "2.0", "id": id, "method": method });
+        // 与ethers一致：无参数（零大小类型）时不发送params字段
+        if std::mem::size_of::<T>() != 0 {
+            payload["params"] =
+                serde_json::to_value(&params).map_err(|err| HttpTransportError::SerdeJson {
+                    err,
+                    text: format!("{:?}", params),
+                })?;
+        }
+
+        let response = self
+            .client
+            .post(self.url.clone())
+            .json(&payload)
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(HttpTransportError::RateLimited {
+                retry_after: parse_retry_after(response.headers()),
+            });
+        }
+
+        let text = response.text().await?;
+        let value: Value = match serde_json::from_str(&text) {
+            Ok(value) => value,
+            Err(_) if !status.is_success() => {
+                return Err(HttpTransportError::Status { status, body: text })
+            }
+            Err(err) => return Err(HttpTransportError::SerdeJson { err, text }),
+        };
+
+        if let Some(error) = value.get("error") {
+            let error = serde_json::from_value::<JsonRpcError>(error.clone()).map_err(|err| {
+                HttpTransportError::SerdeJson {
+                    err,
+                    text: text.clone(),
+                }
+            })?;
+            return Err(error.into());
+        }
+
+        let result = value.get("result").cloned().unwrap_or(Value::Null);
+        serde_json::from_value(result).map_err(|err| HttpTransportError::SerdeJson { err, text })
+    }
+}
+
+/// 解析 `Retry-After`，支持秒数和HTTP日期两种格式
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let at = DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&Utc) - Utc::now()).to_std().ok()
+}
+
+/// 对限流、节点5xx和网络错误进行重试，优先使用节点给出的 `Retry-After`
+#[derive(Debug, Default)]
+pub struct RateLimitRetryPolicy;
+
+impl RetryPolicy<HttpTransportError> for RateLimitRetryPolicy {
+    fn should_retry(&self, error: &HttpTransportError) -> bool {
+        match error {
+            HttpTransportError::RateLimited { .. } => true,
+            HttpTransportError::Status { status, .. } => status.is_server_error(),
+            HttpTransportError::Reqwest(err) => err.is_timeout() || err.is_connect(),
+            HttpTransportError::JsonRpc(err) => {
+                ErrorKind::from_rpc_error(err.code, &err.message) == ErrorKind::RateLimited
+                    || err.message == "header not found"
+            }
+            HttpTransportError::SerdeJson { .. } => false,
+        }
+    }
+
+    fn backoff_hint(&self, error: &HttpTransportError) -> Option<Duration> {
+        match error {
+            HttpTransportError::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{test_client, MockNode, MockReply};
+    use ethers::providers::Middleware;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn rate_limited_request_is_retried_after_retry_after() {
+        let node = MockNode::start(|method, _, nth| match method {
+            "eth_blockNumber" if nth == 0 => MockReply::Status {
+                status: 429,
+                retry_after: Some(1),
+            },
+            "eth_blockNumber" => MockReply::Result(json!("0x64")),
+            _ => MockReply::error(-32601, "method not found"),
+        })
+        .await;
+        let client = test_client(node.url(), 1);
+
+        let block = client.get_block_number().await.unwrap();
+
+        assert_eq!(block.as_u64(), 100);
+        let calls = node.calls();
+        assert_eq!(calls.len(), 2);
+        // 按节点给出的Retry-After等待，而不是客户端自身10ms的初始退避
+        let waited = calls[1].at - calls[0].at;
+        assert!(
+            waited >= Duration::from_secs(1),
+            "重试间隔过短: {:?}",
+            waited
+        );
+        assert!(
+            waited < Duration::from_secs(3),
+            "重试间隔过长: {:?}",
+            waited
+        );
+    }
+
+    #[tokio::test]
+    async fn rate_limit_retries_are_bounded() {
+        let node = MockNode::start(|_, _, _| MockReply::Status {
+            status: 429,
+            retry_after: Some(0),
+        })
+        .await;
+        let client = test_client(node.url(), 1);
+
+        let err = client.get_block_number().await.unwrap_err();
+
+        // 首次请求加上一次重试后放弃
+        assert_eq!(node.count("eth_blockNumber"), 2, "{}", err);
+    }
+
+    #[test]
+    fn retry_after_accepts_seconds_and_http_dates() {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, "5".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), Some(Duration::from_secs(5)));
+
+        let at = (Utc::now() + chrono::Duration::seconds(30)).to_rfc2822();
+        headers.insert(RETRY_AFTER, at.parse().unwrap());
+        let wait = parse_retry_after(&headers).unwrap();
+        assert!(wait > Duration::from_secs(25) && wait <= Duration::from_secs(30));
+
+        headers.insert(RETRY_AFTER, "soon".parse().unwrap());
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+}